@@ -1,29 +1,427 @@
-//! Configuration utilities (ports, CORS, env vars)
+//! Configuration: a typed `Config`, loaded once at startup.
+//!
+//! Every setting has an env var. Any of them can also go in a TOML file,
+//! `zobbo.toml` in the working directory or wherever `ZOBBO_CONFIG` points,
+//! keyed by the env var's name in lower case without any `ZOBBO_` prefix
+//! (`room_idle_ttl_secs = 600`, `dev_tools = true`). The environment wins
+//! over the file, and the defaults below fill in the rest.
+//!
+//! A value that doesn't parse, or a key in the file that isn't a setting,
+//! stops the server at startup rather than being quietly ignored.
 
-use std::{env, net::{Ipv4Addr, SocketAddr}};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddr};
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
 
-/// Socket address to bind the server to.
-///
-/// Reads the `PORT` env var (Fly.io) or defaults to 8080, binds to 0.0.0.0.
-pub fn server_addr() -> SocketAddr {
-    let port = env::var("PORT")
-        .ok()
-        .and_then(|v| v.parse::<u16>().ok())
-        .unwrap_or(8080);
-    SocketAddr::from((Ipv4Addr::UNSPECIFIED, port))
-}
-
-/// Resolve the static directory path used by the server.
-/// Order:
-/// 1) STATIC_DIR env var
-/// 2) ./game/frontend/static (container runtime layout)
-/// 3) ../frontend/static (local dev from backend dir)
-pub fn static_dir() -> PathBuf {
-    if let Ok(p) = env::var("STATIC_DIR") {
-        return PathBuf::from(p);
-    }
-    let p1 = Path::new("./game/frontend/static");
-    if p1.exists() { return p1.to_path_buf(); }
-    PathBuf::from("../frontend/static")
+use crate::http::cors::AllowedOrigins;
+use crate::http::ratelimit::RateLimit;
+
+/// Where the config file is looked for when `ZOBBO_CONFIG` isn't set.
+const DEFAULT_FILE: &str = "zobbo.toml";
+
+pub struct Config {
+    /// The TOML file read, if there was one.
+    pub file: Option<PathBuf>,
+    /// Where to listen: the Unix socket at `UNIX_SOCKET` if set, for a
+    /// reverse proxy on the same host, else `PORT` (Fly.io), default 8080,
+    /// on 0.0.0.0.
+    pub server_addr: ServerAddr,
+    /// Serve the frontend's static files from this directory instead of the
+    /// copies built into the binary, e.g. `../frontend/static` to edit them
+    /// without rebuilding: `STATIC_DIR`.
+    pub static_dir: Option<PathBuf>,
+    /// SQLite database to persist rooms in, e.g. `sqlite://zobbo.db`:
+    /// `DATABASE_URL`. Only used when built with the `sqlite` feature.
+    #[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+    pub database_url: Option<String>,
+    /// File to snapshot rooms and games to when there's no database, e.g.
+    /// `/data/zobbo.json`; loaded back on boot: `SNAPSHOT_PATH`. Ignored
+    /// when `DATABASE_URL` is used.
+    pub snapshot_path: Option<PathBuf>,
+    /// How often the snapshot file is rewritten, when anything changed; it's
+    /// also written on shutdown: `SNAPSHOT_INTERVAL_SECS`, default 30.
+    pub snapshot_interval: Duration,
+    /// How long a room with nobody connected survives before the GC evicts
+    /// it: `ROOM_IDLE_TTL_SECS`, default 30 minutes.
+    pub room_idle_ttl: Duration,
+    /// How long a finished game with nobody connected lingers before
+    /// eviction: `ROOM_FINISHED_LINGER_SECS`, default 5 minutes.
+    pub room_finished_linger: Duration,
+    /// How often the room GC sweeps: `ROOM_GC_INTERVAL_SECS`, default 60.
+    pub room_gc_interval: Duration,
+    /// How long `POST /api/quickmatch` holds a request open waiting for an
+    /// opponent: `QUICKMATCH_WAIT_SECS`, default 25.
+    pub quickmatch_wait: Duration,
+    /// How long a WS reconnect token stays valid: `RECONNECT_TOKEN_TTL_SECS`,
+    /// default 120. Connected clients receive a fresh token every half TTL.
+    pub reconnect_token_ttl: Duration,
+    /// How long clients are told to wait before reconnecting when the server
+    /// shuts down, roughly how long a deploy takes to come back up:
+    /// `SHUTDOWN_RECONNECT_AFTER_SECS`, default 10.
+    pub shutdown_reconnect_after: Duration,
+    /// Key for signing guest identity tokens: `IDENTITY_SECRET`. Without it
+    /// each run signs with a random key, so clients' identities stop working
+    /// after a restart, and `/readyz` fails unless `dev_tools` is on.
+    pub identity_secret: Option<String>,
+    /// Bearer token for the operator routes under `/admin`: `ADMIN_TOKEN`.
+    /// Without it the routes aren't mounted at all.
+    pub admin_token: Option<String>,
+    /// File of words to filter out of display names, one per line: `word` to
+    /// mask it with asterisks, or `word=replacement`. Lines starting `#` are
+    /// comments: `NAME_BLOCKLIST`; without it names aren't filtered.
+    pub name_blocklist: Option<PathBuf>,
+    /// Header a proxy in front of the server puts the client's IP in, e.g.
+    /// `Fly-Client-IP`, for bans and rate limits: `CLIENT_IP_HEADER`. Only
+    /// set it behind a proxy that overwrites the header; without it the peer
+    /// address is used.
+    pub client_ip_header: Option<String>,
+    /// Per-IP limit on creating rooms (`POST /rooms`, `/api/practice`,
+    /// `/api/tutorial`), adding bots, quickmatching and verifying game
+    /// exports: `CREATE_RATE_BURST` and `CREATE_RATE_PER_MIN`, default 5 at
+    /// once and 10 a minute.
+    pub create_rate_limit: RateLimit,
+    /// Per-IP limit on taking seats: joining with a token, by code, or
+    /// claiming a public room's seat, and on WebSocket tickets:
+    /// `JOIN_RATE_BURST` and `JOIN_RATE_PER_MIN`, default 20 at once and 30
+    /// a minute.
+    pub join_rate_limit: RateLimit,
+    /// How long a player who drops mid-game has to reconnect before
+    /// forfeiting: `DISCONNECT_GRACE_SECS`, default 60.
+    pub disconnect_grace: Duration,
+    /// How many turns in a row a player may let time out before forfeiting:
+    /// `AFK_FORFEIT_TURNS`, default 3. Only rooms with a turn timer can time
+    /// turns out.
+    pub afk_forfeit_turns: u32,
+    /// Messages a WS client may send per second before they're dropped;
+    /// three seconds over budget in a row and the socket is closed:
+    /// `WS_MESSAGES_PER_SEC`, default 20.
+    pub ws_messages_per_sec: u32,
+    /// Game updates a room broadcasts per second at most; more frequent
+    /// changes are held back and sent together. Turn changes and the end of
+    /// a game always go out at once: `ROOM_UPDATES_PER_SEC`, default 20.
+    pub room_updates_per_sec: u32,
+    /// Largest WS frame from a client that's parsed; bigger ones are
+    /// dropped: `WS_MAX_MESSAGE_BYTES`, default 4096.
+    pub ws_max_message_bytes: usize,
+    /// Whether logs are written as JSON lines instead of plain text:
+    /// `LOG_FORMAT`, `text` (the default) or `json`.
+    pub json_logs: bool,
+    /// Most rooms live at once; past it new rooms are refused and `/readyz`
+    /// reports the instance not ready: `MAX_ROOMS`, default 5000.
+    pub max_rooms: usize,
+    /// Where to report panics and internal errors, as a Sentry DSN:
+    /// `SENTRY_DSN`. Only used when built with the `sentry` feature.
+    pub sentry_dsn: Option<String>,
+    /// Whether developer-only routes (e.g. the `/dev` wire tap) are mounted:
+    /// `ZOBBO_DEV_TOOLS`, off by default. Never enable in production.
+    pub dev_tools: bool,
+    /// Other sites whose pages may call the API: `ALLOWED_ORIGINS`, comma
+    /// separated, each an origin (`https://zobbo.example`), a subdomain
+    /// wildcard (`https://*.example.com`) or `*`. Defaults to none, or to
+    /// any with `ZOBBO_DEV_TOOLS`.
+    pub allowed_origins: AllowedOrigins,
+}
+
+/// Where the server listens.
+#[derive(Debug, Clone)]
+pub enum ServerAddr {
+    Tcp(SocketAddr),
+    /// A Unix domain socket at this path.
+    Unix(PathBuf),
+}
+
+impl fmt::Display for ServerAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerAddr::Tcp(addr) => addr.fmt(f),
+            ServerAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("can't read config file {path}: {source}")]
+    Read { path: PathBuf, source: std::io::Error },
+    #[error("config file {path} isn't valid TOML: {source}")]
+    Parse { path: PathBuf, source: Box<toml::de::Error> },
+    #[error("config file {path} has unknown setting `{key}`")]
+    Unknown { path: PathBuf, key: String },
+    #[error("{setting} = {value:?} (from {from}): {problem}")]
+    Invalid { setting: String, value: String, from: String, problem: &'static str },
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Load and validate the config; call once, first thing in `main`.
+pub fn load() -> Result<&'static Config, ConfigError> {
+    let config = Config::load()?;
+    Ok(CONFIG.get_or_init(|| config))
+}
+
+/// The config `load` read.
+pub fn get() -> &'static Config {
+    CONFIG.get().expect("config::load runs at startup")
+}
+
+impl Config {
+    fn load() -> Result<Self, ConfigError> {
+        let layers = Layers::open()?;
+        let dev_tools = layers.flag("ZOBBO_DEV_TOOLS")?;
+        let config = Config {
+            file: layers.path.clone(),
+            server_addr: layers.server_addr()?,
+            static_dir: layers.dir("STATIC_DIR")?,
+            database_url: layers.string("DATABASE_URL"),
+            snapshot_path: layers.string("SNAPSHOT_PATH").map(PathBuf::from),
+            snapshot_interval: layers.secs("SNAPSHOT_INTERVAL_SECS", 30)?,
+            room_idle_ttl: layers.secs("ROOM_IDLE_TTL_SECS", 30 * 60)?,
+            room_finished_linger: layers.secs("ROOM_FINISHED_LINGER_SECS", 5 * 60)?,
+            room_gc_interval: layers.secs("ROOM_GC_INTERVAL_SECS", 60)?,
+            quickmatch_wait: layers.secs("QUICKMATCH_WAIT_SECS", 25)?,
+            reconnect_token_ttl: layers.secs("RECONNECT_TOKEN_TTL_SECS", 120)?,
+            shutdown_reconnect_after: layers.secs("SHUTDOWN_RECONNECT_AFTER_SECS", 10)?,
+            identity_secret: layers.string("IDENTITY_SECRET"),
+            admin_token: layers.string("ADMIN_TOKEN"),
+            name_blocklist: layers.file("NAME_BLOCKLIST")?,
+            client_ip_header: layers.header("CLIENT_IP_HEADER")?,
+            create_rate_limit: RateLimit {
+                burst: layers.count("CREATE_RATE_BURST", 5)?,
+                per_minute: layers.count("CREATE_RATE_PER_MIN", 10)?,
+            },
+            join_rate_limit: RateLimit {
+                burst: layers.count("JOIN_RATE_BURST", 20)?,
+                per_minute: layers.count("JOIN_RATE_PER_MIN", 30)?,
+            },
+            disconnect_grace: layers.secs("DISCONNECT_GRACE_SECS", 60)?,
+            afk_forfeit_turns: layers.count("AFK_FORFEIT_TURNS", 3)?,
+            ws_messages_per_sec: layers.count("WS_MESSAGES_PER_SEC", 20)?,
+            room_updates_per_sec: layers.count("ROOM_UPDATES_PER_SEC", 20)?,
+            ws_max_message_bytes: layers.count("WS_MAX_MESSAGE_BYTES", 4096)? as usize,
+            json_logs: layers.log_format("LOG_FORMAT")?,
+            max_rooms: layers.count("MAX_ROOMS", 5000)? as usize,
+            sentry_dsn: layers.string("SENTRY_DSN"),
+            dev_tools,
+            allowed_origins: layers.origins("ALLOWED_ORIGINS", dev_tools)?,
+        };
+        layers.check_unknown()?;
+        Ok(config)
+    }
+}
+
+/// Where settings come from: the environment over the config file.
+struct Layers {
+    /// The environment as it was when the layers were opened.
+    env: HashMap<String, String>,
+    path: Option<PathBuf>,
+    file: toml::Table,
+    /// Every file key a setting looked for, to catch the ones none did.
+    asked: RefCell<BTreeSet<String>>,
+}
+
+/// A setting's value and where it came from, for errors: the env var or
+/// file key it was set under, and the environment or the file.
+struct Raw {
+    setting: String,
+    value: String,
+    from: String,
+}
+
+impl Raw {
+    fn invalid(self, problem: &'static str) -> ConfigError {
+        ConfigError::Invalid { setting: self.setting, value: self.value, from: self.from, problem }
+    }
+}
+
+impl Layers {
+    fn open() -> Result<Self, ConfigError> {
+        let env = env::vars().collect();
+        let (path, required) = match env::var("ZOBBO_CONFIG").ok().filter(|v| !v.is_empty()) {
+            Some(path) => (PathBuf::from(path), true),
+            None => (PathBuf::from(DEFAULT_FILE), false),
+        };
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if !required && err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self { env, path: None, file: toml::Table::new(), asked: RefCell::default() });
+            }
+            Err(source) => return Err(ConfigError::Read { path, source }),
+        };
+        let file = text.parse().map_err(|source| ConfigError::Parse { path: path.clone(), source: Box::new(source) })?;
+        Ok(Self { env, path: Some(path), file, asked: RefCell::default() })
+    }
+
+    /// `var` from the environment, or its key in the file. Empty counts as unset.
+    fn get(&self, var: &'static str) -> Option<Raw> {
+        let key = var.strip_prefix("ZOBBO_").unwrap_or(var).to_lowercase();
+        self.asked.borrow_mut().insert(key.clone());
+        if let Some(value) = self.env.get(var).filter(|v| !v.is_empty()) {
+            return Some(Raw { setting: var.to_string(), value: value.clone(), from: "the environment".into() });
+        }
+        let value = match self.file.get(&key)? {
+            toml::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        };
+        let from = self.path.as_deref().unwrap_or(Path::new(DEFAULT_FILE)).display().to_string();
+        Some(Raw { setting: key, value, from }).filter(|raw| !raw.value.is_empty())
+    }
+
+    fn string(&self, var: &'static str) -> Option<String> {
+        self.get(var).map(|raw| raw.value)
+    }
+
+    fn parse<T: std::str::FromStr>(&self, var: &'static str, default: T, problem: &'static str) -> Result<T, ConfigError> {
+        let Some(raw) = self.get(var) else { return Ok(default) };
+        raw.value.trim().parse().map_err(|_| raw.invalid(problem))
+    }
+
+    /// A positive whole number.
+    fn count(&self, var: &'static str, default: u32) -> Result<u32, ConfigError> {
+        let Some(raw) = self.get(var) else { return Ok(default) };
+        match raw.value.trim().parse() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(raw.invalid("expected a whole number above 0")),
+        }
+    }
+
+    /// A positive whole number of seconds.
+    fn secs(&self, var: &'static str, default: u64) -> Result<Duration, ConfigError> {
+        let Some(raw) = self.get(var) else { return Ok(Duration::from_secs(default)) };
+        match raw.value.trim().parse() {
+            Ok(secs) if secs > 0 => Ok(Duration::from_secs(secs)),
+            _ => Err(raw.invalid("expected a whole number of seconds above 0")),
+        }
+    }
+
+    fn flag(&self, var: &'static str) -> Result<bool, ConfigError> {
+        let Some(raw) = self.get(var) else { return Ok(false) };
+        match raw.value.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" => Ok(true),
+            "0" | "false" => Ok(false),
+            _ => Err(raw.invalid("expected true or false")),
+        }
+    }
+
+    /// A file that has to be there, since it's only read once it's needed.
+    fn file(&self, var: &'static str) -> Result<Option<PathBuf>, ConfigError> {
+        let Some(raw) = self.get(var) else { return Ok(None) };
+        if !Path::new(&raw.value).is_file() { return Err(raw.invalid("no such file")); }
+        Ok(Some(PathBuf::from(raw.value)))
+    }
+
+    fn dir(&self, var: &'static str) -> Result<Option<PathBuf>, ConfigError> {
+        let Some(raw) = self.get(var) else { return Ok(None) };
+        if !Path::new(&raw.value).is_dir() { return Err(raw.invalid("no such directory")); }
+        Ok(Some(PathBuf::from(raw.value)))
+    }
+
+    /// `UNIX_SOCKET` if set, else `PORT` on every interface.
+    fn server_addr(&self) -> Result<ServerAddr, ConfigError> {
+        let port = self.parse("PORT", 8080, "expected a port number")?;
+        let Some(raw) = self.get("UNIX_SOCKET") else {
+            return Ok(ServerAddr::Tcp(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port))));
+        };
+        if !cfg!(unix) { return Err(raw.invalid("Unix sockets need a Unix platform")); }
+        let path = PathBuf::from(&raw.value);
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        if !dir.is_dir() { return Err(raw.invalid("no such directory")); }
+        Ok(ServerAddr::Unix(path))
+    }
+
+    fn header(&self, var: &'static str) -> Result<Option<String>, ConfigError> {
+        let Some(raw) = self.get(var) else { return Ok(None) };
+        if axum::http::HeaderName::try_from(raw.value.as_str()).is_err() {
+            return Err(raw.invalid("not a valid header name"));
+        }
+        Ok(Some(raw.value))
+    }
+
+    fn origins(&self, var: &'static str, dev_tools: bool) -> Result<AllowedOrigins, ConfigError> {
+        let Some(raw) = self.get(var) else {
+            return Ok(if dev_tools { AllowedOrigins::any() } else { AllowedOrigins::default() });
+        };
+        AllowedOrigins::parse(&raw.value).map_err(|problem| raw.invalid(problem))
+    }
+
+    /// `json` or `text`.
+    fn log_format(&self, var: &'static str) -> Result<bool, ConfigError> {
+        let Some(raw) = self.get(var) else { return Ok(false) };
+        match raw.value.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(true),
+            "text" => Ok(false),
+            _ => Err(raw.invalid("expected json or text")),
+        }
+    }
+
+    fn check_unknown(&self) -> Result<(), ConfigError> {
+        let asked = self.asked.borrow();
+        match (&self.path, self.file.keys().find(|key| !asked.contains(*key))) {
+            (Some(path), Some(key)) => Err(ConfigError::Unknown { path: path.clone(), key: key.clone() }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers(env: &[(&str, &str)], file: &str) -> Layers {
+        Layers {
+            env: env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            path: Some(PathBuf::from("test.toml")),
+            file: file.parse().unwrap(),
+            asked: RefCell::default(),
+        }
+    }
+
+    fn problem(err: ConfigError) -> (String, &'static str) {
+        let ConfigError::Invalid { setting, problem, .. } = err else { panic!("{err}") };
+        (setting, problem)
+    }
+
+    #[test]
+    fn the_environment_wins_over_the_file() {
+        let file = "port = 9000\ndev_tools = true\nsentry_dsn = \"https://file\"";
+        let both = layers(&[("PORT", "9001"), ("ZOBBO_DEV_TOOLS", "false")], file);
+        assert_eq!(both.parse("PORT", 8080, "port").unwrap(), 9001);
+        assert!(!both.flag("ZOBBO_DEV_TOOLS").unwrap());
+        assert_eq!(both.string("SENTRY_DSN").as_deref(), Some("https://file"));
+
+        // An empty variable counts as unset, and the file's TOML types are read as text.
+        let file_only = layers(&[("PORT", "")], file);
+        assert_eq!(file_only.parse("PORT", 8080, "port").unwrap(), 9000);
+        assert!(file_only.flag("ZOBBO_DEV_TOOLS").unwrap());
+        assert_eq!(layers(&[], "").parse("PORT", 8080, "port").unwrap(), 8080);
+    }
+
+    #[test]
+    fn unknown_file_keys_are_an_error() {
+        let layers = layers(&[], "port = 9000\nprot = 9001");
+        layers.parse("PORT", 8080, "port").unwrap();
+        let err = layers.check_unknown().unwrap_err();
+        assert!(matches!(&err, ConfigError::Unknown { key, .. } if key == "prot"), "{err}");
+        layers.get("PROT");
+        assert!(layers.check_unknown().is_ok());
+    }
+
+    #[test]
+    fn bad_values_name_the_setting_and_where_it_came_from() {
+        let layers = layers(&[("MAX_ROOMS", "0"), ("LOG_FORMAT", "xml")], "room_idle_ttl_secs = -5\ndev_tools = \"maybe\"");
+        assert_eq!(problem(layers.count("MAX_ROOMS", 1).unwrap_err()), ("MAX_ROOMS".into(), "expected a whole number above 0"));
+        let (setting, _) = problem(layers.secs("ROOM_IDLE_TTL_SECS", 1).unwrap_err());
+        assert_eq!(setting, "room_idle_ttl_secs");
+        assert_eq!(problem(layers.flag("ZOBBO_DEV_TOOLS").unwrap_err()).1, "expected true or false");
+        assert_eq!(problem(layers.log_format("LOG_FORMAT").unwrap_err()).1, "expected json or text");
+        assert_eq!(
+            layers.secs("ROOM_IDLE_TTL_SECS", 1).unwrap_err().to_string(),
+            "room_idle_ttl_secs = \"-5\" (from test.toml): expected a whole number of seconds above 0"
+        );
+        assert!(layers.count("MAX_ROOMS", 1).unwrap_err().to_string().ends_with("(from the environment): expected a whole number above 0"));
+    }
 }
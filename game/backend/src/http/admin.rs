@@ -0,0 +1,227 @@
+//! Operator routes under `/admin`, for seeing what rooms are doing, banning
+//! abusive players and winding the server down for a deploy. Only mounted when `ADMIN_TOKEN` is
+//! set; `require_admin` checks every call presents it, and logs each one to
+//! the `zobbo::audit` target.
+
+use std::net::{IpAddr, SocketAddr};
+
+use axum::extract::{ConnectInfo, Path, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::http::auth::admin_authorized;
+use crate::http::routes::AppState;
+use crate::room::bans::{self, Ban};
+use crate::room::manager::RoomStatus;
+use crate::util::id::new_ban_id;
+
+/// Every `/admin` route, behind `require_admin`.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/admin/rooms", get(list_rooms))
+        .route("/admin/rooms/:id", get(room).delete(close_room))
+        .route("/admin/bans", get(list_bans).post(add_ban))
+        .route("/admin/bans/:id", delete(lift_ban))
+        .route("/admin/maintenance", get(maintenance).put(set_maintenance))
+        .route_layer(middleware::from_fn(require_admin))
+}
+
+/// Refuse calls without the admin token, and log every call for the audit
+/// trail, refused or not.
+async fn require_admin(ConnectInfo(peer): ConnectInfo<SocketAddr>, req: Request, next: Next) -> Response {
+    let (method, path) = (req.method().clone(), req.uri().path().to_string());
+    if !admin_authorized(req.headers()) {
+        tracing::warn!(target: "zobbo::audit", %peer, %method, %path, "admin call refused");
+        return (StatusCode::UNAUTHORIZED, "admin token required").into_response();
+    }
+    let response = next.run(req).await;
+    tracing::info!(target: "zobbo::audit", %peer, %method, %path, status = response.status().as_u16(), "admin call");
+    response
+}
+
+/// Every room on the server, oldest first, with who's seated, who's
+/// connected and where its game is.
+#[utoipa::path(
+    get,
+    path = "/admin/rooms",
+    tag = "admin",
+    responses(
+        (status = 200, body = Vec<RoomStatus>),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token"),
+    ),
+)]
+pub async fn list_rooms(State(state): State<AppState>) -> Response {
+    Json(state.rooms.statuses().await).into_response()
+}
+
+/// One room, as in `GET /admin/rooms`.
+#[utoipa::path(
+    get,
+    path = "/admin/rooms/{id}",
+    tag = "admin",
+    params(("id" = String, Path, description = "Room id")),
+    responses(
+        (status = 200, body = RoomStatus),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token"),
+        (status = 404, description = "No such room"),
+    ),
+)]
+pub async fn room(Path(id): Path<String>, State(state): State<AppState>) -> Response {
+    match state.rooms.status(&id).await {
+        Some(status) => Json(status).into_response(),
+        None => (StatusCode::NOT_FOUND, "room not found").into_response(),
+    }
+}
+
+/// Close a room: its players are told it's gone and their sockets close, and
+/// the room is dropped, game and all.
+#[utoipa::path(
+    delete,
+    path = "/admin/rooms/{id}",
+    tag = "admin",
+    params(("id" = String, Path, description = "Room id")),
+    responses(
+        (status = 204, description = "Closed"),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token"),
+        (status = 404, description = "No such room"),
+    ),
+)]
+pub async fn close_room(Path(id): Path<String>, State(state): State<AppState>) -> Response {
+    if !state.rooms.close_room(&id) {
+        return (StatusCode::NOT_FOUND, "room not found").into_response();
+    }
+    tracing::info!(room_id = %id, "room closed by an operator");
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// Bans in force, newest first.
+#[utoipa::path(
+    get,
+    path = "/admin/bans",
+    tag = "admin",
+    responses(
+        (status = 200, body = Vec<Ban>),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token"),
+    ),
+)]
+pub async fn list_bans(State(state): State<AppState>) -> Json<Vec<Ban>> {
+    Json(state.rooms.bans())
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct BanRequest {
+    /// Guest identity to ban.
+    pub player_id: Option<String>,
+    /// Address to ban.
+    #[schema(value_type = Option<String>, example = "203.0.113.7")]
+    pub ip: Option<IpAddr>,
+    /// Shown to the banned player.
+    pub reason: Option<String>,
+    /// How long the ban lasts; absent for good.
+    pub duration_secs: Option<u64>,
+}
+
+/// Ban a player, an address, or both at once. They're turned away from
+/// joining rooms and opening sockets; a game they're in carries on without
+/// them once their socket drops.
+#[utoipa::path(
+    post,
+    path = "/admin/bans",
+    tag = "admin",
+    request_body = BanRequest,
+    responses(
+        (status = 201, body = Ban),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token"),
+        (status = 422, description = "Neither `player_id` nor `ip` given, or `ip` isn't an address"),
+    ),
+)]
+pub async fn add_ban(State(state): State<AppState>, Json(req): Json<BanRequest>) -> Response {
+    if req.player_id.is_none() && req.ip.is_none() {
+        return (StatusCode::UNPROCESSABLE_ENTITY, "name a player_id, an ip or both").into_response();
+    }
+    let now = bans::unix_now();
+    let ban = Ban {
+        id: new_ban_id(),
+        player_id: req.player_id,
+        ip: req.ip,
+        reason: req.reason.filter(|r| !r.trim().is_empty()),
+        created_at_unix: now,
+        expires_at_unix: req.duration_secs.map(|secs| now.saturating_add(secs)),
+    };
+    tracing::info!(ban_id = %ban.id, player_id = ?ban.player_id, ip = ?ban.ip, "ban added");
+    state.rooms.ban(ban.clone());
+    (StatusCode::CREATED, Json(ban)).into_response()
+}
+
+/// Lift a ban early.
+#[utoipa::path(
+    delete,
+    path = "/admin/bans/{id}",
+    tag = "admin",
+    params(("id" = String, Path, description = "Ban id")),
+    responses(
+        (status = 204, description = "Lifted"),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token"),
+        (status = 404, description = "No such ban"),
+    ),
+)]
+pub async fn lift_ban(Path(id): Path<String>, State(state): State<AppState>) -> Response {
+    match state.rooms.lift_ban(&id) {
+        Some(_) => StatusCode::NO_CONTENT.into_response(),
+        None => (StatusCode::NOT_FOUND, "ban not found").into_response(),
+    }
+}
+
+/// Whether the server is refusing new rooms.
+#[derive(Serialize, ToSchema)]
+pub struct Maintenance {
+    pub enabled: bool,
+    /// Rooms still live; once it's down to those left idle, it's safe to deploy.
+    pub rooms: usize,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MaintenanceRequest {
+    pub enabled: bool,
+}
+
+/// Whether maintenance mode is on.
+#[utoipa::path(
+    get,
+    path = "/admin/maintenance",
+    tag = "admin",
+    responses(
+        (status = 200, body = Maintenance),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token"),
+    ),
+)]
+pub async fn maintenance(State(state): State<AppState>) -> Response {
+    Json(Maintenance { enabled: state.rooms.maintenance(), rooms: state.rooms.room_count() }).into_response()
+}
+
+/// Turn maintenance mode on or off. While it's on, creating a room or
+/// quickmatching answers 503; games already running play on.
+#[utoipa::path(
+    put,
+    path = "/admin/maintenance",
+    tag = "admin",
+    request_body = MaintenanceRequest,
+    responses(
+        (status = 200, body = Maintenance),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token"),
+    ),
+)]
+pub async fn set_maintenance(
+    State(state): State<AppState>,
+    Json(MaintenanceRequest { enabled }): Json<MaintenanceRequest>,
+) -> Response {
+    state.rooms.set_maintenance(enabled);
+    tracing::warn!(enabled, "maintenance mode switched");
+    Json(Maintenance { enabled, rooms: state.rooms.room_count() }).into_response()
+}
+
@@ -0,0 +1,165 @@
+//! The frontend's static files, under `/static`, built into the binary so it
+//! runs from any directory. With `STATIC_DIR` set they're served from disk
+//! instead, to work on the frontend without rebuilding.
+//!
+//! Pages link to them through [`url`], which puts a content hash in the file
+//! name (`js/app.3hJx0Vq1pW9sLk2a.js`). A hashed name never changes content,
+//! so browsers keep it for good; the plain name is revalidated every time.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::extract::Path;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rust_embed::{EmbeddedFile, RustEmbed};
+
+use crate::config;
+
+#[derive(RustEmbed)]
+#[folder = "../frontend/static/"]
+// Type declarations for editors, not the browser.
+#[exclude = "*.d.ts"]
+struct Embedded;
+
+/// Characters of the content hash put in file names.
+const HASH_LEN: usize = 16;
+
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+const REVALIDATE: &str = "no-cache";
+
+/// The URL a page should link `path` (relative to the static directory) at:
+/// hashed when it's embedded, plain when served from `STATIC_DIR` or unknown.
+pub fn url(path: &str) -> String {
+    let hashed = config::get().static_dir.is_none().then(|| Embedded::get(path)).flatten().and_then(|file| {
+        let (stem, ext) = path.rsplit_once('.').filter(|(stem, _)| !stem.ends_with('/'))?;
+        Some(format!("{stem}.{}.{ext}", content_hash(&file)))
+    });
+    format!("/static/{}", hashed.as_deref().unwrap_or(path))
+}
+
+/// `GET /static/*path` from the embedded files, by plain or hashed name.
+/// Answers `If-None-Match` and `If-Modified-Since` with a 304 when the
+/// browser's copy is current.
+pub async fn embedded(Path(path): Path<String>, headers: HeaderMap) -> Response {
+    let Some((file, cache_control)) = lookup(&path) else {
+        return (StatusCode::NOT_FOUND, "not found").into_response();
+    };
+    let etag = format!("\"{}\"", URL_SAFE_NO_PAD.encode(file.metadata.sha256_hash()));
+    let modified = file.metadata.last_modified().map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+
+    let mut response = if fresh(&headers, &etag, modified) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        ([(header::CONTENT_TYPE, file.metadata.mimetype())], file.data).into_response()
+    };
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(cache_control));
+    response_headers.insert(header::ETAG, HeaderValue::from_str(&etag).expect("base64 is a valid header value"));
+    if let Some(modified) = modified {
+        let date = httpdate::fmt_http_date(modified);
+        response_headers.insert(header::LAST_MODIFIED, HeaderValue::from_str(&date).expect("HTTP dates are ASCII"));
+    }
+    response
+}
+
+/// The file at `path` and how long it may be cached. A hashed name whose hash
+/// is stale (a page from before a deploy) still gets the current file, but
+/// only for revalidation.
+fn lookup(path: &str) -> Option<(EmbeddedFile, &'static str)> {
+    if let Some(file) = Embedded::get(path) {
+        return Some((file, REVALIDATE));
+    }
+    let (rest, ext) = path.rsplit_once('.')?;
+    let (stem, hash) = rest.rsplit_once('.')?;
+    let file = Embedded::get(&format!("{stem}.{ext}"))?;
+    let current = content_hash(&file) == hash;
+    Some((file, if current { IMMUTABLE } else { REVALIDATE }))
+}
+
+fn content_hash(file: &EmbeddedFile) -> String {
+    let mut hash = URL_SAFE_NO_PAD.encode(file.metadata.sha256_hash());
+    hash.truncate(HASH_LEN);
+    hash
+}
+
+/// Whether the browser's cached copy is current. `If-None-Match` wins when
+/// both are sent (RFC 9110 §13.2.2).
+fn fresh(headers: &HeaderMap, etag: &str, modified: Option<SystemTime>) -> bool {
+    if let Some(tags) = headers.get(header::IF_NONE_MATCH) {
+        let tags = tags.to_str().unwrap_or_default();
+        return tags.trim() == "*" || tags.split(',').any(|tag| tag.trim().trim_start_matches("W/") == etag);
+    }
+    let since = headers.get(header::IF_MODIFIED_SINCE).and_then(|since| since.to_str().ok());
+    match (since.and_then(|since| httpdate::parse_http_date(since).ok()), modified) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(name: header::HeaderName, value: &str) -> HeaderMap {
+        HeaderMap::from_iter([(name, HeaderValue::from_str(value).unwrap())])
+    }
+
+    async fn get(path: &str, headers: HeaderMap) -> Response {
+        embedded(Path(path.to_string()), headers).await
+    }
+
+    #[test]
+    fn a_matching_tag_or_an_unchanged_date_is_fresh() {
+        let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let at = |secs| httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(secs));
+        assert!(fresh(&headers(header::IF_NONE_MATCH, "\"abc\""), "\"abc\"", None));
+        assert!(fresh(&headers(header::IF_NONE_MATCH, "\"old\", W/\"abc\""), "\"abc\"", None));
+        assert!(fresh(&headers(header::IF_NONE_MATCH, "*"), "\"abc\"", None));
+        assert!(!fresh(&headers(header::IF_NONE_MATCH, "\"old\""), "\"abc\"", Some(modified)));
+        assert!(fresh(&headers(header::IF_MODIFIED_SINCE, &at(1_700_000_000)), "\"abc\"", Some(modified)));
+        assert!(!fresh(&headers(header::IF_MODIFIED_SINCE, &at(1_699_999_999)), "\"abc\"", Some(modified)));
+        assert!(!fresh(&headers(header::IF_MODIFIED_SINCE, "yesterday"), "\"abc\"", Some(modified)));
+        assert!(!fresh(&headers(header::IF_MODIFIED_SINCE, &at(1_700_000_000)), "\"abc\"", None));
+        assert!(!fresh(&HeaderMap::new(), "\"abc\"", Some(modified)));
+
+        // A stale tag isn't outvoted by a date that would pass on its own.
+        let mut both = headers(header::IF_NONE_MATCH, "\"old\"");
+        both.insert(header::IF_MODIFIED_SINCE, HeaderValue::from_str(&at(1_800_000_000)).unwrap());
+        assert!(!fresh(&both, "\"abc\"", Some(modified)));
+    }
+
+    #[test]
+    fn hashed_names_are_immutable_only_while_current() {
+        let file = Embedded::get("js/app.js").unwrap();
+        let hash = content_hash(&file);
+        assert_eq!(hash.len(), HASH_LEN);
+        assert_eq!(lookup("js/app.js").map(|(_, cache)| cache), Some(REVALIDATE));
+        assert_eq!(lookup(&format!("js/app.{hash}.js")).map(|(_, cache)| cache), Some(IMMUTABLE));
+        let (stale, cache) = lookup("js/app.AAAAAAAAAAAAAAAA.js").unwrap();
+        assert_eq!((stale.data, cache), (file.data, REVALIDATE));
+        assert!(lookup("js/nope.js").is_none());
+        assert!(lookup(&format!("js/nope.{hash}.js")).is_none());
+        assert!(lookup("js/protocol.d.ts").is_none());
+    }
+
+    #[tokio::test]
+    async fn a_current_copy_gets_a_304_and_a_stale_one_the_file() {
+        let first = get("css/styles.css", HeaderMap::new()).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(first.headers()[header::CACHE_CONTROL], REVALIDATE);
+        let etag = first.headers()[header::ETAG].to_str().unwrap().to_string();
+
+        let again = get("css/styles.css", headers(header::IF_NONE_MATCH, &etag)).await;
+        assert_eq!(again.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(again.headers()[header::ETAG], etag.as_str());
+        let changed = get("css/styles.css", headers(header::IF_NONE_MATCH, "\"something-else\"")).await;
+        assert_eq!(changed.status(), StatusCode::OK);
+
+        let hash = content_hash(&Embedded::get("css/styles.css").unwrap());
+        let hashed = get(&format!("css/styles.{hash}.css"), HeaderMap::new()).await;
+        assert_eq!(hashed.headers()[header::CACHE_CONTROL], IMMUTABLE);
+        assert_eq!(get("css/missing.css", HeaderMap::new()).await.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,3 +1,139 @@
-//! Auth helpers: join tokens, guest auth.
+//! Auth helpers: guest identities, and the operator token for `/admin`.
+//!
+//! A guest identity is a `player_id` and display name, signed by the server
+//! and kept by the client across rooms. Join tokens still decide who may sit
+//! where; the identity only says who is sitting there.
 
-// Placeholder; token generation/validation to be implemented.
+use std::sync::OnceLock;
+
+use axum::http::{header, HeaderMap};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::config;
+use crate::http::names::{clean_name, NameError};
+use crate::util::id::{new_guest_name, new_player_id};
+
+/// A player, as vouched for by an identity token.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Identity {
+    pub player_id: String,
+    pub name: String,
+}
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentityError {
+    #[error("identity token is malformed")]
+    Malformed,
+    #[error("identity token wasn't issued by this server")]
+    BadSignature,
+    #[error(transparent)]
+    Name(#[from] NameError),
+}
+
+impl Identity {
+    /// A new player, called `name` or a generated guest name.
+    pub fn issue(name: Option<&str>) -> Result<Self, IdentityError> {
+        let name = name.map(clean_name).transpose()?.unwrap_or_else(new_guest_name);
+        Ok(Self { player_id: new_player_id(), name })
+    }
+
+    /// The same player under a new name.
+    pub fn renamed(self, name: &str) -> Result<Self, IdentityError> {
+        Ok(Self { name: clean_name(name)?, ..self })
+    }
+
+    /// The token the client keeps and presents to prove it's this player.
+    pub fn token(&self) -> String {
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("identity serializes"));
+        let signature = URL_SAFE_NO_PAD.encode(sign(payload.as_bytes()).finalize().into_bytes());
+        format!("{payload}.{signature}")
+    }
+
+    /// The identity `token` was issued for.
+    pub fn verify(token: &str) -> Result<Self, IdentityError> {
+        let (payload, signature) = token.split_once('.').ok_or(IdentityError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| IdentityError::Malformed)?;
+        sign(payload.as_bytes()).verify_slice(&signature).map_err(|_| IdentityError::BadSignature)?;
+        let json = URL_SAFE_NO_PAD.decode(payload).map_err(|_| IdentityError::Malformed)?;
+        serde_json::from_slice(&json).map_err(|_| IdentityError::Malformed)
+    }
+}
+
+static SECRET: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// Load the signing key now rather than on the first identity, so a missing
+/// `IDENTITY_SECRET` is warned about at startup.
+pub fn load_secret() {
+    secret();
+}
+
+/// Whether identities will survive a restart, for `/readyz`: `IDENTITY_SECRET`
+/// is set. A dev server (`ZOBBO_DEV_TOOLS`) makes do with a key of its own.
+pub fn secret_configured() -> bool {
+    let config = config::get();
+    config.dev_tools || config.identity_secret.as_deref().is_some_and(|secret| !secret.is_empty())
+}
+
+/// The key identity tokens are signed with: `IDENTITY_SECRET`, or a random
+/// one for this process only.
+fn secret() -> &'static [u8] {
+    SECRET.get_or_init(|| match &config::get().identity_secret {
+        Some(secret) => secret.clone().into_bytes(),
+        None => {
+            tracing::warn!("IDENTITY_SECRET is not set; guest identities won't survive a restart");
+            let mut secret = vec![0; 32];
+            rand::thread_rng().fill_bytes(&mut secret);
+            secret
+        }
+    })
+}
+
+/// HMAC-SHA256 of `message` under `secret()`, to finalize or verify against.
+fn sign(message: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret()).expect("HMAC takes a key of any length");
+    mac.update(message);
+    mac
+}
+
+/// Whether `headers` carry `Authorization: Bearer <ADMIN_TOKEN>`. Always
+/// false when no token is configured.
+pub fn admin_authorized(headers: &HeaderMap) -> bool {
+    let Some(expected) = &config::get().admin_token else { return false };
+    let Some(token) = headers.get(header::AUTHORIZATION).and_then(|v| v.as_bytes().strip_prefix(b"Bearer ")) else {
+        return false;
+    };
+    // Digests first, so not even the token's length shows in the timing.
+    Sha256::digest(token).ct_eq(&Sha256::digest(expected)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_tampered_identity_token_is_refused() {
+        config::load().unwrap();
+        let ann = Identity { player_id: "01ANN".into(), name: "Ann".into() };
+        let token = ann.token();
+        assert_eq!(Identity::verify(&token), Ok(ann.clone()));
+
+        // Someone else's name under Ann's signature.
+        let (_, signature) = token.split_once('.').unwrap();
+        let bo = Identity { name: "Bo".into(), ..ann };
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&bo).unwrap());
+        assert_eq!(Identity::verify(&format!("{payload}.{signature}")), Err(IdentityError::BadSignature));
+        let mut flipped = token.clone().into_bytes();
+        let first = &mut flipped[token.find('.').unwrap() + 1];
+        *first = if *first == b'A' { b'B' } else { b'A' };
+        assert_eq!(Identity::verify(&String::from_utf8(flipped).unwrap()), Err(IdentityError::BadSignature));
+
+        assert_eq!(Identity::verify("no-signature"), Err(IdentityError::Malformed));
+        assert_eq!(Identity::verify(&format!("{payload}.not base64!")), Err(IdentityError::Malformed));
+    }
+}
@@ -0,0 +1,153 @@
+//! CORS: which other sites' pages may call the API from a browser.
+//!
+//! Join, reconnect and identity tokens are as good as a login, so by default
+//! no other origin may read responses; `ALLOWED_ORIGINS` lists the ones that
+//! may. The game's own pages are same-origin and need no entry.
+
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::telemetry::REQUEST_ID;
+
+/// One `ALLOWED_ORIGINS` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Pattern {
+    /// `*`: every origin.
+    Any,
+    /// `https://zobbo.example`: that origin only.
+    Exact(String),
+    /// `https://*.example.com`: any subdomain of `example.com` over `https`,
+    /// at any depth, but not `example.com` itself.
+    Subdomain { scheme: String, suffix: String },
+}
+
+/// The origins allowed to make cross-origin requests.
+#[derive(Debug, Clone, Default)]
+pub struct AllowedOrigins(Vec<Pattern>);
+
+impl AllowedOrigins {
+    /// Every origin, for local development.
+    pub fn any() -> Self {
+        Self(vec![Pattern::Any])
+    }
+
+    /// Parse a comma-separated list of origins and `scheme://*.domain` wildcards.
+    pub fn parse(list: &str) -> Result<Self, &'static str> {
+        let entries = list.split(',').map(str::trim).filter(|entry| !entry.is_empty());
+        entries.map(parse_pattern).collect::<Result<_, _>>().map(Self)
+    }
+
+    pub fn allows(&self, origin: &str) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        self.0.iter().any(|pattern| match pattern {
+            Pattern::Any => true,
+            Pattern::Exact(allowed) => *allowed == origin,
+            Pattern::Subdomain { scheme, suffix } => origin
+                .strip_prefix(scheme.as_str())
+                .and_then(|host| host.strip_prefix("://"))
+                .and_then(|host| host.strip_suffix(suffix.as_str()))
+                .is_some_and(|sub| !sub.is_empty() && sub.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.')),
+        })
+    }
+}
+
+fn parse_pattern(entry: &str) -> Result<Pattern, &'static str> {
+    const EXPECTED: &str = "expected origins like https://example.com or https://*.example.com, or *";
+    if entry == "*" { return Ok(Pattern::Any); }
+    let entry = entry.trim_end_matches('/').to_ascii_lowercase();
+    let (scheme, host) = entry.split_once("://").ok_or(EXPECTED)?;
+    if !matches!(scheme, "http" | "https") || host.is_empty() || host.contains(['/', '?', '#', '@']) {
+        return Err(EXPECTED);
+    }
+    match host.strip_prefix('*') {
+        Some(suffix) if suffix.starts_with('.') && !suffix[1..].is_empty() && !suffix.contains('*') => {
+            Ok(Pattern::Subdomain { scheme: scheme.to_string(), suffix: suffix.to_string() })
+        }
+        Some(_) => Err(EXPECTED),
+        None if host.contains('*') => Err(EXPECTED),
+        None => Ok(Pattern::Exact(entry)),
+    }
+}
+
+/// The layer answering preflights and tagging responses for `origins`, or
+/// `None` when no other origin is allowed and responses need no CORS headers.
+pub fn layer(origins: &AllowedOrigins) -> Option<CorsLayer> {
+    if origins.0.is_empty() { return None; }
+    let origins = origins.clone();
+    Some(
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+                origin.to_str().is_ok_and(|origin| origins.allows(origin))
+            }))
+            .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+            .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION, HeaderName::from_static(REQUEST_ID)])
+            .expose_headers([HeaderName::from_static(REQUEST_ID), header::RETRY_AFTER]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_origins_match_whole() {
+        let origins = AllowedOrigins::parse("https://Zobbo.example/, http://localhost:3000").unwrap();
+        assert!(origins.allows("https://zobbo.example"));
+        assert!(origins.allows("HTTPS://ZOBBO.EXAMPLE"));
+        assert!(origins.allows("http://localhost:3000"));
+        assert!(!origins.allows("https://zobbo.example.evil.net"));
+        assert!(!origins.allows("https://www.zobbo.example"));
+    }
+
+    #[test]
+    fn wildcards_take_subdomains_at_any_depth() {
+        let origins = AllowedOrigins::parse("https://*.example.com").unwrap();
+        assert!(origins.allows("https://a.example.com"));
+        assert!(origins.allows("https://a.b-c.example.com"));
+        assert!(!origins.allows("https://example.com"));
+        assert!(!origins.allows("https://evilexample.com"));
+        assert!(!origins.allows("https://evil.com/.example.com"));
+        assert!(!origins.allows("https://a.example.com.evil.net"));
+    }
+
+    #[test]
+    fn ports_are_part_of_the_origin() {
+        let origins = AllowedOrigins::parse("https://zobbo.example:8443, https://*.example.com").unwrap();
+        assert!(origins.allows("https://zobbo.example:8443"));
+        assert!(!origins.allows("https://zobbo.example"));
+        assert!(!origins.allows("https://a.example.com:8443"));
+        let ported = AllowedOrigins::parse("https://*.example.com:8443").unwrap();
+        assert!(ported.allows("https://a.example.com:8443"));
+        assert!(!ported.allows("https://a.example.com"));
+    }
+
+    #[test]
+    fn schemes_must_match() {
+        let origins = AllowedOrigins::parse("https://zobbo.example, https://*.example.com").unwrap();
+        assert!(!origins.allows("http://zobbo.example"));
+        assert!(!origins.allows("http://a.example.com"));
+        assert!(!origins.allows("wss://a.example.com"));
+    }
+
+    #[test]
+    fn malformed_patterns_are_rejected() {
+        for entry in [
+            "zobbo.example",
+            "ftp://zobbo.example",
+            "https://",
+            "https://*",
+            "https://*.",
+            "https://*example.com",
+            "https://a.*.example.com",
+            "https://*.*.example.com",
+            "https://zobbo.example/path",
+            "https://zobbo.example?q",
+            "https://user@zobbo.example",
+        ] {
+            assert!(parse_pattern(entry).is_err(), "{entry}");
+        }
+        assert_eq!(parse_pattern("*"), Ok(Pattern::Any));
+        assert!(AllowedOrigins::parse("https://zobbo.example, nope").is_err());
+        assert!(AllowedOrigins::parse(" , ").unwrap().0.is_empty());
+    }
+}
@@ -0,0 +1,24 @@
+//! Health checks for the orchestrator: `/livez` says the process is up,
+//! `/readyz` whether it can take new games right now.
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+use crate::http::auth;
+use crate::http::routes::AppState;
+
+/// Always `ok` while the process serves HTTP at all.
+pub async fn livez() -> &'static str { "ok" }
+
+/// `ready`, or 503 listing every check that failed: `IDENTITY_SECRET` (outside
+/// dev mode), the store, maintenance mode and the room cap.
+pub async fn readyz(State(state): State<AppState>) -> Response {
+    let mut failing = Vec::new();
+    if !auth::secret_configured() { failing.push("IDENTITY_SECRET isn't set".to_string()); }
+    if !state.rooms.store_reachable().await { failing.push("store is unreachable".to_string()); }
+    if let Err(err) = state.rooms.accepting() { failing.push(err.to_string()); }
+    if failing.is_empty() { return "ready".into_response(); }
+    tracing::debug!(?failing, "not ready");
+    (StatusCode::SERVICE_UNAVAILABLE, format!("not ready: {}", failing.join("; "))).into_response()
+}
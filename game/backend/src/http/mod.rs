@@ -3,3 +3,12 @@
 // submodules
 pub mod routes;
 pub mod auth;
+pub mod names;
+pub mod openapi;
+pub mod admin;
+pub mod ratelimit;
+pub mod health;
+pub mod cors;
+pub mod assets;
+#[cfg(unix)]
+pub mod unix;
@@ -0,0 +1,152 @@
+//! Display names: cleaned up and checked before anyone else sees them.
+//!
+//! A name is NFKC-normalized, so look-alike forms (fullwidth letters,
+//! ligatures) collapse to plain ones; control and invisible formatting
+//! characters are stripped and runs of whitespace squeezed. Words on the
+//! optional `NAME_BLOCKLIST` are then masked or replaced.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use unicode_normalization::UnicodeNormalization;
+use utoipa::ToSchema;
+
+use crate::config;
+
+/// Longest display name, in characters.
+pub const MAX_NAME_CHARS: usize = 24;
+
+/// Why a name was refused; the `code` of the 422 body.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NameError {
+    #[error("names can't be empty")]
+    Empty,
+    #[error("names can be at most {MAX_NAME_CHARS} characters")]
+    TooLong,
+}
+
+/// The 422 body for a refused name.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct NameRejection {
+    pub code: NameError,
+    pub message: String,
+}
+
+impl From<NameError> for NameRejection {
+    fn from(code: NameError) -> Self {
+        NameRejection { code, message: code.to_string() }
+    }
+}
+
+/// `raw` as it'll be shown, or why it can't be used.
+pub fn clean_name(raw: &str) -> Result<String, NameError> {
+    clean_with(raw, blocklist())
+}
+
+/// `clean_name` against `list`. The length is checked on what the player
+/// typed, so a refusal never depends on the blocklist; a replacement that
+/// runs long is cut to fit.
+fn clean_with(raw: &str, list: &HashMap<String, Option<String>>) -> Result<String, NameError> {
+    // Tabs and newlines are whitespace, squeezed to a space below rather than dropped.
+    let visible: String = raw.nfkc().filter(|&c| (c.is_whitespace() || !c.is_control()) && !invisible(c)).collect();
+    let squeezed = visible.split_whitespace().collect::<Vec<_>>().join(" ");
+    match squeezed.chars().count() {
+        0 => return Err(NameError::Empty),
+        n if n > MAX_NAME_CHARS => return Err(NameError::TooLong),
+        _ => {}
+    }
+    let name: String = filter_words(&squeezed, list).chars().take(MAX_NAME_CHARS).collect();
+    Ok(name.trim_end().to_string())
+}
+
+/// Zero-width and bidi formatting characters, which can hide or reorder
+/// what a name seems to say.
+fn invisible(c: char) -> bool {
+    matches!(c, '\u{00ad}' | '\u{200b}'..='\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2060}'..='\u{2069}' | '\u{feff}')
+}
+
+/// Replace every whole word of `name` found in `list`, ignoring case: with
+/// its replacement, or with asterisks if it has none.
+fn filter_words(name: &str, list: &HashMap<String, Option<String>>) -> String {
+    if list.is_empty() { return name.to_string(); }
+    let mut out = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(start) = rest.find(char::is_alphanumeric) {
+        out.push_str(&rest[..start]);
+        let len = rest[start..].find(|c: char| !c.is_alphanumeric()).unwrap_or(rest.len() - start);
+        let word = &rest[start..start + len];
+        match list.get(&word.to_lowercase()) {
+            Some(Some(replacement)) => out.push_str(replacement),
+            Some(None) => out.push_str(&"*".repeat(word.chars().count())),
+            None => out.push_str(word),
+        }
+        rest = &rest[start + len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The words `NAME_BLOCKLIST` names, lower case, each with its replacement if
+/// it has one. Loaded once; a file that can't be read is logged and ignored.
+fn blocklist() -> &'static HashMap<String, Option<String>> {
+    static LIST: OnceLock<HashMap<String, Option<String>>> = OnceLock::new();
+    LIST.get_or_init(|| {
+        let Some(path) = &config::get().name_blocklist else { return HashMap::new() };
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                tracing::warn!(path = %path.display(), %err, "can't read the name blocklist; names won't be filtered");
+                return HashMap::new();
+            }
+        };
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| match line.split_once('=') {
+                // An empty replacement masks, so a name never filters down to nothing.
+                Some((word, replacement)) => {
+                    let replacement = Some(replacement.trim()).filter(|r| !r.is_empty());
+                    (word.trim().to_lowercase(), replacement.map(str::to_string))
+                }
+                None => (line.to_lowercase(), None),
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(entries: &[(&str, Option<&str>)]) -> HashMap<String, Option<String>> {
+        entries.iter().map(|&(word, replacement)| (word.to_string(), replacement.map(str::to_string))).collect()
+    }
+
+    #[test]
+    fn normalizes_and_squeezes() {
+        let none = HashMap::new();
+        assert_eq!(clean_with("  \u{ff21}da\u{200b}\tLove\n ", &none), Ok("Ada Love".to_string()));
+        assert_eq!(clean_with(" \u{200b}\t", &none), Err(NameError::Empty));
+        assert_eq!(clean_with(&"x".repeat(MAX_NAME_CHARS + 1), &none), Err(NameError::TooLong));
+    }
+
+    #[test]
+    fn filters_whole_words() {
+        let words = list(&[("darn", None), ("heck", Some("hoot"))]);
+        assert_eq!(clean_with("Darn it, HECK", &words), Ok("**** it, hoot".to_string()));
+        assert_eq!(clean_with("darnation", &words), Ok("darnation".to_string()));
+    }
+
+    #[test]
+    fn length_is_judged_before_filtering() {
+        let words = list(&[("x", Some("a much longer replacement"))]);
+        assert_eq!(clean_with("x", &words), Ok("a much longer replacement"[..MAX_NAME_CHARS].trim_end().to_string()));
+        let long = format!("{} x", "y".repeat(MAX_NAME_CHARS - 2));
+        assert_eq!(clean_with(&long, &words).map(|n| n.chars().count()), Ok(MAX_NAME_CHARS));
+        // A long name that filters down short is still too long.
+        let words = list(&[("darn", Some("d"))]);
+        assert_eq!(clean_with(&"darn ".repeat(6), &words), Err(NameError::TooLong));
+    }
+}
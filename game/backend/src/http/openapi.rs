@@ -0,0 +1,110 @@
+//! OpenAPI description of the HTTP endpoints, served at `/api/openapi.json`
+//! with a Swagger UI at `/api/docs`, plus a JSON Schema of the game
+//! protocol at `/api/protocol/schema`.
+
+use axum::routing::get;
+use axum::{Json, Router};
+use schemars::schema_for;
+use serde_json::{Value, json};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::http::admin;
+use crate::http::routes::{self, AppState};
+use crate::ws::{connection, protocol, replay};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Zobbo", description = "Room management and the game WebSocket upgrade.", license(name = "MIT")),
+    paths(
+        routes::create_room,
+        routes::join_room,
+        routes::add_bot,
+        routes::quickmatch,
+        routes::practice,
+        routes::tutorial,
+        routes::list_rooms,
+        routes::daily_challenge,
+        routes::list_presets,
+        routes::game_log,
+        routes::export_game,
+        routes::verify_game,
+        routes::replay_game,
+        routes::player_history,
+        routes::player_stats,
+        routes::identity,
+        routes::leaderboard,
+        replay::replay_stream,
+        routes::claim_room,
+        routes::join_by_code,
+        routes::ws_ticket,
+        connection::ws_handler,
+        protocol_schema,
+        admin::list_rooms,
+        admin::room,
+        admin::close_room,
+        admin::list_bans,
+        admin::add_ban,
+        admin::lift_ban,
+        admin::maintenance,
+        admin::set_maintenance,
+    ),
+    tags(
+        (name = "rooms", description = "Create, find and join rooms. The form posts answer with redirects."),
+        (name = "games", description = "Records of finished games."),
+        (name = "players", description = "Guest identities and what they've played."),
+        (name = "game", description = "The game WebSocket; see the `zobbo-protocol` crate for messages."),
+        (name = "admin", description = "Operator routes; only served with `ADMIN_TOKEN` set, which every call must present."),
+    ),
+)]
+pub struct ApiDoc;
+
+pub fn router() -> Router<AppState> {
+    Router::from(SwaggerUi::new("/api/docs").url("/api/openapi.json", ApiDoc::openapi()))
+        .route("/api/protocol/schema", get(protocol_schema))
+}
+
+/// JSON Schema for every WebSocket message each way and every HTTP payload,
+/// tagged with the `protocol_version` it describes.
+#[utoipa::path(
+    get,
+    path = "/api/protocol/schema",
+    tag = "game",
+    responses((status = 200, description = "`{ protocol_version, client, server, http }`", body = Object)),
+)]
+pub async fn protocol_schema() -> Json<Value> {
+    Json(schema())
+}
+
+fn schema() -> Value {
+    let http = ApiDoc::openapi().components.map(|c| c.schemas).unwrap_or_default();
+    json!({
+        "protocol_version": protocol::PROTOCOL_VERSION,
+        "client": schema_for!(protocol::ClientToServer),
+        "server": schema_for!(protocol::ServerToClient),
+        "http": http,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_schema_covers_both_directions_and_http() {
+        let schema = schema();
+        assert_eq!(schema["protocol_version"], protocol::PROTOCOL_VERSION);
+        let kinds = |side: &str| {
+            schema[side]["oneOf"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v["properties"]["type"]["const"].as_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+        assert!(kinds("client").contains(&"set_handicap".to_owned()));
+        assert!(kinds("server").contains(&"game_delta".to_owned()));
+        assert!(schema["server"]["$defs"]["GameDelta"]["properties"]["held_from"].is_object());
+        assert!(schema["http"]["PresetView"].is_object());
+    }
+}
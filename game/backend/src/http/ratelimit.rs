@@ -0,0 +1,167 @@
+//! Per-IP rate limits on the routes that create rooms, seat bots, hand out
+//! seats or replay uploaded games, so a single client can't fill
+//! `RoomManager`, guess room codes or keep the server busy verifying.
+//!
+//! Each limiter is a token bucket per client address: `burst` requests at
+//! once, refilled at `per_minute`. Over the limit, requests get 429 with
+//! `Retry-After`.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use dashmap::DashMap;
+
+use crate::util::net::client_ip;
+
+/// How often idle buckets are swept; one idle long enough to have refilled
+/// is the same as none.
+const SWEEP_EVERY: Duration = Duration::from_secs(60);
+
+/// How many requests a client may make: `burst` at once, then `per_minute`.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    at: Instant,
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    buckets: DashMap<IpAddr, Bucket>,
+    /// When idle buckets were last swept.
+    swept: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self { limit, buckets: DashMap::new(), swept: Mutex::new(Instant::now()) }
+    }
+
+    fn per_sec(&self) -> f64 {
+        f64::from(self.limit.per_minute) / 60.0
+    }
+
+    /// Take a token from `ip`'s bucket, or say how long until there is one.
+    fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        self.check_at(ip, Instant::now())
+    }
+
+    fn check_at(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let burst = f64::from(self.limit.burst);
+        self.sweep(now);
+        let mut bucket = self.buckets.entry(ip).or_insert(Bucket { tokens: burst, at: now });
+        bucket.tokens = (bucket.tokens + now.duration_since(bucket.at).as_secs_f64() * self.per_sec()).min(burst);
+        bucket.at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_sec()))
+    }
+
+    /// Drop the buckets not used for long enough to have refilled, at most
+    /// once per `SWEEP_EVERY`; a request that finds a sweep under way skips it.
+    fn sweep(&self, now: Instant) {
+        let Ok(mut swept) = self.swept.try_lock() else { return };
+        if now.duration_since(*swept) < SWEEP_EVERY { return; }
+        *swept = now;
+        let refill = Duration::from_secs_f64(f64::from(self.limit.burst) / self.per_sec());
+        self.buckets.retain(|_, b| now.duration_since(b.at) < refill);
+    }
+}
+
+/// Middleware: let the request through if the client has a token left.
+pub async fn limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let ip = client_ip(req.headers(), peer);
+    match limiter.check(ip) {
+        Ok(()) => next.run(req).await,
+        Err(wait) => {
+            tracing::debug!(%ip, path = %req.uri().path(), "rate limited");
+            too_many_requests(wait)
+        }
+    }
+}
+
+/// 429 with `Retry-After` in whole seconds, rounded up and at least 1.
+fn too_many_requests(wait: Duration) -> Response {
+    let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+    (StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, retry_after.to_string())], "too many requests; slow down").into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IP: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 1));
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimit { burst: 3, per_minute: 30 })
+    }
+
+    #[test]
+    fn a_burst_then_one_every_refill() {
+        let limiter = limiter();
+        let t0 = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(limiter.check_at(IP, t0), Ok(()));
+        }
+        // 30 a minute is one every 2 seconds.
+        assert_eq!(limiter.check_at(IP, t0), Err(Duration::from_secs(2)));
+        assert_eq!(limiter.check_at(IP, t0 + Duration::from_millis(500)), Err(Duration::from_millis(1500)));
+        assert_eq!(limiter.check_at(IP, t0 + Duration::from_secs(2)), Ok(()));
+        assert!(limiter.check_at(IP, t0 + Duration::from_secs(2)).is_err());
+        // Other addresses have their own buckets.
+        assert_eq!(limiter.check_at(IpAddr::V4(std::net::Ipv4Addr::LOCALHOST), t0), Ok(()));
+    }
+
+    #[test]
+    fn a_bucket_refills_no_further_than_the_burst() {
+        let limiter = limiter();
+        let t0 = Instant::now();
+        assert_eq!(limiter.check_at(IP, t0), Ok(()));
+        let later = t0 + Duration::from_secs(600);
+        for _ in 0..3 {
+            assert_eq!(limiter.check_at(IP, later), Ok(()));
+        }
+        assert!(limiter.check_at(IP, later).is_err());
+    }
+
+    #[test]
+    fn retry_after_rounds_up_to_whole_seconds() {
+        let retry_after = |wait| too_many_requests(wait).headers()[header::RETRY_AFTER].to_str().unwrap().to_owned();
+        assert_eq!(too_many_requests(Duration::from_secs(2)).status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(retry_after(Duration::from_millis(1500)), "2");
+        assert_eq!(retry_after(Duration::from_secs(2)), "2");
+        assert_eq!(retry_after(Duration::from_millis(10)), "1");
+    }
+
+    #[test]
+    fn idle_buckets_are_swept_once_a_minute() {
+        let limiter = limiter();
+        let t0 = *limiter.swept.lock().unwrap();
+        let other = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+        limiter.check_at(IP, t0).unwrap();
+        // Refilled after 6 seconds, but not swept until a minute is up.
+        limiter.check_at(other, t0 + Duration::from_secs(10)).unwrap();
+        assert_eq!(limiter.buckets.len(), 2);
+        limiter.check_at(other, t0 + SWEEP_EVERY).unwrap();
+        assert!(!limiter.buckets.contains_key(&IP));
+        assert!(limiter.buckets.contains_key(&other));
+    }
+}
@@ -1,49 +1,968 @@
 //! HTTP routes: lobby, create/join room, health, template rendering endpoints.
 
 use askama::Template;
-use axum::{extract::{Path, Query, State}, response::{IntoResponse, Redirect}, Form};
-use serde::Deserialize;
-use axum::http::StatusCode;
+use axum::{extract::{ConnectInfo, Path, Query, State}, response::{IntoResponse, Redirect, Response}, Form, Json};
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Deserializer, Serialize};
+use axum::http::{header, HeaderMap, StatusCode};
+use utoipa::{IntoParams, ToSchema};
+use std::net::SocketAddr;
 use std::sync::Arc;
+use zobbo_core::log::GameExport;
 
-use crate::room::manager::{RoomError, RoomManager};
+use crate::http::auth::{Identity, IdentityError};
+use crate::http::names::NameRejection;
+use crate::room::archive::GameRecord;
+use crate::room::bans::Ban;
+use crate::room::bot;
+use crate::room::daily::{self, DailyScore};
+use crate::room::history::{GameSummary, PlayerStats};
+use crate::config;
+use crate::room::manager::{RoomError, RoomManager, MAX_SEATS};
+use crate::room::matchmaking::Quickmatch;
+use crate::room::replay::{self, ReplayFrame};
+use crate::room::room::{self, parse_power_table, RoomSettings, RulePreset, TimingPreset};
+use crate::room::tutorial;
+use crate::ws::protocol::{CardPublic, GameMode, RuleConfig};
+use crate::util::net::client_ip;
+use crate::ws::tap::WireTap;
+use crate::ws::ticket::{WsTickets, TICKET_TTL};
+use tokio_util::task::TaskTracker;
+use crate::reporting::{self, Context};
+#[cfg(feature = "fault-injection")]
+use crate::ws::faults::FaultInjector;
 
 #[derive(Clone)]
 pub struct AppState {
     pub rooms: Arc<RoomManager>,
+    pub taps: Arc<WireTap>,
+    pub quickmatch: Arc<Quickmatch>,
+    /// `/ws` tickets issued and not yet used.
+    pub tickets: Arc<WsTickets>,
+    /// Every live game socket and its writer, so a shutdown can wait for them to close.
+    pub sockets: TaskTracker,
+    #[cfg(feature = "fault-injection")]
+    pub faults: Arc<FaultInjector>,
 }
 
 #[derive(Template)]
 #[template(path = "room.html")]
 struct RoomTemplate {
     room_id: String,
+    room_code: String,
     has_invite: bool,
-    invite_token: String,
+    /// `(seat, token)` for every other human seat.
+    invites: Vec<(usize, String)>,
     viewer_token: String,
 }
 
-pub async fn create_room(State(state): State<AppState>) -> impl IntoResponse {
-    let created = state.rooms.create_room();
-    tracing::debug!(room_id = %created.id, creator = %created.creator_token, invite = %created.invite_token, "created room");
+#[derive(Deserialize, ToSchema)]
+pub struct CreateRoomForm {
+    /// Start from a named preset's rules and pace (see `GET /api/presets`); the
+    /// fields below still override it. Absent or empty for the standard rules.
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub rule_preset: Option<RulePreset>,
+    /// More than one round makes the room a `ZobboBattle`, or a `BestOf` with `best_of`.
+    #[schema(minimum = 1, maximum = 10, default = 1)]
+    pub rounds: Option<u8>,
+    /// Win the match on rounds won instead of total points.
+    #[schema(default = false)]
+    pub best_of: Option<bool>,
+    /// Play until someone's total reaches this many points, ignoring `rounds`; absent or 0 for none.
+    #[schema(maximum = 500, default = 0, example = 100)]
+    pub race_to: Option<i32>,
+    /// Cards dealt to each seat.
+    #[schema(minimum = 2, maximum = 8, default = 6)]
+    pub hand_size: Option<usize>,
+    /// Cards each player sees before the first turn, from the end of their hand.
+    #[schema(default = 3)]
+    pub initial_peeks: Option<usize>,
+    /// Whether a turn may start by taking the discard.
+    #[schema(default = true)]
+    pub discard_draw: Option<bool>,
+    /// Whether 5–K discarded from the deck grant their powers; off for a casual game.
+    #[schema(default = true)]
+    pub powers: Option<bool>,
+    /// Add a red and a black joker, worth −1 each, to the deck.
+    #[schema(default = false)]
+    pub jokers: Option<bool>,
+    /// Deal a penalty card from the deck to anyone who tries a wrong match.
+    #[schema(default = false)]
+    pub match_penalty_draw: Option<bool>,
+    /// Points added to a Zobbo caller who doesn't have the strictly lowest hand; 0 for none.
+    #[schema(maximum = 50, default = 10)]
+    pub zobbo_penalty: Option<i32>,
+    /// Which ranks grant which power, as `rank=power` pairs (`Joker` included); ranks left out
+    /// grant none. Absent or empty for the standard table.
+    #[schema(example = "7=peek_own,8=peek_own,9=peek_opp,10=peek_opp,J=blind_swap")]
+    pub power_table: Option<String>,
+    /// Sets the turn timer, time bank and reconnect grace period together; `turn_secs` and
+    /// `time_bank_secs` still override it. Absent or empty for untimed play.
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub preset: Option<TimingPreset>,
+    /// Per-turn time limit; absent or 0 for none.
+    #[schema(maximum = 600, default = 0)]
+    pub turn_secs: Option<u64>,
+    /// Each player's thinking time for a whole game, run like a chess clock; running out
+    /// loses the game. Absent or 0 for none.
+    #[schema(maximum = 3600, default = 0, example = 300)]
+    pub time_bank_secs: Option<u64>,
+    /// Deal today's daily challenge, with the standard rules whatever the fields above say.
+    #[schema(default = false)]
+    pub daily: Option<bool>,
+    /// Rate the players on every game. Everyone needs a guest identity, so bots can't sit in.
+    #[schema(default = false)]
+    pub ranked: Option<bool>,
+    /// Seats in the room, counting the creator's.
+    #[schema(minimum = 2, maximum = 4, default = 2)]
+    pub players: Option<usize>,
+    /// List the room in `GET /api/rooms` so strangers can join.
+    #[serde(default)]
+    pub public: bool,
+}
+
+/// An empty form field, like a select's "none" option, as `None`.
+fn empty_as_none<'de, D: Deserializer<'de>, T: Deserialize<'de>>(d: D) -> Result<Option<T>, D::Error> {
+    match Option::<String>::deserialize(d)?.filter(|s| !s.is_empty()) {
+        Some(s) => T::deserialize(IntoDeserializer::<D::Error>::into_deserializer(s)).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Create a room and redirect to its page, seated as player 1.
+#[utoipa::path(
+    post,
+    path = "/rooms",
+    tag = "rooms",
+    request_body(content = Option<CreateRoomForm>, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Created; `Location` is `/rooms/{id}/view?token={creator_token}`"),
+        (status = 400, description = "`players`, a rule, `turn_secs` or `time_bank_secs` out of range"),
+        (status = 403, description = "The caller's address is banned"),
+        (status = 429, description = "Too many rooms created from this address; see `Retry-After`"),
+        (status = 503, description = "The server is in maintenance mode or at capacity"),
+    ),
+)]
+pub async fn create_room(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    form: Option<Form<CreateRoomForm>>,
+) -> impl IntoResponse {
+    if let Some(ban) = state.rooms.banned(None, client_ip(&headers, peer)) {
+        return banned(&ban);
+    }
+    let form = form.map(|Form(f)| f);
+    let rule_preset = form.as_ref().and_then(|f| f.rule_preset);
+    let standard = rule_preset.map_or_else(RuleConfig::default, RulePreset::rules);
+    let power_table = match form.as_ref().and_then(|f| f.power_table.as_deref()).filter(|s| !s.trim().is_empty()) {
+        Some(spec) => match parse_power_table(spec) {
+            Ok(table) => table,
+            Err(err) => return (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+        },
+        None => standard.power_table,
+    };
+    let rules = match &form {
+        Some(f) => RuleConfig {
+            hand_size: f.hand_size.unwrap_or(standard.hand_size),
+            initial_peeks: f.initial_peeks.unwrap_or(standard.initial_peeks),
+            rounds: f.rounds.unwrap_or(standard.rounds),
+            best_of: f.best_of.unwrap_or(standard.best_of),
+            // 0 turns a preset's race off.
+            race_to: f.race_to.map_or(standard.race_to, |points| Some(points).filter(|p| *p != 0)),
+            discard_draw: f.discard_draw.unwrap_or(standard.discard_draw),
+            powers: f.powers.unwrap_or(standard.powers),
+            jokers: f.jokers.unwrap_or(standard.jokers),
+            match_penalty_draw: f.match_penalty_draw.unwrap_or(standard.match_penalty_draw),
+            zobbo_penalty: f.zobbo_penalty.unwrap_or(standard.zobbo_penalty),
+            power_table,
+            // Set from the lobby, once the host can see who's playing.
+            handicap: None,
+        },
+        None => standard,
+    };
+    let preset = form.as_ref().and_then(|f| f.preset).or(rule_preset.and_then(RulePreset::timing));
+    let turn_secs = form.as_ref().and_then(|f| f.turn_secs).or(preset.map(TimingPreset::turn_secs)).unwrap_or(0);
+    let bank_secs = form.as_ref().and_then(|f| f.time_bank_secs).or(preset.map(TimingPreset::bank_secs)).unwrap_or(0);
+    // Everyone playing the challenge gets the same game, so house rules don't apply.
+    let daily = form.as_ref().and_then(|f| f.daily).unwrap_or(false).then(daily::today);
+    let rules = if daily.is_some() { RuleConfig::default() } else { rules };
+    let ranked = form.as_ref().and_then(|f| f.ranked).unwrap_or(false);
+    let settings = match RoomSettings::new(rules, turn_secs, bank_secs) {
+        Ok(settings) => RoomSettings { grace: preset.map(TimingPreset::grace), daily, ranked, ..settings },
+        Err(err) => return (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+    };
+    let seats = form.as_ref().and_then(|f| f.players).unwrap_or(2);
+    if !(2..=MAX_SEATS).contains(&seats) {
+        return (StatusCode::BAD_REQUEST, format!("players must be between 2 and {MAX_SEATS}")).into_response();
+    }
+    let public = form.is_some_and(|f| f.public);
+    let created = match state.rooms.create_room(settings, seats, public) {
+        Ok(created) => created,
+        Err(err) => return room_error(err),
+    };
+    tracing::debug!(room_id = %created.id, code = %created.code, creator = %created.creator_token, invites = ?created.invite_tokens, "created room");
     let redirect_to = format!("/rooms/{}/view?token={}", created.id, created.creator_token);
-    Redirect::to(&redirect_to)
+    Redirect::to(&redirect_to).into_response()
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct JoinForm {
+    /// A seat token for the room, e.g. the invite token shown to its creator.
     pub token: String,
 }
 
+/// Check a seat token and redirect to the room page for that seat.
+#[utoipa::path(
+    post,
+    path = "/rooms/{id}/join",
+    tag = "rooms",
+    params(("id" = String, Path, description = "Room id")),
+    request_body(content = JoinForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Joined; `Location` is `/rooms/{id}/view?token={token}`"),
+        (status = 401, description = "Token is not one of the room's"),
+        (status = 403, description = "The caller's address is banned"),
+        (status = 404, description = "No such room"),
+        (status = 409, description = "Room full"),
+        (status = 429, description = "Too many joins from this address; see `Retry-After`"),
+    ),
+)]
 pub async fn join_room(
     Path(id): Path<String>,
     State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Form(JoinForm { token }): Form<JoinForm>,
 ) -> impl IntoResponse {
+    if let Some(ban) = state.rooms.banned(None, client_ip(&headers, peer)) {
+        return banned(&ban);
+    }
     match state.rooms.join_room(&id, &token) {
         Ok(()) => Redirect::to(&format!("/rooms/{}/view?token={}", id, token)).into_response(),
-        Err(RoomError::NotFound) => (StatusCode::NOT_FOUND, "room not found").into_response(),
-        Err(RoomError::InvalidToken) => (StatusCode::UNAUTHORIZED, "invalid token").into_response(),
-        Err(RoomError::Full) => (StatusCode::CONFLICT, "room full").into_response(),
+        Err(err) => room_error(err),
+    }
+}
+
+/// Seat a computer opponent in the room opposite the caller's `token`.
+#[utoipa::path(
+    post,
+    path = "/rooms/{id}/add_bot",
+    tag = "rooms",
+    params(("id" = String, Path, description = "Room id")),
+    request_body(content = JoinForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Bot seated; `Location` is the caller's room page"),
+        (status = 401, description = "Token is not one of the room's"),
+        (status = 404, description = "No such room"),
+        (status = 409, description = "No free seat, the room already has a bot, or it's ranked"),
+        (status = 429, description = "Too many rooms or bots created from this address; see `Retry-After`"),
+    ),
+)]
+pub async fn add_bot(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    Form(JoinForm { token }): Form<JoinForm>,
+) -> impl IntoResponse {
+    match state.rooms.add_bot(&id, &token) {
+        Ok((seat, handle)) => {
+            tracing::debug!(room_id = %id, seat, "bot added");
+            bot::spawn(id.clone(), seat, state.taps.conn_id(), handle);
+            Redirect::to(&format!("/rooms/{}/view?token={}", id, token)).into_response()
+        }
+        Err(err) => room_error(err),
+    }
+}
+
+/// A seat handed out by the API; open `url`, or swap `token` for a ticket at
+/// `POST /api/rooms/{id}/ws-ticket` and connect to `/ws`.
+#[derive(Serialize, ToSchema)]
+pub struct SeatGrant {
+    pub room_id: String,
+    pub token: String,
+    pub url: String,
+}
+
+impl SeatGrant {
+    fn new(room_id: String, token: String) -> Self {
+        let url = format!("/rooms/{}/view?token={}", room_id, token);
+        Self { room_id, token, url }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum QuickmatchResponse {
+    /// Seated in a new room.
+    Matched(SeatGrant),
+    /// Nobody turned up in time; post again to keep your place.
+    Waiting,
+}
+
+/// Wait to be paired with another player in a new room.
+///
+/// A long poll: answers as soon as an opponent arrives, or with `waiting`
+/// after `QUICKMATCH_WAIT_SECS`. Dropping the request leaves the queue.
+#[utoipa::path(
+    post,
+    path = "/api/quickmatch",
+    tag = "rooms",
+    responses(
+        (status = 200, body = QuickmatchResponse),
+        (status = 403, description = "The caller's address is banned"),
+        (status = 429, description = "Too many rooms created from this address; see `Retry-After`"),
+        (status = 503, description = "The server is in maintenance mode or at capacity"),
+    ),
+)]
+pub async fn quickmatch(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(ban) = state.rooms.banned(None, client_ip(&headers, peer)) {
+        return banned(&ban);
+    }
+    if let Err(err) = state.rooms.accepting() { return room_error(err); }
+    Json(match state.quickmatch.join(&state.rooms, config::get().quickmatch_wait).await {
+        Some(m) => QuickmatchResponse::Matched(SeatGrant::new(m.room_id, m.token)),
+        None => QuickmatchResponse::Waiting,
+    })
+    .into_response()
+}
+
+/// Start a practice game: a new private room with the standard rules and a
+/// bot already seated opposite the caller.
+#[utoipa::path(
+    post,
+    path = "/api/practice",
+    tag = "rooms",
+    responses(
+        (status = 200, body = SeatGrant),
+        (status = 403, description = "The caller's address is banned"),
+        (status = 429, description = "Too many rooms created from this address; see `Retry-After`"),
+        (status = 503, description = "The server is in maintenance mode or at capacity"),
+    ),
+)]
+pub async fn practice(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(ban) = state.rooms.banned(None, client_ip(&headers, peer)) {
+        return banned(&ban);
+    }
+    let settings = RoomSettings::new(RuleConfig::default(), 0, 0).expect("the standard rules are valid");
+    let created = match state.rooms.create_room(settings, 2, false) {
+        Ok(created) => created,
+        Err(err) => return room_error(err),
+    };
+    match state.rooms.add_bot(&created.id, &created.creator_token) {
+        Ok((seat, handle)) => {
+            tracing::debug!(room_id = %created.id, seat, "practice room created");
+            bot::spawn(created.id.clone(), seat, state.taps.conn_id(), handle);
+            Json(SeatGrant::new(created.id, created.creator_token)).into_response()
+        }
+        Err(err) => room_error(err),
+    }
+}
+
+/// Start the tutorial: a private room dealing the scripted game, with the
+/// caller in the student's seat and the server playing the other.
+#[utoipa::path(
+    post,
+    path = "/api/tutorial",
+    tag = "rooms",
+    responses(
+        (status = 200, body = SeatGrant),
+        (status = 403, description = "The caller's address is banned"),
+        (status = 429, description = "Too many rooms created from this address; see `Retry-After`"),
+        (status = 503, description = "The server is in maintenance mode or at capacity"),
+    ),
+)]
+pub async fn tutorial(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(ban) = state.rooms.banned(None, client_ip(&headers, peer)) {
+        return banned(&ban);
+    }
+    let settings = RoomSettings::new(tutorial::rules(), 0, 0).expect("the tutorial's rules are valid");
+    match state.rooms.create_room(RoomSettings { tutorial: true, ..settings }, 2, false) {
+        Ok(created) => {
+            tracing::debug!(room_id = %created.id, "tutorial room created");
+            Json(SeatGrant::new(created.id, created.creator_token)).into_response()
+        }
+        Err(err) => room_error(err),
+    }
+}
+
+#[derive(Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomStatus {
+    /// Public rooms waiting for a second player.
+    #[default]
+    Waiting,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListRoomsQuery {
+    #[serde(default)]
+    #[param(inline)]
+    pub status: RoomStatus,
+}
+
+/// A public room in `GET /api/rooms`.
+#[derive(Serialize, ToSchema)]
+pub struct OpenRoomView {
+    pub id: String,
+    /// `{"kind": "single"}`, or `{"kind": "zobbo_battle" | "best_of", "rounds": n}`.
+    #[schema(value_type = Object)]
+    pub mode: GameMode,
+    /// Per-turn time limit, if any.
+    pub turn_secs: Option<u64>,
+    /// Players connected right now.
+    pub players: usize,
+    /// Seats in the room, taken or not.
+    pub capacity: usize,
+    pub age_secs: u64,
+}
+
+/// List public rooms, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/rooms",
+    tag = "rooms",
+    params(ListRoomsQuery),
+    responses((status = 200, body = Vec<OpenRoomView>)),
+)]
+pub async fn list_rooms(
+    State(state): State<AppState>,
+    Query(ListRoomsQuery { status: RoomStatus::Waiting }): Query<ListRoomsQuery>,
+) -> Json<Vec<OpenRoomView>> {
+    let rooms = state.rooms.open_rooms().into_iter().map(|r| OpenRoomView {
+        id: r.id,
+        mode: r.mode,
+        turn_secs: r.turn_secs,
+        players: r.players,
+        capacity: r.capacity,
+        age_secs: r.age_secs,
+    });
+    Json(rooms.collect())
+}
+
+/// Today's daily challenge and its best scores so far.
+#[derive(Serialize, ToSchema)]
+pub struct DailyView {
+    /// The UTC date, `YYYY-MM-DD`; every challenge room created today deals the same game.
+    pub seed_id: String,
+    /// Lowest scores first.
+    pub leaderboard: Vec<DailyScore>,
+}
+
+/// A rule preset, as `GET /api/presets` lists it.
+#[derive(Serialize, ToSchema)]
+pub struct PresetView {
+    /// What to send as `rule_preset` when creating a room.
+    pub id: RulePreset,
+    pub name: &'static str,
+    #[schema(value_type = Object)]
+    pub rules: RuleConfig,
+    /// The pace it sets, or `None` for untimed play.
+    pub pace: Option<TimingPreset>,
+}
+
+/// The named rule presets a room can be created with.
+#[utoipa::path(
+    get,
+    path = "/api/presets",
+    tag = "rooms",
+    responses((status = 200, body = Vec<PresetView>)),
+)]
+pub async fn list_presets() -> Json<Vec<PresetView>> {
+    Json(
+        RulePreset::ALL
+            .into_iter()
+            .map(|id| PresetView { id, name: id.name(), rules: id.rules(), pace: id.timing() })
+            .collect(),
+    )
+}
+
+/// How many scores `GET /api/daily` lists.
+const DAILY_LEADERBOARD: usize = 20;
+
+/// Today's daily challenge. Create a room with `daily=true` to play it.
+#[utoipa::path(
+    get,
+    path = "/api/daily",
+    tag = "rooms",
+    responses((status = 200, body = DailyView)),
+)]
+pub async fn daily_challenge(State(state): State<AppState>) -> Json<DailyView> {
+    let day = daily::today();
+    Json(DailyView { seed_id: daily::seed_id(day), leaderboard: state.rooms.daily().top(day, DAILY_LEADERBOARD) })
+}
+
+/// The full log of a finished game, hidden cards included.
+#[utoipa::path(
+    get,
+    path = "/api/games/{game_id}/log",
+    tag = "games",
+    params(("game_id" = String, Path, description = "Game id, from `GameStart`")),
+    responses(
+        (status = 200, body = GameRecord),
+        (status = 404, description = "No such game, it isn't over yet, or it's too old to be kept"),
+    ),
+)]
+pub async fn game_log(Path(game_id): Path<String>, State(state): State<AppState>) -> Response {
+    match state.rooms.archive().get(&game_id) {
+        Some(record) => Json(record.as_ref()).into_response(),
+        None => (StatusCode::NOT_FOUND, "game not found").into_response(),
+    }
+}
+
+/// Most entries a paged endpoint returns at once.
+const PAGE_MAX: usize = 100;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HistoryQuery {
+    /// Newest games to skip.
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default = "default_page")]
+    #[param(minimum = 1, maximum = 100, default = 20)]
+    pub limit: usize,
+}
+
+fn default_page() -> usize {
+    20
+}
+
+/// The 400 for a page `limit` out of range, if it is.
+fn bad_page(limit: usize) -> Option<Response> {
+    (!(1..=PAGE_MAX).contains(&limit))
+        .then(|| (StatusCode::BAD_REQUEST, format!("limit must be between 1 and {PAGE_MAX}")).into_response())
+}
+
+/// A page of a player's match history.
+#[derive(Serialize, ToSchema)]
+pub struct HistoryPage {
+    /// Newest first.
+    pub games: Vec<GameSummary>,
+    /// The `offset` of the next page; absent on the last one.
+    pub next_offset: Option<usize>,
+}
+
+/// A player's finished games, newest first. Only the most recent 200 are kept.
+#[utoipa::path(
+    get,
+    path = "/api/player/{id}/history",
+    tag = "players",
+    params(("id" = String, Path, description = "A `player_id` from `POST /api/identity`"), HistoryQuery),
+    responses(
+        (status = 200, body = HistoryPage, description = "Empty for a player with no finished games"),
+        (status = 400, description = "`limit` out of range"),
+    ),
+)]
+pub async fn player_history(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    Query(HistoryQuery { offset, limit }): Query<HistoryQuery>,
+) -> Response {
+    if let Some(response) = bad_page(limit) { return response; }
+    let (games, next_offset) = state.rooms.history().page(&id, offset, limit);
+    Json(HistoryPage { games, next_offset }).into_response()
+}
+
+/// A player's win rate, average score, Zobbo calls and powers, over the
+/// games their history keeps (the most recent 200).
+#[utoipa::path(
+    get,
+    path = "/api/player/{id}/stats",
+    tag = "players",
+    params(("id" = String, Path, description = "A `player_id` from `POST /api/identity`")),
+    responses((status = 200, body = PlayerStats, description = "All zeros for a player with no finished games")),
+)]
+pub async fn player_stats(Path(id): Path<String>, State(state): State<AppState>) -> Json<PlayerStats> {
+    Json(state.rooms.history().stats(&id))
+}
+
+/// Which leaderboard `GET /api/leaderboard` shows.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    /// Everyone's best daily challenge score from the last seven days, lowest first.
+    Weekly,
+    /// Ratings from ranked games, highest first.
+    #[default]
+    Alltime,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LeaderboardQuery {
+    #[serde(default)]
+    #[param(inline)]
+    pub period: Period,
+    /// Places to skip.
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default = "default_page")]
+    #[param(minimum = 1, maximum = 100, default = 20)]
+    pub limit: usize,
+}
+
+/// A player's place on a leaderboard.
+#[derive(Serialize, ToSchema)]
+pub struct LeaderboardEntry {
+    /// From 1.
+    pub place: usize,
+    pub player_id: String,
+    pub name: String,
+    /// `alltime`: the player's rating. `weekly`: their best daily challenge score.
+    pub score: i32,
+    /// `alltime`: ranked games played. `weekly`: daily challenges finished.
+    pub games: u32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Leaderboard {
+    pub period: Period,
+    pub players: Vec<LeaderboardEntry>,
+    /// The `offset` of the next page; absent on the last one.
+    pub next_offset: Option<usize>,
+}
+
+/// The top players, by rating or by this week's daily challenges. Only
+/// players with a guest identity are listed.
+#[utoipa::path(
+    get,
+    path = "/api/leaderboard",
+    tag = "players",
+    params(LeaderboardQuery),
+    responses(
+        (status = 200, body = Leaderboard),
+        (status = 400, description = "`limit` out of range"),
+    ),
+)]
+pub async fn leaderboard(
+    State(state): State<AppState>,
+    Query(LeaderboardQuery { period, offset, limit }): Query<LeaderboardQuery>,
+) -> Response {
+    if let Some(response) = bad_page(limit) { return response; }
+    let entries: Vec<(String, String, i32, u32)> = match period {
+        Period::Weekly => {
+            let best = state.rooms.daily().weekly(daily::today());
+            best.into_iter().map(|b| (b.player_id, b.name, b.score, b.games)).collect()
+        }
+        Period::Alltime => state
+            .rooms
+            .ratings()
+            .ranked()
+            .into_iter()
+            .map(|(id, r)| (id, r.name, r.rating.round() as i32, r.games))
+            .collect(),
+    };
+    let total = entries.len();
+    let players: Vec<_> = entries
+        .into_iter()
+        .enumerate()
+        .skip(offset)
+        .take(limit)
+        .map(|(i, (player_id, name, score, games))| LeaderboardEntry { place: i + 1, player_id, name, score, games })
+        .collect();
+    let next = offset + players.len();
+    Json(Leaderboard { period, players, next_offset: (next < total).then_some(next) }).into_response()
+}
+
+/// A finished game rebuilt from its log, one frame per move.
+#[derive(Serialize, ToSchema)]
+pub struct ReplayView {
+    pub game_id: String,
+    pub frames: Vec<ReplayFrame>,
+}
+
+/// Every position of a finished game, hands face up, for reviewing it move by move.
+/// To watch it play out instead, open `/api/replay/{game_id}/stream`.
+#[utoipa::path(
+    get,
+    path = "/api/replay/{game_id}",
+    tag = "games",
+    params(("game_id" = String, Path, description = "Game id, from `GameStart`")),
+    responses(
+        (status = 200, body = ReplayView),
+        (status = 404, description = "No such game, it isn't over yet, or it's too old to be kept"),
+    ),
+)]
+pub async fn replay_game(Path(game_id): Path<String>, State(state): State<AppState>) -> Response {
+    let Some(record) = state.rooms.archive().get(&game_id) else {
+        return (StatusCode::NOT_FOUND, "game not found").into_response();
+    };
+    match replay::frames(&record) {
+        Ok(frames) => Json(ReplayView { game_id, frames }).into_response(),
+        Err(err) => {
+            reporting::internal_error(&Context::new("replay").game(&game_id), &err);
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
+    }
+}
+
+/// Download a finished game as a `GameExport` from the `zobbo-core` crate: its seed,
+/// rules and moves, enough to rebuild and check it with `POST /api/games/verify`.
+#[utoipa::path(
+    get,
+    path = "/api/games/{game_id}/export",
+    tag = "games",
+    params(("game_id" = String, Path, description = "Game id, from `GameStart`")),
+    responses(
+        (status = 200, description = "The export, as a JSON attachment", content_type = "application/json"),
+        (status = 404, description = "No such game, it isn't over yet, or it's too old to be kept"),
+    ),
+)]
+pub async fn export_game(Path(game_id): Path<String>, State(state): State<AppState>) -> Response {
+    let Some(record) = state.rooms.archive().get(&game_id) else {
+        return (StatusCode::NOT_FOUND, "game not found").into_response();
+    };
+    match GameExport::from_log(&record.log, record.result.clone()) {
+        Ok(export) => {
+            let disposition = format!("attachment; filename=\"zobbo-{game_id}.json\"");
+            ([(header::CONTENT_DISPOSITION, disposition)], Json(export)).into_response()
+        }
+        Err(err) => {
+            reporting::internal_error(&Context::new("export").game(&game_id), &err);
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
+    }
+}
+
+/// A game rebuilt from an export.
+#[derive(Serialize, ToSchema)]
+pub struct VerifiedGame {
+    pub winner: Option<usize>,
+    pub scores: Vec<i32>,
+    /// Every seat's cards at the end.
+    #[schema(value_type = Vec<Vec<Object>>)]
+    pub hands: Vec<Vec<Option<CardPublic>>>,
+}
+
+/// Rebuild a game from a `GET /api/games/{game_id}/export` download, checking
+/// every move is legal and the game ends with the result it claims.
+#[utoipa::path(
+    post,
+    path = "/api/games/verify",
+    tag = "games",
+    request_body(content = Object, description = "A `GameExport`", content_type = "application/json"),
+    responses(
+        (status = 200, body = VerifiedGame),
+        (status = 422, description = "The export doesn't play out as it claims"),
+        (status = 429, description = "Too many requests from this address; see `Retry-After`"),
+    ),
+)]
+pub async fn verify_game(Json(export): Json<GameExport>) -> Response {
+    // Checked before dealing: the engine trusts its rules.
+    if !(2..=MAX_SEATS).contains(&export.players) {
+        return (StatusCode::UNPROCESSABLE_ENTITY, format!("players must be between 2 and {MAX_SEATS}")).into_response();
+    }
+    if let Err(err) = RoomSettings::new(export.rules, 0, 0) {
+        return (StatusCode::UNPROCESSABLE_ENTITY, err.to_string()).into_response();
+    }
+    match export.verify() {
+        Ok(game) => {
+            let hands = room::hands(&game);
+            let (winner, scores) = game.result.map(|r| (r.winner, r.scores)).unwrap_or_default();
+            Json(VerifiedGame { winner, scores, hands }).into_response()
+        }
+        Err(err) => (StatusCode::UNPROCESSABLE_ENTITY, err.to_string()).into_response(),
+    }
+}
+
+/// Take a free seat in a public room.
+#[utoipa::path(
+    post,
+    path = "/api/rooms/{id}/claim",
+    tag = "rooms",
+    params(("id" = String, Path, description = "Room id")),
+    responses(
+        (status = 200, body = SeatGrant),
+        (status = 403, description = "The caller's address is banned"),
+        (status = 404, description = "No such public room"),
+        (status = 409, description = "Someone else got the seat first"),
+        (status = 429, description = "Too many joins from this address; see `Retry-After`"),
+    ),
+)]
+pub async fn claim_room(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(ban) = state.rooms.banned(None, client_ip(&headers, peer)) {
+        return banned(&ban);
+    }
+    match state.rooms.claim_public_seat(&id) {
+        Ok(token) => Json(SeatGrant::new(id, token)).into_response(),
+        Err(err) => room_error(err),
+    }
+}
+
+/// Seat tokens to swap for a `/ws` ticket: `token` or `reconnect`.
+#[derive(Deserialize, ToSchema)]
+pub struct WsTicketRequest {
+    /// The seat's join token.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// A reconnect token from `Welcome` or `ReconnectToken`; used up here.
+    #[serde(default)]
+    pub reconnect: Option<String>,
+    /// The player's identity token from `POST /api/identity`, so the room
+    /// knows who's in the seat. Optional.
+    #[serde(default)]
+    pub identity: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct WsTicket {
+    /// Connect to `/ws` with `?room_id=` and `?ticket=` this; it opens one socket.
+    pub ticket: String,
+    pub expires_in_secs: u64,
+}
+
+/// Swap seat tokens for a one-time ticket to open the game WebSocket with,
+/// so the tokens themselves stay out of the socket's URL.
+#[utoipa::path(
+    post,
+    path = "/api/rooms/{id}/ws-ticket",
+    tag = "game",
+    params(("id" = String, Path, description = "Room id")),
+    request_body = WsTicketRequest,
+    responses(
+        (status = 200, body = WsTicket),
+        (status = 401, description = "Unknown room, invalid/expired token, or invalid identity"),
+        (status = 403, description = "The identity or the caller's address is banned"),
+        (status = 429, description = "Too many joins from this address; see `Retry-After`"),
+    ),
+)]
+pub async fn ws_ticket(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(WsTicketRequest { token, reconnect, identity }): Json<WsTicketRequest>,
+) -> Response {
+    let Some(token) = state.rooms.seat_token(&id, token.as_deref(), reconnect.as_deref()) else {
+        return (StatusCode::UNAUTHORIZED, "invalid room or token").into_response();
+    };
+    let player = match identity.as_deref().map(Identity::verify).transpose() {
+        Ok(player) => player,
+        Err(err) => return (StatusCode::UNAUTHORIZED, err.to_string()).into_response(),
+    };
+    if let Some(ban) = state.rooms.banned(player.as_ref().map(|p| p.player_id.as_str()), client_ip(&headers, peer)) {
+        return banned(&ban);
+    }
+    let ticket = state.tickets.issue(&id, &token, player);
+    Json(WsTicket { ticket, expires_in_secs: TICKET_TTL.as_secs() }).into_response()
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct JoinByCodeRequest {
+    /// A room code like `K7QX4M`; case and separators are ignored.
+    pub code: String,
+}
+
+/// Take a free seat in the room with a short code, as read out by its creator.
+#[utoipa::path(
+    post,
+    path = "/api/join-by-code",
+    tag = "rooms",
+    request_body = JoinByCodeRequest,
+    responses(
+        (status = 200, body = SeatGrant),
+        (status = 403, description = "The caller's address is banned"),
+        (status = 404, description = "No room has that code"),
+        (status = 409, description = "Every seat is already taken"),
+        (status = 429, description = "Too many joins from this address; see `Retry-After`"),
+    ),
+)]
+pub async fn join_by_code(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(JoinByCodeRequest { code }): Json<JoinByCodeRequest>,
+) -> Response {
+    if let Some(ban) = state.rooms.banned(None, client_ip(&headers, peer)) {
+        return banned(&ban);
+    }
+    match state.rooms.claim_by_code(&code) {
+        Ok((id, token)) => Json(SeatGrant::new(id, token)).into_response(),
+        Err(err) => room_error(err),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct IdentityRequest {
+    /// The identity token the client already holds, to keep its `player_id`.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Display name; a new identity without one gets a guest name.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// A guest identity and the token that proves it. The client keeps the token
+/// and passes it as `identity` when opening `/ws`.
+#[derive(Serialize, ToSchema)]
+pub struct IdentityGrant {
+    pub player_id: String,
+    pub name: String,
+    pub token: String,
+}
+
+/// Get a guest identity, or rename one: send its `token` and a new `name`.
+/// Renaming issues a new token; the old one keeps working under the old name.
+#[utoipa::path(
+    post,
+    path = "/api/identity",
+    tag = "players",
+    request_body = IdentityRequest,
+    responses(
+        (status = 200, body = IdentityGrant),
+        (status = 401, description = "`token` wasn't issued by this server"),
+        (status = 422, body = NameRejection, description = "`name` is empty or too long once cleaned up"),
+    ),
+)]
+pub async fn identity(Json(IdentityRequest { token, name }): Json<IdentityRequest>) -> Response {
+    let identity = match (token, name) {
+        (None, name) => Identity::issue(name.as_deref()),
+        (Some(token), name) => Identity::verify(&token).and_then(|id| match name {
+            Some(name) => id.renamed(&name),
+            None => Ok(id),
+        }),
+    };
+    match identity {
+        Ok(identity) => Json(IdentityGrant { token: identity.token(), player_id: identity.player_id, name: identity.name })
+            .into_response(),
+        Err(IdentityError::Name(err)) => (StatusCode::UNPROCESSABLE_ENTITY, Json(NameRejection::from(err))).into_response(),
+        Err(err) => (StatusCode::UNAUTHORIZED, err.to_string()).into_response(),
+    }
+}
+
+/// Turn away someone `ban` covers, telling them why and for how long.
+pub fn banned(ban: &Ban) -> Response {
+    let mut message = "you are banned".to_string();
+    if let Some(reason) = &ban.reason { message += &format!(": {reason}"); }
+    if let Some(until) = ban.expires_at_unix { message += &format!(" (until {until}, Unix time)"); }
+    (StatusCode::FORBIDDEN, message).into_response()
+}
+
+fn room_error(err: RoomError) -> Response {
+    match err {
+        RoomError::NotFound => (StatusCode::NOT_FOUND, "room not found").into_response(),
+        RoomError::InvalidToken => (StatusCode::UNAUTHORIZED, "invalid token").into_response(),
+        RoomError::Full => (StatusCode::CONFLICT, "room full").into_response(),
+        RoomError::Ranked => (StatusCode::CONFLICT, err.to_string()).into_response(),
+        RoomError::Maintenance | RoomError::AtCapacity => (StatusCode::SERVICE_UNAVAILABLE, err.to_string()).into_response(),
     }
 }
 
@@ -61,11 +980,69 @@ pub async fn view_room(
     if !ok {
         return (StatusCode::UNAUTHORIZED, "invalid room or token").into_response();
     }
-    // Try to compute the other token for convenience
-    let invite = state.rooms.other_token(&id, &token);
-    let (has_invite, invite_token) = match invite {
-        Some(t) => (true, t),
-        None => (false, String::new()),
-    };
-    RoomTemplate { room_id: id, has_invite, invite_token, viewer_token: token }.into_response()
+    // Show the other seats' tokens for convenience
+    let invites = state.rooms.invite_tokens(&id, &token);
+    let room_code = state.rooms.code(&id).unwrap_or_default();
+    // Carries the viewer's token, and names the current asset hashes.
+    let page = RoomTemplate { room_id: id, room_code, has_invite: !invites.is_empty(), invites, viewer_token: token };
+    ([(header::CACHE_CONTROL, "no-store")], page).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::room::ratings::PlayerRating;
+
+    fn state() -> AppState {
+        AppState {
+            rooms: Arc::new(RoomManager::new()),
+            taps: Arc::new(WireTap::new()),
+            quickmatch: Arc::new(Quickmatch::new()),
+            tickets: Arc::new(WsTickets::new()),
+            sockets: TaskTracker::new(),
+            #[cfg(feature = "fault-injection")]
+            faults: Arc::new(FaultInjector::new()),
+        }
+    }
+
+    async fn board(state: &AppState, period: Period, offset: usize, limit: usize) -> (StatusCode, Value) {
+        let response = leaderboard(State(state.clone()), Query(LeaderboardQuery { period, offset, limit })).await;
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn the_leaderboard_ranks_by_rating_in_pages() {
+        let state = state();
+        for (id, rating, games) in [("low", 1150.4, 3), ("high", 1301.6, 8), ("mid", 1240.0, 2), ("mid2", 1240.0, 5)] {
+            state.rooms.ratings().restore(id.into(), PlayerRating { name: id.to_uppercase(), rating, games });
+        }
+        let (status, page) = board(&state, Period::Alltime, 0, 3).await;
+        assert_eq!(status, StatusCode::OK);
+        let places: Vec<_> = page["players"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| (p["place"].as_u64().unwrap(), p["player_id"].as_str().unwrap(), p["score"].as_i64().unwrap()))
+            .collect();
+        // An equal rating goes to whoever played more.
+        assert_eq!(places, [(1, "high", 1302), (2, "mid2", 1240), (3, "mid", 1240)]);
+        assert_eq!((&page["period"], &page["next_offset"]), (&Value::from("alltime"), &Value::from(3)));
+
+        let (_, last) = board(&state, Period::Alltime, 3, 3).await;
+        assert_eq!(last["players"][0]["place"], 4);
+        assert_eq!(last["players"][0]["name"], "LOW");
+        assert_eq!(last["next_offset"], Value::Null);
+        let (_, past) = board(&state, Period::Alltime, 10, 3).await;
+        assert_eq!(past["players"], Value::Array(Vec::new()));
+
+        for limit in [0, PAGE_MAX + 1] {
+            assert_eq!(board(&state, Period::Alltime, 0, limit).await.0, StatusCode::BAD_REQUEST);
+        }
+        let (_, weekly) = board(&state, Period::Weekly, 0, 20).await;
+        assert_eq!(weekly["players"], Value::Array(Vec::new()));
+    }
 }
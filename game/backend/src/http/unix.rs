@@ -0,0 +1,98 @@
+//! Serving on a Unix domain socket (`UNIX_SOCKET`), to sit behind nginx or
+//! Caddy on the same host without opening a port. The socket's permissions
+//! follow the umask; the proxy's user needs write access to it.
+//!
+//! `axum::serve` only takes TCP listeners, so this is its accept loop and
+//! graceful shutdown, over a `UnixListener`.
+
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+use std::time::Duration;
+
+use axum::extract::ConnectInfo;
+use axum::{Extension, Router};
+use hyper::server::conn::http1;
+use hyper_util::rt::TokioIo;
+use hyper_util::service::TowerToHyperService;
+use tokio::net::{UnixListener, UnixStream};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+use crate::config;
+
+/// The peer address handlers see for every connection: the proxy, on this
+/// host. Without `CLIENT_IP_HEADER` all players share it.
+const LOCAL_PEER: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+/// Serve `app` on a socket at `path` until `signal` resolves, then let open
+/// requests finish and remove the socket file.
+pub async fn serve(path: &Path, app: Router, signal: impl Future<Output = ()>) -> io::Result<()> {
+    let listener = bind(path).await?;
+    if config::get().client_ip_header.is_none() {
+        tracing::warn!("CLIENT_IP_HEADER is not set; behind a Unix socket every client shares one rate limit");
+    }
+    let app = app.layer(Extension(ConnectInfo(LOCAL_PEER)));
+    let connections = TaskTracker::new();
+    let stop = CancellationToken::new();
+    tokio::pin!(signal);
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    // E.g. out of file descriptors; back off as `axum::serve` does.
+                    tracing::warn!(%err, "accept failed");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            },
+            _ = &mut signal => break,
+        };
+        connections.spawn(connection(stream, app.clone(), stop.clone()));
+    }
+    drop(listener);
+    stop.cancel();
+    connections.close();
+    connections.wait().await;
+    std::fs::remove_file(path)
+}
+
+async fn connection(stream: UnixStream, app: Router, stop: CancellationToken) {
+    let conn = http1::Builder::new()
+        .serve_connection(TokioIo::new(stream), TowerToHyperService::new(app))
+        .with_upgrades();
+    tokio::pin!(conn);
+    let result = tokio::select! {
+        result = conn.as_mut() => result,
+        _ = stop.cancelled() => {
+            conn.as_mut().graceful_shutdown();
+            conn.await
+        }
+    };
+    if let Err(err) = result {
+        tracing::debug!(%err, "connection closed with an error");
+    }
+}
+
+/// Bind `path`, replacing a socket left behind by a server that's gone, but
+/// not one still answering or a file that isn't a socket.
+async fn bind(path: &Path) -> io::Result<UnixListener> {
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => {
+            if UnixStream::connect(path).await.is_ok() {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("{} is in use", path.display())));
+            }
+            std::fs::remove_file(path)?;
+        }
+        Ok(_) => {
+            let problem = format!("{} exists and isn't a socket", path.display());
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, problem));
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+    UnixListener::bind(path)
+}
@@ -1,3 +0,0 @@
-//! Pure validation and state transitions for Zobbo.
-
-// Placeholder; will contain deterministic rules logic.
@@ -1,4 +0,0 @@
-//! Game domain: rules, state transitions, types.
-
-// pub mod engine; // uncomment when implemented
-// pub mod types;
@@ -1,3 +0,0 @@
-//! Core types: cards, actions, events.
-
-// Placeholder enums/structs to be defined with serde.
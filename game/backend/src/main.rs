@@ -1,45 +1,194 @@
-use axum::{routing::{get, post}, Router};
+use axum::{middleware, routing::{get, post}, Router};
+use axum::http::header;
 use axum::response::IntoResponse;
+use tokio_util::task::TaskTracker;
 use tower_http::services::ServeDir;
 use askama::Template;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 mod config;
 mod http;
+mod reporting;
 mod room;
+mod store;
+mod telemetry;
 mod util;
 mod ws;
 
+use crate::config::ServerAddr;
+use crate::http::ratelimit::{self, RateLimiter};
 use crate::http::routes::{self, AppState};
 use crate::room::manager::RoomManager;
+use crate::room::matchmaking::Quickmatch;
+use crate::store::{Restored, StoreHandle};
+use crate::ws::tap::WireTap;
+use crate::ws::ticket::WsTickets;
+
+/// How long a shutdown waits for sockets to close once their rooms are drained.
+const SOCKET_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Template)]
 #[template(path = "lobby.html")]
 struct LobbyTemplate;
 
-async fn lobby() -> impl IntoResponse { LobbyTemplate }
+/// Pages name the current asset hashes, so they're revalidated every time.
+async fn lobby() -> impl IntoResponse { ([(header::CACHE_CONTROL, "no-cache")], LobbyTemplate) }
+
+/// The room registry, restored from the store when persistence is enabled.
+async fn open_rooms() -> anyhow::Result<RoomManager> {
+    let Some((store, restored)) = open_store().await? else { return Ok(RoomManager::new()) };
+    tracing::info!(
+        rooms = restored.rooms.len(),
+        history = restored.history.len(),
+        ratings = restored.ratings.len(),
+        bans = restored.bans.len(),
+        "restored from the store"
+    );
+    Ok(RoomManager::with_store(store, restored))
+}
 
-async fn healthz() -> &'static str { "ok" }
+/// `DATABASE_URL` when built with SQLite, else `SNAPSHOT_PATH`, else nothing.
+async fn open_store() -> anyhow::Result<Option<(StoreHandle, Restored)>> {
+    #[cfg(feature = "sqlite")]
+    if let Some(url) = &config::get().database_url {
+        return Ok(Some(store::sqlite::open(url).await?));
+    }
+    if let Some(path) = &config::get().snapshot_path {
+        return Ok(Some(store::file::open(path.clone(), config::get().snapshot_interval).await?));
+    }
+    Ok(None)
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let state = AppState { rooms: Arc::new(RoomManager::new()) };
+    let config = config::load()?;
+    telemetry::init();
+    if let Some(file) = &config.file {
+        tracing::info!(file = %file.display(), "loaded config");
+    }
+    let _reporting = reporting::init();
+    http::auth::load_secret();
+    let rooms = Arc::new(open_rooms().await?);
+    tokio::spawn(room::manager::run_gc(
+        rooms.clone(),
+        config.room_gc_interval,
+        config.room_idle_ttl,
+        config.room_finished_linger,
+    ));
+    let state = AppState {
+        rooms,
+        taps: Arc::new(WireTap::new()),
+        quickmatch: Arc::new(Quickmatch::new()),
+        tickets: Arc::new(WsTickets::new()),
+        sockets: TaskTracker::new(),
+        #[cfg(feature = "fault-injection")]
+        faults: Arc::new(ws::faults::FaultInjector::new()),
+    };
+    // Bots don't outlive the process; reseat them in rooms restored from the store.
+    for (room_id, seat, handle) in state.rooms.bot_seats() {
+        room::bot::spawn(room_id, seat, state.taps.conn_id(), handle);
+    }
 
-    let app = Router::new()
+    let create_limiter = Arc::new(RateLimiter::new(config.create_rate_limit));
+    let join_limiter = Arc::new(RateLimiter::new(config.join_rate_limit));
+    let create_limit = middleware::from_fn_with_state(create_limiter, ratelimit::limit);
+    let join_limit = middleware::from_fn_with_state(join_limiter, ratelimit::limit);
+
+    let mut app = Router::new()
         .route("/", get(lobby))
-        .route("/healthz", get(healthz))
-        .route("/rooms", post(routes::create_room))
-        .route("/rooms/:id/join", post(routes::join_room))
+        .route("/livez", get(http::health::livez))
+        .route("/readyz", get(http::health::readyz))
+        .route("/rooms", post(routes::create_room).route_layer(create_limit.clone()))
+        .route("/rooms/:id/join", post(routes::join_room).route_layer(join_limit.clone()))
+        .route("/rooms/:id/add_bot", post(routes::add_bot).route_layer(create_limit.clone()))
         .route("/rooms/:id/view", get(routes::view_room))
+        .route("/api/quickmatch", post(routes::quickmatch).route_layer(create_limit.clone()))
+        .route("/api/practice", post(routes::practice).route_layer(create_limit.clone()))
+        .route("/api/tutorial", post(routes::tutorial).route_layer(create_limit.clone()))
+        .route("/api/rooms", get(routes::list_rooms))
+        .route("/api/daily", get(routes::daily_challenge))
+        .route("/api/presets", get(routes::list_presets))
+        .route("/api/games/:game_id/log", get(routes::game_log))
+        .route("/api/games/:game_id/export", get(routes::export_game))
+        .route("/api/identity", post(routes::identity))
+        .route("/api/leaderboard", get(routes::leaderboard))
+        .route("/api/player/:id/history", get(routes::player_history))
+        .route("/api/player/:id/stats", get(routes::player_stats))
+        .route("/api/games/verify", post(routes::verify_game).route_layer(create_limit))
+        .route("/api/replay/:game_id", get(routes::replay_game))
+        .route("/api/replay/:game_id/stream", get(ws::replay::replay_stream))
+        .route("/api/rooms/:id/ws-ticket", post(routes::ws_ticket).route_layer(join_limit.clone()))
+        .route("/api/rooms/:id/claim", post(routes::claim_room).route_layer(join_limit.clone()))
+        .route("/api/join-by-code", post(routes::join_by_code).route_layer(join_limit))
         .route("/ws", get(ws::connection::ws_handler))
-        // Serve static assets from the frontend directory
-        .nest_service("/static", ServeDir::new(config::static_dir()))
-        .with_state(state);
+        .merge(http::openapi::router());
+    app = match &config.static_dir {
+        Some(dir) => {
+            tracing::info!(dir = %dir.display(), "serving static files from disk");
+            app.nest_service("/static", ServeDir::new(dir))
+        }
+        None => app.route("/static/*path", get(http::assets::embedded)),
+    };
+    if config.dev_tools {
+        tracing::warn!("dev tools enabled: /dev routes are exposed");
+        app = app.route("/dev/rooms/:id/stream", get(ws::tap::stream_handler));
+        #[cfg(feature = "fault-injection")]
+        {
+            use axum::routing::put;
+            app = app
+                .route("/dev/faults", get(ws::faults::get_faults).put(ws::faults::put_default))
+                .route("/dev/faults/:conn", put(ws::faults::put_conn).delete(ws::faults::delete_conn));
+        }
+    }
+    if config.admin_token.is_some() {
+        app = app.merge(http::admin::router());
+    }
+    if let Some(cors) = http::cors::layer(&config.allowed_origins) {
+        app = app.layer(cors);
+    }
+    let app = telemetry::with_request_ids(app.with_state(state.clone()));
 
-    let addr: SocketAddr = config::server_addr();
+    let addr = &config.server_addr;
     tracing::info!(%addr, "listening");
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    match addr {
+        ServerAddr::Tcp(addr) => {
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            // Peer addresses are for the admin audit log.
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                .with_graceful_shutdown(shutdown(state))
+                .await?;
+        }
+        #[cfg(unix)]
+        ServerAddr::Unix(path) => http::unix::serve(path, app, shutdown(state)).await?,
+        #[cfg(not(unix))]
+        ServerAddr::Unix(_) => unreachable!("config refuses Unix sockets off Unix"),
+    }
+    tracing::info!("shut down");
     Ok(())
 }
+
+/// Resolves once the process is told to stop and the rooms are drained:
+/// every player told when to reconnect, every game saved, every socket closed.
+async fn shutdown(state: AppState) {
+    let ctrl_c = async { tokio::signal::ctrl_c().await.expect("install Ctrl-C handler") };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        signal(SignalKind::terminate()).expect("install SIGTERM handler").recv().await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    tracing::info!("shutting down");
+    state.rooms.drain(config::get().shutdown_reconnect_after).await;
+    // With their rooms gone the sockets are on their way out; let them send their close frames.
+    state.sockets.close();
+    if tokio::time::timeout(SOCKET_CLOSE_TIMEOUT, state.sockets.wait()).await.is_err() {
+        tracing::warn!(sockets = state.sockets.len(), "sockets still open at shutdown");
+    }
+}
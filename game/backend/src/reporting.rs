@@ -0,0 +1,146 @@
+//! Error reporting: a panic in a room or socket task, or a 500, is logged
+//! with the room and player it hit instead of dying quietly on a tokio worker.
+//!
+//! Built with the `sentry` feature and given `SENTRY_DSN`, each is also sent
+//! to Sentry (or anything speaking its protocol), tagged the same way.
+
+use std::any::Any;
+use std::error::Error;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+
+use futures_util::FutureExt;
+
+use crate::config;
+
+/// What a report is about: fields on the log line, tags on the Sentry event.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    /// The task or handler that failed, e.g. `room` or `socket`.
+    pub what: &'static str,
+    pub room_id: Option<String>,
+    pub seat: Option<usize>,
+    pub player_id: Option<String>,
+    pub game_id: Option<String>,
+}
+
+impl Context {
+    pub fn new(what: &'static str) -> Self {
+        Self { what, ..Self::default() }
+    }
+
+    pub fn room(self, room_id: &str) -> Self {
+        Self { room_id: Some(room_id.to_string()), ..self }
+    }
+
+    pub fn seat(self, seat: usize) -> Self {
+        Self { seat: Some(seat), ..self }
+    }
+
+    pub fn player(self, player_id: Option<&str>) -> Self {
+        Self { player_id: player_id.map(str::to_string), ..self }
+    }
+
+    pub fn game(self, game_id: &str) -> Self {
+        Self { game_id: Some(game_id.to_string()), ..self }
+    }
+
+    #[cfg(feature = "sentry")]
+    fn tag(&self, scope: &mut sentry::Scope) {
+        scope.set_tag("what", self.what);
+        if let Some(room_id) = &self.room_id { scope.set_tag("room_id", room_id); }
+        if let Some(seat) = self.seat { scope.set_tag("seat", seat); }
+        if let Some(game_id) = &self.game_id { scope.set_tag("game_id", game_id); }
+        if let Some(player_id) = &self.player_id {
+            scope.set_user(Some(sentry::User { id: Some(player_id.clone()), ..Default::default() }));
+        }
+    }
+}
+
+/// Keeps the Sentry client alive; dropping it flushes queued events.
+pub struct Guard {
+    #[cfg(feature = "sentry")]
+    _client: Option<sentry::ClientInitGuard>,
+}
+
+/// Start reporting to `SENTRY_DSN`, if set. Call after `telemetry::init` and
+/// hold the guard until shutdown.
+pub fn init() -> Guard {
+    let dsn = config::get().sentry_dsn.as_deref();
+    #[cfg(feature = "sentry")]
+    {
+        let client = dsn.and_then(|dsn| match dsn.parse::<sentry::types::Dsn>() {
+            Ok(dsn) => {
+                tracing::info!(host = dsn.host(), "reporting errors to Sentry");
+                // The SDK picks up `SENTRY_ENVIRONMENT` and `SENTRY_RELEASE` itself.
+                Some(sentry::init(sentry::ClientOptions { dsn: Some(dsn), ..Default::default() }))
+            }
+            Err(err) => {
+                tracing::warn!(%err, "SENTRY_DSN is invalid; errors will only be logged");
+                None
+            }
+        });
+        Guard { _client: client }
+    }
+    #[cfg(not(feature = "sentry"))]
+    {
+        if dsn.is_some() {
+            tracing::warn!("SENTRY_DSN is set but this build lacks the `sentry` feature; errors will only be logged");
+        }
+        Guard {}
+    }
+}
+
+/// Run `task`, logging (and reporting) a panic with `context` rather than
+/// letting it vanish with the task.
+pub async fn watch<F: Future<Output = ()>>(context: Context, task: F) {
+    // The panic hook reports from whichever hub is current, so the task gets
+    // its own, tagged with the context.
+    #[cfg(feature = "sentry")]
+    let task = {
+        use sentry::SentryFutureExt;
+        let hub = std::sync::Arc::new(sentry::Hub::new_from_top(sentry::Hub::current()));
+        hub.configure_scope(|scope| context.tag(scope));
+        task.bind_hub(hub)
+    };
+    if let Err(panic) = AssertUnwindSafe(task).catch_unwind().await {
+        tracing::error!(
+            what = context.what,
+            room_id = context.room_id.as_deref(),
+            seat = context.seat,
+            player_id = context.player_id.as_deref(),
+            panic = panic_message(&*panic),
+            "task panicked"
+        );
+    }
+}
+
+/// `tokio::spawn` a `watch`ed task.
+pub fn spawn<F>(context: Context, task: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(watch(context, task));
+}
+
+/// Log and report an error that's turning into a 500.
+pub fn internal_error(context: &Context, err: &(dyn Error + 'static)) {
+    tracing::error!(
+        what = context.what,
+        room_id = context.room_id.as_deref(),
+        game_id = context.game_id.as_deref(),
+        player_id = context.player_id.as_deref(),
+        %err,
+        "internal error"
+    );
+    #[cfg(feature = "sentry")]
+    sentry::with_scope(|scope| context.tag(scope), || sentry::capture_error(err));
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload")
+}
@@ -0,0 +1,45 @@
+//! Logs of finished games, kept by game id for review and disputes.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+use utoipa::ToSchema;
+use zobbo_core::engine::GameResult;
+use zobbo_core::log::LogEntry;
+
+/// How many finished games the archive keeps; the oldest go first.
+const KEEP_GAMES: usize = 1000;
+
+/// A finished game's complete log.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GameRecord {
+    pub game_id: String,
+    pub room_id: String,
+    pub finished_at_unix: u64,
+    /// `LogEntry`s from the `zobbo-core` crate, starting with the deal.
+    #[schema(value_type = Vec<Object>)]
+    pub log: Vec<LogEntry>,
+    #[schema(value_type = Object)]
+    pub result: Option<GameResult>,
+}
+
+/// Recently finished games. Kept in memory only; a game still in progress
+/// keeps its log in its room's snapshot instead.
+#[derive(Debug, Default)]
+pub struct GameArchive {
+    games: Mutex<VecDeque<Arc<GameRecord>>>,
+}
+
+impl GameArchive {
+    pub fn insert(&self, record: GameRecord) {
+        let mut games = self.games.lock().unwrap();
+        if games.len() >= KEEP_GAMES {
+            games.pop_front();
+        }
+        games.push_back(Arc::new(record));
+    }
+
+    pub fn get(&self, game_id: &str) -> Option<Arc<GameRecord>> {
+        self.games.lock().unwrap().iter().rev().find(|g| g.game_id == game_id).cloned()
+    }
+}
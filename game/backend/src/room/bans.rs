@@ -0,0 +1,132 @@
+//! Bans on abusive players, by guest identity, IP address or both.
+//!
+//! Checked on every route that hands out a seat: creating, joining,
+//! claiming or quickmatching into a room by IP, and swapping tokens for a
+//! socket ticket or opening the socket by identity and IP. Managed through
+//! `/admin/bans`; rooms with a store save them there too.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Ban {
+    pub id: String,
+    /// Banned guest identity, if any.
+    pub player_id: Option<String>,
+    /// Banned address, if any.
+    #[schema(value_type = Option<String>)]
+    pub ip: Option<IpAddr>,
+    /// Shown to the banned player.
+    pub reason: Option<String>,
+    pub created_at_unix: u64,
+    /// When the ban lifts, in Unix seconds; `None` for never.
+    pub expires_at_unix: Option<u64>,
+}
+
+impl Ban {
+    fn expired(&self, now: u64) -> bool {
+        self.expires_at_unix.is_some_and(|at| at <= now)
+    }
+
+    fn covers(&self, player_id: Option<&str>, ip: IpAddr) -> bool {
+        self.ip == Some(ip) || (self.player_id.is_some() && self.player_id.as_deref() == player_id)
+    }
+}
+
+/// Every ban in force, in memory.
+#[derive(Debug, Default)]
+pub struct BanList {
+    bans: Mutex<HashMap<String, Ban>>,
+}
+
+impl BanList {
+    /// Add `ban`, or replace the one with its id. Bans that have run out go
+    /// at the same time.
+    pub fn insert(&self, ban: Ban) {
+        let now = unix_now();
+        let mut bans = self.bans.lock().unwrap();
+        bans.retain(|_, b| !b.expired(now));
+        bans.insert(ban.id.clone(), ban);
+    }
+
+    pub fn remove(&self, id: &str) -> Option<Ban> {
+        self.bans.lock().unwrap().remove(id)
+    }
+
+    /// Bans in force, newest first.
+    pub fn active(&self) -> Vec<Ban> {
+        let now = unix_now();
+        let mut active: Vec<_> = self.bans.lock().unwrap().values().filter(|b| !b.expired(now)).cloned().collect();
+        active.sort_by(|a, b| b.created_at_unix.cmp(&a.created_at_unix).then_with(|| b.id.cmp(&a.id)));
+        active
+    }
+
+    /// A ban in force on `player_id` or `ip`, if there is one.
+    pub fn find(&self, player_id: Option<&str>, ip: IpAddr) -> Option<Ban> {
+        let now = unix_now();
+        self.bans.lock().unwrap().values().find(|b| !b.expired(now) && b.covers(player_id, ip)).cloned()
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOME: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 1));
+    const AWAY: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 2));
+
+    fn ban(id: &str, player_id: Option<&str>, ip: Option<IpAddr>, expires_at_unix: Option<u64>) -> Ban {
+        Ban {
+            id: id.to_string(),
+            player_id: player_id.map(str::to_string),
+            ip,
+            reason: None,
+            created_at_unix: 0,
+            expires_at_unix,
+        }
+    }
+
+    #[test]
+    fn covers_the_identity_or_the_address() {
+        let by_player = ban("a", Some("p1"), None, None);
+        assert!(by_player.covers(Some("p1"), AWAY));
+        assert!(!by_player.covers(Some("p2"), HOME));
+        assert!(!by_player.covers(None, HOME));
+        let by_ip = ban("b", None, Some(HOME), None);
+        assert!(by_ip.covers(None, HOME));
+        assert!(by_ip.covers(Some("p2"), HOME));
+        // No identity on the ban doesn't match callers without one.
+        assert!(!by_ip.covers(None, AWAY));
+    }
+
+    #[test]
+    fn expiry() {
+        let now = unix_now();
+        assert!(!ban("a", Some("p1"), None, None).expired(now));
+        assert!(!ban("a", Some("p1"), None, Some(now + 60)).expired(now));
+        assert!(ban("a", Some("p1"), None, Some(now)).expired(now));
+    }
+
+    #[test]
+    fn find_skips_lapsed_bans() {
+        let list = BanList::default();
+        list.insert(ban("lapsed", None, Some(HOME), Some(1)));
+        list.insert(ban("player", Some("p1"), None, None));
+        assert!(list.find(None, HOME).is_none());
+        assert_eq!(list.find(Some("p1"), AWAY).map(|b| b.id), Some("player".to_string()));
+        assert!(list.find(Some("p2"), AWAY).is_none());
+        // Inserting swept the lapsed ban; removing lifts the other.
+        assert_eq!(list.active().len(), 1);
+        assert!(list.remove("player").is_some());
+        assert!(list.find(Some("p1"), AWAY).is_none());
+    }
+}
@@ -0,0 +1,187 @@
+//! Server-side computer opponent.
+//!
+//! A bot is just another seat: it connects to the room task like a socket
+//! does, reads the same `ServerToClient` stream and answers with
+//! `ClientToServer` actions, so the arbiter needs no special casing.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+
+use crate::reporting::{self, Context};
+use crate::room::room::{RoomCommand, RoomHandle};
+use crate::ws::protocol::{ClientToServer, DrawSource, GameUpdate, Power, ServerToClient, Stage};
+
+/// Pause before each action so the human can follow along.
+const THINK_TIME: Duration = Duration::from_millis(700);
+/// Expected points of a card the bot hasn't seen.
+const UNKNOWN_POINTS: f32 = 6.5;
+/// Call Zobbo once the estimated hand total is at or below this.
+const ZOBBO_THRESHOLD: f32 = 8.0;
+/// Known cards worth at least this are worth gambling away.
+const HIGH_CARD: i32 = 9;
+
+/// Start a bot playing `seat` in the room behind `room`.
+///
+/// The task ends when the room drops its connection.
+pub fn spawn(room_id: String, seat: usize, conn: u64, room: RoomHandle) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    if !room.send(RoomCommand::Connect { seat, conn, player: None, bot: true, tx }) {
+        return;
+    }
+    let bot = Bot { seat, room, known: HashMap::new(), game: None, held: None, discard_draw: true };
+    reporting::spawn(Context::new("bot").room(&room_id).seat(seat), async move {
+        bot.run(rx).await;
+        tracing::debug!(room_id = %room_id, seat, "bot stopped");
+    });
+}
+
+struct Bot {
+    seat: usize,
+    room: RoomHandle,
+    /// Own cards the bot has seen: index -> (slot version, points).
+    known: HashMap<usize, (u32, i32)>,
+    game: Option<GameUpdate>,
+    /// The card drawn this turn, so play can pick up again after a pause.
+    held: Option<(i32, DrawSource)>,
+    /// Whether the room's rules let a turn start from the discard pile.
+    discard_draw: bool,
+}
+
+impl Bot {
+    async fn run(mut self, mut rx: mpsc::UnboundedReceiver<ServerToClient>) {
+        while let Some(msg) = rx.recv().await {
+            let action = match msg {
+                ServerToClient::LobbyState { players, started, .. } => {
+                    let ready = players.iter().any(|p| p.seat == self.seat && p.ready);
+                    (!started && !ready).then_some(ClientToServer::Ready)
+                }
+                ServerToClient::GameStart { rules, .. } => {
+                    self.known.clear();
+                    self.discard_draw = rules.discard_draw;
+                    None
+                }
+                ServerToClient::GameUpdate(update) => {
+                    self.game = Some(update);
+                    self.on_update()
+                }
+                ServerToClient::GameDelta(delta) => {
+                    if self.game.as_mut().is_some_and(|g| g.apply(&delta)) {
+                        self.on_update()
+                    } else {
+                        // Missed an update; the reply restores the full state.
+                        Some(ClientToServer::RequestState { last_seq: self.game.as_ref().map_or(0, |g| g.seq) })
+                    }
+                }
+                ServerToClient::Drawn { card, from } => {
+                    self.held = Some((card.points, from));
+                    self.on_drawn(card.points, from)
+                }
+                ServerToClient::PeekResult { owner, index, version, card } if owner == self.seat => {
+                    self.known.insert(index, (version, card.points));
+                    None
+                }
+                _ => None,
+            };
+            if self.game.as_ref().is_some_and(|g| g.stage != Stage::Holding && g.stage != Stage::Paused) {
+                self.held = None;
+            }
+            if let Some(action) = action {
+                tokio::time::sleep(THINK_TIME).await;
+                if !self.room.send(RoomCommand::Client { seat: self.seat, msg: action }) {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn my_turn(&self) -> Option<&GameUpdate> {
+        self.game.as_ref().filter(|g| g.active == self.seat)
+    }
+
+    /// Points of the card in own slot `index`, if the bot's knowledge is current.
+    fn points(&self, game: &GameUpdate, index: usize) -> Option<i32> {
+        let slot = game.seats[self.seat].slots[index];
+        self.known
+            .get(&index)
+            .filter(|(version, _)| slot.filled && *version == slot.version)
+            .map(|(_, points)| *points)
+    }
+
+    fn filled(&self, game: &GameUpdate) -> Vec<usize> {
+        let slots = &game.seats[self.seat].slots;
+        (0..slots.len()).filter(|i| slots[*i].filled).collect()
+    }
+
+    fn estimate(&self, game: &GameUpdate, index: usize) -> f32 {
+        self.points(game, index).map_or(UNKNOWN_POINTS, |p| p as f32)
+    }
+
+    /// The own slot the bot would most like to get rid of.
+    fn worst_slot(&self, game: &GameUpdate) -> Option<(usize, f32)> {
+        self.filled(game)
+            .into_iter()
+            .map(|i| (i, self.estimate(game, i)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    fn on_update(&self) -> Option<ClientToServer> {
+        // The bot never minds a pause, so its seat doesn't hold one up.
+        let pause_requests = &self.game.as_ref()?.pause_requests;
+        if !pause_requests.is_empty() && !pause_requests.contains(&self.seat) {
+            return Some(ClientToServer::RequestPause);
+        }
+        let game = self.my_turn()?;
+        match game.stage {
+            Stage::Draw => {
+                let total: f32 = self.filled(game).into_iter().map(|i| self.estimate(game, i)).sum();
+                if game.zobbo_remaining.is_none() && total <= ZOBBO_THRESHOLD {
+                    return Some(ClientToServer::CallZobbo);
+                }
+                let (_, worst) = self.worst_slot(game)?;
+                let take_discard = self.discard_draw
+                    && game.discard_top.is_some_and(|c| c.points <= 4 && (c.points as f32) < worst);
+                Some(if take_discard { ClientToServer::DrawDiscard } else { ClientToServer::DrawDeck })
+            }
+            Stage::Power => Some(self.use_power(game, game.power?)),
+            // Holding is handled once the private `Drawn` arrives, or here
+            // when play resumes after a pause.
+            Stage::Holding => self.held.and_then(|(points, from)| self.on_drawn(points, from)),
+            Stage::Paused | Stage::Finished => None,
+        }
+    }
+
+    fn on_drawn(&self, points: i32, from: DrawSource) -> Option<ClientToServer> {
+        let game = self.my_turn()?;
+        let (index, worst) = self.worst_slot(game)?;
+        if (points as f32) < worst || from == DrawSource::Discard {
+            Some(ClientToServer::SwapWithHand { index })
+        } else {
+            Some(ClientToServer::DiscardDrawn)
+        }
+    }
+
+    fn use_power(&self, game: &GameUpdate, power: Power) -> ClientToServer {
+        let filled = self.filled(game);
+        let high = filled.iter().copied().find(|&i| self.points(game, i).is_some_and(|p| p >= HIGH_CARD));
+        // The bot always aims at the seat after its own.
+        let opp = &game.seats[(self.seat + 1) % game.seats.len()];
+        let opp_filled = (0..opp.slots.len()).find(|&i| opp.slots[i].filled);
+        match power {
+            Power::PeekOwn => filled
+                .iter()
+                .copied()
+                .find(|&i| self.points(game, i).is_none())
+                .map_or(ClientToServer::SkipPower, |index| ClientToServer::PeekOwn { index }),
+            Power::SwapOwnWithDeck => {
+                high.map_or(ClientToServer::SkipPower, |index| ClientToServer::SwapOwnWithDeck { index })
+            }
+            Power::BlindSwap => match (high, opp_filled) {
+                (Some(own_index), Some(opp_index)) => ClientToServer::BlindSwap { own_index, opp_index, target: None },
+                _ => ClientToServer::SkipPower,
+            },
+            Power::PeekOpp | Power::SwapOppWithDeck => ClientToServer::SkipPower,
+        }
+    }
+}
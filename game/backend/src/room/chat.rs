@@ -0,0 +1,73 @@
+//! Per-room chat: validation, rate limiting and recent history.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::ws::protocol::{RejectCode, ServerToClient};
+
+/// Longest message accepted, in characters, after trimming.
+pub const MAX_CHAT_CHARS: usize = 280;
+/// Messages kept for players who (re)connect.
+const HISTORY: usize = 50;
+/// At most `RATE_BURST` messages per seat within `RATE_WINDOW`.
+const RATE_BURST: usize = 5;
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatError {
+    #[error("chat message is empty")]
+    Empty,
+    #[error("chat message is longer than {MAX_CHAT_CHARS} characters")]
+    TooLong,
+    #[error("slow down: too many chat messages")]
+    RateLimited,
+}
+
+impl ChatError {
+    pub fn code(self) -> RejectCode {
+        match self {
+            ChatError::Empty => RejectCode::ChatEmpty,
+            ChatError::TooLong => RejectCode::ChatTooLong,
+            ChatError::RateLimited => RejectCode::RateLimited,
+        }
+    }
+}
+
+pub struct ChatLog {
+    history: VecDeque<ServerToClient>,
+    /// Send times of each seat's recent messages, oldest first.
+    recent: Vec<VecDeque<Instant>>,
+}
+
+impl ChatLog {
+    pub fn new(seats: usize) -> Self {
+        Self { history: VecDeque::with_capacity(HISTORY), recent: vec![VecDeque::new(); seats] }
+    }
+
+    /// Validate and record a message from `seat`, signed `name`, returning the frame to broadcast.
+    pub fn post(&mut self, seat: usize, name: String, text: &str) -> Result<ServerToClient, ChatError> {
+        let text = text.trim();
+        if text.is_empty() { return Err(ChatError::Empty); }
+        if text.chars().count() > MAX_CHAT_CHARS { return Err(ChatError::TooLong); }
+
+        let now = Instant::now();
+        let recent = &mut self.recent[seat];
+        while recent.front().is_some_and(|t| now.duration_since(*t) >= RATE_WINDOW) {
+            recent.pop_front();
+        }
+        if recent.len() >= RATE_BURST { return Err(ChatError::RateLimited); }
+        recent.push_back(now);
+
+        let at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+        let msg = ServerToClient::Chat { from: seat, name, text: text.to_string(), at };
+        if self.history.len() == HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(msg.clone());
+        Ok(msg)
+    }
+
+    pub fn history(&self) -> impl Iterator<Item = &ServerToClient> {
+        self.history.iter()
+    }
+}
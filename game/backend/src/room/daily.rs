@@ -0,0 +1,164 @@
+//! The daily challenge: one deal per UTC day, the same in every room.
+//!
+//! Rooms created for the challenge shuffle with an RNG seeded from the day
+//! and post each seat's score from their first game to the `DailyBoard`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::http::auth::Identity;
+
+/// Mixed into the day so the challenge deals don't line up with other seeded RNGs.
+const SEED_SALT: u64 = 0x5a0b_b0da_11e5_eed5;
+/// Days of scores the board keeps, today's included.
+const KEEP_DAYS: u32 = 7;
+
+/// Days since the Unix epoch, UTC: which challenge is on today.
+pub fn today() -> u32 {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    (secs / 86_400) as u32
+}
+
+/// `day` as `YYYY-MM-DD`, the challenge's public id.
+pub fn seed_id(day: u32) -> String {
+    // Howard Hinnant's `civil_from_days`, for days on or after 1970-01-01.
+    let z = i64::from(day) + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+/// The seed every room playing `day`'s challenge deals from.
+pub fn seed(day: u32) -> u64 {
+    SEED_SALT ^ u64::from(day)
+}
+
+/// One seat's result in a daily challenge game.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DailyScore {
+    pub score: i32,
+    pub won: bool,
+    /// Seats at the table; the deal differs with the number of players.
+    pub players: usize,
+    /// Who posted it, if they had an identity.
+    pub player_id: Option<String>,
+    pub name: Option<String>,
+}
+
+/// A player's best daily challenge score over the days the board keeps.
+#[derive(Debug, Clone)]
+pub struct WeeklyBest {
+    pub player_id: String,
+    pub name: String,
+    pub score: i32,
+    /// Challenge games they finished in that time.
+    pub games: u32,
+}
+
+/// Scores posted for the last few challenges. Kept in memory only, so a
+/// restart clears it.
+#[derive(Debug, Default)]
+pub struct DailyBoard {
+    days: Mutex<BTreeMap<u32, Vec<DailyScore>>>,
+}
+
+impl DailyBoard {
+    /// Post every seat's score from a finished game of `day`'s challenge,
+    /// `players` in seat order.
+    pub fn record(&self, day: u32, scores: &[i32], winner: Option<usize>, players: &[Option<&Identity>]) {
+        let mut days = self.days.lock().unwrap();
+        let entries = days.entry(day).or_default();
+        for (seat, (&score, player)) in scores.iter().zip(players).enumerate() {
+            entries.push(DailyScore {
+                score,
+                won: winner == Some(seat),
+                players: scores.len(),
+                player_id: player.map(|p| p.player_id.clone()),
+                name: player.map(|p| p.name.clone()),
+            });
+        }
+        days.retain(|&d, _| d + KEEP_DAYS > day);
+    }
+
+    /// The best `limit` scores for `day`, lowest first.
+    pub fn top(&self, day: u32, limit: usize) -> Vec<DailyScore> {
+        let days = self.days.lock().unwrap();
+        let mut scores = days.get(&day).cloned().unwrap_or_default();
+        scores.sort_by_key(|s| (s.score, !s.won));
+        scores.truncate(limit);
+        scores
+    }
+
+    /// Each identified player's best score from the challenges of the week
+    /// up to `day`, lowest first. Their latest name is the one shown.
+    pub fn weekly(&self, day: u32) -> Vec<WeeklyBest> {
+        let days = self.days.lock().unwrap();
+        let mut best: HashMap<&str, WeeklyBest> = HashMap::new();
+        for score in days.range(day.saturating_sub(KEEP_DAYS - 1)..=day).flat_map(|(_, scores)| scores) {
+            let (Some(player_id), Some(name)) = (&score.player_id, &score.name) else { continue };
+            let entry = best.entry(player_id).or_insert_with(|| WeeklyBest {
+                player_id: player_id.clone(),
+                name: name.clone(),
+                score: score.score,
+                games: 0,
+            });
+            entry.name.clone_from(name);
+            entry.score = entry.score.min(score.score);
+            entry.games += 1;
+        }
+        let mut best: Vec<_> = best.into_values().collect();
+        best.sort_by(|a, b| a.score.cmp(&b.score).then(b.games.cmp(&a.games)).then_with(|| a.name.cmp(&b.name)));
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(id: &str, name: &str) -> Identity {
+        Identity { player_id: id.into(), name: name.into() }
+    }
+
+    #[test]
+    fn each_day_has_its_own_date_and_deal() {
+        assert_eq!(seed_id(0), "1970-01-01");
+        assert_eq!(seed_id(19_782), "2024-02-29");
+        assert_eq!(seed_id(20_000), "2024-10-04");
+        assert_eq!(seed(20_000), seed(20_000));
+        assert_ne!(seed(20_000), seed(20_001));
+    }
+
+    #[test]
+    fn the_day_board_puts_the_lowest_first_and_a_win_before_a_tie() {
+        let board = DailyBoard::default();
+        let (ann, bo) = (player("a", "Ann"), player("b", "Bo"));
+        board.record(100, &[9, 4], Some(1), &[Some(&ann), None]);
+        board.record(100, &[4, 4], None, &[Some(&bo), Some(&ann)]);
+        let top: Vec<_> = board.top(100, 3).into_iter().map(|s| (s.score, s.won, s.name)).collect();
+        assert_eq!(top, [(4, true, None), (4, false, Some("Bo".into())), (4, false, Some("Ann".into()))]);
+        assert!(board.top(101, 3).is_empty());
+    }
+
+    #[test]
+    fn the_week_keeps_each_players_best_under_their_latest_name() {
+        let board = DailyBoard::default();
+        board.record(89, &[1, 30], Some(0), &[Some(&player("a", "Ann")), Some(&player("b", "Bo"))]);
+        board.record(95, &[8, 6], Some(1), &[Some(&player("a", "Ann")), Some(&player("b", "Bo"))]);
+        board.record(96, &[5, 2], Some(1), &[Some(&player("a", "Annie")), None]);
+        // Day 89 is more than a week before 96, so it's gone.
+        let week: Vec<_> = board.weekly(96).into_iter().map(|b| (b.player_id, b.name, b.score, b.games)).collect();
+        assert_eq!(week, [("a".into(), "Annie".into(), 5, 2), ("b".into(), "Bo".into(), 6, 1)]);
+        assert!(board.top(89, 3).is_empty());
+    }
+}
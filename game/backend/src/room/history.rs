@@ -0,0 +1,230 @@
+//! Match history: a summary of every finished game, filed under the
+//! `player_id` of each guest identity that sat in it.
+//!
+//! Seats nobody with an identity connected to, bots included, aren't
+//! tracked. Rooms with a store also save each summary there, so history
+//! outlives a restart.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::ws::protocol::{FinishReason, GameMode, Power};
+
+/// Games kept per player; the oldest go first.
+pub const KEEP_PER_PLAYER: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Won,
+    Lost,
+    /// Nobody had the strictly lowest score.
+    Drew,
+}
+
+/// Another seat in the game.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Opponent {
+    pub seat: usize,
+    pub score: i32,
+    /// Who sat there, if they had an identity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// One finished game, from one player's side of the table.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GameSummary {
+    pub game_id: String,
+    pub room_id: String,
+    pub finished_at_unix: u64,
+    #[schema(value_type = Object)]
+    pub mode: GameMode,
+    pub seat: usize,
+    pub score: i32,
+    pub opponents: Vec<Opponent>,
+    pub outcome: Outcome,
+    #[schema(value_type = Object)]
+    pub reason: FinishReason,
+    /// Whether this seat called Zobbo.
+    #[serde(default)]
+    pub called_zobbo: bool,
+    /// Every power this seat used, in order; skipped ones aren't counted.
+    #[serde(default)]
+    #[schema(value_type = Vec<String>)]
+    pub powers_used: Vec<Power>,
+}
+
+/// How often a player used a power.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PowerUses {
+    #[schema(value_type = String)]
+    pub power: Power,
+    pub uses: u32,
+}
+
+/// A player's record over the games their history keeps.
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct PlayerStats {
+    pub games: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    /// Wins over games; absent with no games.
+    pub win_rate: Option<f64>,
+    pub average_score: Option<f64>,
+    pub zobbo_calls: u32,
+    /// Calls that won the game.
+    pub zobbo_wins: u32,
+    /// `zobbo_wins` over `zobbo_calls`; absent without calls.
+    pub zobbo_success_rate: Option<f64>,
+    /// Most used first.
+    pub powers: Vec<PowerUses>,
+    #[schema(value_type = Option<String>)]
+    pub favorite_power: Option<Power>,
+}
+
+/// Recent games per player, in memory.
+#[derive(Debug, Default)]
+pub struct MatchHistory {
+    players: Mutex<HashMap<String, VecDeque<GameSummary>>>,
+}
+
+impl MatchHistory {
+    pub fn record(&self, player: &str, summary: GameSummary) {
+        let mut players = self.players.lock().unwrap();
+        let games = players.entry(player.to_string()).or_default();
+        if games.len() >= KEEP_PER_PLAYER {
+            games.pop_front();
+        }
+        games.push_back(summary);
+    }
+
+    /// Up to `limit` of `player`'s games, newest first, skipping the `offset`
+    /// newest. Also returns the offset of the next page, if there is one.
+    pub fn page(&self, player: &str, offset: usize, limit: usize) -> (Vec<GameSummary>, Option<usize>) {
+        let players = self.players.lock().unwrap();
+        let Some(games) = players.get(player) else { return (Vec::new(), None) };
+        let page: Vec<_> = games.iter().rev().skip(offset).take(limit).cloned().collect();
+        let next = offset + page.len();
+        (page, (next < games.len()).then_some(next))
+    }
+
+    /// Totals over every game kept for `player`.
+    pub fn stats(&self, player: &str) -> PlayerStats {
+        let players = self.players.lock().unwrap();
+        let mut stats = PlayerStats::default();
+        let mut total_score = 0i64;
+        for game in players.get(player).into_iter().flatten() {
+            stats.games += 1;
+            match game.outcome {
+                Outcome::Won => stats.wins += 1,
+                Outcome::Lost => stats.losses += 1,
+                Outcome::Drew => stats.draws += 1,
+            }
+            total_score += i64::from(game.score);
+            if game.called_zobbo {
+                stats.zobbo_calls += 1;
+                stats.zobbo_wins += u32::from(game.outcome == Outcome::Won);
+            }
+            for &power in &game.powers_used {
+                match stats.powers.iter_mut().find(|p| p.power == power) {
+                    Some(p) => p.uses += 1,
+                    None => stats.powers.push(PowerUses { power, uses: 1 }),
+                }
+            }
+        }
+        let rate = |n: u32, of: u32| (of > 0).then(|| f64::from(n) / f64::from(of));
+        stats.win_rate = rate(stats.wins, stats.games);
+        stats.average_score = (stats.games > 0).then(|| total_score as f64 / f64::from(stats.games));
+        stats.zobbo_success_rate = rate(stats.zobbo_wins, stats.zobbo_calls);
+        stats.powers.sort_by_key(|p| std::cmp::Reverse(p.uses));
+        stats.favorite_power = stats.powers.first().map(|p| p.power);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(n: usize) -> GameSummary {
+        GameSummary {
+            game_id: format!("g{n}"),
+            room_id: "room".into(),
+            finished_at_unix: n as u64,
+            mode: GameMode::Single,
+            seat: 0,
+            score: 10,
+            opponents: Vec::new(),
+            outcome: Outcome::Won,
+            reason: FinishReason::Zobbo,
+            called_zobbo: false,
+            powers_used: Vec::new(),
+        }
+    }
+
+    fn ids(games: &[GameSummary]) -> Vec<&str> {
+        games.iter().map(|g| g.game_id.as_str()).collect()
+    }
+
+    #[test]
+    fn pages_run_newest_first_and_end_without_a_cursor() {
+        let history = MatchHistory::default();
+        for n in 0..5 {
+            history.record("p", game(n));
+        }
+        let (page, next) = history.page("p", 0, 2);
+        assert_eq!((ids(&page), next), (vec!["g4", "g3"], Some(2)));
+        let (page, next) = history.page("p", 2, 2);
+        assert_eq!((ids(&page), next), (vec!["g2", "g1"], Some(4)));
+        // The last page, short or exactly full, has no next.
+        assert_eq!(history.page("p", 4, 2).1, None);
+        assert_eq!(history.page("p", 3, 2).1, None);
+        assert_eq!(history.page("p", 0, 5).1, None);
+        let (page, next) = history.page("p", 9, 2);
+        assert_eq!((page.len(), next), (0, None));
+        let (page, next) = history.page("nobody", 0, 2);
+        assert_eq!((page.len(), next), (0, None));
+    }
+
+    #[test]
+    fn stats_count_outcomes_calls_and_powers() {
+        let history = MatchHistory::default();
+        assert_eq!(history.stats("p").games, 0);
+        assert_eq!((history.stats("p").win_rate, history.stats("p").favorite_power), (None, None));
+        let played = [
+            (Outcome::Won, 2, true, vec![Power::PeekOwn, Power::PeekOpp]),
+            (Outcome::Lost, 15, true, vec![Power::PeekOpp]),
+            (Outcome::Drew, 7, false, vec![Power::PeekOpp, Power::BlindSwap]),
+            (Outcome::Won, 0, false, vec![]),
+        ];
+        for (n, (outcome, score, called_zobbo, powers_used)) in played.into_iter().enumerate() {
+            history.record("p", GameSummary { outcome, score, called_zobbo, powers_used, ..game(n) });
+        }
+        let stats = history.stats("p");
+        assert_eq!((stats.games, stats.wins, stats.losses, stats.draws), (4, 2, 1, 1));
+        assert_eq!((stats.win_rate, stats.average_score), (Some(0.5), Some(6.0)));
+        assert_eq!((stats.zobbo_calls, stats.zobbo_wins, stats.zobbo_success_rate), (2, 1, Some(0.5)));
+        let powers: Vec<_> = stats.powers.iter().map(|p| (p.power, p.uses)).collect();
+        assert_eq!(powers[0], (Power::PeekOpp, 3));
+        assert_eq!(powers.len(), 3);
+        assert_eq!(stats.favorite_power, Some(Power::PeekOpp));
+        assert_eq!(history.stats("somebody else").games, 0);
+    }
+
+    #[test]
+    fn only_the_newest_games_are_kept() {
+        let history = MatchHistory::default();
+        for n in 0..KEEP_PER_PLAYER + 3 {
+            history.record("p", game(n));
+        }
+        let (page, next) = history.page("p", KEEP_PER_PLAYER - 1, 10);
+        assert_eq!((ids(&page), next), (vec!["g3"], None));
+    }
+}
@@ -1,48 +1,226 @@
 //! Registry of rooms and task orchestration.
 
-use std::time::{Duration, SystemTime};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 use dashmap::DashMap;
-use serde::{Deserialize, Serialize};
+use dashmap::mapref::entry::Entry;
+use dashmap::mapref::one::RefMut;
+use futures_util::future::join_all;
+use tokio::sync::oneshot;
+use serde::Serialize;
+use utoipa::ToSchema;
 
-use crate::util::id::{new_join_token, new_room_id};
+use crate::config;
+use crate::room::archive::GameArchive;
+use crate::room::bans::{Ban, BanList};
+use crate::room::daily::DailyBoard;
+use crate::room::history::MatchHistory;
+use crate::room::ratings::Ratings;
+use crate::room::room::{self, Records, RoomCommand, RoomHandle, RoomInspection, RoomSettings, RoomSnapshot};
+use crate::room::tutorial;
+use crate::store::{Restored, RoomRecord, StoreHandle, StoredRoom, StoredSummary};
+use crate::util::id::{new_join_token, new_reconnect_token, new_room_code, new_room_id, normalize_room_code};
+use crate::ws::protocol::GameMode;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Room {
     pub id: String,
-    pub tokens: Vec<String>, // simple list for MVP (creator + invite)
+    /// Short code for `POST /api/join-by-code`; unique among live rooms.
+    pub code: String,
+    pub tokens: Vec<String>, // creator first, then one invite per other seat; index is the seat
     pub players: usize,
     pub created_at: SystemTime,
+    pub settings: RoomSettings,
+    /// Seat played by a server-side bot, if one was added.
+    pub bot_seat: Option<usize>,
+    /// Listed by `GET /api/rooms` while it has a free seat.
+    pub public: bool,
+    /// Seats whose token has been handed out: the creator's, and any given
+    /// away by `claim_public_seat` or `claim_by_code`.
+    pub claimed: Vec<usize>,
+    #[serde(skip)]
+    pub handle: RoomHandle,
 }
 
 impl Room {
-    fn new() -> (Self, String, String) {
-        let id = new_room_id();
-        let creator = new_join_token();
-        let invite = new_join_token();
-        let room = Room {
+    fn new(
+        id: String,
+        code: String,
+        seats: usize,
+        settings: RoomSettings,
+        public: bool,
+        store: Option<StoreHandle>,
+        records: Records,
+    ) -> Self {
+        let tokens: Vec<String> = (0..seats).map(|_| new_join_token()).collect();
+        Room {
             id: id.clone(),
-            tokens: vec![creator.clone(), invite.clone()],
+            code,
+            handle: room::spawn(id, tokens.len(), settings, store, RoomSnapshot::default(), records),
+            tokens,
             players: 0,
             created_at: SystemTime::now(),
-        };
-        (room, creator, invite)
+            settings,
+            bot_seat: None,
+            public,
+            // Nobody may take the seat the tutorial plays itself.
+            claimed: if settings.tutorial { vec![0, tutorial::TUTOR] } else { vec![0] },
+        }
+    }
+
+    /// Bring back a room saved by a previous run, resuming its game.
+    fn restore(stored: StoredRoom, store: Option<StoreHandle>, records: Records) -> Self {
+        let RoomRecord { id, tokens, settings, bot_seat, created_at, public, mut claimed, invite_claimed, code } =
+            stored.record;
+        if claimed.is_empty() {
+            claimed.push(0);
+            if invite_claimed { claimed.push(1); }
+        }
+        Room {
+            handle: room::spawn(id.clone(), tokens.len(), settings, store, stored.snapshot, records),
+            id,
+            code,
+            tokens,
+            players: 0,
+            created_at,
+            settings,
+            bot_seat,
+            public,
+            claimed,
+        }
+    }
+
+    fn record(&self) -> RoomRecord {
+        RoomRecord {
+            id: self.id.clone(),
+            tokens: self.tokens.clone(),
+            settings: self.settings,
+            bot_seat: self.bot_seat,
+            created_at: self.created_at,
+            public: self.public,
+            claimed: self.claimed.clone(),
+            invite_claimed: false,
+            code: self.code.clone(),
+        }
     }
 
     fn has_token(&self, token: &str) -> bool {
         self.tokens.iter().any(|t| t == token)
     }
+
+    /// Rotate the tokens of seats the host kicked, so they stop working and
+    /// the seat can be handed out again. Returns whether anything changed.
+    fn settle(&mut self) -> bool {
+        let revoked = self.handle.take_revoked();
+        for &seat in &revoked {
+            self.tokens[seat] = new_join_token();
+            self.claimed.retain(|&s| s != seat);
+            if self.bot_seat == Some(seat) { self.bot_seat = None; }
+        }
+        !revoked.is_empty()
+    }
+
+    /// Seats nobody holds: never handed out, not the bot's, and with nobody
+    /// connected who got the token some other way.
+    fn free_seats(&self) -> Vec<usize> {
+        let connected = self.handle.activity().connected;
+        (0..self.tokens.len())
+            .filter(|&s| !self.claimed.contains(&s) && self.bot_seat != Some(s))
+            .filter(|&s| !connected.get(s).copied().unwrap_or(false))
+            .collect()
+    }
+
+    fn status(&self, now: SystemTime) -> RoomStatus {
+        let settings = self.handle.activity().settings;
+        RoomStatus {
+            id: self.id.clone(),
+            code: self.code.clone(),
+            age_secs: now.duration_since(self.created_at).unwrap_or_default().as_secs(),
+            public: self.public,
+            ranked: settings.ranked,
+            mode: settings.mode(),
+            capacity: self.tokens.len(),
+            bot_seat: self.bot_seat,
+            state: None,
+        }
+    }
+
+    /// Hand out the first free seat, once.
+    fn claim_seat(&mut self) -> Result<String, RoomError> {
+        let seat = *self.free_seats().first().ok_or(RoomError::Full)?;
+        self.claimed.push(seat);
+        Ok(self.tokens[seat].clone())
+    }
 }
 
-#[derive(Clone, Default)]
+/// Most players a room can seat.
+pub const MAX_SEATS: usize = 4;
+
+/// How long `/readyz` waits on the store before calling it unreachable.
+const STORE_PING_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a shutdown waits for the rooms, then the store, to finish up.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A short-lived grant to re-open a dropped WS without the join token.
+#[derive(Debug, Clone)]
+struct ReconnectGrant {
+    room_id: String,
+    join_token: String,
+    expires_at: Instant,
+}
+
+#[derive(Default)]
 pub struct RoomManager {
     rooms: DashMap<String, Room>,
+    /// Room code -> room id.
+    codes: DashMap<String, String>,
+    reconnect: DashMap<String, ReconnectGrant>,
+    store: Option<StoreHandle>,
+    records: Records,
+    /// While set, no new rooms are created; games already running carry on.
+    maintenance: AtomicBool,
+    bans: BanList,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct CreatedRoom {
     pub id: String,
+    pub code: String,
     pub creator_token: String,
-    pub invite_token: String,
+    /// One per seat after the creator's.
+    pub invite_tokens: Vec<String>,
+}
+
+/// A public room someone could join right now, as listed by `GET /api/rooms`.
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenRoom {
+    pub id: String,
+    pub mode: GameMode,
+    pub turn_secs: Option<u64>,
+    /// Humans connected right now.
+    pub players: usize,
+    /// Seats in the room, taken or not.
+    pub capacity: usize,
+    pub age_secs: u64,
+}
+
+/// A room as an operator sees it, for `GET /admin/rooms`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RoomStatus {
+    pub id: String,
+    pub code: String,
+    pub age_secs: u64,
+    pub public: bool,
+    pub ranked: bool,
+    #[schema(value_type = Object)]
+    pub mode: GameMode,
+    pub capacity: usize,
+    pub bot_seat: Option<usize>,
+    /// What the room's task reported; absent if it didn't answer, which
+    /// means it's stuck or has just stopped.
+    pub state: Option<RoomInspection>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -53,40 +231,433 @@ pub enum RoomError {
     InvalidToken,
     #[error("room full")]
     Full,
+    #[error("bots can't play in ranked rooms")]
+    Ranked,
+    #[error("the server is down for maintenance; no new rooms for now")]
+    Maintenance,
+    #[error("the server is at capacity; no new rooms for now")]
+    AtCapacity,
 }
 
 impl RoomManager {
-    pub fn new() -> Self { Self { rooms: DashMap::new() } }
+    pub fn new() -> Self { Self::default() }
+
+    /// A manager that persists to `store`, starting with the rooms, match
+    /// history, ratings and bans it held before.
+    pub fn with_store(store: StoreHandle, restored: Restored) -> Self {
+        let manager = Self { store: Some(store.clone()), ..Self::default() };
+        for StoredSummary { player, summary } in restored.history {
+            manager.records.history.record(&player, summary);
+        }
+        for (player_id, rating) in restored.ratings {
+            manager.records.ratings.restore(player_id, rating);
+        }
+        for ban in restored.bans {
+            manager.bans.insert(ban);
+        }
+        for stored in restored.rooms {
+            let mut room = Room::restore(stored, Some(store.clone()), manager.records.clone());
+            if room.code.is_empty() || manager.codes.contains_key(&room.code) {
+                room.code = manager.reserve_code(&room.id);
+                store.save_room(&room.record());
+            } else {
+                manager.codes.insert(room.code.clone(), room.id.clone());
+            }
+            manager.rooms.insert(room.id.clone(), room);
+        }
+        manager
+    }
+
+    /// Draw a code no live room is using and point it at `room_id`.
+    fn reserve_code(&self, room_id: &str) -> String {
+        loop {
+            let code = new_room_code();
+            if let Entry::Vacant(slot) = self.codes.entry(code.clone()) {
+                slot.insert(room_id.to_string());
+                return code;
+            }
+        }
+    }
+
+    /// Room `id`, with any kicks its task reported applied to the tokens.
+    fn settled(&self, id: &str) -> Option<RefMut<'_, String, Room>> {
+        let mut room = self.rooms.get_mut(id)?;
+        if room.settle()
+            && let Some(store) = &self.store
+        {
+            store.save_room(&room.record());
+        }
+        Some(room)
+    }
+
+    /// Rooms with a bot seat, so the bots can be restarted after a restore.
+    pub fn bot_seats(&self) -> Vec<(String, usize, RoomHandle)> {
+        self.rooms
+            .iter()
+            .filter_map(|r| r.bot_seat.map(|seat| (r.id.clone(), seat, r.handle.clone())))
+            .collect()
+    }
+
+    /// Create a room with `seats` seats (2 to `MAX_SEATS`), unless the server
+    /// is in maintenance mode.
+    pub fn create_room(&self, settings: RoomSettings, seats: usize, public: bool) -> Result<CreatedRoom, RoomError> {
+        self.accepting()?;
+        // Claim the id before starting the room, so a clash can't replace a live one.
+        let slot = loop {
+            if let Entry::Vacant(slot) = self.rooms.entry(new_room_id()) { break slot; }
+        };
+        let id = slot.key().clone();
+        let code = self.reserve_code(&id);
+        let room = Room::new(id.clone(), code.clone(), seats, settings, public, self.store.clone(), self.records.clone());
+        if let Some(store) = &self.store { store.save_room(&room.record()); }
+        let mut tokens = room.tokens.clone();
+        slot.insert(room);
+        let creator_token = tokens.remove(0);
+        Ok(CreatedRoom { id, code, creator_token, invite_tokens: tokens })
+    }
+
+    /// Whether new rooms can be made, and if not why: maintenance, or
+    /// `MAX_ROOMS` already live.
+    pub fn accepting(&self) -> Result<(), RoomError> {
+        if self.maintenance() { return Err(RoomError::Maintenance); }
+        if self.room_count() >= config::get().max_rooms { return Err(RoomError::AtCapacity); }
+        Ok(())
+    }
+
+    /// Whether new rooms are refused for maintenance.
+    pub fn maintenance(&self) -> bool {
+        self.maintenance.load(Ordering::Relaxed)
+    }
+
+    /// Start or stop refusing new rooms, ahead of a deploy.
+    pub fn set_maintenance(&self, on: bool) {
+        self.maintenance.store(on, Ordering::Relaxed);
+    }
+
+    /// Wind down for a restart: refuse new rooms, tell every room's players
+    /// to come back in `reconnect_after`, and wait for the store to write
+    /// every game. Rooms stay in the store, so they're restored on boot.
+    pub async fn drain(&self, reconnect_after: Duration) {
+        self.set_maintenance(true);
+        let saved: Vec<_> = self
+            .rooms
+            .iter()
+            .filter_map(|room| {
+                let (done, saved) = oneshot::channel();
+                let drain = RoomCommand::Drain { reconnect_after_secs: reconnect_after.as_secs(), done };
+                room.handle.send(drain).then_some(saved)
+            })
+            .collect();
+        tracing::info!(rooms = saved.len(), "draining rooms");
+        if tokio::time::timeout(DRAIN_TIMEOUT, join_all(saved)).await.is_err() {
+            tracing::warn!("rooms didn't all drain in time");
+        }
+        // The store writes in order, so once it answers everything queued before is written.
+        if let Some(store) = &self.store
+            && !store.ping(DRAIN_TIMEOUT).await
+        {
+            tracing::warn!("store didn't confirm its writes before shutdown");
+        }
+    }
+
+    /// Whether the store, if there is one, answers.
+    pub async fn store_reachable(&self) -> bool {
+        match &self.store {
+            Some(store) => store.ping(STORE_PING_TIMEOUT).await,
+            None => true,
+        }
+    }
+
+    /// How many rooms are live.
+    pub fn room_count(&self) -> usize {
+        self.rooms.len()
+    }
+
+    /// Close room `id` for good: its players are told, and it's dropped from
+    /// the registry and the store. Returns whether there was such a room.
+    pub fn close_room(&self, id: &str) -> bool {
+        let Some((_, room)) = self.rooms.remove(id) else { return false };
+        room.handle.send(RoomCommand::Close);
+        if let Some(store) = &self.store { store.delete(id); }
+        self.codes.remove(&room.code);
+        self.reconnect.retain(|_, g| g.room_id != id);
+        true
+    }
+
+    /// Scores posted by daily challenge rooms.
+    pub fn daily(&self) -> &DailyBoard {
+        &self.records.daily
+    }
+
+    /// Logs of recently finished games.
+    pub fn archive(&self) -> &GameArchive {
+        &self.records.archive
+    }
+
+    /// Every player's recent games.
+    pub fn history(&self) -> &MatchHistory {
+        &self.records.history
+    }
+
+    /// Ratings from ranked games.
+    pub fn ratings(&self) -> &Ratings {
+        &self.records.ratings
+    }
+
+    /// Bans in force, newest first.
+    pub fn bans(&self) -> Vec<Ban> {
+        self.bans.active()
+    }
 
-    pub fn create_room(&self) -> CreatedRoom {
-        let (room, creator, invite) = Room::new();
-        let id = room.id.clone();
-        self.rooms.insert(id.clone(), room);
-        CreatedRoom { id, creator_token: creator, invite_token: invite }
+    /// A ban in force on `player_id` or `ip`, if there is one.
+    pub fn banned(&self, player_id: Option<&str>, ip: IpAddr) -> Option<Ban> {
+        self.bans.find(player_id, ip)
+    }
+
+    pub fn ban(&self, ban: Ban) {
+        if let Some(store) = &self.store { store.save_ban(&ban); }
+        self.bans.insert(ban);
+    }
+
+    /// Lift ban `id`, returning it if there was one.
+    pub fn lift_ban(&self, id: &str) -> Option<Ban> {
+        let ban = self.bans.remove(id)?;
+        if let Some(store) = &self.store { store.delete_ban(id); }
+        Some(ban)
+    }
+
+    /// The code of room `id`, for showing to its players.
+    pub fn code(&self, id: &str) -> Option<String> {
+        self.rooms.get(id).map(|r| r.code.clone())
+    }
+
+    /// Hand out a free seat of the room with `code`, returning the room id and token.
+    pub fn claim_by_code(&self, code: &str) -> Result<(String, String), RoomError> {
+        let id = self.codes.get(&normalize_room_code(code)).map(|id| id.clone()).ok_or(RoomError::NotFound)?;
+        let mut room = self.settled(&id).ok_or(RoomError::NotFound)?;
+        let token = room.claim_seat()?;
+        if let Some(store) = &self.store { store.save_room(&room.record()); }
+        Ok((id, token))
     }
 
     pub fn join_room(&self, id: &str, token: &str) -> Result<(), RoomError> {
-        let mut entry = self.rooms.get_mut(id).ok_or(RoomError::NotFound)?;
+        let mut entry = self.settled(id).ok_or(RoomError::NotFound)?;
         if !entry.has_token(token) { return Err(RoomError::InvalidToken); }
-        if entry.players >= 2 { return Err(RoomError::Full); }
+        if entry.players >= entry.tokens.len() { return Err(RoomError::Full); }
         entry.players += 1;
         Ok(())
     }
 
     pub fn has_token(&self, id: &str, token: &str) -> bool {
-        self.rooms.get(id).map(|r| r.has_token(token)).unwrap_or(false)
+        self.settled(id).map(|r| r.has_token(token)).unwrap_or(false)
     }
 
-    /// Returns the other token in the room that is not `token`, if any.
-    pub fn other_token(&self, id: &str, token: &str) -> Option<String> {
-        self.rooms
-            .get(id)
-            .and_then(|r| r.tokens.iter().find(|t| *t != token).cloned())
+    /// The join token for a seat in room `id`, from the join token itself or
+    /// a reconnect token, which this uses up. A reconnect token wins if both
+    /// are given.
+    pub fn seat_token(&self, id: &str, token: Option<&str>, reconnect: Option<&str>) -> Option<String> {
+        match (token, reconnect) {
+            (_, Some(reconnect)) => self.redeem_reconnect_token(id, reconnect),
+            (Some(token), None) => self.has_token(id, token).then(|| token.to_string()),
+            (None, None) => None,
+        }
+    }
+
+    /// The seat `token` sits in and a handle to the room's task, if the token is valid.
+    pub fn seat_handle(&self, id: &str, token: &str) -> Option<(usize, RoomHandle)> {
+        let room = self.settled(id)?;
+        let seat = room.tokens.iter().position(|t| t == token)?;
+        Some((seat, room.handle.clone()))
+    }
+
+    /// The other seats' tokens in the room, for `token`'s holder to share.
+    ///
+    /// A bot's token is never handed out.
+    pub fn invite_tokens(&self, id: &str, token: &str) -> Vec<(usize, String)> {
+        let Some(room) = self.settled(id) else { return Vec::new() };
+        room.tokens
+            .iter()
+            .enumerate()
+            .filter(|(seat, t)| *t != token && room.bot_seat != Some(*seat))
+            .map(|(seat, t)| (seat, t.clone()))
+            .collect()
     }
 
-    #[allow(dead_code)]
-    pub fn prune_old(&self, max_age: Duration) {
+    /// Public rooms with a seat still up for grabs, newest first.
+    pub fn open_rooms(&self) -> Vec<OpenRoom> {
+        for mut room in self.rooms.iter_mut() {
+            if room.settle()
+                && let Some(store) = &self.store
+            {
+                store.save_room(&room.record());
+            }
+        }
         let now = SystemTime::now();
-        self.rooms.retain(|_, r| now.duration_since(r.created_at).unwrap_or_default() < max_age);
+        let mut open: Vec<OpenRoom> = self
+            .rooms
+            .iter()
+            .filter(|r| r.public && !r.free_seats().is_empty())
+            .map(|r| {
+                let activity = r.handle.activity();
+                let connected = activity.connected;
+                OpenRoom {
+                    id: r.id.clone(),
+                    // The host may have changed these since creating the room.
+                    mode: activity.settings.mode(),
+                    turn_secs: activity.settings.turn_limit.map(|d| d.as_secs()),
+                    players: connected.iter().enumerate().filter(|(s, c)| **c && r.bot_seat != Some(*s)).count(),
+                    capacity: r.tokens.len(),
+                    age_secs: now.duration_since(r.created_at).unwrap_or_default().as_secs(),
+                }
+            })
+            .collect();
+        open.sort_by_key(|r| r.age_secs);
+        open
+    }
+
+    /// Every room, oldest first, with what its task reports.
+    pub async fn statuses(&self) -> Vec<RoomStatus> {
+        let now = SystemTime::now();
+        // Collect first: the map mustn't stay locked while the tasks answer.
+        let mut rooms: Vec<_> = self.rooms.iter().map(|r| (r.status(now), r.handle.clone())).collect();
+        rooms.sort_by_key(|(status, _)| std::cmp::Reverse(status.age_secs));
+        let states = join_all(rooms.iter().map(|(_, handle)| handle.inspect())).await;
+        rooms.into_iter().zip(states).map(|((status, _), state)| RoomStatus { state, ..status }).collect()
+    }
+
+    /// Room `id` with what its task reports.
+    pub async fn status(&self, id: &str) -> Option<RoomStatus> {
+        let (status, handle) = self.rooms.get(id).map(|r| (r.status(SystemTime::now()), r.handle.clone()))?;
+        Some(RoomStatus { state: handle.inspect().await, ..status })
+    }
+
+    /// Hand out a free seat of public room `id` to whoever asks first.
+    ///
+    /// Private rooms answer `NotFound`, so their ids can't be probed.
+    pub fn claim_public_seat(&self, id: &str) -> Result<String, RoomError> {
+        let mut room = self.settled(id).filter(|r| r.public).ok_or(RoomError::NotFound)?;
+        let token = room.claim_seat()?;
+        if let Some(store) = &self.store { store.save_room(&room.record()); }
+        Ok(token)
+    }
+
+    /// Give the first free seat after `token`'s to a bot, returning that seat
+    /// and the room handle. A room takes at most one bot.
+    ///
+    /// The seat's invite token is rotated so a human can no longer take it.
+    pub fn add_bot(&self, id: &str, token: &str) -> Result<(usize, RoomHandle), RoomError> {
+        let mut room = self.settled(id).ok_or(RoomError::NotFound)?;
+        let mine = room.tokens.iter().position(|t| t == token).ok_or(RoomError::InvalidToken)?;
+        if room.bot_seat.is_some() { return Err(RoomError::Full); }
+        if room.settings.ranked { return Err(RoomError::Ranked); }
+        let n = room.tokens.len();
+        let seat = room.free_seats().into_iter().filter(|&s| s != mine).min_by_key(|&s| (s + n - mine) % n);
+        let seat = seat.ok_or(RoomError::Full)?;
+        room.tokens[seat] = new_join_token();
+        room.bot_seat = Some(seat);
+        if let Some(store) = &self.store { store.save_room(&room.record()); }
+        Ok((seat, room.handle.clone()))
+    }
+
+    /// Issue a reconnect token standing in for `join_token` in room `id` for `ttl`.
+    ///
+    /// Expired grants are swept on every issue so the map stays bounded by the
+    /// number of live connections.
+    pub fn issue_reconnect_token(&self, id: &str, join_token: &str, ttl: Duration) -> String {
+        let now = Instant::now();
+        self.reconnect.retain(|_, g| g.expires_at > now);
+        let token = new_reconnect_token();
+        let grant = ReconnectGrant {
+            room_id: id.to_string(),
+            join_token: join_token.to_string(),
+            expires_at: now + ttl,
+        };
+        self.reconnect.insert(token.clone(), grant);
+        token
+    }
+
+    /// Consume a reconnect token for room `id`, returning the join token it stands in for.
+    ///
+    /// Tokens are single-use: a captured one can't be replayed after the
+    /// legitimate client has reconnected with it.
+    pub fn redeem_reconnect_token(&self, id: &str, token: &str) -> Option<String> {
+        let (_, grant) = self.reconnect.remove(token)?;
+        if grant.room_id != id || grant.expires_at <= Instant::now() {
+            return None;
+        }
+        self.has_token(id, &grant.join_token).then_some(grant.join_token)
+    }
+
+    /// Evict rooms no human is connected to: finished ones after
+    /// `finished_linger`, any other after `idle_ttl`. Returns how many went.
+    ///
+    /// A bot's seat doesn't count as someone being there.
+    pub fn prune_old(&self, idle_ttl: Duration, finished_linger: Duration) -> usize {
+        let now = Instant::now();
+        let before = self.rooms.len();
+        self.rooms.retain(|id, room| {
+            let activity = room.handle.activity();
+            let occupied = activity
+                .connected
+                .iter()
+                .enumerate()
+                .any(|(seat, connected)| *connected && room.bot_seat != Some(seat));
+            if occupied { return true; }
+            let (since, ttl) = match activity.finished_at {
+                Some(at) => (at.max(activity.changed_at), finished_linger),
+                None => (activity.changed_at, idle_ttl),
+            };
+            let keep = now.duration_since(since) < ttl;
+            if !keep {
+                tracing::debug!(room_id = %id, "evicting room");
+                room.handle.send(RoomCommand::Shutdown);
+                if let Some(store) = &self.store { store.delete(id); }
+            }
+            keep
+        });
+        self.reconnect.retain(|_, g| g.expires_at > now && self.rooms.contains_key(&g.room_id));
+        self.codes.retain(|_, id| self.rooms.contains_key(id));
+        before - self.rooms.len()
+    }
+}
+
+/// Sweep `rooms` every `every`, evicting idle and finished rooms for good.
+pub async fn run_gc(rooms: Arc<RoomManager>, every: Duration, idle_ttl: Duration, finished_linger: Duration) {
+    let mut tick = tokio::time::interval(every);
+    loop {
+        tick.tick().await;
+        let evicted = rooms.prune_old(idle_ttl, finished_linger);
+        if evicted > 0 {
+            tracing::info!(evicted, remaining = rooms.rooms.len(), "room gc");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn a_code_finds_its_room_however_it_is_typed() {
+        config::load().unwrap();
+        let rooms = RoomManager::new();
+        let room = rooms.create_room(RoomSettings::default(), 3, false).unwrap();
+        assert_eq!(room.code.len(), 6);
+        assert_eq!(normalize_room_code(&room.code), room.code);
+        assert_eq!(rooms.code(&room.id).as_deref(), Some(room.code.as_str()));
+
+        // Lower case, with a space and a dash, as someone might type it.
+        let typed = format!(" {}-{} ", &room.code[..3], &room.code[3..]).to_lowercase();
+        assert_eq!(normalize_room_code(&typed), room.code);
+        let (id, token) = rooms.claim_by_code(&typed).unwrap();
+        assert_eq!(id, room.id);
+        assert!(rooms.has_token(&id, &token) && token != room.creator_token);
+
+        // Each claim hands out a different seat, until none are left.
+        let (_, second) = rooms.claim_by_code(&room.code).unwrap();
+        assert_ne!(second, token);
+        assert!(matches!(rooms.claim_by_code(&room.code), Err(RoomError::Full)));
+        assert!(matches!(rooms.claim_by_code("ZZZZZZ"), Err(RoomError::NotFound)));
+        assert!(matches!(rooms.claim_by_code(""), Err(RoomError::NotFound)));
     }
 }
@@ -0,0 +1,69 @@
+//! Quickmatch: pair strangers into fresh rooms.
+//!
+//! Only one player can ever be waiting: the next one to arrive takes them
+//! out of the queue, so the "queue" is a single slot.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::oneshot;
+
+use crate::room::manager::RoomManager;
+use crate::room::room::RoomSettings;
+
+/// A seat in a freshly created quickmatch room.
+#[derive(Debug, Clone, Serialize)]
+pub struct Matched {
+    pub room_id: String,
+    pub token: String,
+}
+
+struct Waiter {
+    id: u64,
+    tx: oneshot::Sender<Matched>,
+}
+
+#[derive(Default)]
+pub struct Quickmatch {
+    waiting: Mutex<Option<Waiter>>,
+    next_id: AtomicU64,
+}
+
+impl Quickmatch {
+    pub fn new() -> Self { Self::default() }
+
+    /// Pair with the waiting player, or wait up to `wait` for someone to pair with us.
+    ///
+    /// `None` when nobody turned up in time; the caller should try again.
+    pub async fn join(&self, rooms: &RoomManager, wait: Duration) -> Option<Matched> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut rx = {
+            let mut waiting = self.waiting.lock().unwrap();
+            // A waiter whose request was dropped (the client gave up) no longer counts.
+            if let Some(waiter) = waiting.take().filter(|w| !w.tx.is_closed()) {
+                // With new rooms refused the waiter is dropped, which sends them back empty-handed too.
+                let Ok(mut created) = rooms.create_room(RoomSettings::default(), 2, false) else { return None };
+                let theirs = Matched { room_id: created.id.clone(), token: created.creator_token };
+                // Can only fail if they left since the check; the empty room is left to the GC.
+                if waiter.tx.send(theirs).is_ok() {
+                    tracing::info!(room_id = %created.id, "quickmatch paired");
+                    return Some(Matched { room_id: created.id, token: created.invite_tokens.remove(0) });
+                }
+            }
+            let (tx, rx) = oneshot::channel();
+            *waiting = Some(Waiter { id, tx });
+            rx
+        };
+        if let Ok(Ok(matched)) = tokio::time::timeout(wait, &mut rx).await {
+            return Some(matched);
+        }
+        let mut waiting = self.waiting.lock().unwrap();
+        if waiting.as_ref().is_some_and(|w| w.id == id) {
+            *waiting = None;
+        }
+        // We may have been paired between the timeout and taking the lock.
+        rx.try_recv().ok()
+    }
+}
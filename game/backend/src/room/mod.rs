@@ -1,5 +1,16 @@
 //! Room domain: manager and per-room FSM.
 
 // submodules
+pub mod archive;
+pub mod bans;
+pub mod bot;
+pub mod chat;
+pub mod daily;
+pub mod history;
 pub mod manager;
+pub mod matchmaking;
+pub mod ratings;
+pub mod replay;
+#[allow(clippy::module_inception)]
 pub mod room;
+pub mod tutorial;
@@ -0,0 +1,155 @@
+//! Elo ratings for guest identities, updated after every ranked game.
+//!
+//! A game with more than two seats counts as a match between every pair of
+//! seats, each worth a share of `K` so a rating moves about as far whatever
+//! the table size.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use zobbo_core::engine::GameResult;
+
+use crate::http::auth::Identity;
+use crate::ws::protocol::{FinishReason, RatingChange};
+
+/// Where every new player starts.
+pub const INITIAL_RATING: f64 = 1200.0;
+/// Most a rating can move in one game.
+const K: f64 = 32.0;
+
+/// A player's rating, and the name they last played a ranked game under.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerRating {
+    pub name: String,
+    pub rating: f64,
+    /// Ranked games played.
+    pub games: u32,
+}
+
+/// Every rated player's rating, in memory.
+#[derive(Debug, Default)]
+pub struct Ratings {
+    players: Mutex<HashMap<String, PlayerRating>>,
+}
+
+impl Ratings {
+    /// Put back a rating loaded from the store.
+    pub fn restore(&self, player_id: String, rating: PlayerRating) {
+        self.players.lock().unwrap().insert(player_id, rating);
+    }
+
+    /// Every rated player, `(player_id, rating)`, highest first.
+    pub fn ranked(&self) -> Vec<(String, PlayerRating)> {
+        let players = self.players.lock().unwrap();
+        let mut ranked: Vec<_> = players.iter().map(|(id, r)| (id.clone(), r.clone())).collect();
+        ranked.sort_by(|(_, a), (_, b)| b.rating.total_cmp(&a.rating).then(b.games.cmp(&a.games)).then_with(|| a.name.cmp(&b.name)));
+        ranked
+    }
+
+    /// Rate a finished game, `players` in seat order. Returns each seat's
+    /// change, and the ratings to save.
+    pub fn rate(&self, players: &[&Identity], result: &GameResult) -> (Vec<RatingChange>, Vec<PlayerRating>) {
+        let mut ratings = self.players.lock().unwrap();
+        let before: Vec<f64> =
+            players.iter().map(|p| ratings.get(&p.player_id).map_or(INITIAL_RATING, |r| r.rating)).collect();
+        let share = K / (players.len() - 1) as f64;
+        let mut changes = Vec::with_capacity(players.len());
+        let mut saved = Vec::with_capacity(players.len());
+        for (seat, player) in players.iter().enumerate() {
+            let delta: f64 = (0..players.len())
+                .filter(|&other| other != seat)
+                .map(|other| {
+                    let expected = 1.0 / (1.0 + 10f64.powf((before[other] - before[seat]) / 400.0));
+                    share * (points(result, seat, other) - expected)
+                })
+                .sum();
+            let rating = ratings.entry(player.player_id.clone()).or_insert_with(|| PlayerRating {
+                name: player.name.clone(),
+                rating: INITIAL_RATING,
+                games: 0,
+            });
+            rating.name.clone_from(&player.name);
+            rating.rating = before[seat] + delta;
+            rating.games += 1;
+            changes.push(RatingChange {
+                seat,
+                rating: rating.rating.round() as i32,
+                delta: (rating.rating.round() - before[seat].round()) as i32,
+            });
+            saved.push(rating.clone());
+        }
+        (changes, saved)
+    }
+}
+
+/// What `seat` scored against `other`: 1 for a win, ½ for a draw, 0 for a loss.
+/// The winner beats everyone and a seat that forfeited loses to everyone;
+/// otherwise the lower score wins, unless everyone agreed a draw.
+fn points(result: &GameResult, seat: usize, other: usize) -> f64 {
+    let forfeited = match result.reason {
+        FinishReason::AgreedDraw => return 0.5,
+        FinishReason::Forfeit { seat } | FinishReason::Inactive { seat } | FinishReason::OutOfTime { seat } => Some(seat),
+        FinishReason::Zobbo | FinishReason::EmptyHand { .. } => None,
+    };
+    if result.winner == Some(seat) || forfeited == Some(other) { return 1.0; }
+    if result.winner == Some(other) || forfeited == Some(seat) { return 0.0; }
+    match result.scores[seat].cmp(&result.scores[other]) {
+        std::cmp::Ordering::Less => 1.0,
+        std::cmp::Ordering::Equal => 0.5,
+        std::cmp::Ordering::Greater => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn players(n: usize) -> Vec<Identity> {
+        (0..n).map(|i| Identity { player_id: format!("p{i}"), name: format!("Player {i}") }).collect()
+    }
+
+    fn result(winner: Option<usize>, scores: &[i32], reason: FinishReason) -> GameResult {
+        GameResult { winner, scores: scores.to_vec(), zobbo_caller: None, zobbo_penalty: None, reason }
+    }
+
+    fn rate(ratings: &Ratings, players: &[Identity], result: &GameResult) -> Vec<(i32, i32)> {
+        let players: Vec<&Identity> = players.iter().collect();
+        ratings.rate(&players, result).0.iter().map(|c| (c.rating, c.delta)).collect()
+    }
+
+    #[test]
+    fn an_even_game_moves_half_of_k_and_an_upset_more() {
+        let ratings = Ratings::default();
+        let players = players(2);
+        let won = result(Some(0), &[4, 12], FinishReason::Zobbo);
+        assert_eq!(rate(&ratings, &players, &won), [(1216, 16), (1184, -16)]);
+        assert_eq!(ratings.ranked().iter().map(|(id, r)| (id.as_str(), r.games)).collect::<Vec<_>>(), [("p0", 1), ("p1", 1)]);
+
+        // 400 points apart, the favourite is expected to score 10/11.
+        ratings.restore("p0".into(), PlayerRating { name: "Player 0".into(), rating: 1600.0, games: 9 });
+        ratings.restore("p1".into(), PlayerRating { name: "Player 1".into(), rating: 1200.0, games: 9 });
+        let upset = result(Some(1), &[12, 4], FinishReason::Zobbo);
+        assert_eq!(rate(&ratings, &players, &upset), [(1571, -29), (1229, 29)]);
+    }
+
+    #[test]
+    fn an_agreed_draw_between_equals_changes_nothing() {
+        let ratings = Ratings::default();
+        let draw = result(Some(0), &[4, 12], FinishReason::AgreedDraw);
+        assert_eq!(rate(&ratings, &players(2), &draw), [(1200, 0), (1200, 0)]);
+    }
+
+    #[test]
+    fn a_table_of_three_is_rated_pair_by_pair() {
+        let ratings = Ratings::default();
+        // Seat 2 forfeited with the lowest hand; seat 1 still beats it.
+        let forfeit = result(Some(0), &[5, 10, 3], FinishReason::Forfeit { seat: 2 });
+        assert_eq!(rate(&ratings, &players(3), &forfeit), [(1216, 16), (1200, 0), (1184, -16)]);
+        // Without a winner, the lower score takes each pair, and a tie splits it.
+        let tied = result(None, &[5, 5, 9], FinishReason::EmptyHand { seat: 0 });
+        let changes = rate(&ratings, &players(3), &tied);
+        assert!(changes[0].1 > 0 && changes[1].1 > 0 && changes[2].1 < 0, "{changes:?}");
+        assert_eq!(ratings.ranked()[2].0, "p2");
+    }
+}
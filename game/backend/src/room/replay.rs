@@ -0,0 +1,59 @@
+//! Playing archived games back, for review once they're over.
+
+use std::time::Duration;
+
+use serde::Serialize;
+use utoipa::ToSchema;
+use zobbo_core::engine::GameState;
+use zobbo_core::log::{replay, ReplayError};
+
+use crate::room::archive::GameRecord;
+use crate::room::room::{game_over, game_start, hands, public_update};
+use crate::ws::protocol::{CardPublic, GameUpdate, ServerToClient};
+
+/// The table after one entry of a game's log.
+#[derive(Serialize, ToSchema)]
+pub struct ReplayFrame {
+    /// Milliseconds since the deal.
+    pub at_ms: u64,
+    #[schema(value_type = Object)]
+    pub update: GameUpdate,
+    /// Every seat's cards; the game is over, so nothing is secret any more.
+    #[schema(value_type = Vec<Vec<Object>>)]
+    pub hands: Vec<Vec<Option<CardPublic>>>,
+}
+
+/// `record`'s game rebuilt after every entry, each with how long after the deal it was.
+fn states(record: &GameRecord) -> Result<Vec<(u64, GameState)>, ReplayError> {
+    let states = replay(&record.log)?;
+    let dealt = record.log.first().map_or(0, |e| e.at_ms);
+    Ok(record.log.iter().map(|e| e.at_ms.saturating_sub(dealt)).zip(states).collect())
+}
+
+pub fn frames(record: &GameRecord) -> Result<Vec<ReplayFrame>, ReplayError> {
+    Ok(states(record)?
+        .into_iter()
+        .enumerate()
+        .map(|(seq, (at_ms, game))| ReplayFrame { at_ms, update: public_update(&game, seq as u64 + 1), hands: hands(&game) })
+        .collect())
+}
+
+/// The messages a player saw, in order, each with the wait before it at
+/// `speed` times the original pace: `GameStart`, a `GameUpdate` per entry,
+/// then `GameOver`.
+pub fn messages(record: &GameRecord, speed: f64) -> Result<Vec<(Duration, ServerToClient)>, ReplayError> {
+    let states = states(record)?;
+    let mut messages = Vec::with_capacity(states.len() + 2);
+    let (_, first) = &states[0];
+    messages.push((Duration::ZERO, game_start(&record.game_id, first, (1, 1))));
+    let mut previous = 0;
+    for (seq, (at_ms, game)) in states.iter().enumerate() {
+        let wait = Duration::from_millis(at_ms - previous).div_f64(speed);
+        previous = *at_ms;
+        messages.push((wait, ServerToClient::GameUpdate(public_update(game, seq as u64 + 1))));
+    }
+    if let Some((_, last)) = states.last().filter(|(_, g)| g.result.is_some()) {
+        messages.push((Duration::ZERO, game_over(last, &[])));
+    }
+    Ok(messages)
+}
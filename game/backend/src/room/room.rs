@@ -1,3 +1,2305 @@
 //! Single-threaded room arbiter FSM and per-player views.
+//!
+//! Each room runs one task that owns its lobby and `GameState`. Sockets talk
+//! to it only through `RoomHandle`, so actions are applied strictly in order
+//! and no game state is ever shared between tasks.
 
-// Minimal placeholder for compilation.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+use tracing::Instrument;
+use utoipa::ToSchema;
+use zobbo_core::engine::{lowest_unique, GameResult, GameState, RuleError, TurnStage};
+use zobbo_core::log::{GameEvent, LogEntry};
+use zobbo_core::types::{card_public, Action, Card, CardPublic, Event};
+
+use crate::config;
+use crate::reporting::{self, Context};
+use crate::http::auth::Identity;
+use crate::room::archive::{GameArchive, GameRecord};
+use crate::room::chat::{ChatError, ChatLog};
+use crate::room::daily::{self, DailyBoard};
+use crate::room::history::{GameSummary, MatchHistory, Opponent, Outcome};
+use crate::room::ratings::Ratings;
+use crate::room::tutorial::{self, OffScript};
+use crate::store::{StoreHandle, SNAPSHOT_VERSION};
+use crate::util::id::new_game_id;
+use crate::ws::protocol::{
+    ClientToServer, FinishReason, GameMode, GameUpdate, Handicap, HandicapKind, LobbyPlayer, PowerTable, Rank, RatingChange,
+    RejectCode, RuleConfig, SeatPublic, ServerToClient, SlotPublic, Stage,
+};
+
+pub enum RoomCommand {
+    /// A socket for `seat` opened; any previous socket for the seat is dropped.
+    /// `player` is who connected, if they presented an identity; `None` for bots,
+    /// which set `bot` and never host.
+    Connect { seat: usize, conn: u64, player: Option<Identity>, bot: bool, tx: mpsc::UnboundedSender<ServerToClient> },
+    Disconnect { seat: usize, conn: u64 },
+    Client { seat: usize, msg: ClientToServer },
+    /// Sent by the room to itself once the break after a `ZobboBattle` round is over.
+    NextRound,
+    /// Sent by the room to itself when the timer for `turn` runs out.
+    TurnTimeout { turn: u32 },
+    /// Sent by the room to itself when `seat`'s disconnect grace period is over.
+    ForfeitTimeout { seat: usize },
+    /// Sent by the room to itself when the running time bank should be empty.
+    BankTimeout { epoch: u64 },
+    /// Sent by the room to itself once a game update held back by the
+    /// broadcast cap may go out.
+    FlushUpdate,
+    /// A round trip `conn` measured to its client; see `SeatConn::rtt`.
+    Latency { seat: usize, conn: u64, rtt: Duration },
+    /// Report what the room is doing, for an operator; see `RoomHandle::inspect`.
+    Inspect { tx: oneshot::Sender<RoomInspection> },
+    /// Drop every connection and stop the task; sent when the room is evicted.
+    Shutdown,
+    /// Tell every seat the room is closed, then stop like `Shutdown`.
+    Close,
+    /// The server is going down: tell every seat when to come back, save the
+    /// game, then stop like `Shutdown`. `done` is answered once it's saved.
+    Drain { reconnect_after_secs: u64, done: oneshot::Sender<()> },
+}
+
+/// Rules a room is created with; the host may change them between games.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(from = "SavedSettings")]
+pub struct RoomSettings {
+    pub rules: RuleConfig,
+    /// How long each turn may take before the server plays it out; `None` for no limit.
+    pub turn_limit: Option<Duration>,
+    /// Each player's thinking time for a whole game, chess-clock style; `None` for no bank.
+    pub time_bank: Option<Duration>,
+    /// How long a seat that drops mid-game has to come back; `None` for the server's default.
+    pub grace: Option<Duration>,
+    /// The daily challenge this room deals, as days since the Unix epoch; see `daily::seed`.
+    pub daily: Option<u32>,
+    /// Games move the players' ratings; see `ratings::Ratings`.
+    pub ranked: bool,
+    /// Deals the scripted tutorial game; see `tutorial`.
+    pub tutorial: bool,
+}
+
+/// `RoomSettings` as saved by any version: rooms from before `RuleConfig`
+/// only have a `mode`.
+#[derive(Deserialize)]
+struct SavedSettings {
+    #[serde(default)]
+    rules: Option<RuleConfig>,
+    #[serde(default)]
+    mode: GameMode,
+    turn_limit: Option<Duration>,
+    #[serde(default)]
+    time_bank: Option<Duration>,
+    #[serde(default)]
+    grace: Option<Duration>,
+    #[serde(default)]
+    daily: Option<u32>,
+    #[serde(default)]
+    ranked: bool,
+    #[serde(default)]
+    tutorial: bool,
+}
+
+impl From<SavedSettings> for RoomSettings {
+    fn from(saved: SavedSettings) -> Self {
+        let rules = saved.rules.unwrap_or(RuleConfig { rounds: saved.mode.rounds(), ..RuleConfig::default() });
+        RoomSettings {
+            rules,
+            turn_limit: saved.turn_limit,
+            time_bank: saved.time_bank,
+            grace: saved.grace,
+            daily: saved.daily,
+            ranked: saved.ranked,
+            tutorial: saved.tutorial,
+        }
+    }
+}
+
+/// A pace to create a room with, instead of setting each timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimingPreset {
+    /// 15-second turns, a 3-minute bank and 30 seconds to reconnect.
+    Blitz,
+    /// 30-second turns, a 10-minute bank and a minute to reconnect.
+    Standard,
+    /// 2-minute turns, no bank and 5 minutes to reconnect.
+    Relaxed,
+}
+
+impl TimingPreset {
+    pub fn turn_secs(self) -> u64 {
+        match self {
+            TimingPreset::Blitz => 15,
+            TimingPreset::Standard => 30,
+            TimingPreset::Relaxed => 120,
+        }
+    }
+
+    /// 0 for no time bank, as `RoomSettings::new` takes it.
+    pub fn bank_secs(self) -> u64 {
+        match self {
+            TimingPreset::Blitz => 180,
+            TimingPreset::Standard => 600,
+            TimingPreset::Relaxed => 0,
+        }
+    }
+
+    pub fn grace(self) -> Duration {
+        Duration::from_secs(match self {
+            TimingPreset::Blitz => 30,
+            TimingPreset::Standard => 60,
+            TimingPreset::Relaxed => 300,
+        })
+    }
+}
+
+/// A named set of rules and a pace, for hosts who'd rather not set each one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RulePreset {
+    /// Four cards with two seen, played until someone reaches 100 points; untimed.
+    Classic,
+    /// The standard six-card game with jokers and penalty draws, best of three
+    /// at the standard pace.
+    House,
+    /// Four cards with two seen, one game at blitz pace.
+    Speed,
+}
+
+impl RulePreset {
+    pub const ALL: [RulePreset; 3] = [RulePreset::Classic, RulePreset::House, RulePreset::Speed];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RulePreset::Classic => "Classic Cabo",
+            RulePreset::House => "Zobbo House",
+            RulePreset::Speed => "Speed",
+        }
+    }
+
+    pub fn rules(self) -> RuleConfig {
+        let standard = RuleConfig::default();
+        match self {
+            RulePreset::Classic => RuleConfig { hand_size: 4, initial_peeks: 2, race_to: Some(100), ..standard },
+            RulePreset::House => {
+                RuleConfig { rounds: 3, best_of: true, jokers: true, match_penalty_draw: true, ..standard }
+            }
+            RulePreset::Speed => RuleConfig { hand_size: 4, initial_peeks: 2, ..standard },
+        }
+    }
+
+    /// `None` for untimed play.
+    pub fn timing(self) -> Option<TimingPreset> {
+        match self {
+            RulePreset::Classic => None,
+            RulePreset::House => Some(TimingPreset::Standard),
+            RulePreset::Speed => Some(TimingPreset::Blitz),
+        }
+    }
+}
+
+/// Most rounds a `ZobboBattle` may be played over.
+pub const MAX_ROUNDS: u8 = 10;
+/// Allowed `RaceTo` limits.
+const RACE_TO: std::ops::RangeInclusive<i32> = 10..=500;
+/// Allowed penalties for a Zobbo caller who doesn't have the lowest hand.
+const ZOBBO_PENALTIES: std::ops::RangeInclusive<i32> = 0..=50;
+/// Allowed hand sizes: every seat up to `manager::MAX_SEATS`, dealt the most
+/// with a handicap card, plus the discard, must come out of one deck.
+const HAND_SIZES: std::ops::RangeInclusive<usize> = 2..=8;
+/// Allowed per-turn time limits, in seconds.
+const TURN_SECS: std::ops::RangeInclusive<u64> = 5..=600;
+/// Allowed time banks, in seconds.
+const TIME_BANK_SECS: std::ops::RangeInclusive<u64> = 30..=3600;
+/// The most a slow connection adds to a turn; any more and a client could
+/// stretch its turns by holding back its pongs.
+const MAX_TURN_COMPENSATION: Duration = Duration::from_millis(500);
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsError {
+    #[error("rounds must be between 1 and {MAX_ROUNDS}")]
+    Rounds,
+    #[error("race_to must be between 10 and 500")]
+    RaceTo,
+    #[error("zobbo_penalty must be between 0 and 50")]
+    ZobboPenalty,
+    #[error("hand_size must be between 2 and 8")]
+    HandSize,
+    #[error("initial_peeks can't be more than hand_size")]
+    InitialPeeks,
+    #[error("turn_secs must be 0 or between 5 and 600")]
+    TurnSecs,
+    #[error("time_bank_secs must be 0 or between 30 and 3600")]
+    TimeBank,
+    #[error("power_table must be a comma-separated list like `7=peek_own,J=blind_swap`")]
+    PowerTable,
+    #[error("no handicaps in ranked or daily rooms")]
+    HandicapNotAllowed,
+    #[error("no such seat to handicap")]
+    HandicapSeat,
+    #[error("an extra peek needs initial_peeks below hand_size")]
+    HandicapPeek,
+}
+
+/// Parse a creator's power table, e.g. `7=peek_own,8=peek_own,9=peek_opp,J=blind_swap`.
+/// Ranks left out grant no power.
+pub fn parse_power_table(spec: &str) -> Result<PowerTable, SettingsError> {
+    fn parse<T: for<'de> Deserialize<'de>>(name: &str) -> Result<T, SettingsError> {
+        T::deserialize(name.trim().into_deserializer()).map_err(|_: serde::de::value::Error| SettingsError::PowerTable)
+    }
+    let mut table = PowerTable::none();
+    for entry in spec.split(',').filter(|e| !e.trim().is_empty()) {
+        let (rank, power) = entry.split_once('=').ok_or(SettingsError::PowerTable)?;
+        let rank = match rank.trim().to_uppercase().as_str() {
+            "JOKER" => Rank::Joker,
+            rank => parse(rank)?,
+        };
+        table.set(rank, Some(parse(&power.to_lowercase())?));
+    }
+    Ok(table)
+}
+
+impl RoomSettings {
+    /// Settings from what a player picks: more than one round makes a
+    /// `ZobboBattle`, and `turn_secs` or `bank_secs` 0 means no turn limit or
+    /// time bank.
+    pub fn new(rules: RuleConfig, turn_secs: u64, bank_secs: u64) -> Result<Self, SettingsError> {
+        if !(1..=MAX_ROUNDS).contains(&rules.rounds) { return Err(SettingsError::Rounds); }
+        if rules.race_to.is_some_and(|points| !RACE_TO.contains(&points)) { return Err(SettingsError::RaceTo); }
+        if !ZOBBO_PENALTIES.contains(&rules.zobbo_penalty) { return Err(SettingsError::ZobboPenalty); }
+        if !HAND_SIZES.contains(&rules.hand_size) { return Err(SettingsError::HandSize); }
+        if rules.initial_peeks > rules.hand_size { return Err(SettingsError::InitialPeeks); }
+        let turn_limit = match turn_secs {
+            0 => None,
+            secs if TURN_SECS.contains(&secs) => Some(Duration::from_secs(secs)),
+            _ => return Err(SettingsError::TurnSecs),
+        };
+        let time_bank = match bank_secs {
+            0 => None,
+            secs if TIME_BANK_SECS.contains(&secs) => Some(Duration::from_secs(secs)),
+            _ => return Err(SettingsError::TimeBank),
+        };
+        Ok(Self { rules, turn_limit, time_bank, grace: None, daily: None, ranked: false, tutorial: false })
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.rules.mode()
+    }
+}
+
+/// Lobby commands that were refused.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LobbyError {
+    #[error("only the host can do that")]
+    NotHost,
+    #[error("ranked games need a player identity; reconnect with one")]
+    NeedsIdentity,
+    #[error("not while a game or match is in progress")]
+    InProgress,
+    #[error("no such seat to kick")]
+    BadSeat,
+    #[error("the tutorial's table can't be changed")]
+    Tutorial,
+    #[error(transparent)]
+    Settings(#[from] SettingsError),
+}
+
+impl LobbyError {
+    pub fn code(self) -> RejectCode {
+        match self {
+            LobbyError::NotHost => RejectCode::NotHost,
+            LobbyError::NeedsIdentity => RejectCode::NeedsIdentity,
+            LobbyError::InProgress => RejectCode::InProgress,
+            LobbyError::BadSeat => RejectCode::BadIndex,
+            LobbyError::Tutorial => RejectCode::OffScript,
+            LobbyError::Settings(_) => RejectCode::InvalidSettings,
+        }
+    }
+}
+
+/// Why a pause or resume was refused, or an action blocked by a pause.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseError {
+    #[error("no game is in progress")]
+    NotPlaying,
+    #[error("the game is paused")]
+    Paused,
+    #[error("the game isn't paused")]
+    NotPaused,
+}
+
+impl PauseError {
+    pub fn code(self) -> RejectCode {
+        match self {
+            PauseError::NotPlaying => RejectCode::NotStarted,
+            PauseError::Paused => RejectCode::Paused,
+            PauseError::NotPaused => RejectCode::NotPaused,
+        }
+    }
+}
+
+/// Pause between `ZobboBattle` rounds so players can look at the reveal.
+const ROUND_BREAK: Duration = Duration::from_secs(6);
+/// Send a full `GameUpdate` after this many deltas, to bound any drift.
+const FULL_UPDATE_EVERY: u32 = 20;
+/// How long `RoomHandle::inspect` waits for the room task to answer.
+const INSPECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What the room task publishes about itself for the GC sweep.
+#[derive(Clone, Debug)]
+pub struct Activity {
+    /// Which seats currently have a connection (a socket or a bot).
+    pub connected: Vec<bool>,
+    /// Last time a seat connected or disconnected.
+    pub changed_at: Instant,
+    /// When the last game ended, if no game is running now.
+    pub finished_at: Option<Instant>,
+    /// The room's current settings, which the host may have changed.
+    pub settings: RoomSettings,
+    /// Seats the host kicked whose tokens the manager hasn't rotated yet.
+    pub revoked: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RoomHandle {
+    tx: mpsc::UnboundedSender<RoomCommand>,
+    activity: Arc<Mutex<Activity>>,
+}
+
+impl RoomHandle {
+    /// Returns false if the room task has shut down.
+    pub fn send(&self, cmd: RoomCommand) -> bool {
+        self.tx.send(cmd).is_ok()
+    }
+
+    pub fn activity(&self) -> Activity {
+        self.activity.lock().unwrap().clone()
+    }
+
+    /// Seats kicked since the last call, whose tokens must stop working.
+    pub fn take_revoked(&self) -> Vec<usize> {
+        std::mem::take(&mut self.activity.lock().unwrap().revoked)
+    }
+
+    /// Ask the room task what it's doing. `None` if the task has stopped or
+    /// doesn't answer within `INSPECT_TIMEOUT`, which means it's stuck.
+    pub async fn inspect(&self) -> Option<RoomInspection> {
+        let (tx, rx) = oneshot::channel();
+        if !self.send(RoomCommand::Inspect { tx }) { return None; }
+        tokio::time::timeout(INSPECT_TIMEOUT, rx).await.ok()?.ok()
+    }
+}
+
+/// One seat, as `RoomInspection` reports it.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SeatInspection {
+    pub seat: usize,
+    /// Whether a socket or bot is connected right now.
+    pub connected: bool,
+    pub ready: bool,
+    /// Who last connected, if they had an identity.
+    pub player_id: Option<String>,
+    pub name: Option<String>,
+    /// When the seat forfeits unless it reconnects, in Unix seconds.
+    pub forfeit_at: Option<u64>,
+}
+
+/// What a room's task is doing right now, hidden cards included.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RoomInspection {
+    pub seats: Vec<SeatInspection>,
+    pub host: Option<usize>,
+    /// The game dealt last, if any; it may be over.
+    pub game_id: Option<String>,
+    pub turn: Option<u32>,
+    pub active: Option<usize>,
+    #[schema(value_type = Option<Object>)]
+    pub stage: Option<TurnStage>,
+    pub paused: bool,
+    /// When the running turn timer fires, in Unix seconds.
+    pub deadline: Option<u64>,
+}
+
+/// The game-related state of a room, persisted so games survive a restart.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RoomSnapshot {
+    /// `SNAPSHOT_VERSION` when saved; 0 for games saved before it existed.
+    #[serde(default)]
+    pub version: u32,
+    game: Option<GameState>,
+    #[serde(default)]
+    seq: u64,
+    battle: Option<MatchState>,
+    /// Per seat: `(owner, index, version, card)` for every peek it has seen.
+    peeks: Vec<Vec<(usize, usize, u32, CardPublic)>>,
+    #[serde(default)]
+    host: Option<usize>,
+    /// Overrides the settings the room was created with, once the host changed them.
+    #[serde(default)]
+    settings: Option<RoomSettings>,
+    /// What each seat had left of its time bank when saved.
+    #[serde(default)]
+    time_bank: Vec<Duration>,
+    /// The current game's id and log; empty for games saved before logs were kept.
+    #[serde(default)]
+    game_id: String,
+    #[serde(default)]
+    log: Vec<LogEntry>,
+    /// Per seat: who last connected, so a game that ends before they're back
+    /// still reaches their history.
+    #[serde(default)]
+    players: Vec<Option<Identity>>,
+    /// How the last ranked game moved each seat's rating.
+    #[serde(default)]
+    ratings: Vec<RatingChange>,
+    #[serde(default)]
+    tutorial_step: usize,
+}
+
+/// Where rooms post finished games; one per manager, shared by all its rooms.
+#[derive(Debug, Clone, Default)]
+pub struct Records {
+    pub daily: Arc<DailyBoard>,
+    pub archive: Arc<GameArchive>,
+    pub history: Arc<MatchHistory>,
+    pub ratings: Arc<Ratings>,
+}
+
+/// Start the arbiter task for a room with `seats` seats, resuming from
+/// `snapshot` (pass the default for a new room).
+///
+/// The task exits once every `RoomHandle` has been dropped. With a `store`,
+/// the room saves a snapshot after every change to the game.
+pub fn spawn(
+    room_id: String,
+    seats: usize,
+    settings: RoomSettings,
+    store: Option<StoreHandle>,
+    snapshot: RoomSnapshot,
+    records: Records,
+) -> RoomHandle {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let finished = snapshot.game.as_ref().is_some_and(GameState::is_finished);
+    let settings = snapshot.settings.unwrap_or(settings);
+    let activity = Arc::new(Mutex::new(Activity {
+        connected: vec![false; seats],
+        changed_at: Instant::now(),
+        finished_at: finished.then(Instant::now),
+        settings,
+        revoked: Vec::new(),
+    }));
+    let mut peeks = snapshot.peeks.into_iter();
+    let mut players = snapshot.players.into_iter();
+    let clock = settings.time_bank.filter(|_| snapshot.game.is_some()).map(|bank| {
+        // Rooms saved before time banks existed start with a full one.
+        let mut left = snapshot.time_bank;
+        left.resize(seats, bank);
+        Clock::new(left)
+    });
+    let arbiter = RoomArbiter {
+        room_id,
+        seats: (0..seats)
+            .map(|_| SeatConn {
+                peeks: peeks
+                    .next()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(owner, index, version, card)| ((owner, index), (version, card)))
+                    .collect(),
+                player: players.next().flatten(),
+                ..SeatConn::default()
+            })
+            .collect(),
+        game: snapshot.game,
+        chat: ChatLog::new(seats),
+        host: snapshot.host,
+        settings,
+        battle: snapshot.battle,
+        deadline: None,
+        clock,
+        pause: Pause::default(),
+        grace: settings.grace.unwrap_or(config::get().disconnect_grace),
+        afk_turns: config::get().afk_forfeit_turns,
+        seq: snapshot.seq,
+        last_update: None,
+        since_full: 0,
+        update_due: false,
+        update_gap: Duration::from_secs(1) / config::get().room_updates_per_sec,
+        flushed: None,
+        flush_pending: false,
+        timer_tx: tx.downgrade(),
+        activity: activity.clone(),
+        store,
+        // A challenge game restored mid-play still counts; one already over has been posted.
+        daily_pending: settings.daily.is_some() && !finished,
+        game_id: snapshot.game_id,
+        log: snapshot.log,
+        ratings: snapshot.ratings,
+        tutorial_step: snapshot.tutorial_step,
+        records,
+    };
+    // Everything the task logs carries the room, and the game once one is dealt,
+    // but not the request that happened to create it.
+    let span = tracing::info_span!(parent: None, "room", room_id = %arbiter.room_id, game_id = tracing::field::Empty);
+    if !arbiter.game_id.is_empty() { span.record("game_id", arbiter.game_id.as_str()); }
+    let context = Context::new("room").room(&arbiter.room_id);
+    tokio::spawn(reporting::watch(context, arbiter.run(rx)).instrument(span));
+    RoomHandle { tx, activity }
+}
+
+#[derive(Default)]
+struct SeatConn {
+    conn: Option<(u64, mpsc::UnboundedSender<ServerToClient>)>,
+    ready: bool,
+    /// Every card this seat has been shown this game, keyed by `(owner, index)`,
+    /// so a reconnecting client can rebuild its knowledge.
+    peeks: HashMap<(usize, usize), (u32, CardPublic)>,
+    /// When this seat forfeits the game in progress unless it reconnects, on
+    /// tokio's clock, which the grace timer sleeps on.
+    forfeit_at: Option<tokio::time::Instant>,
+    /// Turns in a row this seat let time out this game.
+    timeouts: u32,
+    /// Connected as a bot; see `RoomCommand::Connect`.
+    bot: bool,
+    /// Who last connected to this seat; see `RoomCommand::Connect`.
+    player: Option<Identity>,
+    /// The connected socket's round-trip time, smoothed over its pings.
+    rtt: Option<Duration>,
+}
+
+/// Why a client message was refused, as reported in `ActionRejected`.
+struct Rejection {
+    code: RejectCode,
+    message: String,
+}
+
+impl From<RuleError> for Rejection {
+    fn from(err: RuleError) -> Self {
+        Rejection { code: err.code(), message: err.to_string() }
+    }
+}
+
+impl From<LobbyError> for Rejection {
+    fn from(err: LobbyError) -> Self {
+        Rejection { code: err.code(), message: err.to_string() }
+    }
+}
+
+impl From<PauseError> for Rejection {
+    fn from(err: PauseError) -> Self {
+        Rejection { code: err.code(), message: err.to_string() }
+    }
+}
+
+impl From<OffScript> for Rejection {
+    fn from(err: OffScript) -> Self {
+        Rejection { code: err.code(), message: err.to_string() }
+    }
+}
+
+impl From<ChatError> for Rejection {
+    fn from(err: ChatError) -> Self {
+        Rejection { code: err.code(), message: err.to_string() }
+    }
+}
+
+/// Pause requests and state for the game in progress; reset at every deal.
+#[derive(Debug, Default)]
+struct Pause {
+    /// Seats that asked to pause since the last pause or resume.
+    requests: Vec<usize>,
+    paused: bool,
+    /// What was left of the turn timer when it was held, by a pause or an
+    /// opponent away, and for which turn.
+    time_left: Option<(u32, Duration)>,
+    /// What was left of each away seat's grace when the game paused, or all
+    /// of it for a seat that dropped during the pause.
+    grace_left: HashMap<usize, Duration>,
+}
+
+/// Every seat's time bank for the game in progress, run like a chess clock.
+#[derive(Debug)]
+struct Clock {
+    /// What each seat had left when `running` last started.
+    left: Vec<Duration>,
+    /// The seat whose bank is running, and since when; on tokio's clock, which
+    /// the timers sleep on.
+    running: Option<(usize, tokio::time::Instant)>,
+    /// Bumped whenever a bank starts or stops, making older `BankTimeout`s stale.
+    epoch: u64,
+}
+
+impl Clock {
+    fn new(left: Vec<Duration>) -> Self {
+        Clock { left, running: None, epoch: 0 }
+    }
+
+    /// What each seat has left right now.
+    fn left_now(&self) -> Vec<Duration> {
+        let mut left = self.left.clone();
+        if let Some((seat, since)) = self.running {
+            left[seat] = left[seat].saturating_sub(since.elapsed());
+        }
+        left
+    }
+
+    /// Take the time used so far off the running bank.
+    fn charge(&mut self) {
+        self.left = self.left_now();
+        if let Some((_, since)) = &mut self.running {
+            *since = tokio::time::Instant::now();
+        }
+    }
+}
+
+/// Progress through a match; present from the first deal until `MatchOver`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MatchState {
+    round: u8,
+    totals: Vec<i32>,
+    /// Rounds won per seat.
+    #[serde(default)]
+    wins: Vec<u8>,
+}
+
+/// The running turn timer.
+#[derive(Clone, Copy)]
+struct TurnDeadline {
+    turn: u32,
+    /// When the turn is up, in Unix seconds, as the players are shown.
+    at: u64,
+    /// How long past `at` the timer actually fires, for the active seat's latency.
+    compensation: Duration,
+    /// When the timer fires, on tokio's clock; a `TurnTimeout` for the turn
+    /// that comes sooner is stale.
+    fires: tokio::time::Instant,
+}
+
+struct RoomArbiter {
+    room_id: String,
+    seats: Vec<SeatConn>,
+    game: Option<GameState>,
+    chat: ChatLog,
+    /// May kick players and change settings: the first human seat to connect,
+    /// until it leaves; see `hand_on_host`.
+    host: Option<usize>,
+    settings: RoomSettings,
+    battle: Option<MatchState>,
+    deadline: Option<TurnDeadline>,
+    /// Present while a game is dealt in a room with a time bank.
+    clock: Option<Clock>,
+    pause: Pause,
+    /// How long a seat that drops mid-game has to come back.
+    grace: Duration,
+    /// Timed-out turns in a row that forfeit the game.
+    afk_turns: u32,
+    /// Last `GameUpdate::seq` broadcast; never reset, so it survives rematches.
+    seq: u64,
+    /// The state as of `seq`, which the next `GameDelta` is diffed against.
+    last_update: Option<GameUpdate>,
+    /// Deltas sent since the last full `GameUpdate`.
+    since_full: u32,
+    /// The public state changed since `last_update`; see `broadcast_game_update`.
+    update_due: bool,
+    /// Least time between game updates; see `Config::room_updates_per_sec`.
+    update_gap: Duration,
+    /// When the last game update went out, and the turn it showed.
+    flushed: Option<(tokio::time::Instant, u32)>,
+    /// A `FlushUpdate` is on its way.
+    flush_pending: bool,
+    /// Weak, so timers don't keep the room alive after every handle is gone.
+    timer_tx: mpsc::WeakUnboundedSender<RoomCommand>,
+    activity: Arc<Mutex<Activity>>,
+    store: Option<StoreHandle>,
+    /// Whether the next game to finish posts to the daily board; only a
+    /// challenge room's first game does, as later ones replay a known deal.
+    daily_pending: bool,
+    game_id: String,
+    /// Everything that moved the current game, in order; archived when it ends.
+    log: Vec<LogEntry>,
+    /// How the last game moved each seat's rating, if it was ranked.
+    ratings: Vec<RatingChange>,
+    /// Tutorial rooms: the `tutorial::STEPS` played this game.
+    tutorial_step: usize,
+    records: Records,
+}
+
+impl RoomArbiter {
+    async fn run(mut self, mut rx: mpsc::UnboundedReceiver<RoomCommand>) {
+        // A game restored from the store restarts its turn clock and time bank,
+        // and gives everyone grace to reconnect before they forfeit.
+        if self.started() {
+            for seat in 0..self.seats.len() {
+                if self.seats[seat].conn.is_none() { self.start_grace(seat, self.grace); }
+            }
+        }
+        self.arm_turn_timer();
+        self.run_clock();
+        while let Some(cmd) = rx.recv().await {
+            match cmd {
+                RoomCommand::Connect { seat, conn, player, bot, tx } => self.handle_connect(seat, conn, player, bot, tx),
+                RoomCommand::Disconnect { seat, conn } => self.handle_disconnect(seat, conn),
+                RoomCommand::Client { seat, msg } => {
+                    if self.handle_client(seat, msg) { self.persist(); }
+                }
+                RoomCommand::NextRound => {
+                    self.next_round();
+                    self.persist();
+                }
+                RoomCommand::TurnTimeout { turn } => {
+                    self.handle_turn_timeout(turn);
+                    self.persist();
+                }
+                RoomCommand::ForfeitTimeout { seat } => {
+                    self.handle_forfeit_timeout(seat);
+                    self.persist();
+                }
+                RoomCommand::BankTimeout { epoch } => {
+                    self.handle_bank_timeout(epoch);
+                    self.persist();
+                }
+                RoomCommand::FlushUpdate => {
+                    self.flush_pending = false;
+                    self.flush_capped();
+                }
+                RoomCommand::Latency { seat, conn, rtt } => self.handle_latency(seat, conn, rtt),
+                RoomCommand::Inspect { tx } => {
+                    let _ = tx.send(self.inspect());
+                }
+                // Dropping `self` closes every seat's channel, which ends the sockets and bots.
+                RoomCommand::Shutdown => break,
+                RoomCommand::Close => {
+                    tracing::info!(room_id = %self.room_id, "room closed");
+                    self.broadcast(ServerToClient::RoomClosed);
+                    break;
+                }
+                RoomCommand::Drain { reconnect_after_secs, done } => {
+                    self.broadcast(ServerToClient::ServerShutdown { reconnect_after_secs });
+                    self.persist();
+                    let _ = done.send(());
+                    break;
+                }
+            }
+            // Commands already waiting may change the state again; their
+            // updates go out together.
+            if rx.is_empty() { self.flush_capped(); }
+        }
+        tracing::debug!(room_id = %self.room_id, "room task stopped");
+    }
+
+    fn started(&self) -> bool {
+        self.game.as_ref().is_some_and(|g| !g.is_finished())
+    }
+
+    fn persist(&self) {
+        let Some(store) = &self.store else { return };
+        let snapshot = RoomSnapshot {
+            version: SNAPSHOT_VERSION,
+            game: self.game.clone(),
+            seq: self.seq,
+            battle: self.battle.clone(),
+            host: self.host,
+            settings: Some(self.settings),
+            time_bank: self.clock.as_ref().map(Clock::left_now).unwrap_or_default(),
+            game_id: self.game_id.clone(),
+            log: self.log.clone(),
+            players: self.seats.iter().map(|s| s.player.clone()).collect(),
+            ratings: self.ratings.clone(),
+            tutorial_step: self.tutorial_step,
+            peeks: self
+                .seats
+                .iter()
+                .map(|s| s.peeks.iter().map(|(&(owner, index), &(version, card))| (owner, index, version, card)).collect())
+                .collect(),
+        };
+        store.save_game(&self.room_id, &snapshot);
+    }
+
+    fn inspect(&self) -> RoomInspection {
+        let seats = self
+            .seats
+            .iter()
+            .enumerate()
+            .map(|(seat, s)| SeatInspection {
+                seat,
+                connected: s.conn.is_some(),
+                ready: s.ready,
+                player_id: s.player.as_ref().map(|p| p.player_id.clone()),
+                name: s.player.as_ref().map(|p| p.name.clone()),
+                forfeit_at: s.forfeit_at.map(|at| unix_secs(at.saturating_duration_since(tokio::time::Instant::now()))),
+            })
+            .collect();
+        RoomInspection {
+            seats,
+            host: self.host,
+            game_id: self.game.as_ref().map(|_| self.game_id.clone()).filter(|id| !id.is_empty()),
+            turn: self.game.as_ref().map(|g| g.turn),
+            active: self.game.as_ref().map(|g| g.active),
+            stage: self.game.as_ref().map(|g| g.stage.clone()),
+            paused: self.pause.paused,
+            deadline: self.deadline.map(|d| d.at),
+        }
+    }
+
+    fn publish_connections(&self) {
+        let mut activity = self.activity.lock().unwrap();
+        activity.connected = self.seats.iter().map(|s| s.conn.is_some()).collect();
+        activity.changed_at = Instant::now();
+    }
+
+    /// Current round and total rounds, for `GameStart`.
+    fn round(&self) -> (u8, u8) {
+        (self.battle.as_ref().map_or(1, |b| b.round), self.settings.mode().rounds())
+    }
+
+    /// Send `msg` to `seat`, after any held-back update it may follow on from.
+    fn send_to(&mut self, seat: usize, msg: ServerToClient) {
+        self.flush_update();
+        self.deliver(seat, msg);
+    }
+
+    fn broadcast(&mut self, msg: ServerToClient) {
+        self.flush_update();
+        self.deliver_all(msg);
+    }
+
+    /// `send_to` for callers that have just called `flush_update`.
+    fn deliver(&self, seat: usize, msg: ServerToClient) {
+        if let Some((_, tx)) = &self.seats[seat].conn {
+            let _ = tx.send(msg);
+        }
+    }
+
+    fn deliver_all(&self, msg: ServerToClient) {
+        for seat in 0..self.seats.len() {
+            self.deliver(seat, msg.clone());
+        }
+    }
+
+    fn broadcast_lobby(&mut self) {
+        self.broadcast(self.lobby_state());
+    }
+
+    fn lobby_state(&self) -> ServerToClient {
+        let players = self
+            .seats
+            .iter()
+            .enumerate()
+            .map(|(seat, s)| LobbyPlayer {
+                seat,
+                connected: s.conn.is_some(),
+                ready: s.ready,
+                player_id: s.player.as_ref().map(|p| p.player_id.clone()),
+                name: s.player.as_ref().map(|p| p.name.clone()),
+            })
+            .collect();
+        ServerToClient::LobbyState {
+            players,
+            started: self.started(),
+            host: self.host,
+            mode: self.settings.mode(),
+            turn_secs: self.settings.turn_limit.map(|d| d.as_secs()),
+            time_bank_secs: self.settings.time_bank.map(|d| d.as_secs()),
+            daily: self.settings.daily.map(daily::seed_id),
+            ranked: self.settings.ranked,
+            rules: self.settings.rules,
+        }
+    }
+
+    fn handle_connect(
+        &mut self,
+        seat: usize,
+        conn: u64,
+        player: Option<Identity>,
+        bot: bool,
+        tx: mpsc::UnboundedSender<ServerToClient>,
+    ) {
+        if let Some((_, old)) = self.seats[seat].conn.replace((conn, tx)) {
+            // Dropping the old sender closes that socket's loop.
+            let _ = old.send(ServerToClient::Error { message: "connected from another tab".into() });
+        }
+        self.seats[seat].player = player;
+        self.seats[seat].bot = bot;
+        self.seats[seat].rtt = None;
+        tracing::debug!(room_id = %self.room_id, seat, conn, "seat connected");
+        let returned = self.seats[seat].forfeit_at.take().is_some();
+        // A room with nobody to host it, new or left by its host, takes the first human back.
+        if !bot && self.host.is_none_or(|host| self.seats[host].conn.is_none()) { self.host = Some(seat); }
+        self.publish_connections();
+        self.broadcast_lobby();
+        self.resync(seat);
+        // Back within its grace: start the timers it held.
+        if returned && self.timed() { self.broadcast_game_update(); }
+    }
+
+    /// Replay everything `seat` is entitled to know about the current game.
+    ///
+    /// Peeks whose slot has since changed are stale and are pruned instead.
+    fn resync(&mut self, seat: usize) {
+        self.flush_update();
+        for msg in self.chat.history() {
+            self.deliver(seat, msg.clone());
+        }
+        let Some(game) = &self.game else { return };
+        self.deliver(seat, game_start(&self.game_id, game, self.round()));
+        self.deliver(seat, ServerToClient::GameUpdate(game_update(game, self.deadline, &self.pause, self.bank_ms(), self.seq)));
+        self.seats[seat]
+            .peeks
+            .retain(|&(owner, index), (version, _)| game.seats[owner].versions[index] == *version);
+        for (&(owner, index), &(version, card)) in &self.seats[seat].peeks {
+            self.deliver(seat, ServerToClient::PeekResult { owner, index, version, card });
+        }
+        if let TurnStage::Holding { card, from } = game.stage
+            && game.active == seat
+        {
+            self.deliver(seat, ServerToClient::Drawn { card: card_public(card), from });
+        }
+        if game.result.is_some() {
+            self.deliver(seat, game_over(game, &self.ratings));
+        }
+        if self.settings.tutorial && seat == tutorial::STUDENT {
+            self.deliver(seat, tutorial::hint(self.tutorial_step));
+        }
+    }
+
+    /// Send a message only `seat` may see, remembering peeks for `resync`.
+    fn send_private(&mut self, seat: usize, msg: ServerToClient) {
+        if let ServerToClient::PeekResult { owner, index, version, card } = &msg {
+            self.seats[seat].peeks.insert((*owner, *index), (*version, *card));
+        }
+        self.send_to(seat, msg);
+    }
+
+    fn handle_disconnect(&mut self, seat: usize, conn: u64) {
+        // Ignore stale disconnects from a socket that was already replaced.
+        if self.seats[seat].conn.as_ref().is_some_and(|(c, _)| *c == conn) {
+            self.seats[seat].conn = None;
+            if !self.started() { self.seats[seat].ready = false; }
+            tracing::debug!(room_id = %self.room_id, seat, conn, "seat disconnected");
+            self.hand_on_host();
+            if self.started() {
+                self.start_grace(seat, self.grace);
+                // Holds the turn timer and banks unless it's this seat's turn.
+                if self.timed() { self.broadcast_game_update(); }
+            }
+            self.publish_connections();
+            self.broadcast_lobby();
+        }
+    }
+
+    /// Once the host's seat is left empty, make the next connected human seat
+    /// after it host. With none, the role waits for the next to connect.
+    fn hand_on_host(&mut self) {
+        let Some(host) = self.host.filter(|&host| self.seats[host].conn.is_none()) else { return };
+        let seats = self.seats.len();
+        let next = (1..seats).map(|i| (host + i) % seats).find(|&s| self.seats[s].conn.is_some() && !self.seats[s].bot);
+        let Some(next) = next else { return };
+        tracing::info!(room_id = %self.room_id, from = host, to = next, "host handed on");
+        self.host = Some(next);
+    }
+
+    /// Returns whether the message changed what `persist` saves: chat,
+    /// keepalives, catch-ups and rejected moves don't.
+    fn handle_client(&mut self, seat: usize, msg: ClientToServer) -> bool {
+        let action = msg.kind();
+        let detail = Action::from_client(&msg);
+        let saved = detail.is_some()
+            || matches!(
+                msg,
+                ClientToServer::Ready
+                    | ClientToServer::KickPlayer { .. }
+                    | ClientToServer::UpdateSettings { .. }
+                    | ClientToServer::SetHandicap { .. }
+                    | ClientToServer::RequestPause
+                    | ClientToServer::Resume
+            );
+        let result = match msg {
+            ClientToServer::Ready => self.handle_ready(seat),
+            ClientToServer::Resync => {
+                // On connecting, the lobby goes to everyone just before `resync`.
+                self.send_to(seat, self.lobby_state());
+                self.resync(seat);
+                Ok(())
+            }
+            ClientToServer::KickPlayer { seat: target } => self.handle_kick(seat, target).map_err(Rejection::from),
+            ClientToServer::UpdateSettings { rounds, turn_secs } => {
+                self.handle_update_settings(seat, rounds, turn_secs).map_err(Rejection::from)
+            }
+            ClientToServer::SetHandicap { handicap } => self.handle_set_handicap(seat, handicap).map_err(Rejection::from),
+            ClientToServer::Chat { text } => {
+                let name = self.seats[seat].player.as_ref().map_or_else(|| format!("Player {}", seat + 1), |p| p.name.clone());
+                match self.chat.post(seat, name, &text) {
+                    Ok(msg) => {
+                        self.broadcast(msg);
+                        Ok(())
+                    }
+                    Err(err) => Err(err.into()),
+                }
+            }
+            ClientToServer::RequestState { last_seq } => {
+                self.handle_request_state(seat, last_seq);
+                Ok(())
+            }
+            ClientToServer::Ping => {
+                self.send_to(seat, ServerToClient::Pong);
+                Ok(())
+            }
+            ClientToServer::RequestPause => self.handle_request_pause(seat).map_err(Rejection::from),
+            ClientToServer::Resume => self.handle_resume(seat).map_err(Rejection::from),
+            msg => match Action::from_client(&msg) {
+                Some(_) if self.pause.paused => Err(PauseError::Paused.into()),
+                Some(action) if self.settings.tutorial => self.handle_tutorial_action(seat, action),
+                Some(action) => self.handle_action(seat, action).map_err(Rejection::from),
+                None => Ok(()),
+            },
+        };
+        let player_id = self.seats[seat].player.as_ref().map(|p| tracing::field::display(&p.player_id));
+        let code = result.as_ref().err().map(|r| tracing::field::debug(r.code));
+        let detail = detail.map(tracing::field::debug);
+        let outcome = if result.is_ok() { "ok" } else { "rejected" };
+        match action {
+            // Keepalives and catch-ups would drown out the moves.
+            "ping" | "request_state" | "resync" => {
+                tracing::trace!(actor = seat, player_id, action, result = outcome, "client action");
+            }
+            _ => tracing::info!(actor = seat, player_id, action, detail, result = outcome, code, "client action"),
+        }
+        match result {
+            Ok(()) => saved,
+            Err(Rejection { code, message }) => {
+                self.send_to(seat, ServerToClient::ActionRejected { action: action.to_string(), code, message });
+                false
+            }
+        }
+    }
+
+    fn handle_ready(&mut self, seat: usize) -> Result<(), Rejection> {
+        if self.started() { return Err(RuleError::WrongStage.into()); }
+        if self.battle.is_some() { return Err(RuleError::BetweenRounds.into()); }
+        if self.settings.ranked && self.seats[seat].player.is_none() { return Err(LobbyError::NeedsIdentity.into()); }
+        self.seats[seat].ready = true;
+        self.broadcast_lobby();
+        let ready = |seat: usize| self.scripted(seat) || (self.seats[seat].ready && self.seats[seat].conn.is_some());
+        if (0..self.seats.len()).all(ready) {
+            if self.settings.mode() != GameMode::Single {
+                let seats = self.seats.len();
+                self.battle = Some(MatchState { round: 1, totals: vec![0; seats], wins: vec![0; seats] });
+            }
+            self.start_game();
+        }
+        Ok(())
+    }
+
+    /// Host-only lobby commands need the host, and no game or match under way.
+    fn check_host(&self, seat: usize) -> Result<(), LobbyError> {
+        if self.host != Some(seat) { return Err(LobbyError::NotHost); }
+        if self.settings.tutorial { return Err(LobbyError::Tutorial); }
+        if self.started() || self.battle.is_some() { return Err(LobbyError::InProgress); }
+        Ok(())
+    }
+
+    fn handle_kick(&mut self, seat: usize, target: usize) -> Result<(), LobbyError> {
+        self.check_host(seat)?;
+        if target == seat || target >= self.seats.len() { return Err(LobbyError::BadSeat); }
+        tracing::info!(room_id = %self.room_id, host = seat, target, "player kicked");
+        let kicked = std::mem::take(&mut self.seats[target]);
+        // Whoever takes the seat next didn't agree to its handicap.
+        if self.settings.rules.handicap.is_some_and(|h| h.seat == target) {
+            self.settings.rules.handicap = None;
+            self.activity.lock().unwrap().settings = self.settings;
+        }
+        if let Some((_, tx)) = kicked.conn {
+            // Dropping the sender right after closes their socket (or stops the bot).
+            let _ = tx.send(ServerToClient::Kicked);
+        }
+        self.activity.lock().unwrap().revoked.push(target);
+        self.publish_connections();
+        self.broadcast_lobby();
+        Ok(())
+    }
+
+    fn handle_update_settings(&mut self, seat: usize, rounds: u8, turn_secs: u64) -> Result<(), LobbyError> {
+        self.check_host(seat)?;
+        // The lobby form only offers rounds and the timer; the rest of the rules stay.
+        let bank_secs = self.settings.time_bank.map_or(0, |d| d.as_secs());
+        self.settings = RoomSettings {
+            grace: self.settings.grace,
+            daily: self.settings.daily,
+            ranked: self.settings.ranked,
+            ..RoomSettings::new(RuleConfig { rounds, ..self.settings.rules }, turn_secs, bank_secs)?
+        };
+        self.activity.lock().unwrap().settings = self.settings;
+        self.unready_all();
+        Ok(())
+    }
+
+    fn handle_set_handicap(&mut self, seat: usize, handicap: Option<Handicap>) -> Result<(), LobbyError> {
+        self.check_host(seat)?;
+        if let Some(handicap) = handicap {
+            let rules = &self.settings.rules;
+            if self.settings.ranked || self.settings.daily.is_some() { return Err(SettingsError::HandicapNotAllowed.into()); }
+            if handicap.seat >= self.seats.len() { return Err(SettingsError::HandicapSeat.into()); }
+            if handicap.kind == HandicapKind::ExtraPeek && rules.initial_peeks >= rules.hand_size {
+                return Err(SettingsError::HandicapPeek.into());
+            }
+        }
+        self.settings.rules.handicap = handicap;
+        self.activity.lock().unwrap().settings = self.settings;
+        self.unready_all();
+        Ok(())
+    }
+
+    /// Nobody should find themselves in a game they didn't agree to.
+    fn unready_all(&mut self) {
+        for s in &mut self.seats {
+            s.ready = false;
+        }
+        self.broadcast_lobby();
+    }
+
+    fn next_round(&mut self) {
+        if self.started() { return; }
+        let Some(battle) = &mut self.battle else { return };
+        battle.round += 1;
+        self.start_game();
+    }
+
+    fn start_game(&mut self) {
+        let seed = match self.settings.daily {
+            _ if self.settings.tutorial => tutorial::SEED,
+            Some(day) => daily::seed(day),
+            None => rand::random(),
+        };
+        let (players, rules) = (self.seats.len(), self.settings.rules);
+        let game = GameState::new(players, rules, seed);
+        self.game_id = new_game_id();
+        tracing::Span::current().record("game_id", self.game_id.as_str());
+        self.log.clear();
+        self.ratings.clear();
+        self.tutorial_step = 0;
+        self.record(GameEvent::Dealt { players, rules, seed });
+        tracing::info!(room_id = %self.room_id, game_id = %self.game_id, first = game.active, "game started");
+        for s in &mut self.seats {
+            s.ready = false;
+            s.peeks.clear();
+            s.timeouts = 0;
+        }
+        self.broadcast(game_start(&self.game_id, &game, self.round()));
+        self.activity.lock().unwrap().finished_at = None;
+        self.game = Some(game);
+        self.pause = Pause::default();
+        self.clock = self.settings.time_bank.map(|bank| Clock::new(vec![bank; self.seats.len()]));
+        // Every deal opens with a full update.
+        self.last_update = None;
+        self.broadcast_lobby();
+        self.send_initial_peeks();
+        self.broadcast_game_update();
+        self.send_hint();
+        // A match deals the next round whether or not everyone is still here.
+        for seat in 0..self.seats.len() {
+            if self.seats[seat].conn.is_none() { self.start_grace(seat, self.grace); }
+        }
+    }
+
+    /// Show every player the end of their own hand, as many cards as the rules
+    /// (and any handicap) say.
+    fn send_initial_peeks(&mut self) {
+        let Some(game) = &self.game else { return };
+        let mut peeks = Vec::new();
+        for (seat, hand) in game.seats.iter().enumerate() {
+            for index in game.initial_peeks(seat) {
+                if let Some(card) = hand.slots[index] {
+                    peeks.push((seat, peek(seat, index, card, hand.versions[index])));
+                }
+            }
+        }
+        for (seat, msg) in peeks {
+            self.send_private(seat, msg);
+        }
+    }
+
+    fn record(&mut self, event: GameEvent) {
+        let at_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+        self.log.push(LogEntry { at_ms, event });
+    }
+
+    /// Broadcast the new public state, as a `GameDelta` where possible.
+    ///
+    /// It goes out before the room next sends anything else, or once the
+    /// commands already queued are handled (see `flush_capped`), so changes
+    /// that land together reach clients as one update instead of a flicker
+    /// of in-between ones.
+    fn broadcast_game_update(&mut self) {
+        self.arm_turn_timer();
+        self.run_clock();
+        self.update_due = true;
+    }
+
+    /// `flush_update`, unless the last update went out too recently: then
+    /// it waits for a `FlushUpdate`. A new turn, a new game and the end of
+    /// one never wait.
+    fn flush_capped(&mut self) {
+        if !self.update_due { return; }
+        let urgent = match (&self.game, self.flushed) {
+            (Some(game), Some((_, turn))) => game.turn != turn || game.is_finished() || self.last_update.is_none(),
+            _ => true,
+        };
+        let wait = self.flushed.map_or(Duration::ZERO, |(at, _)| self.update_gap.saturating_sub(at.elapsed()));
+        if urgent || wait.is_zero() {
+            self.flush_update();
+            return;
+        }
+        if std::mem::replace(&mut self.flush_pending, true) { return; }
+        let tx = self.timer_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(wait).await;
+            if let Some(tx) = tx.upgrade() {
+                let _ = tx.send(RoomCommand::FlushUpdate);
+            }
+        });
+    }
+
+    /// Send the update `broadcast_game_update` held back, if there is one.
+    fn flush_update(&mut self) {
+        if !std::mem::take(&mut self.update_due) { return; }
+        let Some(game) = &self.game else { return };
+        self.flushed = Some((tokio::time::Instant::now(), game.turn));
+        self.seq += 1;
+        let update = game_update(game, self.deadline, &self.pause, self.bank_ms(), self.seq);
+        let delta = self
+            .last_update
+            .as_ref()
+            .filter(|_| self.since_full < FULL_UPDATE_EVERY)
+            .and_then(|prev| prev.diff(&update));
+        match delta {
+            Some(delta) => {
+                self.since_full += 1;
+                self.broadcast(ServerToClient::GameDelta(delta));
+            }
+            None => {
+                self.since_full = 0;
+                self.broadcast(ServerToClient::GameUpdate(update.clone()));
+            }
+        }
+        self.last_update = Some(update);
+    }
+
+    /// Cheap when the client is current: just confirm with the latest update.
+    fn handle_request_state(&mut self, seat: usize, last_seq: u64) {
+        self.flush_update();
+        if last_seq < self.seq {
+            tracing::debug!(room_id = %self.room_id, seat, last_seq, seq = self.seq, "client behind, resyncing");
+            self.resync(seat);
+        } else if let Some(game) = &self.game {
+            self.deliver(seat, ServerToClient::GameUpdate(game_update(game, self.deadline, &self.pause, self.bank_ms(), self.seq)));
+        }
+    }
+
+    /// Start the countdown when a new turn has begun, or with what was left
+    /// of it once no longer held; clear it once the game ends.
+    fn arm_turn_timer(&mut self) {
+        let (Some(limit), Some(game)) = (self.settings.turn_limit, &self.game) else { return };
+        if game.is_finished() {
+            self.deadline = None;
+            return;
+        }
+        let turn = game.turn;
+        if self.held() {
+            self.hold_turn_timer();
+            return;
+        }
+        if self.deadline.is_some_and(|d| d.turn == turn) { return; }
+        let held = self.pause.time_left.take().filter(|(t, _)| *t == turn);
+        let limit = held.map_or(limit, |(_, left)| left);
+        // The player sees the turn start half a round trip late, and their
+        // move arrives half a round trip after they make it.
+        let compensation = self.seats[game.active].rtt.unwrap_or_default().min(MAX_TURN_COMPENSATION);
+        let fires = tokio::time::Instant::now() + limit + compensation;
+        self.deadline = Some(TurnDeadline { turn, at: unix_secs(limit), compensation, fires });
+        let tx = self.timer_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep_until(fires).await;
+            if let Some(tx) = tx.upgrade() {
+                let _ = tx.send(RoomCommand::TurnTimeout { turn });
+            }
+        });
+    }
+
+    /// Fold a socket's latest round trip into its seat's average. The next
+    /// turn timer the seat gets is compensated by it; a running one isn't.
+    fn handle_latency(&mut self, seat: usize, conn: u64, rtt: Duration) {
+        let s = &mut self.seats[seat];
+        if s.conn.as_ref().is_none_or(|(current, _)| *current != conn) { return; }
+        s.rtt = Some(s.rtt.map_or(rtt, |avg| (avg * 3 + rtt) / 4));
+    }
+
+    /// Stop the turn timer, keeping what was left for when it starts again.
+    /// Dropping the deadline makes the running timer's `TurnTimeout` stale.
+    fn hold_turn_timer(&mut self) {
+        let Some(TurnDeadline { turn, compensation, fires, .. }) = self.deadline.take() else { return };
+        let left = fires.saturating_duration_since(tokio::time::Instant::now()).saturating_sub(compensation);
+        self.pause.time_left = Some((turn, left));
+    }
+
+    /// Whether the room has a turn timer or time banks.
+    fn timed(&self) -> bool {
+        self.settings.turn_limit.is_some() || self.clock.is_some()
+    }
+
+    /// Whether the turn timer and banks stand still: while the game is paused,
+    /// or while a seat other than the active one is away within its grace, so
+    /// nobody plays against the clock alone.
+    fn held(&self) -> bool {
+        let Some(game) = &self.game else { return false };
+        self.pause.paused || self.seats.iter().enumerate().any(|(seat, s)| seat != game.active && s.forfeit_at.is_some())
+    }
+
+    /// Charge the running bank and start the active seat's, if it isn't
+    /// already; no bank runs while the game is held or over.
+    fn run_clock(&mut self) {
+        let held = self.held();
+        let (Some(clock), Some(game)) = (&mut self.clock, &self.game) else { return };
+        clock.charge();
+        let seat = (!game.is_finished() && !held).then_some(game.active);
+        if clock.running.map(|(running, _)| running) == seat { return; }
+        clock.epoch += 1;
+        clock.running = seat.map(|seat| (seat, tokio::time::Instant::now()));
+        let Some(seat) = seat else { return };
+        let (tx, epoch, left) = (self.timer_tx.clone(), clock.epoch, clock.left[seat]);
+        tokio::spawn(async move {
+            tokio::time::sleep(left).await;
+            if let Some(tx) = tx.upgrade() {
+                let _ = tx.send(RoomCommand::BankTimeout { epoch });
+            }
+        });
+    }
+
+    /// Each seat's bank left, in milliseconds, for `GameUpdate::time_bank_ms`.
+    fn bank_ms(&self) -> Vec<u64> {
+        let Some(clock) = &self.clock else { return Vec::new() };
+        clock.left_now().iter().map(|d| d.as_millis() as u64).collect()
+    }
+
+    /// Count `seat` in for a pause; once every seat is, stop the game and
+    /// its turn timer.
+    fn handle_request_pause(&mut self, seat: usize) -> Result<(), PauseError> {
+        self.game.as_ref().filter(|g| !g.is_finished()).ok_or(PauseError::NotPlaying)?;
+        if self.pause.paused { return Err(PauseError::Paused); }
+        if !self.pause.requests.contains(&seat) {
+            self.pause.requests.push(seat);
+        }
+        if self.pause.requests.len() == self.seats.len() {
+            tracing::info!(room_id = %self.room_id, "game paused");
+            self.pause.requests.clear();
+            self.pause.paused = true;
+            self.hold_turn_timer();
+            // Nobody forfeits for being away during an agreed pause.
+            for (seat, s) in self.seats.iter().enumerate() {
+                if let (Some(at), None) = (s.forfeit_at, &s.conn) {
+                    self.pause.grace_left.insert(seat, at.saturating_duration_since(tokio::time::Instant::now()));
+                }
+            }
+        }
+        self.broadcast_game_update();
+        Ok(())
+    }
+
+    fn handle_resume(&mut self, seat: usize) -> Result<(), PauseError> {
+        if !self.pause.paused { return Err(PauseError::NotPaused); }
+        tracing::info!(room_id = %self.room_id, seat, "game resumed");
+        self.pause.paused = false;
+        for (away, left) in std::mem::take(&mut self.pause.grace_left) {
+            if self.seats[away].conn.is_none() && self.seats[away].forfeit_at.is_some() {
+                self.start_grace(away, left);
+            }
+        }
+        self.broadcast_game_update();
+        Ok(())
+    }
+
+    /// Give `seat`, gone mid-game, `left` to come back before it forfeits.
+    /// While the game is paused the countdown waits for `handle_resume`.
+    fn start_grace(&mut self, seat: usize, left: Duration) {
+        if self.scripted(seat) { return; }
+        self.seats[seat].forfeit_at = Some(tokio::time::Instant::now() + left);
+        self.broadcast(ServerToClient::OpponentDisconnected { seat, deadline: unix_secs(left) });
+        if self.pause.paused {
+            self.pause.grace_left.insert(seat, left);
+            return;
+        }
+        let tx = self.timer_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(left).await;
+            if let Some(tx) = tx.upgrade() {
+                let _ = tx.send(RoomCommand::ForfeitTimeout { seat });
+            }
+        });
+    }
+
+    fn handle_forfeit_timeout(&mut self, seat: usize) {
+        // Reconnecting clears `forfeit_at`; a later drop starts a fresh deadline.
+        // A pause holds the countdown, and resuming starts a new timer.
+        if self.pause.paused { return; }
+        let Some(at) = self.seats[seat].forfeit_at else { return };
+        if self.seats[seat].conn.is_some() || tokio::time::Instant::now() < at { return; }
+        self.seats[seat].forfeit_at = None;
+        let Some(game) = &mut self.game else { return };
+        if game.is_finished() { return; }
+        tracing::info!(room_id = %self.room_id, seat, "seat forfeited after disconnecting");
+        game.forfeit(FinishReason::Forfeit { seat });
+        self.record(GameEvent::Forfeited { reason: FinishReason::Forfeit { seat } });
+        self.broadcast_game_update();
+        self.finish_if_over();
+    }
+
+    fn handle_bank_timeout(&mut self, epoch: u64) {
+        // A bank stopped since this timer was set has its own, newer timer.
+        let Some(clock) = &mut self.clock else { return };
+        let Some((seat, _)) = clock.running.filter(|_| clock.epoch == epoch) else { return };
+        clock.charge();
+        let left = clock.left[seat];
+        if !left.is_zero() {
+            let tx = self.timer_tx.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(left).await;
+                if let Some(tx) = tx.upgrade() {
+                    let _ = tx.send(RoomCommand::BankTimeout { epoch });
+                }
+            });
+            return;
+        }
+        let Some(game) = &mut self.game else { return };
+        if game.is_finished() { return; }
+        tracing::info!(room_id = %self.room_id, seat, "seat ran out of time");
+        game.forfeit(FinishReason::OutOfTime { seat });
+        self.record(GameEvent::Forfeited { reason: FinishReason::OutOfTime { seat } });
+        self.broadcast_game_update();
+        self.finish_if_over();
+    }
+
+    fn handle_turn_timeout(&mut self, turn: u32) {
+        // Stale timers from turns that were played in time, or from an
+        // earlier game's turn with the same number, are ignored.
+        let Some(TurnDeadline { turn: armed, fires, .. }) = self.deadline else { return };
+        if armed != turn || tokio::time::Instant::now() < fires { return; }
+        let Some(game) = &mut self.game else { return };
+        if game.is_finished() || game.turn != turn { return; }
+        let seat = game.active;
+        tracing::debug!(room_id = %self.room_id, seat, turn, "turn timed out");
+        self.seats[seat].timeouts += 1;
+        if self.seats[seat].timeouts >= self.afk_turns {
+            tracing::info!(room_id = %self.room_id, seat, "seat forfeited after timing out");
+            game.forfeit(FinishReason::Inactive { seat });
+            self.record(GameEvent::Forfeited { reason: FinishReason::Inactive { seat } });
+        } else {
+            game.expire_turn();
+            self.record(GameEvent::TurnExpired { seat });
+        }
+        self.broadcast_game_update();
+        self.finish_if_over();
+    }
+
+    fn handle_action(&mut self, seat: usize, action: Action) -> Result<(), RuleError> {
+        let game = self.game.as_mut().ok_or(RuleError::NotStarted)?;
+        let events = game.apply(seat, action)?;
+        self.record(GameEvent::Acted { seat, action, events: events.clone() });
+        self.seats[seat].timeouts = 0;
+        self.broadcast_game_update();
+        // Anything only one player may see goes out after the public update.
+        for event in events {
+            match event {
+                Event::Drawn { seat, card, from } => {
+                    self.send_private(seat, ServerToClient::Drawn { card: card_public(card), from });
+                }
+                Event::Peeked { viewer, owner, index, card, version } => {
+                    self.send_private(viewer, peek(owner, index, card, version));
+                }
+                Event::MatchAttempted { seat, index, matched } => {
+                    tracing::debug!(room_id = %self.room_id, seat, index, matched, "match attempt");
+                }
+                Event::OppMatchAttempted { seat, target, opp_index, give_index, matched } => {
+                    tracing::debug!(room_id = %self.room_id, seat, target, opp_index, give_index, matched, "opponent match attempt");
+                }
+                Event::DrawAgreed { seat } => {
+                    tracing::debug!(room_id = %self.room_id, seat, "draw offered or accepted");
+                }
+                Event::DrawDeclined { seat } => {
+                    tracing::debug!(room_id = %self.room_id, seat, "draw offer declined");
+                }
+                Event::ZobboCalled { seat } => {
+                    tracing::info!(room_id = %self.room_id, seat, "zobbo called");
+                    if let Some(call) = self.game.as_ref().and_then(|g| g.zobbo) {
+                        self.broadcast(ServerToClient::ZobboCalled { caller: call.caller, turns_remaining: call.remaining });
+                    }
+                }
+                // Visible in the public update.
+                Event::PowerOffered { .. } => {}
+                Event::Finished(_) => self.finish_if_over(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Play the student's move if it's the one the tutorial waits for, then
+    /// the tutor's reply, and hint at the next step.
+    fn handle_tutorial_action(&mut self, seat: usize, action: Action) -> Result<(), Rejection> {
+        let step = tutorial::check(self.tutorial_step, seat, action)?;
+        self.handle_action(seat, action)?;
+        for &reply in step.reply {
+            self.handle_action(tutorial::TUTOR, reply)?;
+        }
+        self.tutorial_step += 1;
+        self.send_hint();
+        Ok(())
+    }
+
+    fn send_hint(&mut self) {
+        if !self.settings.tutorial { return; }
+        self.send_to(tutorial::STUDENT, tutorial::hint(self.tutorial_step));
+    }
+
+    /// Whether the room plays `seat` itself: the tutor, in a tutorial.
+    fn scripted(&self, seat: usize) -> bool {
+        self.settings.tutorial && seat == tutorial::TUTOR
+    }
+
+    /// Update every seat's rating after a ranked game. A game without an
+    /// identity in every seat, which can only be one already under way when
+    /// someone reconnected without theirs, goes unrated.
+    fn rate(&self, result: &GameResult) -> Vec<RatingChange> {
+        if !self.settings.ranked { return Vec::new(); }
+        let Some(players) = self.seats.iter().map(|s| s.player.as_ref()).collect::<Option<Vec<_>>>() else {
+            tracing::warn!(room_id = %self.room_id, game_id = %self.game_id, "ranked game left unrated: a seat has no identity");
+            return Vec::new();
+        };
+        let (changes, saved) = self.records.ratings.rate(&players, result);
+        if let Some(store) = &self.store {
+            for (player, rating) in players.iter().zip(&saved) {
+                store.save_rating(&player.player_id, rating);
+            }
+        }
+        changes
+    }
+
+    /// File a summary of the finished game under every seated player's id.
+    fn record_history(&self, result: &GameResult) {
+        let finished_at_unix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        for (seat, player) in self.seats.iter().enumerate() {
+            let Some(player) = &player.player else { continue };
+            let outcome = match result.winner {
+                Some(winner) if winner == seat => Outcome::Won,
+                Some(_) => Outcome::Lost,
+                None => Outcome::Drew,
+            };
+            let summary = GameSummary {
+                game_id: self.game_id.clone(),
+                room_id: self.room_id.clone(),
+                finished_at_unix,
+                mode: self.settings.mode(),
+                seat,
+                score: result.scores[seat],
+                opponents: (0..result.scores.len())
+                    .filter(|&s| s != seat)
+                    .map(|s| Opponent {
+                        seat: s,
+                        score: result.scores[s],
+                        player_id: self.seats[s].player.as_ref().map(|p| p.player_id.clone()),
+                        name: self.seats[s].player.as_ref().map(|p| p.name.clone()),
+                    })
+                    .collect(),
+                outcome,
+                reason: result.reason,
+                called_zobbo: result.zobbo_caller == Some(seat),
+                powers_used: self
+                    .log
+                    .iter()
+                    .filter_map(|entry| match &entry.event {
+                        GameEvent::Acted { seat: s, action, .. } if *s == seat => action.power(),
+                        _ => None,
+                    })
+                    .collect(),
+            };
+            if let Some(store) = &self.store {
+                store.save_summary(&player.player_id, &summary);
+            }
+            self.records.history.record(&player.player_id, summary);
+        }
+    }
+
+    /// Once the engine has revealed, announce the result, then either schedule
+    /// the next round of a match or reopen the lobby for a rematch.
+    fn finish_if_over(&mut self) {
+        self.flush_update();
+        let Some(game) = &self.game else { return };
+        let Some(result) = &game.result else { return };
+        tracing::info!(room_id = %self.room_id, winner = ?result.winner, scores = ?result.scores, "game over");
+        self.ratings = self.rate(result);
+        self.deliver_all(game_over(game, &self.ratings));
+        self.activity.lock().unwrap().finished_at = Some(Instant::now());
+        self.records.archive.insert(GameRecord {
+            game_id: self.game_id.clone(),
+            room_id: self.room_id.clone(),
+            finished_at_unix: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            log: self.log.clone(),
+            result: Some(result.clone()),
+        });
+        // A scripted game says nothing about how anyone plays.
+        if !self.settings.tutorial { self.record_history(result); }
+        if let Some(day) = self.settings.daily.filter(|_| self.daily_pending) {
+            let players: Vec<_> = self.seats.iter().map(|s| s.player.as_ref()).collect();
+            self.records.daily.record(day, &result.scores, result.winner, &players);
+            self.daily_pending = false;
+        }
+        if let Some(battle) = &mut self.battle {
+            for (total, score) in battle.totals.iter_mut().zip(&result.scores) {
+                *total += score;
+            }
+            // Matches saved before round wins were tracked start counting now.
+            battle.wins.resize(battle.totals.len(), 0);
+            if let Some(winner) = result.winner {
+                battle.wins[winner] += 1;
+            }
+            let mode = self.settings.mode();
+            let rounds = mode.rounds();
+            let wins_needed = mode.wins_needed();
+            let eliminated: Vec<usize> = match mode {
+                GameMode::RaceTo { points } => (0..battle.totals.len()).filter(|s| battle.totals[*s] >= points).collect(),
+                _ => Vec::new(),
+            };
+            let knocked_out = !eliminated.is_empty();
+            let round_result = ServerToClient::RoundResult {
+                round: battle.round,
+                rounds,
+                winner: result.winner,
+                scores: result.scores.clone(),
+                totals: battle.totals.clone(),
+                eliminated,
+            };
+            let scoreboard = ServerToClient::Scoreboard {
+                round: battle.round,
+                rounds,
+                wins: battle.wins.clone(),
+                totals: battle.totals.clone(),
+                wins_needed,
+            };
+            let totals = battle.totals.clone();
+            let clinched = wins_needed.is_some_and(|needed| battle.wins.iter().any(|w| *w >= needed));
+            let last = clinched || knocked_out || battle.round >= rounds;
+            let winner = match wins_needed {
+                // Most round wins; `lowest_unique` over the negated counts.
+                Some(_) => lowest_unique(&battle.wins.iter().map(|w| -i32::from(*w)).collect::<Vec<_>>()),
+                None => lowest_unique(&totals),
+            };
+            self.broadcast(round_result);
+            self.broadcast(scoreboard);
+            if last {
+                tracing::info!(room_id = %self.room_id, ?winner, ?totals, "match over");
+                self.battle = None;
+                self.broadcast(ServerToClient::MatchOver { winner, totals });
+            } else {
+                let tx = self.timer_tx.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(ROUND_BREAK).await;
+                    if let Some(tx) = tx.upgrade() {
+                        let _ = tx.send(RoomCommand::NextRound);
+                    }
+                });
+            }
+        }
+        self.broadcast_lobby();
+    }
+}
+
+/// Unix seconds `from_now` from now, as players are shown deadlines.
+fn unix_secs(from_now: Duration) -> u64 {
+    (SystemTime::now() + from_now).duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn peek(owner: usize, index: usize, card: Card, version: u32) -> ServerToClient {
+    ServerToClient::PeekResult { owner, index, version, card: card_public(card) }
+}
+
+/// Every seat's cards, face up.
+pub(crate) fn hands(game: &GameState) -> Vec<Vec<Option<CardPublic>>> {
+    game.seats.iter().map(|s| s.slots.iter().map(|c| c.map(card_public)).collect()).collect()
+}
+
+/// The final reveal; only meaningful once `game.result` is set.
+pub(crate) fn game_over(game: &GameState, ratings: &[RatingChange]) -> ServerToClient {
+    let result = game.result.as_ref().expect("game_over before the game finished");
+    ServerToClient::GameOver {
+        winner: result.winner,
+        scores: result.scores.clone(),
+        hands: hands(game),
+        zobbo_caller: result.zobbo_caller,
+        zobbo_penalty: result.zobbo_penalty,
+        reason: result.reason,
+        ratings: ratings.to_vec(),
+    }
+}
+
+pub(crate) fn game_start(game_id: &str, game: &GameState, (round, rounds): (u8, u8)) -> ServerToClient {
+    ServerToClient::GameStart {
+        game_id: game_id.to_string(),
+        seats: game.seats.len(),
+        hand_size: game.rules.hand_size,
+        first: game.active,
+        round,
+        rounds,
+        rules: game.rules,
+    }
+}
+
+/// The public view of `game` outside a room, with no timers or pause.
+pub(crate) fn public_update(game: &GameState, seq: u64) -> GameUpdate {
+    game_update(game, None, &Pause::default(), Vec::new(), seq)
+}
+
+/// The public view of `game`, identical for every seat.
+fn game_update(game: &GameState, deadline: Option<TurnDeadline>, pause: &Pause, time_bank_ms: Vec<u64>, seq: u64) -> GameUpdate {
+    let deadline = deadline.filter(|d| d.turn == game.turn);
+    let (stage, held_from, power) = match &game.stage {
+        // What the player was doing is kept, so the client can show it greyed out.
+        TurnStage::Holding { from, .. } if pause.paused => (Stage::Paused, Some(*from), None),
+        TurnStage::Power(p) if pause.paused => (Stage::Paused, None, Some(*p)),
+        TurnStage::Draw if pause.paused => (Stage::Paused, None, None),
+        TurnStage::Draw => (Stage::Draw, None, None),
+        TurnStage::Holding { from, .. } => (Stage::Holding, Some(*from), None),
+        TurnStage::Power(p) => (Stage::Power, None, Some(*p)),
+        TurnStage::Finished => (Stage::Finished, None, None),
+    };
+    let seats = game
+        .seats
+        .iter()
+        .enumerate()
+        .map(|(seat, s)| SeatPublic {
+            seat,
+            slots: s
+                .slots
+                .iter()
+                .zip(&s.versions)
+                .map(|(c, v)| SlotPublic { filled: c.is_some(), version: *v })
+                .collect(),
+            skip_next: s.skip_next,
+        })
+        .collect();
+    GameUpdate {
+        seq,
+        active: game.active,
+        stage,
+        held_from,
+        power,
+        deck_count: game.deck.len(),
+        discard_top: game.discard_top().map(card_public),
+        discard_count: game.discard.len(),
+        seats,
+        zobbo_remaining: game.zobbo.map(|z| z.remaining),
+        zobbo_caller: game.zobbo.map(|z| z.caller),
+        draw_agreed: game.draw_agreed.clone(),
+        pause_requests: pause.requests.clone(),
+        turn_deadline_unix: deadline.map(|d| d.at),
+        turn_compensation_ms: deadline.map(|d| d.compensation.as_millis() as u32),
+        time_bank_ms,
+        checksum: 0,
+    }
+    .sealed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A room's task driven from the test, with a channel standing in for
+    /// each seat's socket.
+    struct Harness {
+        room: RoomHandle,
+        seats: Vec<mpsc::UnboundedReceiver<ServerToClient>>,
+    }
+
+    impl Harness {
+        /// Every seat connected, in the lobby; all but the tutor in a tutorial.
+        fn seated(seats: usize, settings: RoomSettings) -> Harness {
+            config::load().unwrap();
+            let room = spawn("test".into(), seats, settings, None, RoomSnapshot::default(), Records::default());
+            let seats = (0..seats)
+                .map(|seat| {
+                    let (tx, rx) = mpsc::unbounded_channel();
+                    if !(settings.tutorial && seat == tutorial::TUTOR) {
+                        room.send(RoomCommand::Connect { seat, conn: seat as u64, player: None, bot: false, tx });
+                    }
+                    rx
+                })
+                .collect();
+            Harness { room, seats }
+        }
+
+        /// Every seat ready, and the game dealt.
+        async fn dealt(seats: usize, settings: RoomSettings) -> Harness {
+            let mut room = Harness::seated(seats, settings);
+            for seat in 0..seats {
+                room.send(seat, ClientToServer::Ready);
+            }
+            for seat in 0..seats {
+                room.drain(seat).await;
+            }
+            room
+        }
+
+        /// Drop `seat`'s socket, as if it went away.
+        fn disconnect(&self, seat: usize) {
+            self.room.send(RoomCommand::Disconnect { seat, conn: seat as u64 });
+        }
+
+        /// Give `seat` a fresh socket.
+        fn reconnect(&mut self, seat: usize) {
+            let (tx, rx) = mpsc::unbounded_channel();
+            self.room.send(RoomCommand::Connect { seat, conn: seat as u64, player: None, bot: false, tx });
+            self.seats[seat] = rx;
+        }
+
+        fn send(&self, seat: usize, msg: ClientToServer) {
+            self.room.send(RoomCommand::Client { seat, msg });
+        }
+
+        /// Everything `seat` has been sent, once the room has handled what
+        /// was queued before.
+        async fn drain(&mut self, seat: usize) -> Vec<ServerToClient> {
+            self.inspect().await;
+            std::iter::from_fn(|| self.seats[seat].try_recv().ok()).collect()
+        }
+
+        async fn inspect(&self) -> RoomInspection {
+            self.room.inspect().await.expect("the room is running")
+        }
+
+        /// The full public state, as `seat` would catch up on it.
+        async fn update(&mut self, seat: usize) -> GameUpdate {
+            self.send(seat, ClientToServer::RequestState { last_seq: u64::MAX });
+            let got = self.drain(seat).await;
+            let Some(ServerToClient::GameUpdate(update)) = got.last() else { panic!("{got:?}") };
+            update.clone()
+        }
+
+        /// Play `seat`'s turn out: draw, discard and skip any power.
+        async fn pass(&mut self, seat: usize) {
+            self.send(seat, ClientToServer::DrawDeck);
+            self.send(seat, ClientToServer::DiscardDrawn);
+            let inspection = self.inspect().await;
+            if inspection.active == Some(seat) && matches!(inspection.stage, Some(TurnStage::Power(_))) {
+                self.send(seat, ClientToServer::SkipPower);
+            }
+        }
+
+        /// Play a two-seat round out with a Zobbo call on the first turn, and
+        /// return what seat 0 was sent.
+        async fn call_zobbo(&mut self) -> Vec<ServerToClient> {
+            let caller = self.inspect().await.active.unwrap();
+            self.send(caller, ClientToServer::CallZobbo);
+            self.pass(caller).await;
+            self.pass(1 - caller).await;
+            self.drain(0).await
+        }
+
+        /// Why `seat`'s last message was refused.
+        async fn rejected(&mut self, seat: usize) -> RejectCode {
+            let got = self.drain(seat).await;
+            let Some(ServerToClient::ActionRejected { code, .. }) = got.last() else { panic!("{got:?}") };
+            *code
+        }
+
+        /// The `GameOver` among what `seat` has been sent.
+        async fn game_over(&mut self, seat: usize) -> FinishReason {
+            let got = self.drain(seat).await;
+            let Some(ServerToClient::GameOver { reason, .. }) = got.iter().find(|m| matches!(m, ServerToClient::GameOver { .. }))
+            else {
+                panic!("{got:?}")
+            };
+            *reason
+        }
+    }
+
+    #[test]
+    fn presets_are_valid_settings() {
+        for preset in RulePreset::ALL {
+            let (turn, bank) = preset.timing().map_or((0, 0), |pace| (pace.turn_secs(), pace.bank_secs()));
+            assert!(RoomSettings::new(preset.rules(), turn, bank).is_ok(), "{}", preset.name());
+        }
+    }
+
+    /// Long enough for the broadcast cap to let the next update straight out.
+    fn gap() -> Duration {
+        Duration::from_secs(1) / config::get().room_updates_per_sec
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn updates_that_land_together_go_out_as_one() {
+        let mut room = Harness::dealt(2, RoomSettings::default()).await;
+        tokio::time::sleep(gap()).await;
+        room.send(0, ClientToServer::RequestPause);
+        let got = room.drain(1).await;
+        let [ServerToClient::GameDelta(delta)] = &got[..] else { panic!("{got:?}") };
+        assert_eq!(delta.pause_requests, Some(vec![0]));
+
+        // Paused and resumed before the room gets to send either: one
+        // update, with nothing in it about the pause.
+        tokio::time::sleep(gap()).await;
+        room.send(1, ClientToServer::RequestPause);
+        room.send(0, ClientToServer::Resume);
+        let got = room.drain(1).await;
+        let [ServerToClient::GameDelta(delta)] = &got[..] else { panic!("{got:?}") };
+        assert_eq!((delta.stage, delta.pause_requests.as_deref()), (None, Some(&[][..])));
+
+        // Anything else sent meanwhile still comes after the update it follows.
+        room.send(0, ClientToServer::RequestPause);
+        room.send(1, ClientToServer::Resume);
+        let got = room.drain(1).await;
+        assert!(matches!(&got[..], [ServerToClient::GameDelta(_), ServerToClient::ActionRejected { .. }]), "{got:?}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn the_update_cap_holds_back_all_but_turn_changes() {
+        let mut room = Harness::dealt(2, RoomSettings::default()).await;
+        let active = room.inspect().await.active.unwrap();
+        tokio::time::sleep(gap()).await;
+        room.send(1 - active, ClientToServer::OfferDraw);
+        assert_eq!(room.drain(active).await.len(), 1);
+
+        room.send(1 - active, ClientToServer::RequestPause);
+        assert_eq!(room.drain(active).await, []);
+        tokio::time::sleep(gap()).await;
+        let got = room.drain(active).await;
+        let [ServerToClient::GameDelta(delta)] = &got[..] else { panic!("{got:?}") };
+        assert_eq!(delta.pause_requests, Some(vec![1 - active]));
+
+        // The draw is private, so the update before it goes out with it; the
+        // turn ending right after doesn't wait either.
+        room.send(active, ClientToServer::DrawDeck);
+        assert!(matches!(&room.drain(active).await[..], [ServerToClient::GameDelta(_), ServerToClient::Drawn { .. }]));
+        room.send(active, ClientToServer::DiscardDrawn);
+        let mut got = room.drain(1 - active).await;
+        if room.inspect().await.active == Some(active) {
+            // It had a power to use first.
+            room.send(active, ClientToServer::SkipPower);
+            got.extend(room.drain(1 - active).await);
+        }
+        let [.., ServerToClient::GameDelta(delta)] = &got[..] else { panic!("{got:?}") };
+        assert_eq!(delta.active, Some(1 - active));
+    }
+
+    #[tokio::test]
+    async fn the_tutorial_takes_only_the_step_it_hints_at() {
+        let settings = RoomSettings { tutorial: true, ..RoomSettings::new(tutorial::rules(), 0, 0).unwrap() };
+        let mut room = Harness::seated(2, settings);
+        room.send(tutorial::STUDENT, ClientToServer::Ready);
+        let got = room.drain(tutorial::STUDENT).await;
+        assert!(matches!(got.last(), Some(ServerToClient::TutorialHint { step: 0, .. })), "{got:?}");
+
+        room.send(tutorial::STUDENT, ClientToServer::DrawDiscard);
+        let got = room.drain(tutorial::STUDENT).await;
+        let [ServerToClient::ActionRejected { code: RejectCode::OffScript, .. }] = &got[..] else { panic!("{got:?}") };
+        room.send(tutorial::STUDENT, ClientToServer::KickPlayer { seat: tutorial::TUTOR });
+        let got = room.drain(tutorial::STUDENT).await;
+        let [ServerToClient::ActionRejected { code: RejectCode::OffScript, .. }] = &got[..] else { panic!("{got:?}") };
+
+        for (i, step) in tutorial::STEPS.iter().enumerate() {
+            let msg = match step.expect {
+                Action::DrawDeck => ClientToServer::DrawDeck,
+                Action::SwapWithHand { index } => ClientToServer::SwapWithHand { index },
+                Action::DiscardDrawn => ClientToServer::DiscardDrawn,
+                Action::PeekOwn { index } => ClientToServer::PeekOwn { index },
+                Action::CallZobbo => ClientToServer::CallZobbo,
+                other => panic!("no message for {other:?}"),
+            };
+            room.send(tutorial::STUDENT, msg);
+            let got = room.drain(tutorial::STUDENT).await;
+            let Some(ServerToClient::TutorialHint { step, .. }) = got.last() else { panic!("{got:?}") };
+            assert_eq!(*step as usize, i + 1);
+        }
+        room.send(tutorial::STUDENT, ClientToServer::Resync);
+        let got = room.drain(tutorial::STUDENT).await;
+        let [.., ServerToClient::GameOver { winner, .. }, ServerToClient::TutorialHint { highlight: None, .. }] = &got[..] else {
+            panic!("{got:?}")
+        };
+        assert_eq!(*winner, Some(tutorial::STUDENT));
+    }
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_bank_runs_only_on_its_own_turn_and_forfeits_when_empty() {
+        let mut room = Harness::dealt(2, RoomSettings::new(RuleConfig::default(), 0, 60).unwrap()).await;
+        let first = room.inspect().await.active.unwrap();
+        let second = 1 - first;
+        // A timer from before the bank started is stale.
+        room.room.send(RoomCommand::BankTimeout { epoch: 0 });
+        tokio::time::sleep(secs(20)).await;
+        room.pass(first).await;
+        let mut left = vec![60_000; 2];
+        left[first] = 40_000;
+        assert_eq!(room.update(first).await.time_bank_ms, left);
+
+        // The first seat's timer, set for a full minute, fires stale on the second's turn.
+        tokio::time::sleep(secs(45)).await;
+        assert_eq!(room.inspect().await.active, Some(second));
+        left[second] = 15_000;
+        assert_eq!(room.update(first).await.time_bank_ms, left);
+
+        tokio::time::sleep(secs(15)).await;
+        assert_eq!(room.game_over(first).await, FinishReason::OutOfTime { seat: second });
+        left[second] = 0;
+        assert_eq!(room.update(first).await.time_bank_ms, left);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_pause_needs_every_seat_and_stops_play_and_the_clock() {
+        let mut room = Harness::seated(2, RoomSettings::new(RuleConfig::default(), 0, 60).unwrap());
+        room.send(0, ClientToServer::RequestPause);
+        assert_eq!(room.rejected(0).await, RejectCode::NotStarted);
+        for seat in 0..2 {
+            room.send(seat, ClientToServer::Ready);
+        }
+        let active = room.inspect().await.active.unwrap();
+
+        room.send(active, ClientToServer::RequestPause);
+        room.send(active, ClientToServer::RequestPause);
+        let update = room.update(active).await;
+        assert_eq!((update.stage, update.pause_requests), (Stage::Draw, vec![active]));
+        room.send(active, ClientToServer::Resume);
+        assert_eq!(room.rejected(active).await, RejectCode::NotPaused);
+
+        tokio::time::sleep(secs(10)).await;
+        room.send(1 - active, ClientToServer::RequestPause);
+        let update = room.update(active).await;
+        assert_eq!((update.stage, update.pause_requests), (Stage::Paused, vec![]));
+        assert!(room.inspect().await.paused);
+        room.send(active, ClientToServer::DrawDeck);
+        assert_eq!(room.rejected(active).await, RejectCode::Paused);
+        room.send(active, ClientToServer::RequestPause);
+        assert_eq!(room.rejected(active).await, RejectCode::Paused);
+
+        // Held well past what the bank had left, and charged only for play.
+        tokio::time::sleep(secs(120)).await;
+        assert_eq!(room.update(active).await.time_bank_ms[active], 50_000);
+        room.send(1 - active, ClientToServer::Resume);
+        tokio::time::sleep(secs(5)).await;
+        let update = room.update(active).await;
+        assert_eq!((update.stage, update.time_bank_ms[active]), (Stage::Draw, 45_000));
+        room.pass(active).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn an_away_seat_forfeits_once_its_grace_runs_out_not_counting_a_pause() {
+        let mut room = Harness::dealt(2, RoomSettings { grace: Some(secs(60)), ..RoomSettings::default() }).await;
+        // Back in time, so the countdown from this drop is stale by the next.
+        room.disconnect(1);
+        let got = room.drain(0).await;
+        assert!(got.iter().any(|m| matches!(m, ServerToClient::OpponentDisconnected { seat: 1, .. })), "{got:?}");
+        tokio::time::sleep(secs(10)).await;
+        room.reconnect(1);
+        assert_eq!(room.inspect().await.seats[1].forfeit_at, None);
+
+        // Asked to pause, then dropped with 60s to come back, and the pause
+        // agreed to with 50s of that left.
+        tokio::time::sleep(secs(10)).await;
+        room.send(1, ClientToServer::RequestPause);
+        room.disconnect(1);
+        tokio::time::sleep(secs(10)).await;
+        room.send(0, ClientToServer::RequestPause);
+        assert!(room.inspect().await.paused);
+        tokio::time::sleep(secs(200)).await;
+        assert!(room.inspect().await.seats[1].forfeit_at.is_some());
+        assert!(!room.drain(0).await.iter().any(|m| matches!(m, ServerToClient::GameOver { .. })));
+
+        room.send(0, ClientToServer::Resume);
+        tokio::time::sleep(secs(49)).await;
+        assert!(!room.drain(0).await.iter().any(|m| matches!(m, ServerToClient::GameOver { .. })));
+        tokio::time::sleep(secs(1)).await;
+        assert_eq!(room.game_over(0).await, FinishReason::Forfeit { seat: 1 });
+        assert_eq!(room.inspect().await.seats[1].forfeit_at, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_seat_that_lets_its_turns_run_out_in_a_row_forfeits() {
+        let mut room = Harness::dealt(2, RoomSettings::new(RuleConfig::default(), 30, 0).unwrap()).await;
+        let afk = config::get().afk_forfeit_turns;
+        let idle = room.inspect().await.active.unwrap();
+        let other = 1 - idle;
+        // Early, so stale.
+        let turn = room.inspect().await.turn.unwrap();
+        room.room.send(RoomCommand::TurnTimeout { turn });
+        assert_eq!(room.inspect().await.active, Some(idle));
+
+        for _ in 1..afk {
+            tokio::time::sleep(secs(31)).await;
+            assert_eq!(room.inspect().await.active, Some(other));
+            room.pass(other).await;
+        }
+        // A turn played in time starts the count again.
+        room.pass(idle).await;
+        room.pass(other).await;
+        for _ in 1..afk {
+            tokio::time::sleep(secs(31)).await;
+            room.pass(other).await;
+        }
+        assert!(!room.drain(other).await.iter().any(|m| matches!(m, ServerToClient::GameOver { .. })));
+        tokio::time::sleep(secs(31)).await;
+        assert_eq!(room.game_over(other).await, FinishReason::Inactive { seat: idle });
+    }
+
+    /// The same deal every round, so playing it the same way gives the same result.
+    fn fixed_deal(rules: RuleConfig) -> RoomSettings {
+        RoomSettings { daily: Some(20_000), ..RoomSettings::new(rules, 0, 0).unwrap() }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_best_of_match_ends_once_a_seat_has_the_wins_to_take_it() {
+        let mut room = Harness::dealt(2, fixed_deal(RuleConfig { rounds: 5, best_of: true, ..RuleConfig::default() })).await;
+        let mut winner = None;
+        for round in 1..=3 {
+            let got = room.call_zobbo().await;
+            let Some(ServerToClient::RoundResult { winner: Some(won), .. }) =
+                got.iter().find(|m| matches!(m, ServerToClient::RoundResult { .. }))
+            else {
+                panic!("{got:?}")
+            };
+            assert_eq!(*winner.get_or_insert(*won), *won);
+            let Some(ServerToClient::Scoreboard { wins, wins_needed, .. }) =
+                got.iter().find(|m| matches!(m, ServerToClient::Scoreboard { .. }))
+            else {
+                panic!("{got:?}")
+            };
+            assert_eq!((wins[*won], *wins_needed), (round, Some(3)));
+            let over = got.iter().find_map(|m| match m {
+                ServerToClient::MatchOver { winner, .. } => Some(*winner),
+                _ => None,
+            });
+            assert_eq!(over, (round == 3).then_some(Some(*won)), "round {round}");
+            tokio::time::sleep(ROUND_BREAK + secs(1)).await;
+        }
+        // Three wins of five: the last two rounds aren't dealt.
+        assert!(!room.drain(0).await.iter().any(|m| matches!(m, ServerToClient::GameStart { .. })));
+        room.send(0, ClientToServer::Ready);
+        assert!(matches!(room.drain(0).await.last(), Some(ServerToClient::LobbyState { .. })));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_race_ends_the_round_a_seat_reaches_the_limit() {
+        let mut room = Harness::dealt(2, fixed_deal(RuleConfig { race_to: Some(120), ..RuleConfig::default() })).await;
+        for round in 1.. {
+            let got = room.call_zobbo().await;
+            let Some(ServerToClient::RoundResult { scores, totals, eliminated, .. }) =
+                got.iter().find(|m| matches!(m, ServerToClient::RoundResult { .. }))
+            else {
+                panic!("{got:?}")
+            };
+            assert_eq!(*totals, scores.iter().map(|s| s * round).collect::<Vec<_>>());
+            let out: Vec<usize> = (0..2).filter(|&seat| totals[seat] >= 120).collect();
+            assert_eq!(*eliminated, out);
+            let over = got.iter().find_map(|m| match m {
+                ServerToClient::MatchOver { winner, totals } => Some((*winner, totals.clone())),
+                _ => None,
+            });
+            if out.is_empty() {
+                assert_eq!(over, None, "round {round}");
+                tokio::time::sleep(ROUND_BREAK + secs(1)).await;
+                continue;
+            }
+            assert_eq!(over, Some((lowest_unique(totals), totals.clone())));
+            assert!(round > 1, "the limit is set to take a few rounds");
+            break;
+        }
+    }
+
+    /// The last lobby `seat` was sent.
+    async fn lobby(room: &mut Harness, seat: usize) -> ServerToClient {
+        let got = room.drain(seat).await;
+        got.into_iter().rfind(|m| matches!(m, ServerToClient::LobbyState { .. })).expect("a lobby")
+    }
+
+    #[tokio::test]
+    async fn only_the_host_kicks_or_changes_settings_and_only_in_the_lobby() {
+        let mut room = Harness::seated(3, RoomSettings::default());
+        assert_eq!(room.inspect().await.host, Some(0));
+        room.send(1, ClientToServer::KickPlayer { seat: 2 });
+        assert_eq!(room.rejected(1).await, RejectCode::NotHost);
+        room.send(1, ClientToServer::UpdateSettings { rounds: 3, turn_secs: 0 });
+        assert_eq!(room.rejected(1).await, RejectCode::NotHost);
+        for target in [0, 3] {
+            room.send(0, ClientToServer::KickPlayer { seat: target });
+            assert_eq!(room.rejected(0).await, RejectCode::BadIndex);
+        }
+        room.send(0, ClientToServer::UpdateSettings { rounds: 0, turn_secs: 0 });
+        assert_eq!(room.rejected(0).await, RejectCode::InvalidSettings);
+        room.send(0, ClientToServer::UpdateSettings { rounds: 1, turn_secs: 2 });
+        assert_eq!(room.rejected(0).await, RejectCode::InvalidSettings);
+
+        // New settings need everyone to ready up again.
+        room.send(1, ClientToServer::Ready);
+        room.send(0, ClientToServer::UpdateSettings { rounds: 3, turn_secs: 60 });
+        let ServerToClient::LobbyState { players, mode, turn_secs, .. } = lobby(&mut room, 1).await else { unreachable!() };
+        assert_eq!((mode, turn_secs), (GameMode::ZobboBattle { rounds: 3 }, Some(60)));
+        assert!(players.iter().all(|p| !p.ready));
+
+        room.send(0, ClientToServer::KickPlayer { seat: 2 });
+        assert_eq!(room.drain(2).await.last(), Some(&ServerToClient::Kicked));
+        assert!(!room.inspect().await.seats[2].connected);
+        assert_eq!(room.room.take_revoked(), [2]);
+
+        room.reconnect(2);
+        for seat in 0..3 {
+            room.send(seat, ClientToServer::Ready);
+        }
+        room.send(0, ClientToServer::KickPlayer { seat: 2 });
+        assert_eq!(room.rejected(0).await, RejectCode::InProgress);
+        room.send(0, ClientToServer::UpdateSettings { rounds: 1, turn_secs: 0 });
+        assert_eq!(room.rejected(0).await, RejectCode::InProgress);
+    }
+
+    #[tokio::test]
+    async fn the_host_role_goes_to_the_next_human_seat_still_here() {
+        let mut room = Harness::seated(3, RoomSettings::default());
+        room.disconnect(0);
+        let ServerToClient::LobbyState { host, .. } = lobby(&mut room, 1).await else { unreachable!() };
+        assert_eq!(host, Some(1));
+
+        // Seat 0 is a bot now, so it's passed over.
+        let (tx, _bot) = mpsc::unbounded_channel();
+        room.room.send(RoomCommand::Connect { seat: 0, conn: 10, player: None, bot: true, tx });
+        room.disconnect(1);
+        assert_eq!(room.inspect().await.host, Some(2));
+        room.disconnect(2);
+        assert_eq!(room.inspect().await.host, Some(2));
+
+        // With the host away, whoever comes back first takes over.
+        room.reconnect(1);
+        assert_eq!(room.inspect().await.host, Some(1));
+        room.reconnect(2);
+        assert_eq!(room.inspect().await.host, Some(1));
+        room.send(1, ClientToServer::KickPlayer { seat: 2 });
+        assert_eq!(room.drain(2).await.last(), Some(&ServerToClient::Kicked));
+    }
+
+    #[test]
+    fn the_turn_deadline_carries_its_compensation() {
+        let game = GameState::new(2, RuleConfig::default(), 1);
+        let deadline = TurnDeadline {
+            turn: game.turn,
+            at: 1_790_000_000,
+            compensation: Duration::from_millis(180),
+            fires: tokio::time::Instant::now(),
+        };
+        let update = game_update(&game, Some(deadline), &Pause::default(), Vec::new(), 1);
+        assert_eq!((update.turn_deadline_unix, update.turn_compensation_ms), (Some(1_790_000_000), Some(180)));
+        // A timer left over from an earlier turn isn't shown.
+        let stale = TurnDeadline { turn: game.turn + 1, ..deadline };
+        let update = game_update(&game, Some(stale), &Pause::default(), Vec::new(), 2);
+        assert_eq!((update.turn_deadline_unix, update.turn_compensation_ms), (None, None));
+    }
+}
@@ -0,0 +1,167 @@
+//! The tutorial: a scripted two-seat game that walks a new player through a
+//! turn, a power and a Zobbo call.
+//!
+//! The deal is fixed by `SEED`, so the game logs and replays like any other.
+//! The student sits in `STUDENT`; nobody joins `TUTOR`, whose moves the room
+//! plays itself. At each `Step` the room takes only the move its hint asks for.
+
+use zobbo_core::types::Action;
+
+use crate::ws::protocol::{RejectCode, RuleConfig, ServerToClient, TutorialHighlight};
+
+/// Deals the student A♦ 8♥ K♠ 10♣ (shown the last two) and the tutor
+/// 3♠ 9♠ 6♥ 7♥, with J♦ face up and 2♦ 4♦ 8♠ A♥ A♣ K♣ on top of the deck,
+/// the student to play first; `the_seed_deals_the_fixture` holds it to that.
+pub const SEED: u64 = 10_853;
+pub const STUDENT: usize = 0;
+pub const TUTOR: usize = 1;
+
+/// One move the student is asked for, and the tutor's turn that follows it.
+pub struct Step {
+    pub text: &'static str,
+    pub highlight: Option<TutorialHighlight>,
+    pub expect: Action,
+    /// Played for `TUTOR` straight after `expect`; empty mid-turn.
+    pub reply: &'static [Action],
+}
+
+pub const STEPS: &[Step] = &[
+    Step {
+        text: "Lowest hand wins. You were shown your last two cards: the king of spades, \
+               worth 0 as black kings are, and the 10 of clubs. Start your turn by drawing from the deck.",
+        highlight: Some(TutorialHighlight::Deck),
+        expect: Action::DrawDeck,
+        reply: &[],
+    },
+    Step {
+        text: "A 2. Swap it for your 10: the 10 goes on the discard pile and your hand drops by 8.",
+        highlight: Some(TutorialHighlight::Slot { seat: STUDENT, index: 3 }),
+        expect: Action::SwapWithHand { index: 3 },
+        reply: &[Action::DrawDeck, Action::SwapWithHand { index: 1 }],
+    },
+    Step {
+        text: "Your opponent swapped a card of theirs too. Your turn: draw again.",
+        highlight: Some(TutorialHighlight::Deck),
+        expect: Action::DrawDeck,
+        reply: &[],
+    },
+    Step {
+        text: "An 8. A 5 to 8 discarded straight from the deck lets you look at one of your own cards. Discard it.",
+        highlight: Some(TutorialHighlight::Discard),
+        expect: Action::DiscardDrawn,
+        reply: &[],
+    },
+    Step {
+        text: "Look at your first card, which you haven't seen yet.",
+        highlight: Some(TutorialHighlight::Slot { seat: STUDENT, index: 0 }),
+        expect: Action::PeekOwn { index: 0 },
+        reply: &[Action::DrawDeck, Action::SwapWithHand { index: 3 }],
+    },
+    Step {
+        text: "An ace, worth 1: the three cards you know add up to 3. When you think your hand is lowest, \
+               call Zobbo at the start of your turn. You still play it, then everyone else gets one more.",
+        highlight: Some(TutorialHighlight::Zobbo),
+        expect: Action::CallZobbo,
+        reply: &[],
+    },
+    Step {
+        text: "Draw for your last turn.",
+        highlight: Some(TutorialHighlight::Deck),
+        expect: Action::DrawDeck,
+        reply: &[],
+    },
+    Step {
+        text: "Another ace. Swap it for the one card you haven't seen.",
+        highlight: Some(TutorialHighlight::Slot { seat: STUDENT, index: 1 }),
+        expect: Action::SwapWithHand { index: 1 },
+        reply: &[Action::DrawDeck, Action::SwapWithHand { index: 2 }],
+    },
+];
+
+/// The hint once every step is played and the hands are revealed.
+const DONE: &str = "Every hand is turned over and the lowest total wins. That's Zobbo: you're ready for a real game.";
+
+/// The rules the tutorial deals with: four cards each, two of them shown.
+pub fn rules() -> RuleConfig {
+    RuleConfig { hand_size: 4, initial_peeks: 2, ..RuleConfig::default() }
+}
+
+/// A move the tutorial isn't waiting for.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("the tutorial is waiting for something else; see its hint")]
+pub struct OffScript;
+
+impl OffScript {
+    pub fn code(self) -> RejectCode {
+        RejectCode::OffScript
+    }
+}
+
+/// The step that `action` by `seat` plays, if it's the one `step` waits for.
+pub fn check(step: usize, seat: usize, action: Action) -> Result<&'static Step, OffScript> {
+    STEPS.get(step).filter(|s| seat == STUDENT && s.expect == action).ok_or(OffScript)
+}
+
+/// What the student is asked to do at `step`, or the closing words past the last one.
+pub fn hint(step: usize) -> ServerToClient {
+    let (text, highlight) = STEPS.get(step).map_or((DONE, None), |s| (s.text, s.highlight));
+    ServerToClient::TutorialHint { step: step as u32, text: text.into(), highlight }
+}
+
+#[cfg(test)]
+mod tests {
+    use zobbo_core::engine::GameState;
+    use zobbo_core::types::Card;
+
+    use super::*;
+    use crate::ws::protocol::{FinishReason, Rank, Suit};
+
+    fn card(rank: Rank, suit: Suit) -> Card {
+        Card { rank, suit }
+    }
+
+    #[test]
+    fn the_seed_deals_the_fixture() {
+        use Rank::*;
+        use Suit::*;
+        let game = GameState::new(2, rules(), SEED);
+        let hand = |seat: usize| game.seats[seat].slots.iter().map(|c| c.unwrap()).collect::<Vec<_>>();
+        assert_eq!(game.active, STUDENT);
+        assert_eq!(hand(STUDENT), [card(Ace, Diamonds), card(Eight, Hearts), card(King, Spades), card(Ten, Clubs)]);
+        assert_eq!(hand(TUTOR), [card(Three, Spades), card(Nine, Spades), card(Six, Hearts), card(Seven, Hearts)]);
+        assert_eq!(game.discard_top(), Some(card(Jack, Diamonds)));
+        let top: Vec<Card> = game.deck.iter().rev().take(6).copied().collect();
+        let draws =
+            [card(Two, Diamonds), card(Four, Diamonds), card(Eight, Spades), card(Ace, Hearts), card(Ace, Clubs), card(King, Clubs)];
+        assert_eq!(top, draws);
+    }
+
+    #[test]
+    fn the_script_plays_out_to_a_student_win() {
+        let mut game = GameState::new(2, rules(), SEED);
+        for (i, step) in STEPS.iter().enumerate() {
+            assert!(!game.is_finished(), "finished before step {i}");
+            assert_eq!(game.active, STUDENT, "step {i}");
+            game.apply(STUDENT, step.expect).unwrap_or_else(|err| panic!("step {i}: {err}"));
+            for &reply in step.reply {
+                game.apply(TUTOR, reply).unwrap_or_else(|err| panic!("reply to step {i}: {err}"));
+            }
+        }
+        let result = game.result.expect("the last reply reveals");
+        assert_eq!(result.reason, FinishReason::Zobbo);
+        assert_eq!(result.winner, Some(STUDENT));
+        assert_eq!(result.scores, [4, 8]);
+        assert_eq!(result.zobbo_penalty, None);
+    }
+
+    #[test]
+    fn only_the_expected_move_is_taken() {
+        assert!(check(0, STUDENT, Action::DrawDeck).is_ok());
+        assert_eq!(check(0, STUDENT, Action::DrawDiscard).err(), Some(OffScript));
+        assert_eq!(check(0, TUTOR, Action::DrawDeck).err(), Some(OffScript));
+        assert_eq!(check(1, STUDENT, Action::SwapWithHand { index: 2 }).err(), Some(OffScript));
+        assert_eq!(check(STEPS.len(), STUDENT, Action::DrawDeck).err(), Some(OffScript));
+        let ServerToClient::TutorialHint { step, highlight, .. } = hint(STEPS.len()) else { panic!("not a hint") };
+        assert_eq!((step as usize, highlight), (STEPS.len(), None));
+    }
+}
@@ -0,0 +1,211 @@
+//! File-backed store, for deployments without a database: everything the
+//! SQLite store keeps, held in memory and written out whole as one JSON
+//! snapshot every `SNAPSHOT_INTERVAL_SECS` and again on shutdown.
+//!
+//! A crash loses at most one interval of changes. The file is replaced
+//! atomically, so a crash mid-write leaves the previous snapshot intact.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use crate::room::bans::unix_now;
+use crate::room::history::KEEP_PER_PLAYER;
+use crate::store::{check_version, Restored, StoreHandle, StoreOp, StoredRoom, StoredSummary, SNAPSHOT_VERSION};
+
+/// The whole file.
+#[derive(Default, Serialize, Deserialize)]
+struct Snapshot {
+    /// `SNAPSHOT_VERSION` of the server that wrote it.
+    version: u32,
+    saved_at_unix: u64,
+    rooms: BTreeMap<String, RoomRow>,
+    /// Per player, oldest first.
+    history: BTreeMap<String, VecDeque<Value>>,
+    ratings: BTreeMap<String, Value>,
+    bans: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RoomRow {
+    meta: Value,
+    game: Option<Value>,
+}
+
+/// Load the snapshot at `path`, if there is one yet, and start the writer
+/// task. A snapshot that can't be read, or was written by a newer server,
+/// is an error rather than something to overwrite.
+pub async fn open(path: PathBuf, interval: Duration) -> io::Result<(StoreHandle, Restored)> {
+    let snapshot: Snapshot = match fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Snapshot::default(),
+        Err(err) => return Err(err),
+    };
+    if snapshot.version > SNAPSHOT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is snapshot version {}; this server reads up to {SNAPSHOT_VERSION}", path.display(), snapshot.version),
+        ));
+    }
+    let restored = restore(&snapshot);
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(write_loop(path, snapshot, rx, interval));
+    Ok((StoreHandle::new(tx), restored))
+}
+
+fn restore(snapshot: &Snapshot) -> Restored {
+    let mut rooms = Vec::with_capacity(snapshot.rooms.len());
+    for (id, row) in &snapshot.rooms {
+        let record = match serde_json::from_value(row.meta.clone()) {
+            Ok(record) => record,
+            Err(err) => {
+                tracing::warn!(room_id = %id, %err, "skipping unreadable room");
+                continue;
+            }
+        };
+        let snapshot = match row.game.clone().map(serde_json::from_value).transpose() {
+            Ok(game) => check_version(id, game.unwrap_or_default()),
+            Err(err) => {
+                tracing::warn!(room_id = %id, %err, "dropping unreadable game state");
+                Default::default()
+            }
+        };
+        rooms.push(StoredRoom { record, snapshot });
+    }
+
+    let mut history = Vec::new();
+    for (player, summaries) in &snapshot.history {
+        for summary in summaries {
+            match serde_json::from_value(summary.clone()) {
+                Ok(summary) => history.push(StoredSummary { player: player.clone(), summary }),
+                Err(err) => tracing::warn!(%err, "skipping unreadable match history entry"),
+            }
+        }
+    }
+
+    let mut ratings = Vec::with_capacity(snapshot.ratings.len());
+    for (player, rating) in &snapshot.ratings {
+        match serde_json::from_value(rating.clone()) {
+            Ok(rating) => ratings.push((player.clone(), rating)),
+            Err(err) => tracing::warn!(%player, %err, "skipping unreadable rating"),
+        }
+    }
+
+    let mut bans = Vec::with_capacity(snapshot.bans.len());
+    for (id, ban) in &snapshot.bans {
+        match serde_json::from_value(ban.clone()) {
+            Ok(ban) => bans.push(ban),
+            Err(err) => tracing::warn!(ban_id = %id, %err, "skipping unreadable ban"),
+        }
+    }
+
+    Restored { rooms, history, ratings, bans }
+}
+
+async fn write_loop(path: PathBuf, mut snapshot: Snapshot, mut rx: mpsc::UnboundedReceiver<StoreOp>, interval: Duration) {
+    let mut tick = tokio::time::interval(interval);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut dirty = false;
+    loop {
+        tokio::select! {
+            op = rx.recv() => match op {
+                // A ping is a flush: answered once everything so far is on disk.
+                Some(StoreOp::Ping { tx }) => {
+                    let ok = if dirty { save(&path, &mut snapshot).await } else { reachable(&path) };
+                    dirty &= !ok;
+                    let _ = tx.send(ok);
+                }
+                Some(op) => {
+                    apply(&mut snapshot, op);
+                    dirty = true;
+                }
+                None => break,
+            },
+            _ = tick.tick(), if dirty => dirty = !save(&path, &mut snapshot).await,
+        }
+    }
+    if dirty { save(&path, &mut snapshot).await; }
+}
+
+fn apply(snapshot: &mut Snapshot, op: StoreOp) {
+    // Every op's JSON came from `serde_json::to_string`, so it parses.
+    let value = |json: String| serde_json::from_str::<Value>(&json).expect("store ops carry valid JSON");
+    match op {
+        StoreOp::SaveRoom { id, meta } => match snapshot.rooms.get_mut(&id) {
+            Some(row) => row.meta = value(meta),
+            None => {
+                snapshot.rooms.insert(id, RoomRow { meta: value(meta), game: None });
+            }
+        },
+        StoreOp::SaveGame { id, game } => {
+            if let Some(row) = snapshot.rooms.get_mut(&id) { row.game = Some(value(game)); }
+        }
+        StoreOp::Delete { id } => {
+            snapshot.rooms.remove(&id);
+        }
+        StoreOp::SaveSummary { player, summary } => {
+            let games = snapshot.history.entry(player).or_default();
+            games.push_back(value(summary));
+            while games.len() > KEEP_PER_PLAYER { games.pop_front(); }
+        }
+        StoreOp::SaveRating { player, rating } => {
+            snapshot.ratings.insert(player, value(rating));
+        }
+        StoreOp::SaveBan { id, ban } => {
+            snapshot.bans.insert(id, value(ban));
+        }
+        StoreOp::DeleteBan { id } => {
+            snapshot.bans.remove(&id);
+        }
+        StoreOp::Ping { .. } => unreachable!("answered by the write loop"),
+    }
+}
+
+/// Write `snapshot` to `path`. Returns whether it's on disk.
+async fn save(path: &Path, snapshot: &mut Snapshot) -> bool {
+    snapshot.version = SNAPSHOT_VERSION;
+    snapshot.saved_at_unix = unix_now();
+    let bytes = match serde_json::to_vec(snapshot) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            tracing::error!(%err, "failed to serialize the snapshot");
+            return false;
+        }
+    };
+    let target = path.to_path_buf();
+    match tokio::task::spawn_blocking(move || write_atomically(&target, &bytes)).await {
+        Ok(Ok(())) => {
+            tracing::debug!(path = %path.display(), rooms = snapshot.rooms.len(), "snapshot written");
+            true
+        }
+        Ok(Err(err)) => {
+            tracing::error!(path = %path.display(), %err, "snapshot write failed");
+            false
+        }
+        Err(err) => {
+            tracing::error!(%err, "snapshot writer panicked");
+            false
+        }
+    }
+}
+
+/// Write to a temporary file beside `path`, then rename it over `path`.
+fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+/// Whether the directory the snapshot goes in is there to write to.
+fn reachable(path: &Path) -> bool {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    fs::metadata(dir).is_ok_and(|meta| meta.is_dir())
+}
@@ -0,0 +1,144 @@
+//! Optional persistence of rooms and in-progress games, plus the records
+//! kept alongside them: match history, ratings and bans. Kept in SQLite
+//! (`DATABASE_URL`, with the `sqlite` feature) or a snapshot file
+//! (`SNAPSHOT_PATH`).
+//!
+//! Rooms and their tasks only ever enqueue writes on a `StoreHandle`; one
+//! writer task per store applies them in order, so a slow database never
+//! stalls a game.
+
+pub mod file;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::room::bans::Ban;
+use crate::room::history::GameSummary;
+use crate::room::ratings::PlayerRating;
+use crate::room::room::{RoomSettings, RoomSnapshot};
+
+/// Version of the saved game format, `RoomSnapshot::version`. Bump it when a
+/// change to `RoomSnapshot` or the game types it holds (`GameState`, `Card`,
+/// ...) means older servers can't read what newer ones save.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// `snapshot`, unless a newer server saved it: then the room comes back
+/// without its game rather than with one misread.
+fn check_version(room_id: &str, snapshot: RoomSnapshot) -> RoomSnapshot {
+    if snapshot.version <= SNAPSHOT_VERSION { return snapshot; }
+    tracing::warn!(room_id, version = snapshot.version, "dropping game state saved by a newer server");
+    RoomSnapshot::default()
+}
+
+/// Room metadata as persisted: everything needed to re-admit its players.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomRecord {
+    pub id: String,
+    pub tokens: Vec<String>,
+    pub settings: RoomSettings,
+    pub bot_seat: Option<usize>,
+    pub created_at: SystemTime,
+    #[serde(default)]
+    pub public: bool,
+    #[serde(default)]
+    pub claimed: Vec<usize>,
+    /// Saved by versions with a single invite seat (seat 1) instead of `claimed`.
+    #[serde(default, skip_serializing)]
+    pub invite_claimed: bool,
+    /// Empty for rooms saved before codes existed; a fresh one is drawn on restore.
+    #[serde(default)]
+    pub code: String,
+}
+
+/// A room loaded back on boot.
+pub struct StoredRoom {
+    pub record: RoomRecord,
+    pub snapshot: RoomSnapshot,
+}
+
+/// A match history entry loaded back on boot.
+pub struct StoredSummary {
+    pub player: String,
+    pub summary: GameSummary,
+}
+
+/// Everything a store held, as loaded on boot.
+pub struct Restored {
+    pub rooms: Vec<StoredRoom>,
+    pub history: Vec<StoredSummary>,
+    /// `(player_id, rating)`.
+    pub ratings: Vec<(String, PlayerRating)>,
+    pub bans: Vec<Ban>,
+}
+
+pub enum StoreOp {
+    SaveRoom { id: String, meta: String },
+    SaveGame { id: String, game: String },
+    Delete { id: String },
+    SaveSummary { player: String, summary: String },
+    SaveRating { player: String, rating: String },
+    SaveBan { id: String, ban: String },
+    DeleteBan { id: String },
+    /// Answer whether the database responds.
+    Ping { tx: oneshot::Sender<bool> },
+}
+
+#[derive(Clone, Debug)]
+pub struct StoreHandle {
+    tx: mpsc::UnboundedSender<StoreOp>,
+}
+
+impl StoreHandle {
+    fn new(tx: mpsc::UnboundedSender<StoreOp>) -> Self {
+        Self { tx }
+    }
+
+    pub fn save_room(&self, record: &RoomRecord) {
+        self.enqueue(record, |meta| StoreOp::SaveRoom { id: record.id.clone(), meta });
+    }
+
+    pub fn save_game(&self, id: &str, snapshot: &RoomSnapshot) {
+        self.enqueue(snapshot, |game| StoreOp::SaveGame { id: id.to_string(), game });
+    }
+
+    pub fn save_summary(&self, player: &str, summary: &GameSummary) {
+        self.enqueue(summary, |summary| StoreOp::SaveSummary { player: player.to_string(), summary });
+    }
+
+    pub fn save_rating(&self, player: &str, rating: &PlayerRating) {
+        self.enqueue(rating, |rating| StoreOp::SaveRating { player: player.to_string(), rating });
+    }
+
+    pub fn save_ban(&self, ban: &Ban) {
+        self.enqueue(ban, |json| StoreOp::SaveBan { id: ban.id.clone(), ban: json });
+    }
+
+    pub fn delete_ban(&self, id: &str) {
+        let _ = self.tx.send(StoreOp::DeleteBan { id: id.to_string() });
+    }
+
+    pub fn delete(&self, id: &str) {
+        let _ = self.tx.send(StoreOp::Delete { id: id.to_string() });
+    }
+
+    /// Whether the database answers within `timeout`, behind any writes
+    /// already queued.
+    pub async fn ping(&self, timeout: Duration) -> bool {
+        let (tx, rx) = oneshot::channel();
+        if self.tx.send(StoreOp::Ping { tx }).is_err() { return false; }
+        tokio::time::timeout(timeout, rx).await.is_ok_and(|answer| answer.unwrap_or(false))
+    }
+
+    fn enqueue<T: Serialize>(&self, value: &T, op: impl FnOnce(String) -> StoreOp) {
+        match serde_json::to_string(value) {
+            Ok(json) => {
+                let _ = self.tx.send(op(json));
+            }
+            Err(err) => tracing::error!(%err, "failed to serialize for the store"),
+        }
+    }
+}
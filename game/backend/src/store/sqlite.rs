@@ -0,0 +1,156 @@
+//! SQLite-backed store: one `rooms` row per room, metadata and game state as
+//! JSON, one `history` row per player per finished game, one `ratings` row
+//! per rated player and one `bans` row per ban.
+
+use std::str::FromStr;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteQueryResult};
+use tokio::sync::mpsc;
+
+use crate::room::history::KEEP_PER_PLAYER;
+use crate::store::{check_version, Restored, StoreHandle, StoreOp, StoredRoom, StoredSummary};
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS rooms (
+    id TEXT PRIMARY KEY,
+    meta TEXT NOT NULL,
+    game TEXT
+)";
+
+const HISTORY_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS history (
+    player TEXT NOT NULL,
+    summary TEXT NOT NULL
+)";
+
+const HISTORY_INDEX: &str = "CREATE INDEX IF NOT EXISTS history_player ON history (player)";
+
+const RATINGS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS ratings (
+    player TEXT PRIMARY KEY,
+    rating TEXT NOT NULL
+)";
+
+const BANS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS bans (
+    id TEXT PRIMARY KEY,
+    ban TEXT NOT NULL
+)";
+
+/// Open (creating if needed) the database at `url`, load everything saved
+/// and start the writer task.
+pub async fn open(url: &str) -> Result<(StoreHandle, Restored), sqlx::Error> {
+    let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+    let pool = SqlitePoolOptions::new().max_connections(1).connect_with(options).await?;
+    for statement in [SCHEMA, HISTORY_SCHEMA, HISTORY_INDEX, RATINGS_SCHEMA, BANS_SCHEMA] {
+        sqlx::query(statement).execute(&pool).await?;
+    }
+
+    let rows: Vec<(String, String, Option<String>)> =
+        sqlx::query_as("SELECT id, meta, game FROM rooms").fetch_all(&pool).await?;
+    let mut rooms = Vec::with_capacity(rows.len());
+    for (id, meta, game) in rows {
+        let record = match serde_json::from_str(&meta) {
+            Ok(record) => record,
+            Err(err) => {
+                tracing::warn!(room_id = %id, %err, "skipping unreadable room");
+                continue;
+            }
+        };
+        let snapshot = match game.as_deref().map(serde_json::from_str).transpose() {
+            Ok(snapshot) => check_version(&id, snapshot.unwrap_or_default()),
+            Err(err) => {
+                tracing::warn!(room_id = %id, %err, "dropping unreadable game state");
+                Default::default()
+            }
+        };
+        rooms.push(StoredRoom { record, snapshot });
+    }
+
+    let rows: Vec<(String, String)> =
+        sqlx::query_as("SELECT player, summary FROM history ORDER BY rowid").fetch_all(&pool).await?;
+    let mut history = Vec::with_capacity(rows.len());
+    for (player, summary) in rows {
+        match serde_json::from_str(&summary) {
+            Ok(summary) => history.push(StoredSummary { player, summary }),
+            Err(err) => tracing::warn!(%err, "skipping unreadable match history entry"),
+        }
+    }
+
+    let rows: Vec<(String, String)> = sqlx::query_as("SELECT player, rating FROM ratings").fetch_all(&pool).await?;
+    let mut ratings = Vec::with_capacity(rows.len());
+    for (player, rating) in rows {
+        match serde_json::from_str(&rating) {
+            Ok(rating) => ratings.push((player, rating)),
+            Err(err) => tracing::warn!(%player, %err, "skipping unreadable rating"),
+        }
+    }
+
+    let rows: Vec<(String, String)> = sqlx::query_as("SELECT id, ban FROM bans").fetch_all(&pool).await?;
+    let mut bans = Vec::with_capacity(rows.len());
+    for (id, ban) in rows {
+        match serde_json::from_str(&ban) {
+            Ok(ban) => bans.push(ban),
+            Err(err) => tracing::warn!(ban_id = %id, %err, "skipping unreadable ban"),
+        }
+    }
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(write_loop(pool, rx));
+    Ok((StoreHandle::new(tx), Restored { rooms, history, ratings, bans }))
+}
+
+async fn write_loop(pool: SqlitePool, mut rx: mpsc::UnboundedReceiver<StoreOp>) {
+    while let Some(op) = rx.recv().await {
+        let op = match op {
+            StoreOp::Ping { tx } => {
+                let _ = tx.send(sqlx::query("SELECT 1").execute(&pool).await.is_ok());
+                continue;
+            }
+            op => op,
+        };
+        let result = match &op {
+            StoreOp::SaveRoom { id, meta } => {
+                sqlx::query("INSERT INTO rooms (id, meta) VALUES (?, ?) ON CONFLICT(id) DO UPDATE SET meta = excluded.meta")
+                    .bind(id)
+                    .bind(meta)
+                    .execute(&pool)
+                    .await
+            }
+            StoreOp::SaveGame { id, game } => {
+                sqlx::query("UPDATE rooms SET game = ? WHERE id = ?").bind(game).bind(id).execute(&pool).await
+            }
+            StoreOp::Delete { id } => sqlx::query("DELETE FROM rooms WHERE id = ?").bind(id).execute(&pool).await,
+            StoreOp::SaveSummary { player, summary } => save_summary(&pool, player, summary).await,
+            StoreOp::SaveRating { player, rating } => {
+                sqlx::query("INSERT INTO ratings (player, rating) VALUES (?, ?) ON CONFLICT(player) DO UPDATE SET rating = excluded.rating")
+                    .bind(player)
+                    .bind(rating)
+                    .execute(&pool)
+                    .await
+            }
+            StoreOp::SaveBan { id, ban } => {
+                sqlx::query("INSERT INTO bans (id, ban) VALUES (?, ?) ON CONFLICT(id) DO UPDATE SET ban = excluded.ban")
+                    .bind(id)
+                    .bind(ban)
+                    .execute(&pool)
+                    .await
+            }
+            StoreOp::DeleteBan { id } => sqlx::query("DELETE FROM bans WHERE id = ?").bind(id).execute(&pool).await,
+            StoreOp::Ping { .. } => unreachable!("answered above"),
+        };
+        if let Err(err) = result {
+            tracing::error!(%err, "store write failed");
+        }
+    }
+}
+
+/// Add a history row, dropping the player's oldest beyond what's kept in memory.
+async fn save_summary(pool: &SqlitePool, player: &str, summary: &str) -> Result<SqliteQueryResult, sqlx::Error> {
+    sqlx::query("INSERT INTO history (player, summary) VALUES (?, ?)").bind(player).bind(summary).execute(pool).await?;
+    sqlx::query(
+        "DELETE FROM history WHERE player = ? AND rowid NOT IN \
+         (SELECT rowid FROM history WHERE player = ? ORDER BY rowid DESC LIMIT ?)",
+    )
+    .bind(player)
+    .bind(player)
+    .bind(KEEP_PER_PLAYER as i64)
+    .execute(pool)
+    .await
+}
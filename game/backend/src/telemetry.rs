@@ -0,0 +1,67 @@
+//! Logging setup: human-readable lines by default, or one JSON object per
+//! line with `LOG_FORMAT=json` for log stores that query on fields. Every
+//! HTTP request gets an `x-request-id`, logged on its span and echoed on the
+//! response, so a player's report can be matched to the server's logs.
+
+use axum::body::Body;
+use axum::http::{HeaderValue, Request};
+use axum::Router;
+use tower::ServiceBuilder;
+use tower_http::request_id::{MakeRequestId, PropagateRequestIdLayer, RequestId, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
+use tracing::Span;
+use tracing_subscriber::EnvFilter;
+
+use crate::config;
+use crate::util::id::new_request_id;
+
+/// The header request ids travel in, both ways.
+pub const REQUEST_ID: &str = "x-request-id";
+
+/// Install the global subscriber. `RUST_LOG` picks what's logged, `info` and
+/// up by default; the admin audit trail is on the `zobbo::audit` target.
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let logs = tracing_subscriber::fmt().with_env_filter(filter);
+    if config::get().json_logs {
+        // Every span the event happened in, fields included, so a socket's
+        // lines all carry its `room_id` and `player_id`.
+        logs.json().flatten_event(true).with_current_span(false).with_span_list(true).init();
+    } else {
+        logs.init();
+    }
+}
+
+/// Wrap `app` so every request has an id: the client's `x-request-id` if it
+/// sent one, or a fresh ULID.
+pub fn with_request_ids(app: Router) -> Router {
+    app.layer(
+        ServiceBuilder::new()
+            .layer(SetRequestIdLayer::x_request_id(MakeRequestUlid))
+            .layer(TraceLayer::new_for_http().make_span_with(request_span))
+            .layer(PropagateRequestIdLayer::x_request_id()),
+    )
+}
+
+#[derive(Clone, Copy)]
+struct MakeRequestUlid;
+
+impl MakeRequestId for MakeRequestUlid {
+    fn make_request_id<B>(&mut self, _: &Request<B>) -> Option<RequestId> {
+        HeaderValue::from_str(&new_request_id()).ok().map(RequestId::new)
+    }
+}
+
+fn request_span(req: &Request<Body>) -> Span {
+    tracing::info_span!(
+        "request",
+        method = %req.method(),
+        path = %req.uri().path(),
+        request_id = request_id(req.headers()),
+    )
+}
+
+/// The id `SetRequestIdLayer` gave the request, for spans that outlive it.
+pub fn request_id(headers: &axum::http::HeaderMap) -> &str {
+    headers.get(REQUEST_ID).and_then(|v| v.to_str().ok()).unwrap_or_default()
+}
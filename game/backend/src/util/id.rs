@@ -3,11 +3,54 @@
 use rand::{distributions::Alphanumeric, Rng};
 use ulid::Ulid;
 
-/// Generate a short room ID using ULID, truncated for readability.
+/// Generate a room ID: a full ULID. Its first 10 characters are only the
+/// timestamp, so rooms made in the same millisecond differ in the rest.
 pub fn new_room_id() -> String {
-    let ulid = Ulid::new().to_string();
-    // 26-char ULID, take first 10 for brevity. Collisions are extremely unlikely for MVP.
-    ulid.chars().take(10).collect()
+    Ulid::new().to_string()
+}
+
+/// Generate a game ID: a full ULID, so games sort by when they were dealt.
+pub fn new_game_id() -> String {
+    Ulid::new().to_string()
+}
+
+/// Generate a player ID for a new guest identity: a full ULID.
+pub fn new_player_id() -> String {
+    Ulid::new().to_string()
+}
+
+/// Generate an `x-request-id` for a request that came without one: a full ULID.
+pub fn new_request_id() -> String {
+    Ulid::new().to_string()
+}
+
+/// Generate a ban ID: a full ULID.
+pub fn new_ban_id() -> String {
+    Ulid::new().to_string()
+}
+
+/// Letters and digits that can't be confused when read aloud or handwritten
+/// (no 0/O, 1/I/L, 2/Z, 5/S, 8/B, U/V).
+const CODE_ALPHABET: &[u8] = b"34679ACDEFGHJKMNPQRTWXY";
+
+/// Generate a 6-character room code for sharing over voice or chat.
+///
+/// About 150M combinations; callers still check for collisions with live rooms.
+pub fn new_room_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..6).map(|_| char::from(CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())])).collect()
+}
+
+/// A name for a guest who hasn't picked one, e.g. `Guest 7KQ4`.
+pub fn new_guest_name() -> String {
+    let mut rng = rand::thread_rng();
+    let tag: String = (0..4).map(|_| char::from(CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())])).collect();
+    format!("Guest {tag}")
+}
+
+/// Canonical form of a code as typed by a person: upper case, separators dropped.
+pub fn normalize_room_code(input: &str) -> String {
+    input.chars().filter(|c| c.is_ascii_alphanumeric()).map(|c| c.to_ascii_uppercase()).collect()
 }
 
 /// Generate a short join token (URL-safe alphanumeric).
@@ -18,3 +61,23 @@ pub fn new_join_token() -> String {
         .map(char::from)
         .collect()
 }
+
+/// Generate a one-time `/ws` ticket; as long as a reconnect token, for the
+/// same reason.
+pub fn new_ws_ticket() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(24)
+        .map(char::from)
+        .collect()
+}
+
+/// Generate a reconnect token. Longer than a join token since it travels in
+/// reconnect URLs and is meant to be thrown away quickly.
+pub fn new_reconnect_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(24)
+        .map(char::from)
+        .collect()
+}
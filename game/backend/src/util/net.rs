@@ -0,0 +1,17 @@
+//! Network helpers: where a request came from.
+
+use std::net::{IpAddr, SocketAddr};
+
+use axum::http::HeaderMap;
+
+use crate::config;
+
+/// The client's address: from the `CLIENT_IP_HEADER` header set by a trusted
+/// proxy in front of the server, or else the peer the request came in on.
+pub fn client_ip(headers: &HeaderMap, peer: SocketAddr) -> IpAddr {
+    config::get()
+        .client_ip_header
+        .as_ref()
+        .and_then(|name| headers.get(name.as_str())?.to_str().ok()?.trim().parse().ok())
+        .unwrap_or(peer.ip())
+}
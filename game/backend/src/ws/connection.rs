@@ -1,45 +1,394 @@
 //! WebSocket connection lifecycle management.
 
-use axum::{extract::{Query, State}, response::IntoResponse};
-use axum::http::StatusCode;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use axum::{extract::{ConnectInfo, Query, State}, response::IntoResponse};
+use axum::http::{header, HeaderMap, StatusCode};
 use axum::extract::ws::{WebSocketUpgrade, WebSocket, Message};
+use futures_util::StreamExt;
+#[cfg(not(feature = "fault-injection"))]
+use futures_util::{stream::SplitSink, SinkExt};
 use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::Instrument;
+use utoipa::{IntoParams, ToSchema};
 
-use crate::http::routes::AppState;
+use crate::config;
+use crate::http::auth::Identity;
+use crate::http::routes::{self, AppState};
+use crate::room::room::{RoomCommand, RoomHandle};
+use crate::reporting::{self, Context};
+use crate::telemetry;
+use crate::util::net::client_ip;
+use crate::ws::protocol::{self, ClientToServer, GameUpdate, ServerToClient};
 
-#[derive(Deserialize)]
+/// Seat and identity tokens are long-lived secrets and URLs end up in proxy
+/// logs, so the socket is opened with a one-time ticket instead.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct WsParams {
+    /// Room id, as in `/rooms/{id}/view`.
     pub room_id: String,
-    pub token: String,
+    /// A one-time ticket from `POST /api/rooms/{id}/ws-ticket`, standing in
+    /// for the seat and identity tokens.
+    pub ticket: String,
+    #[serde(default)]
+    #[param(inline)]
+    pub encoding: Encoding,
+}
+
+/// How frames are encoded on a socket, picked with `?encoding=` on upgrade.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    /// JSON text frames.
+    #[default]
+    Json,
+    /// MessagePack binary frames, with structs as maps so they decode to the
+    /// same shapes as the JSON. JSON text frames are still accepted inbound.
+    Msgpack,
+}
+
+impl Encoding {
+    fn encode(self, msg: &ServerToClient) -> Message {
+        match self {
+            Encoding::Json => Message::Text(serde_json::to_string(msg).expect("server messages serialize")),
+            Encoding::Msgpack => Message::Binary(rmp_serde::to_vec_named(msg).expect("server messages serialize")),
+        }
+    }
 }
 
+/// Upgrade to the game WebSocket for one seat.
+///
+/// Messages are `ClientToServer`/`ServerToClient` from the `zobbo-protocol`
+/// crate. Offer the `zobbo.v1` subprotocol and send `hello` first.
+#[utoipa::path(
+    get,
+    path = "/ws",
+    tag = "game",
+    params(WsParams),
+    responses(
+        (status = 101, description = "Switching to the game WebSocket"),
+        (status = 400, description = "No `ticket`, or none of the offered subprotocols is supported"),
+        (status = 401, description = "Unknown room, or an invalid, expired or used ticket"),
+        (status = 403, description = "The identity or the caller's address is banned"),
+    ),
+)]
 pub async fn ws_handler(
     State(state): State<AppState>,
-    Query(WsParams { room_id, token }): Query<WsParams>,
+    Query(WsParams { room_id, ticket, encoding }): Query<WsParams>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     ws: WebSocketUpgrade,
 ) -> impl IntoResponse {
-    if !state.rooms.has_token(&room_id, &token) {
-        return (StatusCode::UNAUTHORIZED, "invalid room or token").into_response();
+    let Some((token, player, seat)) = state.tickets.redeem(&room_id, &ticket).and_then(|ticket| {
+        let seat = state.rooms.seat_handle(&room_id, &ticket.join_token)?;
+        Some((ticket.join_token, ticket.player, seat))
+    }) else {
+        return (StatusCode::UNAUTHORIZED, "invalid room or ticket").into_response();
+    };
+    if let Some(ban) = state.rooms.banned(player.as_ref().map(|p| p.player_id.as_str()), client_ip(&headers, peer)) {
+        tracing::info!(%room_id, ban_id = %ban.id, "ws rejected: banned");
+        return routes::banned(&ban);
+    }
+    // Clients that don't name a subprotocol are let through for now; clients that
+    // only name ones we don't speak are turned away before the upgrade.
+    if let Some(offered) = headers.get(header::SEC_WEBSOCKET_PROTOCOL) {
+        let offered = offered.to_str().unwrap_or_default();
+        if protocol::select_subprotocol(offered).is_none() {
+            tracing::debug!(%room_id, offered, "ws rejected: unsupported subprotocol");
+            return (StatusCode::BAD_REQUEST, "unsupported subprotocol").into_response();
+        }
     }
-    ws.on_upgrade(move |socket| handle_socket(socket, room_id, token))
+    // Frames a little over the limit get an error back; anything far over
+    // ends the socket before it's even buffered.
+    // The socket outlives the upgrade request, so it carries the request id on its own span.
+    let span = tracing::info_span!(
+        parent: None,
+        "ws",
+        %room_id,
+        seat = seat.0,
+        player_id = player.as_ref().map(|p| tracing::field::display(&p.player_id)),
+        request_id = telemetry::request_id(&headers),
+    );
+    let context = Context::new("socket")
+        .room(&room_id)
+        .seat(seat.0)
+        .player(player.as_ref().map(|p| p.player_id.as_str()));
+    ws.protocols(protocol::SUBPROTOCOLS.iter().copied())
+        .max_message_size(config::get().ws_max_message_bytes * 16)
+        .on_upgrade(move |socket| {
+            let sockets = state.sockets.clone();
+            let task = reporting::watch(context, handle_socket(socket, state, room_id, token, player, seat, encoding));
+            sockets.track_future(task.instrument(span))
+        })
 }
 
-async fn handle_socket(mut socket: WebSocket, room_id: String, token: String) {
-    let _ = socket
-        .send(Message::Text(format!("welcome to room {}", room_id)))
-        .await;
-    // Simple echo/read loop placeholder
-    while let Some(Ok(msg)) = socket.recv().await {
+/// Per-socket context shared by the send helpers.
+///
+/// Frames are queued to a writer task rather than written inline, so the read
+/// loop never blocks on a slow client.
+struct Conn {
+    state: AppState,
+    room_id: String,
+    id: u64,
+    encoding: Encoding,
+    out: mpsc::UnboundedSender<Message>,
+    /// Whether the client has sent anything yet; `Hello` must come first.
+    heard_from: bool,
+    /// The schema version from `Hello`; 1 until then, as for clients that
+    /// never send one.
+    version: u32,
+    /// Set by a `Hello` listing `capability::GAME_DELTA`. Until then deltas
+    /// are folded into `last_update` and sent as full updates instead.
+    deltas: bool,
+    last_update: Option<GameUpdate>,
+}
+
+impl Conn {
+    fn send_msg(&self, msg: &ServerToClient) -> bool {
+        self.state.taps.outbound(&self.room_id, self.id, msg);
+        self.out.send(self.encoding.encode(msg)).is_ok()
+    }
+
+    /// Pass a room message on in the shape this client understands.
+    fn relay(&mut self, room: &RoomHandle, seat: usize, msg: ServerToClient) -> bool {
+        match msg {
+            ServerToClient::GameUpdate(update) if !self.deltas => {
+                self.last_update = Some(update.clone());
+                self.send_msg(&ServerToClient::GameUpdate(update))
+            }
+            ServerToClient::GameDelta(delta) if !self.deltas => {
+                if let Some(update) = &mut self.last_update
+                    && update.apply(&delta)
+                {
+                    let update = update.clone();
+                    return self.send_msg(&ServerToClient::GameUpdate(update));
+                }
+                // Can't expand it; have the room send the full state instead.
+                let last_seq = self.last_update.as_ref().map_or(0, |u| u.seq);
+                room.send(RoomCommand::Client { seat, msg: ClientToServer::RequestState { last_seq } })
+            }
+            msg => match msg.downgrade(self.version) {
+                Some(msg) => self.send_msg(&msg),
+                None => true,
+            },
+        }
+    }
+
+    /// Forward a decoded client frame to the room, or report why it didn't decode.
+    ///
+    /// Returns `false` once the connection should close.
+    fn forward<E: std::fmt::Display>(&mut self, room: &RoomHandle, seat: usize, msg: Result<ClientToServer, E>) -> bool {
+        let first = !std::mem::replace(&mut self.heard_from, true);
         match msg {
-            Message::Text(text) => {
-                let _ = socket.send(Message::Text(format!("echo: {}", text))).await;
+            Ok(ClientToServer::Hello { version, capabilities }) => self.hello(first, version, &capabilities),
+            Ok(msg) => room.send(RoomCommand::Client { seat, msg }),
+            Err(err) => {
+                self.send_msg(&ServerToClient::Error { message: format!("invalid message: {err}") });
+                true
+            }
+        }
+    }
+
+    fn hello(&mut self, first: bool, version: u32, capabilities: &[String]) -> bool {
+        if !first {
+            self.send_msg(&ServerToClient::Error { message: "hello must be the first message".into() });
+            return true;
+        }
+        if version < protocol::MIN_PROTOCOL_VERSION {
+            tracing::debug!(room_id = %self.room_id, version, "ws closed: client protocol too old");
+            self.send_msg(&ServerToClient::UnsupportedVersion {
+                client_version: version,
+                min_version: protocol::MIN_PROTOCOL_VERSION,
+                server_version: protocol::PROTOCOL_VERSION,
+            });
+            return false;
+        }
+        self.version = version;
+        self.deltas = capabilities.iter().any(|c| c == protocol::capability::GAME_DELTA);
+        true
+    }
+}
+
+/// Seconds in a row a client may go over its message budget before the
+/// socket is closed.
+const FLOOD_STRIKES: u32 = 3;
+
+/// How often the socket is pinged to measure its round trip, which the room
+/// adds to the seat's turn timer.
+const PING_EVERY: Duration = Duration::from_secs(5);
+
+/// What to do with a client message, by `Budget::spend`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verdict {
+    Allow,
+    /// Over budget: drop it, and warn the client the first time this second.
+    Drop { warn: bool },
+    /// Over budget for `FLOOD_STRIKES` seconds running: close the socket.
+    Disconnect,
+}
+
+/// How many messages a client has sent this second, against `per_sec`.
+struct Budget {
+    per_sec: u32,
+    window: Instant,
+    used: u32,
+    /// Seconds in a row the client went over budget.
+    strikes: u32,
+}
+
+impl Budget {
+    fn new(per_sec: u32) -> Self {
+        Budget { per_sec, window: Instant::now(), used: 0, strikes: 0 }
+    }
+
+    fn spend(&mut self) -> Verdict {
+        let elapsed = self.window.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            // A second within budget, or a quiet one in between, clears the strikes.
+            if self.used <= self.per_sec || elapsed >= Duration::from_secs(2) { self.strikes = 0; }
+            self.window = Instant::now();
+            self.used = 0;
+        }
+        self.used += 1;
+        if self.used <= self.per_sec { return Verdict::Allow; }
+        if self.used > self.per_sec + 1 { return Verdict::Drop { warn: false }; }
+        self.strikes += 1;
+        if self.strikes >= FLOOD_STRIKES { Verdict::Disconnect } else { Verdict::Drop { warn: true } }
+    }
+}
+
+#[cfg(not(feature = "fault-injection"))]
+async fn write_loop(mut sink: SplitSink<WebSocket, Message>, mut rx: mpsc::UnboundedReceiver<Message>) {
+    while let Some(msg) = rx.recv().await {
+        if sink.send(msg).await.is_err() { break; }
+    }
+    let _ = sink.close().await;
+}
+
+async fn handle_socket(
+    socket: WebSocket,
+    state: AppState,
+    room_id: String,
+    token: String,
+    player: Option<Identity>,
+    (seat, room): (usize, RoomHandle),
+    encoding: Encoding,
+) {
+    let (sink, mut stream) = socket.split();
+    let (out, out_rx) = mpsc::unbounded_channel();
+    let mut conn = Conn {
+        id: state.taps.conn_id(),
+        state: state.clone(),
+        room_id: room_id.clone(),
+        encoding,
+        out,
+        heard_from: false,
+        version: 1,
+        deltas: false,
+        last_update: None,
+    };
+    #[cfg(feature = "fault-injection")]
+    state.sockets.spawn(crate::ws::faults::write_loop(sink, out_rx, state.faults.clone(), conn.id));
+    #[cfg(not(feature = "fault-injection"))]
+    state.sockets.spawn(write_loop(sink, out_rx));
+
+    let ttl = config::get().reconnect_token_ttl;
+    let welcome = ServerToClient::Welcome {
+        room_id: room_id.clone(),
+        seat,
+        reconnect_token: state.rooms.issue_reconnect_token(&room_id, &token, ttl),
+        reconnect_ttl_secs: ttl.as_secs(),
+        protocol_version: protocol::PROTOCOL_VERSION,
+    };
+    conn.send_msg(&welcome);
+
+    let (room_tx, mut room_rx) = mpsc::unbounded_channel();
+    if !room.send(RoomCommand::Connect { seat, conn: conn.id, player, bot: false, tx: room_tx }) {
+        return;
+    }
+    let mut budget = Budget::new(config::get().ws_messages_per_sec);
+    let max_bytes = config::get().ws_max_message_bytes;
+    // Rotate at half the TTL so the client always holds a token with time left on it.
+    let mut rotate = tokio::time::interval(ttl / 2);
+    rotate.tick().await;
+    let mut ping = tokio::time::interval(PING_EVERY);
+    // The last ping's number and when it went out, until its pong comes back.
+    let (mut pings, mut probe) = (0u64, None::<(u64, Instant)>);
+    loop {
+        tokio::select! {
+            _ = ping.tick() => {
+                pings += 1;
+                probe = Some((pings, Instant::now()));
+                if conn.out.send(Message::Ping(pings.to_be_bytes().to_vec())).is_err() { break; }
+            }
+            _ = rotate.tick() => {
+                let msg = ServerToClient::ReconnectToken {
+                    token: state.rooms.issue_reconnect_token(&room_id, &token, ttl),
+                    ttl_secs: ttl.as_secs(),
+                };
+                if !conn.send_msg(&msg) { break; }
+            }
+            // The room drops our sender when another socket takes over the seat.
+            msg = room_rx.recv() => {
+                let Some(msg) = msg else { break };
+                if !conn.relay(&room, seat, msg) { break; }
             }
-            Message::Binary(bin) => {
-                let _ = socket.send(Message::Binary(bin)).await;
+            msg = stream.next() => {
+                let Some(Ok(msg)) = msg else { break };
+                if matches!(msg, Message::Text(_) | Message::Binary(_)) {
+                    match budget.spend() {
+                        Verdict::Allow => {}
+                        Verdict::Drop { warn } => {
+                            if warn {
+                                conn.send_msg(&ServerToClient::Error { message: "too many messages; slow down".into() });
+                            }
+                            continue;
+                        }
+                        Verdict::Disconnect => {
+                            tracing::info!(%room_id, seat, conn = conn.id, "ws closed: flooding");
+                            conn.send_msg(&ServerToClient::Error { message: "disconnected for sending too many messages".into() });
+                            break;
+                        }
+                    }
+                }
+                let size = match &msg {
+                    Message::Text(text) => text.len(),
+                    Message::Binary(bytes) => bytes.len(),
+                    _ => 0,
+                };
+                if size > max_bytes {
+                    conn.send_msg(&ServerToClient::Error { message: format!("message too large (over {max_bytes} bytes)") });
+                    continue;
+                }
+                match msg {
+                    Message::Text(text) => {
+                        state.taps.inbound_raw(&room_id, conn.id, &text);
+                        if !conn.forward(&room, seat, serde_json::from_str(&text)) { break; }
+                    }
+                    Message::Binary(bytes) if encoding == Encoding::Msgpack => {
+                        state.taps.inbound_msgpack(&room_id, conn.id, &bytes);
+                        if !conn.forward(&room, seat, rmp_serde::from_slice(&bytes)) { break; }
+                    }
+                    Message::Binary(_) => {
+                        conn.send_msg(&ServerToClient::Error { message: "binary frames need ?encoding=msgpack".into() });
+                    }
+                    Message::Pong(payload) => {
+                        if let Some((_, sent)) = probe.filter(|(n, _)| payload == n.to_be_bytes()) {
+                            probe = None;
+                            room.send(RoomCommand::Latency { seat, conn: conn.id, rtt: sent.elapsed() });
+                        }
+                    }
+                    Message::Close(_) => break,
+                    _ => {}
+                }
             }
-            Message::Close(_) => break,
-            _ => {}
         }
     }
-    tracing::debug!(%room_id, %token, "ws closed");
+    room.send(RoomCommand::Disconnect { seat, conn: conn.id });
+    #[cfg(feature = "fault-injection")]
+    state.faults.forget(conn.id);
+    tracing::debug!(%room_id, seat, "ws closed");
 }
@@ -0,0 +1,165 @@
+//! Dev-only network fault injection on outbound WS frames (`fault-injection` feature).
+//!
+//! Lets reconnect and timer logic be exercised locally against a bad network:
+//! frames can be delayed, dropped, or swapped with the next frame. Settings are
+//! a server-wide default plus optional per-connection overrides, keyed by the
+//! connection ids shown in the `/dev` wire tap.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use axum::extract::ws::{Message, WebSocket};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use dashmap::DashMap;
+use futures_util::stream::SplitSink;
+use futures_util::SinkExt;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::http::routes::AppState;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FaultConfig {
+    /// Fixed delay added before each frame goes out.
+    pub latency_ms: u64,
+    /// Extra random delay in `0..=jitter_ms`.
+    pub jitter_ms: u64,
+    /// Probability (0..=1) that a frame is silently dropped.
+    pub drop_rate: f64,
+    /// Probability (0..=1) that a frame is held back and sent after the next one.
+    pub reorder_rate: f64,
+}
+
+impl FaultConfig {
+    fn is_valid(&self) -> bool {
+        (0.0..=1.0).contains(&self.drop_rate) && (0.0..=1.0).contains(&self.reorder_rate)
+    }
+}
+
+/// What to do with one outbound frame.
+struct FaultPlan {
+    drop: bool,
+    delay: Duration,
+    reorder: bool,
+}
+
+#[derive(Default)]
+pub struct FaultInjector {
+    default: RwLock<FaultConfig>,
+    per_conn: DashMap<u64, FaultConfig>,
+}
+
+impl FaultInjector {
+    pub fn new() -> Self { Self::default() }
+
+    fn config_for(&self, conn: u64) -> FaultConfig {
+        self.per_conn
+            .get(&conn)
+            .map(|c| *c)
+            .unwrap_or_else(|| *self.default.read().expect("fault config lock"))
+    }
+
+    fn plan(&self, conn: u64) -> FaultPlan {
+        let cfg = self.config_for(conn);
+        let mut rng = rand::thread_rng();
+        let jitter = if cfg.jitter_ms > 0 { rng.gen_range(0..=cfg.jitter_ms) } else { 0 };
+        FaultPlan {
+            drop: rng.gen_bool(cfg.drop_rate),
+            delay: Duration::from_millis(cfg.latency_ms + jitter),
+            reorder: rng.gen_bool(cfg.reorder_rate),
+        }
+    }
+
+    /// Drop any override for a connection that has gone away.
+    pub fn forget(&self, conn: u64) {
+        self.per_conn.remove(&conn);
+    }
+}
+
+/// Socket writer that applies the current fault plan to every data frame.
+///
+/// A frame chosen for reordering is held until the next frame has been sent;
+/// control frames (close, ping/pong) always go straight through.
+pub async fn write_loop(
+    mut sink: SplitSink<WebSocket, Message>,
+    mut rx: mpsc::UnboundedReceiver<Message>,
+    faults: Arc<FaultInjector>,
+    conn: u64,
+) {
+    let mut held: Option<Message> = None;
+    while let Some(msg) = rx.recv().await {
+        if matches!(msg, Message::Text(_) | Message::Binary(_)) {
+            let plan = faults.plan(conn);
+            if plan.drop {
+                tracing::debug!(conn, "fault injection: dropped frame");
+                continue;
+            }
+            if !plan.delay.is_zero() {
+                tokio::time::sleep(plan.delay).await;
+            }
+            if plan.reorder && held.is_none() {
+                held = Some(msg);
+                continue;
+            }
+        }
+        if sink.send(msg).await.is_err() { break; }
+        if let Some(h) = held.take()
+            && sink.send(h).await.is_err()
+        {
+            break;
+        }
+    }
+    let _ = sink.close().await;
+}
+
+#[derive(Serialize)]
+pub struct FaultsView {
+    pub default: FaultConfig,
+    pub connections: BTreeMap<u64, FaultConfig>,
+}
+
+/// `GET /dev/faults`
+pub async fn get_faults(State(state): State<AppState>) -> impl IntoResponse {
+    let default = *state.faults.default.read().expect("fault config lock");
+    let connections = state.faults.per_conn.iter().map(|e| (*e.key(), *e.value())).collect();
+    Json(FaultsView { default, connections })
+}
+
+/// `PUT /dev/faults` — set the default applied to every connection without an override.
+pub async fn put_default(
+    State(state): State<AppState>,
+    Json(cfg): Json<FaultConfig>,
+) -> impl IntoResponse {
+    if !cfg.is_valid() {
+        return (StatusCode::BAD_REQUEST, "rates must be within 0..=1").into_response();
+    }
+    *state.faults.default.write().expect("fault config lock") = cfg;
+    tracing::info!(?cfg, "fault injection default updated");
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// `PUT /dev/faults/:conn`
+pub async fn put_conn(
+    State(state): State<AppState>,
+    Path(conn): Path<u64>,
+    Json(cfg): Json<FaultConfig>,
+) -> impl IntoResponse {
+    if !cfg.is_valid() {
+        return (StatusCode::BAD_REQUEST, "rates must be within 0..=1").into_response();
+    }
+    state.faults.per_conn.insert(conn, cfg);
+    tracing::info!(conn, ?cfg, "fault injection override set");
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// `DELETE /dev/faults/:conn`
+pub async fn delete_conn(State(state): State<AppState>, Path(conn): Path<u64>) -> impl IntoResponse {
+    state.faults.per_conn.remove(&conn);
+    StatusCode::NO_CONTENT
+}
@@ -3,3 +3,8 @@
 // submodules
 pub mod connection;
 pub mod protocol;
+pub mod replay;
+pub mod tap;
+pub mod ticket;
+#[cfg(feature = "fault-injection")]
+pub mod faults;
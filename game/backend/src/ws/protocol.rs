@@ -1,3 +1,6 @@
 //! WS message schema: Snapshot/Event/Error/Pong.
+//!
+//! The types live in the `zobbo-protocol` crate (`game/shared`) so tooling
+//! shares one definition of the wire format; this module re-exports them.
 
-// Placeholder; define enums/structs with serde later.
+pub use zobbo_protocol::*;
@@ -0,0 +1,75 @@
+//! Streaming a finished game back over a WebSocket, as the players saw it.
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::http::routes::AppState;
+use crate::reporting::{self, Context};
+use crate::room::replay;
+use crate::ws::protocol::ServerToClient;
+
+/// Fastest and slowest playback `speed`.
+const SPEEDS: std::ops::RangeInclusive<f64> = 0.25..=32.0;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReplayParams {
+    /// Playback speed: 1 keeps the original pace, 4 plays four times as fast.
+    #[serde(default = "original_speed")]
+    #[param(minimum = 0.25, maximum = 32, default = 1)]
+    pub speed: f64,
+}
+
+fn original_speed() -> f64 {
+    1.0
+}
+
+/// Replay a finished game over a WebSocket.
+///
+/// Sends the `GameStart`, each `GameUpdate` and the `GameOver` a player saw,
+/// with the original pauses between them scaled by `speed`, then closes.
+/// Nothing is read from the socket.
+#[utoipa::path(
+    get,
+    path = "/api/replay/{game_id}/stream",
+    tag = "games",
+    params(("game_id" = String, Path, description = "Game id, from `GameStart`"), ReplayParams),
+    responses(
+        (status = 101, description = "Switching to the replay WebSocket"),
+        (status = 400, description = "`speed` out of range"),
+        (status = 404, description = "No such finished game"),
+    ),
+)]
+pub async fn replay_stream(
+    State(state): State<AppState>,
+    Path(game_id): Path<String>,
+    Query(ReplayParams { speed }): Query<ReplayParams>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    if !SPEEDS.contains(&speed) {
+        return (StatusCode::BAD_REQUEST, "speed must be between 0.25 and 32").into_response();
+    }
+    let Some(record) = state.rooms.archive().get(&game_id) else {
+        return (StatusCode::NOT_FOUND, "game not found").into_response();
+    };
+    match replay::messages(&record, speed) {
+        Ok(messages) => ws.on_upgrade(move |socket| play(socket, messages)),
+        Err(err) => {
+            reporting::internal_error(&Context::new("replay").game(&game_id), &err);
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
+    }
+}
+
+async fn play(mut socket: WebSocket, messages: Vec<(std::time::Duration, ServerToClient)>) {
+    for (wait, msg) in messages {
+        tokio::time::sleep(wait).await;
+        let text = serde_json::to_string(&msg).expect("server messages serialize");
+        if socket.send(Message::Text(text)).await.is_err() { return; }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
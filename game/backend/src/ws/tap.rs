@@ -0,0 +1,154 @@
+//! Developer wire tap: mirrors every frame a room sends/receives to dev watchers.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::response::IntoResponse;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+use crate::http::routes::AppState;
+use crate::ws::protocol::ServerToClient;
+
+const TAP_CAPACITY: usize = 256;
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    In,
+    Out,
+}
+
+/// One mirrored frame. `conn` distinguishes sockets without exposing tokens.
+#[derive(Debug, Clone, Serialize)]
+pub struct TapFrame {
+    pub dir: Direction,
+    pub conn: u64,
+    pub at_ms: u64,
+    pub msg: Value,
+    /// Same frame with hidden data intact; only sent to unlocked watchers.
+    #[serde(skip)]
+    pub unredacted: Option<Value>,
+}
+
+/// Per-room broadcast channels, created lazily when a watcher subscribes.
+///
+/// Publishing to a room nobody watches is a single map lookup.
+#[derive(Default)]
+pub struct WireTap {
+    rooms: DashMap<String, broadcast::Sender<TapFrame>>,
+    next_conn: AtomicU64,
+}
+
+impl WireTap {
+    pub fn new() -> Self { Self::default() }
+
+    /// Allocate an id for a new socket, used to tell connections apart in the stream.
+    pub fn conn_id(&self) -> u64 {
+        self.next_conn.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn subscribe(&self, room_id: &str) -> broadcast::Receiver<TapFrame> {
+        self.rooms
+            .entry(room_id.to_string())
+            .or_insert_with(|| broadcast::channel(TAP_CAPACITY).0)
+            .subscribe()
+    }
+
+    pub fn outbound(&self, room_id: &str, conn: u64, msg: &ServerToClient) {
+        self.publish(room_id, || {
+            let full = serde_json::to_value(msg).unwrap_or(Value::Null);
+            let mut redacted = full.clone();
+            if let Value::Object(fields) = &mut redacted {
+                for name in msg.secret_fields() {
+                    if let Some(v) = fields.get_mut(*name) {
+                        *v = Value::String(REDACTED.to_string());
+                    }
+                }
+            }
+            (Direction::Out, conn, redacted, Some(full))
+        });
+    }
+
+    pub fn inbound_raw(&self, room_id: &str, conn: u64, text: &str) {
+        self.publish(room_id, || (Direction::In, conn, raw_value(text), None));
+    }
+
+    pub fn inbound_msgpack(&self, room_id: &str, conn: u64, bytes: &[u8]) {
+        self.publish(room_id, || {
+            let msg = rmp_serde::from_slice(bytes).unwrap_or_else(|_| Value::String(format!("<{} undecodable bytes>", bytes.len())));
+            (Direction::In, conn, msg, None)
+        });
+    }
+
+    fn publish(&self, room_id: &str, build: impl FnOnce() -> (Direction, u64, Value, Option<Value>)) {
+        let Some(tx) = self.rooms.get(room_id) else { return };
+        if tx.receiver_count() == 0 {
+            drop(tx);
+            self.rooms.remove_if(room_id, |_, tx| tx.receiver_count() == 0);
+            return;
+        }
+        let (dir, conn, msg, unredacted) = build();
+        let at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        let _ = tx.send(TapFrame { dir, conn, at_ms, msg, unredacted });
+    }
+}
+
+/// Frames are JSON where possible so watchers can filter on `type`.
+fn raw_value(text: &str) -> Value {
+    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+}
+
+#[derive(Deserialize)]
+pub struct StreamParams {
+    #[serde(default)]
+    pub unredacted: bool,
+}
+
+/// `GET /dev/rooms/:id/stream` — only routed when dev tools are enabled.
+pub async fn stream_handler(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    Query(StreamParams { unredacted }): Query<StreamParams>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let rx = state.taps.subscribe(&room_id);
+    tracing::info!(%room_id, unredacted, "dev stream attached");
+    ws.on_upgrade(move |socket| forward(socket, rx, unredacted))
+}
+
+async fn forward(mut socket: WebSocket, mut rx: broadcast::Receiver<TapFrame>, unredacted: bool) {
+    loop {
+        tokio::select! {
+            frame = rx.recv() => {
+                let mut frame = match frame {
+                    Ok(f) => f,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::debug!(skipped = n, "dev stream lagged");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if unredacted && let Some(full) = frame.unredacted.take() {
+                    frame.msg = full;
+                }
+                let text = serde_json::to_string(&frame).expect("tap frames serialize");
+                if socket.send(Message::Text(text)).await.is_err() { break; }
+            }
+            msg = socket.recv() => {
+                match msg {
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! WebSocket tickets: one-time passes to open `/ws`, so long-lived join and
+//! identity tokens stay out of the URL, where proxies and access logs see it.
+//!
+//! A client posts its tokens to `POST /api/rooms/{id}/ws-ticket` and connects
+//! with `?ticket=` within `TICKET_TTL`. Each ticket opens one socket; a
+//! replayed one is refused.
+
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+
+use crate::http::auth::Identity;
+use crate::util::id::new_ws_ticket;
+
+/// How long a ticket stays good; long enough to open the socket right away.
+pub const TICKET_TTL: Duration = Duration::from_secs(30);
+
+/// What a ticket stands in for.
+#[derive(Debug, Clone)]
+pub struct Ticket {
+    pub room_id: String,
+    pub join_token: String,
+    /// The identity presented with the join token, already verified.
+    pub player: Option<Identity>,
+    expires_at: Instant,
+}
+
+/// Tickets issued and not yet used.
+#[derive(Default)]
+pub struct WsTickets {
+    issued: DashMap<String, Ticket>,
+}
+
+impl WsTickets {
+    pub fn new() -> Self { Self::default() }
+
+    /// A ticket for the seat `join_token` holds in room `room_id`.
+    pub fn issue(&self, room_id: &str, join_token: &str, player: Option<Identity>) -> String {
+        let now = Instant::now();
+        self.issued.retain(|_, ticket| ticket.expires_at > now);
+        let ticket = new_ws_ticket();
+        let grant = Ticket {
+            room_id: room_id.to_string(),
+            join_token: join_token.to_string(),
+            player,
+            expires_at: now + TICKET_TTL,
+        };
+        self.issued.insert(ticket.clone(), grant);
+        ticket
+    }
+
+    /// Use up `ticket` for room `room_id`. Removed even when it's for another
+    /// room or has expired, so each ticket gets exactly one try.
+    pub fn redeem(&self, room_id: &str, ticket: &str) -> Option<Ticket> {
+        let (_, grant) = self.issued.remove(ticket)?;
+        (grant.room_id == room_id && grant.expires_at > Instant::now()).then_some(grant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_ticket_opens_one_socket_for_its_own_room() {
+        let tickets = WsTickets::new();
+        let player = Identity { player_id: "p".into(), name: "Ann".into() };
+        let ticket = tickets.issue("room", "join", Some(player.clone()));
+        let grant = tickets.redeem("room", &ticket).unwrap();
+        assert_eq!((grant.join_token.as_str(), grant.player), ("join", Some(player)));
+        assert!(tickets.redeem("room", &ticket).is_none());
+
+        // Tried on another room, it's spent all the same.
+        let ticket = tickets.issue("room", "join", None);
+        assert!(tickets.redeem("other", &ticket).is_none());
+        assert!(tickets.redeem("room", &ticket).is_none());
+        assert!(tickets.redeem("room", "never-issued").is_none());
+    }
+
+    #[test]
+    fn an_expired_ticket_is_refused_and_swept() {
+        let tickets = WsTickets::new();
+        let expired = Ticket { room_id: "room".into(), join_token: "join".into(), player: None, expires_at: Instant::now() };
+        tickets.issued.insert("old".into(), expired.clone());
+        assert!(tickets.redeem("room", "old").is_none());
+        tickets.issued.insert("old".into(), expired);
+        tickets.issue("room", "join", None);
+        assert!(!tickets.issued.contains_key("old"));
+    }
+}
@@ -0,0 +1,577 @@
+//! The protocol's documented flows, played against the real server over
+//! real sockets: the executable spec a protocol change has to keep green.
+//!
+//! Each test starts the `zobbo` binary on a free port, seats two players
+//! with `/api/quickmatch`, and drives them turn by turn. `Player::settle`
+//! pings the room and collects everything up to the `pong`, so the
+//! sequences asserted here are exactly what a client receives, in order.
+
+use std::net::TcpListener;
+use std::process::Stdio;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use zobbo_protocol::{
+    capability, CardPublic, ClientToServer, DrawSource, FinishReason, GameUpdate, Handicap, HandicapKind, Power,
+    RejectCode, RuleConfig, ServerToClient, Stage, PROTOCOL_VERSION,
+};
+
+/// How long any one message may take to arrive.
+const PATIENCE: Duration = Duration::from_secs(5);
+
+/// A server of our own, killed when the test ends.
+struct Server {
+    port: u16,
+    _child: Child,
+}
+
+impl Server {
+    async fn start() -> Server {
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let child = Command::new(env!("CARGO_BIN_EXE_zobbo"))
+            // Nowhere near a `zobbo.toml`.
+            .current_dir(std::env::temp_dir())
+            .env_clear()
+            .env("PORT", port.to_string())
+            .env("IDENTITY_SECRET", "protocol-tests")
+            .env("CREATE_RATE_BURST", "100")
+            .env("JOIN_RATE_BURST", "100")
+            .env("WS_MESSAGES_PER_SEC", "1000")
+            .env("RUST_LOG", "warn")
+            .stdout(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .expect("the server binary runs");
+        let server = Server { port, _child: child };
+        for _ in 0..100 {
+            if TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+                return server;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("the server didn't start listening");
+    }
+
+    /// POST `body` as JSON; the status and the parsed response, `null` if it isn't JSON.
+    async fn post(&self, path: &str, body: Value) -> (u16, Value) {
+        let body = body.to_string();
+        let mut stream = TcpStream::connect(("127.0.0.1", self.port)).await.unwrap();
+        let request = format!(
+            "POST {path} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).and_then(|s| s.parse().ok()).unwrap();
+        (status, serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+
+    /// Two players, paired by quickmatch into a fresh room: `(room_id, tokens)`.
+    async fn pair(&self) -> (String, [String; 2]) {
+        let (a, b) = tokio::join!(self.post("/api/quickmatch", json!({})), self.post("/api/quickmatch", json!({})));
+        let grant = |(status, body): (u16, Value)| {
+            assert_eq!((status, &body["status"]), (200, &json!("matched")), "{body}");
+            (body["room_id"].as_str().unwrap().to_string(), body["token"].as_str().unwrap().to_string())
+        };
+        let ((room, a), (other_room, b)) = (grant(a), grant(b));
+        assert_eq!(room, other_room);
+        (room, [a, b])
+    }
+
+    /// Swap seat tokens (`{"token": ..}` or `{"reconnect": ..}`) for a `/ws` ticket.
+    async fn ticket(&self, room_id: &str, tokens: Value) -> String {
+        let (status, body) = self.post(&format!("/api/rooms/{room_id}/ws-ticket"), tokens).await;
+        assert_eq!(status, 200, "{body}");
+        body["ticket"].as_str().unwrap().to_string()
+    }
+
+    /// Open `/ws` with `ticket`, offering `subprotocol`; the HTTP status on refusal.
+    async fn open(&self, room_id: &str, ticket: &str, subprotocol: &str) -> Result<Socket, u16> {
+        let mut request = format!("ws://127.0.0.1:{}/ws?room_id={room_id}&ticket={ticket}", self.port)
+            .into_client_request()
+            .unwrap();
+        request.headers_mut().insert("Sec-WebSocket-Protocol", HeaderValue::from_str(subprotocol).unwrap());
+        match tokio_tungstenite::connect_async(request).await {
+            Ok((socket, _)) => Ok(socket),
+            Err(tokio_tungstenite::tungstenite::Error::Http(response)) => Err(response.status().as_u16()),
+            Err(err) => panic!("ws connect failed: {err}"),
+        }
+    }
+
+    /// Connect to the seat `tokens` name and say hello, taking game deltas.
+    async fn join(&self, room_id: &str, tokens: Value) -> Player {
+        let hello = ClientToServer::Hello { version: PROTOCOL_VERSION, capabilities: vec![capability::GAME_DELTA.into()] };
+        self.join_with(room_id, tokens, hello).await
+    }
+
+    /// Connect to the seat `tokens` name and open with `hello`.
+    async fn join_with(&self, room_id: &str, tokens: Value, hello: ClientToServer) -> Player {
+        let ticket = self.ticket(room_id, tokens).await;
+        let socket = self.open(room_id, &ticket, "zobbo.v1").await.expect("the ticket opens a socket");
+        let mut player = Player { socket, seat: usize::MAX, reconnect: String::new(), update: None };
+        let ServerToClient::Welcome { seat, reconnect_token, protocol_version, .. } = player.recv().await else {
+            panic!("welcome comes first");
+        };
+        assert_eq!(protocol_version, PROTOCOL_VERSION);
+        (player.seat, player.reconnect) = (seat, reconnect_token);
+        player.send(hello).await;
+        player
+    }
+}
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// One seat's socket, and the game as that seat sees it.
+struct Player {
+    socket: Socket,
+    seat: usize,
+    reconnect: String,
+    update: Option<GameUpdate>,
+}
+
+impl Player {
+    async fn send(&mut self, msg: ClientToServer) {
+        let text = serde_json::to_string(&msg).unwrap();
+        self.socket.send(Message::Text(text)).await.unwrap();
+    }
+
+    async fn recv(&mut self) -> ServerToClient {
+        loop {
+            let frame = tokio::time::timeout(PATIENCE, self.socket.next())
+                .await
+                .expect("a message in time")
+                .expect("the socket is open")
+                .unwrap();
+            if let Message::Text(text) = frame {
+                let msg = serde_json::from_str(&text).unwrap();
+                self.track(&msg);
+                return msg;
+            }
+        }
+    }
+
+    /// Keep `update` current, applying deltas as a client does.
+    fn track(&mut self, msg: &ServerToClient) {
+        match msg {
+            ServerToClient::GameUpdate(update) => self.update = Some(update.clone()),
+            ServerToClient::GameDelta(delta) => {
+                let update = self.update.as_mut().expect("a full update before any delta");
+                assert!(update.apply(delta), "delta {} doesn't follow update {}", delta.seq, update.seq);
+            }
+            _ => {}
+        }
+    }
+
+    /// Everything the room has sent this seat so far.
+    async fn settle(&mut self) -> Vec<ServerToClient> {
+        self.send(ClientToServer::Ping).await;
+        let mut got = Vec::new();
+        loop {
+            match self.recv().await {
+                ServerToClient::Pong => return got,
+                msg => got.push(msg),
+            }
+        }
+    }
+
+    /// `msg`, and what it brought this seat.
+    async fn act(&mut self, msg: ClientToServer) -> Vec<ServerToClient> {
+        self.send(msg).await;
+        self.settle().await
+    }
+
+    fn game(&self) -> &GameUpdate {
+        self.update.as_ref().expect("a game in progress")
+    }
+}
+
+/// The `type` tags of `msgs`, to compare sequences at a glance. A
+/// `game_delta` counts as the `game_update` it stands for, since the room
+/// sends a full one instead every few updates.
+fn kinds(msgs: &[ServerToClient]) -> Vec<String> {
+    msgs.iter()
+        .map(|msg| match msg {
+            ServerToClient::GameDelta(_) => "game_update".to_string(),
+            msg => serde_json::to_value(msg).unwrap()["type"].as_str().unwrap().to_string(),
+        })
+        .collect()
+}
+
+/// Two connected players, by seat, in one room.
+struct Table {
+    server: Server,
+    room_id: String,
+    tokens: [String; 2],
+    players: [Player; 2],
+}
+
+impl Table {
+    /// Two players seated and connected, before anyone is ready.
+    async fn seated() -> Table {
+        let server = Server::start().await;
+        let (room_id, tokens) = server.pair().await;
+        let a = server.join(&room_id, json!({ "token": tokens[0] })).await;
+        let b = server.join(&room_id, json!({ "token": tokens[1] })).await;
+        let (mut players, tokens) = if a.seat == 0 { ([a, b], tokens) } else { ([b, a], [tokens[1].clone(), tokens[0].clone()]) };
+        for player in &mut players {
+            player.settle().await;
+        }
+        Table { server, room_id, tokens, players }
+    }
+
+    /// Two players into a freshly dealt game.
+    async fn dealt() -> Table {
+        let mut table = Table::seated().await;
+        for player in &mut table.players {
+            player.act(ClientToServer::Ready).await;
+        }
+        for player in &mut table.players {
+            player.settle().await;
+        }
+        table
+    }
+
+    fn active(&self) -> usize {
+        self.players[0].game().active
+    }
+
+    /// `msg` from `seat`, then what each seat received, by seat. The actor
+    /// settles first, so the other seat's share is all in by the time it does.
+    async fn play(&mut self, seat: usize, msg: ClientToServer) -> [Vec<ServerToClient>; 2] {
+        let mine = self.players[seat].act(msg).await;
+        let theirs = self.players[1 - seat].settle().await;
+        if seat == 0 { [mine, theirs] } else { [theirs, mine] }
+    }
+
+    /// The active seat draws from the deck: the card it holds.
+    async fn draw(&mut self) -> CardPublic {
+        let seat = self.active();
+        let got = self.play(seat, ClientToServer::DrawDeck).await;
+        assert_eq!(kinds(&got[seat]), ["game_update", "drawn"]);
+        assert_eq!(kinds(&got[1 - seat]), ["game_update"]);
+        let ServerToClient::Drawn { card, from: DrawSource::Deck } = got[seat][1] else { panic!("drew from the deck") };
+        card
+    }
+
+    /// The active seat keeps what it drew in `index`; the turn passes.
+    async fn keep(&mut self, index: usize) {
+        let seat = self.active();
+        let got = self.play(seat, ClientToServer::SwapWithHand { index }).await;
+        assert_eq!(kinds(&got[seat]), ["game_update", "peek_result"]);
+        assert_eq!(kinds(&got[1 - seat]), ["game_update"]);
+        assert_eq!(self.active(), 1 - seat);
+    }
+}
+
+/// What a seat is shown on connecting mid-game, in order.
+const RESYNC: [&str; 6] = ["lobby_state", "game_start", "game_update", "peek_result", "peek_result", "peek_result"];
+
+#[tokio::test]
+async fn joining_and_readying_deals_a_game() {
+    let server = Server::start().await;
+    let (room_id, tokens) = server.pair().await;
+    let mut first = server.join(&room_id, json!({ "token": tokens[0] })).await;
+    let got = first.settle().await;
+    assert_eq!(kinds(&got), ["lobby_state"]);
+    let ServerToClient::LobbyState { players, started: false, .. } = &got[0] else { panic!("a lobby") };
+    assert!(players[first.seat].connected);
+
+    let mut second = server.join(&room_id, json!({ "token": tokens[1] })).await;
+    assert_eq!(kinds(&first.settle().await), ["lobby_state"]);
+    assert_eq!(kinds(&second.settle().await), ["lobby_state"]);
+    assert_ne!(first.seat, second.seat);
+
+    let got = first.act(ClientToServer::Ready).await;
+    assert_eq!(kinds(&got), ["lobby_state"]);
+    let ServerToClient::LobbyState { players, .. } = &got[0] else { panic!("a lobby") };
+    assert!(players[first.seat].ready && !players[second.seat].ready);
+    assert_eq!(kinds(&second.settle().await), ["lobby_state"]);
+
+    // The last seat ready deals: the start, each seat's initial peeks, and the table.
+    let dealt = ["lobby_state", "game_start", "lobby_state", "peek_result", "peek_result", "peek_result", "game_update"];
+    let got = [second.act(ClientToServer::Ready).await, first.settle().await];
+    for (got, player) in got.iter().zip([&second, &first]) {
+        assert_eq!(kinds(got), dealt);
+        let ServerToClient::GameStart { seats: 2, hand_size: 6, first, round: 1, rounds: 1, .. } = got[1] else {
+            panic!("a single two-seat game")
+        };
+        assert!(matches!(got[2], ServerToClient::LobbyState { started: true, .. }));
+        for (msg, index) in got[3..6].iter().zip(3..) {
+            assert!(matches!(msg, ServerToClient::PeekResult { owner, index: i, .. } if *owner == player.seat && *i == index));
+        }
+        let game = player.game();
+        assert_eq!((game.active, game.stage, game.deck_count), (first, Stage::Draw, 52 - 12 - 1));
+        assert!(game.seats.iter().all(|seat| seat.slots.len() == 6 && seat.slots.iter().all(|slot| slot.filled)));
+    }
+    assert_eq!(first.game(), second.game());
+}
+
+#[tokio::test]
+async fn each_power_from_discard_to_use() {
+    let mut table = Table::dealt().await;
+    let powers = RuleConfig::default().power_table;
+    let mut unused = vec![Power::PeekOwn, Power::PeekOpp, Power::SwapOwnWithDeck, Power::BlindSwap, Power::SwapOppWithDeck];
+    // Keeping each draw in the next slot round sends every dealt card to the
+    // discards, which are reshuffled into the deck, so every power turns up.
+    let mut kept = [0; 2];
+    for _ in 0..500 {
+        if unused.is_empty() { break }
+        let seat = table.active();
+        let card = table.draw().await;
+        let Some(power) = powers.power(card.rank, card.suit).filter(|p| unused.contains(p)) else {
+            table.keep(kept[seat] % 6).await;
+            kept[seat] += 1;
+            continue;
+        };
+        unused.retain(|p| *p != power);
+
+        let got = table.play(seat, ClientToServer::DiscardDrawn).await;
+        assert_eq!([kinds(&got[0]), kinds(&got[1])], [["game_update"], ["game_update"]]);
+        let game = table.players[1 - seat].game();
+        assert_eq!((game.stage, game.power, game.discard_top), (Stage::Power, Some(power), Some(card)));
+
+        let opp = 1 - seat;
+        let (msg, private) = match power {
+            Power::PeekOwn => (ClientToServer::PeekOwn { index: 1 }, Some((seat, 1))),
+            Power::PeekOpp => (ClientToServer::PeekOpp { index: 2, target: None }, Some((opp, 2))),
+            Power::SwapOwnWithDeck => (ClientToServer::SwapOwnWithDeck { index: 3 }, None),
+            Power::BlindSwap => (ClientToServer::BlindSwap { own_index: 0, opp_index: 4, target: None }, None),
+            Power::SwapOppWithDeck => (ClientToServer::SwapOppWithDeck { opp_index: 5, target: None }, None),
+        };
+        let before = table.players[seat].game().seats.clone();
+        let got = table.play(seat, msg).await;
+        match private {
+            // Only the seat that used the power sees the card.
+            Some((owner, index)) => {
+                assert_eq!(kinds(&got[seat]), ["game_update", "peek_result"], "{power:?}");
+                let ServerToClient::PeekResult { owner: o, index: i, version, .. } = got[seat][1] else { unreachable!() };
+                assert_eq!((o, i, version), (owner, index, before[owner].slots[index].version));
+            }
+            None => {
+                assert_eq!(kinds(&got[seat]), ["game_update"], "{power:?}");
+                assert_ne!(table.players[seat].game().seats, before, "{power:?} moved a card");
+            }
+        }
+        assert_eq!(kinds(&got[opp]), ["game_update"], "{power:?}");
+        assert_eq!((table.active(), table.players[opp].game().stage), (opp, Stage::Draw));
+    }
+    assert_eq!(unused, [], "powers never drawn");
+    assert_eq!(table.players[0].game(), table.players[1].game());
+}
+
+#[tokio::test]
+async fn zobbo_call_to_the_reveal() {
+    let mut table = Table::dealt().await;
+    // Take the top discard once, for the other draw: it can't be thrown back.
+    let seat = table.active();
+    let top = table.players[seat].game().discard_top;
+    let got = table.play(seat, ClientToServer::DrawDiscard).await;
+    assert_eq!(kinds(&got[seat]), ["game_update", "drawn"]);
+    assert_eq!(kinds(&got[1 - seat]), ["game_update"]);
+    assert!(matches!(got[seat][1], ServerToClient::Drawn { card, from: DrawSource::Discard } if Some(card) == top));
+    let got = table.play(seat, ClientToServer::DiscardDrawn).await;
+    assert!(matches!(&got[seat][..], [ServerToClient::ActionRejected { code: RejectCode::WrongStage, .. }]));
+    table.keep(0).await;
+
+    let caller = table.active();
+    let got = table.play(caller, ClientToServer::CallZobbo).await;
+    for got in &got {
+        assert_eq!(kinds(got), ["game_update", "zobbo_called"]);
+        assert!(matches!(got[1], ServerToClient::ZobboCalled { caller: c, turns_remaining: 2 } if c == caller));
+    }
+    assert_eq!(table.players[0].game().zobbo_caller, Some(caller));
+
+    // The caller's turn, then the other seat's last one.
+    table.draw().await;
+    table.keep(5).await;
+    let last = table.active();
+    table.draw().await;
+    let got = table.play(last, ClientToServer::SwapWithHand { index: 5 }).await;
+    assert_eq!(kinds(&got[last]), ["game_update", "peek_result", "game_over", "lobby_state"]);
+    assert_eq!(kinds(&got[1 - last]), ["game_update", "game_over", "lobby_state"]);
+    assert_eq!(got[last][2..], got[1 - last][1..]);
+    let ServerToClient::GameOver { winner, scores, hands, zobbo_caller, zobbo_penalty, reason, ratings } = &got[1 - last][1] else {
+        unreachable!()
+    };
+    assert_eq!((*zobbo_caller, *reason, ratings.len()), (Some(caller), FinishReason::Zobbo, 0));
+    assert!(hands.iter().all(|hand| hand.len() == 6 && hand.iter().all(Option::is_some)), "every card is shown");
+    let points: Vec<i32> = hands.iter().map(|hand| hand.iter().flatten().map(|card| card.points).sum()).collect();
+    let penalty = zobbo_penalty.unwrap_or(0);
+    assert_eq!(points[caller] + penalty, scores[caller]);
+    assert_eq!(points[1 - caller], scores[1 - caller]);
+    assert_eq!(penalty != 0, points[caller] >= points[1 - caller], "penalised unless strictly lowest");
+    let lowest = scores.iter().min().unwrap();
+    let expected = (scores.iter().filter(|s| *s == lowest).count() == 1).then(|| scores.iter().position(|s| s == lowest).unwrap());
+    assert_eq!(*winner, expected);
+    assert_eq!(table.players[0].game().stage, Stage::Finished);
+
+    let got = table.play(caller, ClientToServer::DrawDeck).await;
+    assert!(matches!(&got[caller][..], [ServerToClient::ActionRejected { code: RejectCode::GameOver, .. }]));
+}
+
+#[tokio::test]
+async fn reconnecting_mid_game() {
+    let mut table = Table::dealt().await;
+    let [stays, leaves] = &mut table.players;
+    let reconnect = leaves.reconnect.clone();
+    leaves.socket.close(None).await.unwrap();
+    // The room hears of the drop from the socket's own task.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let got = stays.settle().await;
+    assert_eq!(kinds(&got), ["opponent_disconnected", "lobby_state"]);
+    assert!(matches!(got[0], ServerToClient::OpponentDisconnected { seat: 1, .. }));
+    let ServerToClient::LobbyState { players, .. } = &got[1] else { unreachable!() };
+    assert!(!players[1].connected);
+
+    let mut back = table.server.join(&table.room_id, json!({ "reconnect": reconnect })).await;
+    assert_eq!(back.seat, 1);
+    assert_eq!(kinds(&back.settle().await), RESYNC);
+    assert_eq!(back.game(), table.players[0].game());
+    let got = table.players[0].settle().await;
+    assert_eq!(kinds(&got), ["lobby_state"]);
+    let ServerToClient::LobbyState { players, .. } = &got[0] else { unreachable!() };
+    assert!(players[1].connected);
+
+    // A reconnect token is good once; the join token still is.
+    let (status, _) = table.server.post(&format!("/api/rooms/{}/ws-ticket", table.room_id), json!({ "reconnect": reconnect })).await;
+    assert_eq!(status, 401);
+    table.players[1] = back;
+    let mut tab = table.server.join(&table.room_id, json!({ "token": table.tokens[1] })).await;
+    assert_eq!(kinds(&tab.settle().await), RESYNC);
+    // The newer socket takes the seat over.
+    assert!(matches!(table.players[1].recv().await, ServerToClient::Error { .. }));
+    assert_eq!(kinds(&table.players[0].settle().await), ["lobby_state"]);
+    table.players[1] = tab;
+
+    // Play goes on where it was.
+    table.draw().await;
+
+    // A confused client can ask for the same again without reconnecting:
+    // the held card comes last, for the seat holding it.
+    let seat = table.active();
+    let got = table.players[seat].act(ClientToServer::Resync).await;
+    assert_eq!(kinds(&got), [&RESYNC[..], &["drawn"]].concat());
+    let got = table.players[1 - seat].act(ClientToServer::Resync).await;
+    assert_eq!(kinds(&got), RESYNC);
+}
+
+#[tokio::test]
+async fn practice_deals_against_a_bot() {
+    let server = Server::start().await;
+    let (status, grant) = server.post("/api/practice", json!({})).await;
+    assert_eq!(status, 200, "{grant}");
+    let room_id = grant["room_id"].as_str().unwrap();
+    let mut player = server.join(room_id, json!({ "token": grant["token"] })).await;
+    let bot = 1 - player.seat;
+    // The bot readies once it's thought about the lobby.
+    let bot_ready = |msg: &ServerToClient| {
+        matches!(msg, ServerToClient::LobbyState { players, .. } if players[bot].connected && players[bot].ready)
+    };
+    while !bot_ready(&player.recv().await) {}
+    let got = player.act(ClientToServer::Ready).await;
+    assert!(got.iter().any(|msg| matches!(msg, ServerToClient::GameStart { seats: 2, .. })), "{:?}", kinds(&got));
+}
+
+#[tokio::test]
+async fn refusals() {
+    let mut table = Table::dealt().await;
+    let idle = 1 - table.active();
+    let got = table.play(idle, ClientToServer::DrawDeck).await;
+    let [ServerToClient::ActionRejected { action, code: RejectCode::NotYourTurn, .. }] = &got[idle][..] else {
+        panic!("{:?}", kinds(&got[idle]))
+    };
+    assert_eq!(action, "draw_deck");
+    assert_eq!(got[1 - idle], [], "nothing changed");
+
+    let player = &mut table.players[idle];
+    let hello = ClientToServer::Hello { version: PROTOCOL_VERSION, capabilities: vec![] };
+    assert!(matches!(&player.act(hello).await[..], [ServerToClient::Error { .. }]), "hello comes first or not at all");
+    player.socket.send(Message::Text("{\"type\":\"draw_a_card\"}".into())).await.unwrap();
+    assert!(matches!(&player.settle().await[..], [ServerToClient::Error { .. }]));
+
+    // Tickets: one try each, for their own room and a subprotocol we speak.
+    let server = &table.server;
+    let ticket = server.ticket(&table.room_id, json!({ "token": table.tokens[idle] })).await;
+    assert_eq!(server.open(&table.room_id, &ticket, "zobbo.v0").await.err(), Some(400));
+    assert_eq!(server.open(&table.room_id, &ticket, "zobbo.v1").await.err(), Some(401));
+    let ticket = server.ticket(&table.room_id, json!({ "token": table.tokens[idle] })).await;
+    assert_eq!(server.open("elsewhere", &ticket, "zobbo.v1").await.err(), Some(401));
+    let (status, _) = server.post(&format!("/api/rooms/{}/ws-ticket", table.room_id), json!({ "token": "nope" })).await;
+    assert_eq!(status, 401);
+    // Only a ticket opens a socket, not the seat token itself.
+    let url = format!("ws://127.0.0.1:{}/ws?room_id={}&token={}", server.port, table.room_id, table.tokens[idle]);
+    let refused = tokio_tungstenite::connect_async(url).await;
+    assert!(matches!(refused, Err(tokio_tungstenite::tungstenite::Error::Http(r)) if r.status() == 400));
+}
+
+#[tokio::test]
+async fn the_host_hands_out_a_handicap() {
+    let mut table = Table::seated().await;
+    // Readying shows the lobby, host and all; a new handicap unreadies everyone anyway.
+    let got = table.play(0, ClientToServer::Ready).await;
+    let Some(ServerToClient::LobbyState { host: Some(host), .. }) = got[0].first() else { panic!("{:?}", kinds(&got[0])) };
+    let (host, guest) = (*host, 1 - *host);
+    let handicap = |seat, kind| ClientToServer::SetHandicap { handicap: Some(Handicap { seat, kind }) };
+
+    let got = table.play(guest, handicap(guest, HandicapKind::ExtraCard)).await;
+    assert!(matches!(&got[guest][..], [ServerToClient::ActionRejected { code: RejectCode::NotHost, .. }]));
+    let got = table.play(host, handicap(2, HandicapKind::ExtraCard)).await;
+    assert!(matches!(&got[host][..], [ServerToClient::ActionRejected { code: RejectCode::InvalidSettings, .. }]));
+
+    let got = table.play(host, handicap(guest, HandicapKind::ExtraCard)).await;
+    for got in &got {
+        let [ServerToClient::LobbyState { rules, .. }] = &got[..] else { panic!("{:?}", kinds(got)) };
+        assert_eq!(rules.handicap, Some(Handicap { seat: guest, kind: HandicapKind::ExtraCard }));
+    }
+    table.play(host, ClientToServer::Ready).await;
+    let got = table.play(guest, ClientToServer::Ready).await;
+    // The extra card is dealt unseen: the same three peeks, one slot further along.
+    let peeks = |got: &[ServerToClient]| {
+        got.iter().filter_map(|msg| match msg {
+            ServerToClient::PeekResult { index, .. } => Some(*index),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+    };
+    assert_eq!((peeks(&got[host]), peeks(&got[guest])), (vec![3, 4, 5], vec![4, 5, 6]));
+    let game = table.players[host].game();
+    assert_eq!((game.seats[host].slots.len(), game.seats[guest].slots.len(), game.deck_count), (6, 7, 52 - 13 - 1));
+}
+
+#[tokio::test]
+async fn older_clients_get_what_they_know() {
+    let server = Server::start().await;
+    let (room_id, tokens) = server.pair().await;
+    let mut current = server.join(&room_id, json!({ "token": tokens[0] })).await;
+    // From before `ZobboCalled`, and not asking for deltas.
+    let hello = ClientToServer::Hello { version: 11, capabilities: vec![] };
+    let mut older = server.join_with(&room_id, json!({ "token": tokens[1] }), hello).await;
+    for player in [&mut current, &mut older] {
+        player.act(ClientToServer::Ready).await;
+    }
+    current.settle().await;
+    older.settle().await;
+
+    // Whoever's turn it is calls Zobbo.
+    let caller = current.game().active;
+    let (for_current, for_older) = if caller == current.seat {
+        (current.act(ClientToServer::CallZobbo).await, older.settle().await)
+    } else {
+        let for_older = older.act(ClientToServer::CallZobbo).await;
+        (current.settle().await, for_older)
+    };
+    assert_eq!(kinds(&for_current), ["game_update", "zobbo_called"]);
+    let [ServerToClient::GameUpdate(update)] = &for_older[..] else { panic!("{:?}", kinds(&for_older)) };
+    assert_eq!(update.zobbo_caller, Some(caller), "the call still shows in the update");
+}
@@ -0,0 +1,1084 @@
+//! Pure validation and state transitions for Zobbo.
+//!
+//! `GameState` knows nothing about sockets or rooms: every action validates
+//! against the current turn and either mutates state or returns a `RuleError`.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use zobbo_protocol::{FinishReason, RejectCode, RuleConfig};
+
+use crate::types::{build_deck, rank_points, Action, Card, DrawSource, Event, Power};
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleError {
+    #[error("the game has not started")]
+    NotStarted,
+    #[error("the game is over")]
+    GameOver,
+    #[error("not your turn")]
+    NotYourTurn,
+    #[error("not allowed at this point in the turn")]
+    WrongStage,
+    #[error("that power is not on offer")]
+    WrongPower,
+    #[error("no such slot")]
+    BadIndex,
+    #[error("that slot is empty")]
+    EmptySlot,
+    #[error("the deck is empty")]
+    EmptyDeck,
+    #[error("the discard pile is empty")]
+    EmptyDiscard,
+    #[error("zobbo has already been called")]
+    ZobboAlreadyCalled,
+    #[error("the next round is about to start")]
+    BetweenRounds,
+    #[error("this room doesn't allow drawing from the discard pile")]
+    DiscardDrawOff,
+    #[error("no such opponent")]
+    BadTarget,
+    #[error("nobody has offered a draw")]
+    NoDrawOffer,
+}
+
+impl RuleError {
+    pub fn code(self) -> RejectCode {
+        match self {
+            RuleError::NotStarted => RejectCode::NotStarted,
+            RuleError::GameOver => RejectCode::GameOver,
+            RuleError::NotYourTurn => RejectCode::NotYourTurn,
+            RuleError::WrongStage => RejectCode::WrongStage,
+            RuleError::WrongPower => RejectCode::WrongPower,
+            RuleError::BadIndex => RejectCode::BadIndex,
+            RuleError::EmptySlot => RejectCode::EmptySlot,
+            RuleError::EmptyDeck => RejectCode::EmptyDeck,
+            RuleError::EmptyDiscard => RejectCode::EmptyDiscard,
+            RuleError::ZobboAlreadyCalled => RejectCode::ZobboAlreadyCalled,
+            RuleError::BetweenRounds => RejectCode::BetweenRounds,
+            RuleError::DiscardDrawOff => RejectCode::DiscardDrawOff,
+            RuleError::BadTarget => RejectCode::BadTarget,
+            RuleError::NoDrawOffer => RejectCode::NoDrawOffer,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TurnStage {
+    Draw,
+    Holding { card: Card, from: DrawSource },
+    Power(Power),
+    Finished,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Seat {
+    /// `None` once a card has been matched away.
+    pub slots: Vec<Option<Card>>,
+    /// Bumped whenever the card in the matching slot changes.
+    pub versions: Vec<u32>,
+    pub skip_next: bool,
+}
+
+impl Seat {
+    fn card(&self, index: usize) -> Result<Card, RuleError> {
+        self.slots.get(index).ok_or(RuleError::BadIndex)?.ok_or(RuleError::EmptySlot)
+    }
+
+    fn replace(&mut self, index: usize, card: Option<Card>) -> Option<Card> {
+        self.versions[index] += 1;
+        std::mem::replace(&mut self.slots[index], card)
+    }
+
+    pub fn points(&self) -> i32 {
+        self.slots.iter().flatten().map(|c| rank_points(*c)).sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZobboCall {
+    pub caller: usize,
+    pub remaining: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameResult {
+    /// `None` when the lowest score is shared.
+    pub winner: Option<usize>,
+    pub scores: Vec<i32>,
+    pub zobbo_caller: Option<usize>,
+    /// Added to the caller's score when they didn't have the strictly lowest hand.
+    #[serde(default)]
+    pub zobbo_penalty: Option<i32>,
+    #[serde(default)]
+    pub reason: FinishReason,
+}
+
+/// Serializable for persistence. Everything random follows from `seed`, so a
+/// game is reproducible from its seed and the moves played.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameState {
+    pub seats: Vec<Seat>,
+    /// Top of the deck is the end of the vec.
+    pub deck: Vec<Card>,
+    /// Top of the discard pile is the end of the vec.
+    pub discard: Vec<Card>,
+    pub active: usize,
+    pub stage: TurnStage,
+    pub zobbo: Option<ZobboCall>,
+    pub result: Option<GameResult>,
+    /// Counts turns started; bumps even when the same seat goes again after a skip.
+    pub turn: u32,
+    /// Seats that have offered or accepted a draw, in the order they did.
+    #[serde(default)]
+    pub draw_agreed: Vec<usize>,
+    /// Games saved before rules were configurable were dealt with the standard ones.
+    #[serde(default)]
+    pub rules: RuleConfig,
+    /// Seeds the deal and, with `reshuffles`, every reshuffle of the discard pile.
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    reshuffles: u64,
+}
+
+impl GameState {
+    /// Shuffle, deal `rules.hand_size` cards to each of `players` seats (one
+    /// more to a seat with an extra-card handicap), flip the first discard
+    /// and pick a random first player, all drawn from `seed`.
+    pub fn new(players: usize, rules: RuleConfig, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut deck = build_deck(&mut rng, rules.jokers);
+        let seats = (0..players)
+            .map(|seat| {
+                let size = rules.hand_size_of(seat);
+                Seat {
+                    slots: deck.split_off(deck.len() - size).into_iter().map(Some).collect(),
+                    versions: vec![0; size],
+                    skip_next: false,
+                }
+            })
+            .collect();
+        let discard = deck.pop().into_iter().collect();
+        let active = rng.gen_range(0..players);
+        GameState {
+            seats,
+            deck,
+            discard,
+            active,
+            stage: TurnStage::Draw,
+            zobbo: None,
+            result: None,
+            turn: 0,
+            draw_agreed: Vec::new(),
+            rules,
+            seed,
+            reshuffles: 0,
+        }
+    }
+
+    /// The power `card` grants when discarded straight from the deck, under this game's rules.
+    pub fn power_of(&self, card: Card) -> Option<Power> {
+        if !self.rules.powers { return None; }
+        self.rules.power_table.power(card.rank, card.suit)
+    }
+
+    /// Slots `seat` looks at before the first turn: the last
+    /// `rules.initial_peeks` of the hand, or one more under a handicap.
+    pub fn initial_peeks(&self, seat: usize) -> std::ops::Range<usize> {
+        let size = self.rules.hand_size_of(seat);
+        size - self.rules.initial_peeks_of(seat).min(size)..size
+    }
+
+    /// The seat that plays after `seat`.
+    pub fn next_seat(&self, seat: usize) -> usize {
+        (seat + 1) % self.seats.len()
+    }
+
+    /// Check that `seat` may aim an opponent power at `target`.
+    fn check_target(&self, seat: usize, target: usize) -> Result<(), RuleError> {
+        if target == seat || target >= self.seats.len() { return Err(RuleError::BadTarget); }
+        Ok(())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.stage == TurnStage::Finished
+    }
+
+    pub fn discard_top(&self) -> Option<Card> {
+        self.discard.last().copied()
+    }
+
+    fn check_turn(&self, seat: usize) -> Result<(), RuleError> {
+        if self.is_finished() { return Err(RuleError::GameOver); }
+        if seat != self.active { return Err(RuleError::NotYourTurn); }
+        Ok(())
+    }
+
+    fn expect_power(&self, seat: usize, power: Power) -> Result<(), RuleError> {
+        self.check_turn(seat)?;
+        match self.stage {
+            TurnStage::Power(p) if p == power => Ok(()),
+            TurnStage::Power(_) => Err(RuleError::WrongPower),
+            _ => Err(RuleError::WrongStage),
+        }
+    }
+
+    /// Reshuffle the discard pile (minus its top card) into an empty deck.
+    fn ensure_deck(&mut self) -> Result<(), RuleError> {
+        if self.deck.is_empty() && self.discard.len() > 1 {
+            let top = self.discard.pop();
+            self.deck = std::mem::take(&mut self.discard);
+            self.reshuffles += 1;
+            // A fresh stream per reshuffle, as the deal's RNG isn't saved with the game.
+            let mut rng = StdRng::seed_from_u64(self.seed ^ self.reshuffles.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+            self.deck.shuffle(&mut rng);
+            self.discard.extend(top);
+        }
+        if self.deck.is_empty() { Err(RuleError::EmptyDeck) } else { Ok(()) }
+    }
+
+    pub fn draw_deck(&mut self, seat: usize) -> Result<Card, RuleError> {
+        self.check_turn(seat)?;
+        if self.stage != TurnStage::Draw { return Err(RuleError::WrongStage); }
+        self.ensure_deck()?;
+        let card = self.deck.pop().ok_or(RuleError::EmptyDeck)?;
+        self.stage = TurnStage::Holding { card, from: DrawSource::Deck };
+        Ok(card)
+    }
+
+    pub fn draw_discard(&mut self, seat: usize) -> Result<Card, RuleError> {
+        self.check_turn(seat)?;
+        if self.stage != TurnStage::Draw { return Err(RuleError::WrongStage); }
+        if !self.rules.discard_draw { return Err(RuleError::DiscardDrawOff); }
+        let card = self.discard.pop().ok_or(RuleError::EmptyDiscard)?;
+        self.stage = TurnStage::Holding { card, from: DrawSource::Discard };
+        Ok(card)
+    }
+
+    /// Put the held card into `index` and discard what was there. Returns the
+    /// placed card, which its owner now knows.
+    pub fn swap_with_hand(&mut self, seat: usize, index: usize) -> Result<Card, RuleError> {
+        self.check_turn(seat)?;
+        let TurnStage::Holding { card, .. } = self.stage else { return Err(RuleError::WrongStage) };
+        self.seats[seat].card(index)?;
+        let old = self.seats[seat].replace(index, Some(card)).expect("slot checked above");
+        self.discard.push(old);
+        self.end_turn_common();
+        Ok(card)
+    }
+
+    /// Discard a card drawn from the deck. Returns the power it offers, if any;
+    /// otherwise the turn ends.
+    pub fn discard_drawn(&mut self, seat: usize) -> Result<Option<Power>, RuleError> {
+        self.check_turn(seat)?;
+        let TurnStage::Holding { card, from: DrawSource::Deck } = self.stage else {
+            return Err(RuleError::WrongStage);
+        };
+        self.discard.push(card);
+        let power = self.power_of(card);
+        match power {
+            Some(p) => self.stage = TurnStage::Power(p),
+            None => self.end_turn_common(),
+        }
+        Ok(power)
+    }
+
+    /// `PeekOwn` (5–8 by default). Returns the card and the slot version it was seen at.
+    pub fn peek_own(&mut self, seat: usize, index: usize) -> Result<(Card, u32), RuleError> {
+        self.expect_power(seat, Power::PeekOwn)?;
+        let card = self.seats[seat].card(index)?;
+        let version = self.seats[seat].versions[index];
+        self.end_turn_common();
+        Ok((card, version))
+    }
+
+    /// `PeekOpp` (9–10 by default): look at `target`'s slot `index`. Returns
+    /// the card and the slot version it was seen at.
+    pub fn peek_opp(&mut self, seat: usize, target: usize, index: usize) -> Result<(Card, u32), RuleError> {
+        self.expect_power(seat, Power::PeekOpp)?;
+        self.check_target(seat, target)?;
+        let card = self.seats[target].card(index)?;
+        let version = self.seats[target].versions[index];
+        self.end_turn_common();
+        Ok((card, version))
+    }
+
+    /// `SwapOwnWithDeck` (J by default): swap one of your cards with the top of the deck, unseen.
+    pub fn swap_own_with_deck(&mut self, seat: usize, index: usize) -> Result<(), RuleError> {
+        self.expect_power(seat, Power::SwapOwnWithDeck)?;
+        self.swap_with_deck(seat, index)
+    }
+
+    /// `SwapOppWithDeck` (red K by default): swap one of `target`'s cards with the top of the deck, unseen.
+    pub fn swap_opp_with_deck(&mut self, seat: usize, target: usize, opp_index: usize) -> Result<(), RuleError> {
+        self.expect_power(seat, Power::SwapOppWithDeck)?;
+        self.check_target(seat, target)?;
+        self.swap_with_deck(target, opp_index)
+    }
+
+    fn swap_with_deck(&mut self, owner: usize, index: usize) -> Result<(), RuleError> {
+        self.seats[owner].card(index)?;
+        self.ensure_deck()?;
+        let from_deck = self.deck.pop().expect("deck checked above");
+        let old = self.seats[owner].replace(index, Some(from_deck)).expect("slot checked above");
+        self.deck.push(old);
+        self.end_turn_common();
+        Ok(())
+    }
+
+    /// `BlindSwap` (Q by default): swap one of your cards with one of `target`'s, unseen.
+    pub fn blind_swap(
+        &mut self,
+        seat: usize,
+        own_index: usize,
+        target: usize,
+        opp_index: usize,
+    ) -> Result<(), RuleError> {
+        self.expect_power(seat, Power::BlindSwap)?;
+        self.check_target(seat, target)?;
+        let mine = self.seats[seat].card(own_index)?;
+        let theirs = self.seats[target].card(opp_index)?;
+        self.seats[seat].replace(own_index, Some(theirs));
+        self.seats[target].replace(opp_index, Some(mine));
+        self.end_turn_common();
+        Ok(())
+    }
+
+    pub fn skip_power(&mut self, seat: usize) -> Result<(), RuleError> {
+        self.check_turn(seat)?;
+        if !matches!(self.stage, TurnStage::Power(_)) { return Err(RuleError::WrongStage); }
+        self.end_turn_common();
+        Ok(())
+    }
+
+    /// Throw `index` onto the discard pile if it matches the top card's rank.
+    ///
+    /// Allowed on either player's turn. Returns whether it matched; a wrong
+    /// guess leaves the card in place and skips the seat's next turn, and
+    /// with `rules.match_penalty_draw` deals the seat a penalty card.
+    /// Matching away your last card wins the game on the spot.
+    pub fn match_top(&mut self, seat: usize, index: usize) -> Result<bool, RuleError> {
+        if self.is_finished() { return Err(RuleError::GameOver); }
+        let card = self.seats[seat].card(index)?;
+        let top = self.discard_top().ok_or(RuleError::EmptyDiscard)?;
+        if card.rank != top.rank {
+            self.miss_match(seat);
+            return Ok(false);
+        }
+        self.seats[seat].replace(index, None);
+        self.discard.push(card);
+        self.finish_if_empty(seat);
+        Ok(true)
+    }
+
+    /// Throw `target`'s `opp_index` onto the discard pile if it matches the
+    /// top card's rank, and give them `seat`'s `give_index` in its place.
+    ///
+    /// Allowed on anyone's turn. Returns whether it matched; a wrong guess
+    /// moves nothing and is punished like one in `match_top`.
+    pub fn match_top_opp(
+        &mut self,
+        seat: usize,
+        target: usize,
+        opp_index: usize,
+        give_index: usize,
+    ) -> Result<bool, RuleError> {
+        if self.is_finished() { return Err(RuleError::GameOver); }
+        self.check_target(seat, target)?;
+        let card = self.seats[target].card(opp_index)?;
+        let gift = self.seats[seat].card(give_index)?;
+        let top = self.discard_top().ok_or(RuleError::EmptyDiscard)?;
+        if card.rank != top.rank {
+            self.miss_match(seat);
+            return Ok(false);
+        }
+        self.discard.push(card);
+        self.seats[seat].replace(give_index, None);
+        self.seats[target].replace(opp_index, Some(gift));
+        self.finish_if_empty(seat);
+        Ok(true)
+    }
+
+    /// End the game with `seat` as the winner once its hand is empty.
+    fn finish_if_empty(&mut self, seat: usize) {
+        if self.seats[seat].slots.iter().all(Option::is_none) {
+            self.reveal_and_finish(FinishReason::EmptyHand { seat });
+        }
+    }
+
+    /// Punish a wrong match: skip `seat`'s next turn, plus a penalty card
+    /// with `rules.match_penalty_draw`.
+    fn miss_match(&mut self, seat: usize) {
+        self.seats[seat].skip_next = true;
+        if self.rules.match_penalty_draw {
+            self.deal_penalty(seat);
+        }
+    }
+
+    /// Deal the top of the deck, unseen, into `seat`'s first empty slot or a
+    /// new one. Nothing happens when there is no card left to deal.
+    fn deal_penalty(&mut self, seat: usize) {
+        if self.ensure_deck().is_err() { return; }
+        let card = self.deck.pop().expect("deck checked above");
+        let hand = &mut self.seats[seat];
+        match hand.slots.iter().position(Option::is_none) {
+            Some(index) => {
+                hand.replace(index, Some(card));
+            }
+            None => {
+                hand.slots.push(Some(card));
+                hand.versions.push(0);
+            }
+        }
+    }
+
+    /// Call Zobbo at the start of your turn. You still play this turn, then
+    /// everyone else plays one more before the reveal.
+    pub fn call_zobbo(&mut self, seat: usize) -> Result<(), RuleError> {
+        self.check_turn(seat)?;
+        if self.stage != TurnStage::Draw { return Err(RuleError::WrongStage); }
+        if self.zobbo.is_some() { return Err(RuleError::ZobboAlreadyCalled); }
+        self.zobbo = Some(ZobboCall { caller: seat, remaining: self.seats.len() as u8 });
+        Ok(())
+    }
+
+    /// Offer to end the game as a tie, or join an offer already on the table.
+    /// Returns whether the game ended because everyone has now agreed.
+    pub fn offer_draw(&mut self, seat: usize) -> Result<bool, RuleError> {
+        if self.is_finished() { return Err(RuleError::GameOver); }
+        if !self.draw_agreed.contains(&seat) {
+            self.draw_agreed.push(seat);
+        }
+        if self.draw_agreed.len() < self.seats.len() { return Ok(false); }
+        self.reveal_and_finish(FinishReason::AgreedDraw);
+        Ok(true)
+    }
+
+    /// Agree to a pending draw offer; see `offer_draw`.
+    pub fn accept_draw(&mut self, seat: usize) -> Result<bool, RuleError> {
+        if self.is_finished() { return Err(RuleError::GameOver); }
+        if self.draw_agreed.is_empty() { return Err(RuleError::NoDrawOffer); }
+        self.offer_draw(seat)
+    }
+
+    /// Turn down a pending draw offer, or withdraw one's own: nobody has
+    /// agreed any more.
+    pub fn decline_draw(&mut self) -> Result<(), RuleError> {
+        if self.is_finished() { return Err(RuleError::GameOver); }
+        if self.draw_agreed.is_empty() { return Err(RuleError::NoDrawOffer); }
+        self.draw_agreed.clear();
+        Ok(())
+    }
+
+    /// Playing on instead of accepting turns a draw offer down, so an offer
+    /// can't be taken up many turns later once the game has swung. Returns
+    /// whether there was an offer to lapse.
+    fn lapse_draw_offer(&mut self, seat: usize) -> bool {
+        if self.draw_agreed.is_empty() || self.draw_agreed.contains(&seat) { return false; }
+        self.draw_agreed.clear();
+        true
+    }
+
+    /// A seat gave up the game, as `reason` (`Forfeit`, `Inactive` or `OutOfTime`) says:
+    /// end it with everyone else ahead of that seat.
+    pub fn forfeit(&mut self, reason: FinishReason) {
+        if self.is_finished() { return; }
+        self.reveal_and_finish(reason);
+    }
+
+    /// The active player ran out of time: any held card goes to the discard
+    /// pile, any power is forfeited and the turn ends. Letting the clock run
+    /// out lapses a draw offer like any other move.
+    pub fn expire_turn(&mut self) {
+        match self.stage {
+            TurnStage::Finished => return,
+            TurnStage::Holding { card, .. } => self.discard.push(card),
+            TurnStage::Draw | TurnStage::Power(_) => {}
+        }
+        self.lapse_draw_offer(self.active);
+        self.end_turn_common();
+    }
+
+    /// Apply `action` for `seat`, returning what happened for the host to relay.
+    ///
+    /// The per-action methods stay available; this is the single entry point
+    /// for hosts that just forward player input.
+    pub fn apply(&mut self, seat: usize, action: Action) -> Result<Vec<Event>, RuleError> {
+        // Opponent powers without a target hit the next seat.
+        let target = |t: Option<usize>| t.unwrap_or_else(|| self.next_seat(seat));
+        let mut events = match action {
+            Action::DrawDeck => {
+                let card = self.draw_deck(seat)?;
+                vec![Event::Drawn { seat, card, from: DrawSource::Deck }]
+            }
+            Action::DrawDiscard => {
+                let card = self.draw_discard(seat)?;
+                vec![Event::Drawn { seat, card, from: DrawSource::Discard }]
+            }
+            Action::SwapWithHand { index } => {
+                let card = self.swap_with_hand(seat, index)?;
+                let version = self.seats[seat].versions[index];
+                vec![Event::Peeked { viewer: seat, owner: seat, index, card, version }]
+            }
+            Action::DiscardDrawn => match self.discard_drawn(seat)? {
+                Some(power) => vec![Event::PowerOffered { seat, power }],
+                None => Vec::new(),
+            },
+            Action::PeekOwn { index } => {
+                let (card, version) = self.peek_own(seat, index)?;
+                vec![Event::Peeked { viewer: seat, owner: seat, index, card, version }]
+            }
+            Action::PeekOpp { target: t, index } => {
+                let owner = target(t);
+                let (card, version) = self.peek_opp(seat, owner, index)?;
+                vec![Event::Peeked { viewer: seat, owner, index, card, version }]
+            }
+            Action::SwapOwnWithDeck { index } => {
+                self.swap_own_with_deck(seat, index)?;
+                Vec::new()
+            }
+            Action::BlindSwap { own_index, target: t, opp_index } => {
+                self.blind_swap(seat, own_index, target(t), opp_index)?;
+                Vec::new()
+            }
+            Action::SwapOppWithDeck { target: t, opp_index } => {
+                self.swap_opp_with_deck(seat, target(t), opp_index)?;
+                Vec::new()
+            }
+            Action::SkipPower => {
+                self.skip_power(seat)?;
+                Vec::new()
+            }
+            Action::MatchTop { index } => {
+                let matched = self.match_top(seat, index)?;
+                vec![Event::MatchAttempted { seat, index, matched }]
+            }
+            Action::MatchTopOpp { target: t, opp_index, give_index } => {
+                let target = target(t);
+                let matched = self.match_top_opp(seat, target, opp_index, give_index)?;
+                vec![Event::OppMatchAttempted { seat, target, opp_index, give_index, matched }]
+            }
+            Action::CallZobbo => {
+                self.call_zobbo(seat)?;
+                vec![Event::ZobboCalled { seat }]
+            }
+            Action::OfferDraw => match self.offer_draw(seat)? {
+                true => Vec::new(),
+                false => vec![Event::DrawAgreed { seat }],
+            },
+            Action::AcceptDraw => match self.accept_draw(seat)? {
+                true => Vec::new(),
+                false => vec![Event::DrawAgreed { seat }],
+            },
+            Action::DeclineDraw => {
+                self.decline_draw()?;
+                vec![Event::DrawDeclined { seat }]
+            }
+        };
+        if !matches!(action, Action::OfferDraw | Action::AcceptDraw | Action::DeclineDraw)
+            && self.lapse_draw_offer(seat)
+        {
+            events.push(Event::DrawDeclined { seat });
+        }
+        if let Some(result) = &self.result {
+            events.push(Event::Finished(result.clone()));
+        }
+        Ok(events)
+    }
+
+    /// Advance to the next player, honouring skipped turns and the Zobbo countdown.
+    fn end_turn_common(&mut self) {
+        self.stage = TurnStage::Draw;
+        self.turn += 1;
+        if self.tick_zobbo() { return; }
+        self.active = self.next_seat(self.active);
+        while self.seats[self.active].skip_next {
+            // A skipped turn still counts as that player's turn for the countdown.
+            self.seats[self.active].skip_next = false;
+            if self.tick_zobbo() { return; }
+            self.active = self.next_seat(self.active);
+        }
+    }
+
+    /// Count one turn off a pending Zobbo call; reveals and returns true when it runs out.
+    fn tick_zobbo(&mut self) -> bool {
+        let Some(call) = self.zobbo.as_mut() else { return false };
+        call.remaining = call.remaining.saturating_sub(1);
+        if call.remaining == 0 {
+            self.reveal_and_finish(FinishReason::Zobbo);
+            return true;
+        }
+        false
+    }
+
+    /// Flip every hand and score it; the lowest total wins, unless someone
+    /// emptied their hand, forfeited or everyone agreed a draw. A Zobbo caller without
+    /// the strictly lowest hand takes `rules.zobbo_penalty` first, except in
+    /// an agreed draw.
+    pub fn reveal_and_finish(&mut self, reason: FinishReason) {
+        let mut scores: Vec<i32> = self.seats.iter().map(Seat::points).collect();
+        let zobbo_caller = self.zobbo.map(|z| z.caller);
+        let mut zobbo_penalty = None;
+        if let Some(caller) = zobbo_caller
+            && reason != FinishReason::AgreedDraw
+            && self.rules.zobbo_penalty != 0
+            && lowest_unique(&scores) != Some(caller)
+        {
+            scores[caller] += self.rules.zobbo_penalty;
+            zobbo_penalty = Some(self.rules.zobbo_penalty);
+        }
+        let winner = match reason {
+            FinishReason::EmptyHand { seat } => Some(seat),
+            FinishReason::AgreedDraw => None,
+            // The lowest hand among those still playing; in two-player games, the other seat.
+            FinishReason::Forfeit { seat } | FinishReason::Inactive { seat } | FinishReason::OutOfTime { seat } => {
+                let mut standing = scores.clone();
+                standing[seat] = i32::MAX;
+                lowest_unique(&standing)
+            }
+            FinishReason::Zobbo => lowest_unique(&scores),
+        };
+        self.stage = TurnStage::Finished;
+        self.result = Some(GameResult { winner, scores, zobbo_caller, zobbo_penalty, reason });
+    }
+}
+
+/// The seat with the strictly lowest score, or `None` on a tie.
+pub fn lowest_unique(scores: &[i32]) -> Option<usize> {
+    let best = scores.iter().copied().min()?;
+    let mut lowest = scores.iter().enumerate().filter(|(_, s)| **s == best).map(|(i, _)| i);
+    match (lowest.next(), lowest.next()) {
+        (Some(only), None) => Some(only),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Rank, Suit};
+    use zobbo_protocol::{Handicap, HandicapKind};
+
+    const SEED: u64 = 2012;
+
+    fn card(rank: Rank, suit: Suit) -> Card {
+        Card { rank, suit }
+    }
+
+    /// A two-seat game on `SEED` with seat 0 to play.
+    fn game() -> GameState {
+        game_with(2, RuleConfig::default())
+    }
+
+    fn game_with(players: usize, rules: RuleConfig) -> GameState {
+        let mut game = GameState::new(players, rules, SEED);
+        game.active = 0;
+        game
+    }
+
+    fn set_hand(game: &mut GameState, seat: usize, cards: &[Card]) {
+        game.seats[seat].slots = cards.iter().copied().map(Some).collect();
+        game.seats[seat].versions = vec![0; cards.len()];
+    }
+
+    /// Put `card` on top of the deck, for the next draw.
+    fn stack(game: &mut GameState, card: Card) {
+        game.deck.push(card);
+    }
+
+    /// Have `seat` draw `card` from the deck and discard it, offering its power.
+    fn offer(game: &mut GameState, seat: usize, card: Card) -> Vec<Event> {
+        stack(game, card);
+        game.apply(seat, Action::DrawDeck).unwrap();
+        game.apply(seat, Action::DiscardDrawn).unwrap()
+    }
+
+    /// Play out `seat`'s turn without touching any hand.
+    fn pass(game: &mut GameState, seat: usize) {
+        assert!(offer(game, seat, card(Rank::Two, Suit::Clubs)).iter().all(|e| matches!(e, Event::Finished(_))));
+    }
+
+    #[test]
+    fn same_seed_deals_the_same_game() {
+        let (a, b) = (GameState::new(3, RuleConfig::default(), SEED), GameState::new(3, RuleConfig::default(), SEED));
+        assert_eq!(a.deck, b.deck);
+        assert_eq!(a.discard, b.discard);
+        assert_eq!(a.active, b.active);
+        assert!(a.seats.iter().zip(&b.seats).all(|(x, y)| x.slots == y.slots));
+        assert!(a.seats.iter().all(|seat| seat.slots.len() == 6 && seat.slots.iter().all(Option::is_some)));
+        assert_eq!(a.deck.len(), 52 - 3 * 6 - 1);
+        assert_eq!(a.discard.len(), 1);
+    }
+
+    #[test]
+    fn handicaps_deal_or_show_one_more_card() {
+        let extra = |seat, kind| RuleConfig { handicap: Some(Handicap { seat, kind }), ..RuleConfig::default() };
+        let game = game_with(3, extra(1, HandicapKind::ExtraCard));
+        assert_eq!(game.seats.iter().map(|s| s.slots.len()).collect::<Vec<_>>(), vec![6, 7, 6]);
+        assert_eq!(game.deck.len(), 52 - 3 * 6 - 1 - 1);
+        assert_eq!((game.initial_peeks(0), game.initial_peeks(1)), (3..6, 4..7));
+
+        let game = game_with(2, extra(0, HandicapKind::ExtraPeek));
+        assert!(game.seats.iter().all(|s| s.slots.len() == 6));
+        assert_eq!((game.initial_peeks(0), game.initial_peeks(1)), (2..6, 3..6));
+
+        // A seat the game doesn't have is ignored rather than dealt.
+        let game = game_with(2, extra(3, HandicapKind::ExtraCard));
+        assert!(game.seats.iter().all(|s| s.slots.len() == 6));
+    }
+
+    #[test]
+    fn draw_from_deck_and_swap_into_hand() {
+        let mut game = game();
+        let drawn = card(Rank::Three, Suit::Hearts);
+        let old = game.seats[0].slots[2].unwrap();
+        stack(&mut game, drawn);
+
+        let events = game.apply(0, Action::DrawDeck).unwrap();
+        assert_eq!(events, vec![Event::Drawn { seat: 0, card: drawn, from: DrawSource::Deck }]);
+        assert_eq!(game.stage, TurnStage::Holding { card: drawn, from: DrawSource::Deck });
+
+        let events = game.apply(0, Action::SwapWithHand { index: 2 }).unwrap();
+        assert_eq!(events, vec![Event::Peeked { viewer: 0, owner: 0, index: 2, card: drawn, version: 1 }]);
+        assert_eq!(game.seats[0].slots[2], Some(drawn));
+        assert_eq!(game.discard_top(), Some(old));
+        assert_eq!((game.active, game.turn, &game.stage), (1, 1, &TurnStage::Draw));
+    }
+
+    #[test]
+    fn draw_from_discard() {
+        let mut game = game();
+        let top = game.discard_top().unwrap();
+        let events = game.apply(0, Action::DrawDiscard).unwrap();
+        assert_eq!(events, vec![Event::Drawn { seat: 0, card: top, from: DrawSource::Discard }]);
+        assert!(game.discard.is_empty());
+        // Only a card from the deck may go straight back on the pile.
+        assert_eq!(game.apply(0, Action::DiscardDrawn), Err(RuleError::WrongStage));
+        game.apply(0, Action::SwapWithHand { index: 0 }).unwrap();
+        assert_eq!(game.seats[0].slots[0], Some(top));
+    }
+
+    #[test]
+    fn discard_draw_can_be_turned_off() {
+        let mut game = game_with(2, RuleConfig { discard_draw: false, ..RuleConfig::default() });
+        assert_eq!(game.apply(0, Action::DrawDiscard), Err(RuleError::DiscardDrawOff));
+    }
+
+    #[test]
+    fn discarding_a_plain_card_ends_the_turn() {
+        let mut game = game();
+        let two = card(Rank::Two, Suit::Spades);
+        assert_eq!(offer(&mut game, 0, two), Vec::new());
+        assert_eq!(game.discard_top(), Some(two));
+        assert_eq!(game.active, 1);
+    }
+
+    #[test]
+    fn powers_are_off_when_the_rules_say_so() {
+        let mut game = game_with(2, RuleConfig { powers: false, ..RuleConfig::default() });
+        assert_eq!(offer(&mut game, 0, card(Rank::Queen, Suit::Hearts)), Vec::new());
+        assert_eq!(game.active, 1);
+    }
+
+    #[test]
+    fn peek_own() {
+        let mut game = game();
+        let events = offer(&mut game, 0, card(Rank::Seven, Suit::Clubs));
+        assert_eq!(events, vec![Event::PowerOffered { seat: 0, power: Power::PeekOwn }]);
+        let mine = game.seats[0].slots[4].unwrap();
+        let events = game.apply(0, Action::PeekOwn { index: 4 }).unwrap();
+        assert_eq!(events, vec![Event::Peeked { viewer: 0, owner: 0, index: 4, card: mine, version: 0 }]);
+        assert_eq!(game.active, 1);
+    }
+
+    #[test]
+    fn peek_opp_defaults_to_the_next_seat() {
+        let mut game = game();
+        let events = offer(&mut game, 0, card(Rank::Nine, Suit::Clubs));
+        assert_eq!(events, vec![Event::PowerOffered { seat: 0, power: Power::PeekOpp }]);
+        let theirs = game.seats[1].slots[0].unwrap();
+        let events = game.apply(0, Action::PeekOpp { target: None, index: 0 }).unwrap();
+        assert_eq!(events, vec![Event::Peeked { viewer: 0, owner: 1, index: 0, card: theirs, version: 0 }]);
+    }
+
+    #[test]
+    fn opponent_powers_cant_target_yourself() {
+        let mut game = game();
+        offer(&mut game, 0, card(Rank::Ten, Suit::Clubs));
+        assert_eq!(game.apply(0, Action::PeekOpp { target: Some(0), index: 0 }), Err(RuleError::BadTarget));
+        assert_eq!(game.apply(0, Action::PeekOpp { target: Some(5), index: 0 }), Err(RuleError::BadTarget));
+    }
+
+    #[test]
+    fn swap_own_with_deck() {
+        let mut game = game();
+        offer(&mut game, 0, card(Rank::Jack, Suit::Clubs));
+        let (mine, next) = (game.seats[0].slots[1].unwrap(), card(Rank::Ace, Suit::Hearts));
+        stack(&mut game, next);
+        assert_eq!(game.apply(0, Action::SwapOwnWithDeck { index: 1 }).unwrap(), Vec::new());
+        assert_eq!(game.seats[0].slots[1], Some(next));
+        assert_eq!(game.seats[0].versions[1], 1);
+        assert_eq!(game.deck.last(), Some(&mine));
+    }
+
+    #[test]
+    fn swap_opp_with_deck_is_a_red_king_only() {
+        let mut game = game();
+        assert_eq!(offer(&mut game, 0, card(Rank::King, Suit::Spades)), Vec::new());
+
+        let events = offer(&mut game, 1, card(Rank::King, Suit::Diamonds));
+        assert_eq!(events, vec![Event::PowerOffered { seat: 1, power: Power::SwapOppWithDeck }]);
+        let (theirs, next) = (game.seats[0].slots[3].unwrap(), card(Rank::Four, Suit::Hearts));
+        stack(&mut game, next);
+        game.apply(1, Action::SwapOppWithDeck { target: None, opp_index: 3 }).unwrap();
+        assert_eq!(game.seats[0].slots[3], Some(next));
+        assert_eq!(game.deck.last(), Some(&theirs));
+    }
+
+    #[test]
+    fn blind_swap() {
+        let mut game = game();
+        offer(&mut game, 0, card(Rank::Queen, Suit::Clubs));
+        let (mine, theirs) = (game.seats[0].slots[0].unwrap(), game.seats[1].slots[5].unwrap());
+        game.apply(0, Action::BlindSwap { own_index: 0, target: None, opp_index: 5 }).unwrap();
+        assert_eq!(game.seats[0].slots[0], Some(theirs));
+        assert_eq!(game.seats[1].slots[5], Some(mine));
+        assert_eq!((game.seats[0].versions[0], game.seats[1].versions[5]), (1, 1));
+    }
+
+    #[test]
+    fn skip_power_and_wrong_power() {
+        let mut game = game();
+        offer(&mut game, 0, card(Rank::Five, Suit::Clubs));
+        assert_eq!(game.apply(0, Action::BlindSwap { own_index: 0, target: None, opp_index: 0 }), Err(RuleError::WrongPower));
+        game.apply(0, Action::SkipPower).unwrap();
+        assert_eq!((game.active, &game.stage), (1, &TurnStage::Draw));
+        assert_eq!(game.apply(1, Action::SkipPower), Err(RuleError::WrongStage));
+    }
+
+    #[test]
+    fn match_top_throws_a_matching_card_out_of_turn() {
+        let mut game = game();
+        let top = card(Rank::Six, Suit::Clubs);
+        game.discard.push(top);
+        set_hand(&mut game, 1, &[card(Rank::Six, Suit::Hearts), card(Rank::Ace, Suit::Clubs)]);
+        let events = game.apply(1, Action::MatchTop { index: 0 }).unwrap();
+        assert_eq!(events, vec![Event::MatchAttempted { seat: 1, index: 0, matched: true }]);
+        assert_eq!(game.seats[1].slots[0], None);
+        assert_eq!(game.discard_top(), Some(card(Rank::Six, Suit::Hearts)));
+        assert_eq!(game.apply(1, Action::MatchTop { index: 0 }), Err(RuleError::EmptySlot));
+    }
+
+    #[test]
+    fn a_wrong_match_skips_the_next_turn() {
+        let mut game = game();
+        game.discard.push(card(Rank::Six, Suit::Clubs));
+        set_hand(&mut game, 1, &[card(Rank::Ace, Suit::Clubs)]);
+        let events = game.apply(1, Action::MatchTop { index: 0 }).unwrap();
+        assert_eq!(events, vec![Event::MatchAttempted { seat: 1, index: 0, matched: false }]);
+        assert_eq!(game.seats[1].slots, vec![Some(card(Rank::Ace, Suit::Clubs))]);
+        pass(&mut game, 0);
+        assert_eq!(game.active, 0, "seat 1's turn was skipped");
+        assert!(!game.seats[1].skip_next);
+    }
+
+    #[test]
+    fn a_wrong_match_can_deal_a_penalty_card() {
+        let mut game = game_with(2, RuleConfig { match_penalty_draw: true, ..RuleConfig::default() });
+        game.discard.push(card(Rank::Six, Suit::Clubs));
+        set_hand(&mut game, 1, &[card(Rank::Ace, Suit::Clubs)]);
+        let penalty = card(Rank::Queen, Suit::Hearts);
+        stack(&mut game, penalty);
+        game.apply(1, Action::MatchTop { index: 0 }).unwrap();
+        assert_eq!(game.seats[1].slots, vec![Some(card(Rank::Ace, Suit::Clubs)), Some(penalty)]);
+    }
+
+    #[test]
+    fn match_top_opp_gives_a_card_in_exchange() {
+        let mut game = game();
+        game.discard.push(card(Rank::Eight, Suit::Clubs));
+        set_hand(&mut game, 0, &[card(Rank::Ace, Suit::Clubs), card(Rank::Two, Suit::Clubs)]);
+        set_hand(&mut game, 1, &[card(Rank::Eight, Suit::Hearts)]);
+        let events = game.apply(0, Action::MatchTopOpp { target: None, opp_index: 0, give_index: 1 }).unwrap();
+        assert_eq!(events, vec![Event::OppMatchAttempted { seat: 0, target: 1, opp_index: 0, give_index: 1, matched: true }]);
+        assert_eq!(game.seats[0].slots, vec![Some(card(Rank::Ace, Suit::Clubs)), None]);
+        assert_eq!(game.seats[1].slots, vec![Some(card(Rank::Two, Suit::Clubs))]);
+    }
+
+    #[test]
+    fn matching_away_the_last_card_wins() {
+        let mut game = game();
+        game.discard.push(card(Rank::Six, Suit::Clubs));
+        set_hand(&mut game, 1, &[card(Rank::Six, Suit::Hearts)]);
+        let events = game.apply(1, Action::MatchTop { index: 0 }).unwrap();
+        let result = game.result.clone().unwrap();
+        assert_eq!(result.winner, Some(1));
+        assert_eq!(result.reason, FinishReason::EmptyHand { seat: 1 });
+        assert_eq!(events.last(), Some(&Event::Finished(result)));
+        assert!(game.is_finished());
+    }
+
+    #[test]
+    fn zobbo_reveals_after_everyone_else_has_played_once_more() {
+        let mut game = game();
+        set_hand(&mut game, 0, &[card(Rank::Ace, Suit::Clubs), card(Rank::Two, Suit::Clubs)]);
+        set_hand(&mut game, 1, &[card(Rank::Ten, Suit::Clubs)]);
+        assert_eq!(game.apply(0, Action::CallZobbo).unwrap(), vec![Event::ZobboCalled { seat: 0 }]);
+        assert_eq!(game.zobbo, Some(ZobboCall { caller: 0, remaining: 2 }));
+        assert_eq!(game.apply(0, Action::CallZobbo), Err(RuleError::ZobboAlreadyCalled));
+        pass(&mut game, 0);
+        assert!(!game.is_finished());
+        pass(&mut game, 1);
+        let result = game.result.unwrap();
+        assert_eq!(result.reason, FinishReason::Zobbo);
+        assert_eq!((result.winner, result.zobbo_caller, result.zobbo_penalty), (Some(0), Some(0), None));
+        assert_eq!(result.scores, vec![3, 10]);
+    }
+
+    #[test]
+    fn a_zobbo_caller_without_the_lowest_hand_is_penalised() {
+        let mut game = game();
+        set_hand(&mut game, 0, &[card(Rank::Five, Suit::Clubs)]);
+        set_hand(&mut game, 1, &[card(Rank::Five, Suit::Hearts)]);
+        game.apply(0, Action::CallZobbo).unwrap();
+        pass(&mut game, 0);
+        pass(&mut game, 1);
+        let result = game.result.unwrap();
+        // A tie isn't the strictly lowest hand.
+        assert_eq!((result.winner, result.zobbo_penalty), (Some(1), Some(10)));
+        assert_eq!(result.scores, vec![15, 5]);
+    }
+
+    #[test]
+    fn zobbo_is_called_before_drawing() {
+        let mut game = game();
+        game.apply(0, Action::DrawDeck).unwrap();
+        assert_eq!(game.apply(0, Action::CallZobbo), Err(RuleError::WrongStage));
+    }
+
+    #[test]
+    fn forfeit_hands_the_game_to_the_best_of_the_rest() {
+        let mut game = game_with(3, RuleConfig::default());
+        set_hand(&mut game, 0, &[card(Rank::Ace, Suit::Clubs)]);
+        set_hand(&mut game, 1, &[card(Rank::Nine, Suit::Clubs)]);
+        set_hand(&mut game, 2, &[card(Rank::Four, Suit::Clubs)]);
+        game.forfeit(FinishReason::Forfeit { seat: 0 });
+        let result = game.result.clone().unwrap();
+        assert_eq!((result.winner, result.reason), (Some(2), FinishReason::Forfeit { seat: 0 }));
+        // Only the first ending counts.
+        game.forfeit(FinishReason::OutOfTime { seat: 2 });
+        assert_eq!(game.result, Some(result));
+    }
+
+    #[test]
+    fn forfeit_in_a_two_seat_game_goes_to_the_other_seat() {
+        let mut game = game();
+        set_hand(&mut game, 0, &[card(Rank::Ace, Suit::Clubs)]);
+        set_hand(&mut game, 1, &[card(Rank::Queen, Suit::Clubs)]);
+        game.forfeit(FinishReason::Inactive { seat: 0 });
+        assert_eq!(game.result.unwrap().winner, Some(1));
+    }
+
+    #[test]
+    fn a_draw_ends_the_game_once_everyone_agrees() {
+        let mut game = game();
+        assert_eq!(game.apply(1, Action::AcceptDraw), Err(RuleError::NoDrawOffer));
+        game.apply(0, Action::CallZobbo).unwrap();
+        assert_eq!(game.apply(1, Action::OfferDraw).unwrap(), vec![Event::DrawAgreed { seat: 1 }]);
+        assert_eq!(game.draw_agreed, vec![1]);
+        let events = game.apply(0, Action::AcceptDraw).unwrap();
+        let result = game.result.clone().unwrap();
+        assert_eq!(events, vec![Event::Finished(result.clone())]);
+        assert_eq!((result.winner, result.reason, result.zobbo_penalty), (None, FinishReason::AgreedDraw, None));
+    }
+
+    #[test]
+    fn a_draw_offer_can_be_declined_or_withdrawn() {
+        let mut game = game();
+        game.apply(0, Action::OfferDraw).unwrap();
+        assert_eq!(game.apply(1, Action::DeclineDraw).unwrap(), vec![Event::DrawDeclined { seat: 1 }]);
+        assert!(game.draw_agreed.is_empty());
+        assert_eq!(game.apply(1, Action::DeclineDraw), Err(RuleError::NoDrawOffer));
+
+        game.apply(0, Action::OfferDraw).unwrap();
+        game.apply(0, Action::DeclineDraw).unwrap();
+        assert_eq!(game.apply(1, Action::AcceptDraw), Err(RuleError::NoDrawOffer));
+    }
+
+    #[test]
+    fn a_draw_offer_lapses_when_a_seat_plays_on() {
+        let mut game = game();
+        game.apply(0, Action::OfferDraw).unwrap();
+        // The offerer's own moves leave it standing.
+        pass(&mut game, 0);
+        assert_eq!(game.draw_agreed, vec![0]);
+        let events = game.apply(1, Action::DrawDeck).unwrap();
+        assert_eq!(events.last(), Some(&Event::DrawDeclined { seat: 1 }));
+        assert!(game.draw_agreed.is_empty());
+        assert_eq!(game.apply(1, Action::AcceptDraw), Err(RuleError::NoDrawOffer));
+    }
+
+    #[test]
+    fn a_draw_offer_lapses_when_the_turn_runs_out() {
+        let mut game = game();
+        game.apply(1, Action::OfferDraw).unwrap();
+        game.expire_turn();
+        assert!(game.draw_agreed.is_empty());
+    }
+
+    #[test]
+    fn expire_turn_discards_the_held_card() {
+        let mut game = game();
+        let drawn = card(Rank::Three, Suit::Clubs);
+        stack(&mut game, drawn);
+        game.apply(0, Action::DrawDeck).unwrap();
+        game.expire_turn();
+        assert_eq!(game.discard_top(), Some(drawn));
+        assert_eq!((game.active, &game.stage), (1, &TurnStage::Draw));
+    }
+
+    #[test]
+    fn an_empty_deck_is_reshuffled_from_the_discard_pile() {
+        let mut game = game();
+        let mut pile = std::mem::take(&mut game.deck);
+        let top = card(Rank::Two, Suit::Hearts);
+        pile.push(top);
+        game.discard = pile;
+        let size = game.discard.len();
+        game.apply(0, Action::DrawDeck).unwrap();
+        assert_eq!(game.discard, vec![top]);
+        assert_eq!(game.deck.len(), size - 2);
+    }
+
+    #[test]
+    fn out_of_turn_and_out_of_stage_moves_are_refused() {
+        let mut game = game();
+        assert_eq!(game.apply(1, Action::DrawDeck), Err(RuleError::NotYourTurn));
+        assert_eq!(game.apply(0, Action::SwapWithHand { index: 0 }), Err(RuleError::WrongStage));
+        assert_eq!(game.apply(0, Action::DiscardDrawn), Err(RuleError::WrongStage));
+        assert_eq!(game.apply(0, Action::PeekOwn { index: 0 }), Err(RuleError::WrongStage));
+        game.apply(0, Action::DrawDeck).unwrap();
+        assert_eq!(game.apply(0, Action::DrawDeck), Err(RuleError::WrongStage));
+        assert_eq!(game.apply(0, Action::SwapWithHand { index: 9 }), Err(RuleError::BadIndex));
+    }
+
+    #[test]
+    fn nothing_is_allowed_once_the_game_is_over() {
+        let mut game = game();
+        game.forfeit(FinishReason::Forfeit { seat: 1 });
+        for action in [Action::DrawDeck, Action::MatchTop { index: 0 }, Action::OfferDraw, Action::DeclineDraw] {
+            assert_eq!(game.apply(0, action), Err(RuleError::GameOver));
+        }
+        assert_eq!(game.apply(1, Action::CallZobbo), Err(RuleError::GameOver));
+    }
+
+    #[test]
+    fn lowest_unique_needs_a_clear_winner() {
+        assert_eq!(lowest_unique(&[4, 2, 7]), Some(1));
+        assert_eq!(lowest_unique(&[2, 2, 7]), None);
+        assert_eq!(lowest_unique(&[]), None);
+    }
+}
@@ -0,0 +1,9 @@
+//! Zobbo rules engine: cards, game state and every state transition.
+//!
+//! Pure and I/O-free: nothing here knows about sockets, rooms or timers, so
+//! the same engine drives the server, bots and anything else that needs the
+//! rules.
+
+pub mod engine;
+pub mod log;
+pub mod types;
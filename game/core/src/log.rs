@@ -0,0 +1,243 @@
+//! Game logs: everything that moved a game, in order.
+//!
+//! A log starts with the deal's seed, so together with the actions it pins
+//! down the whole game, hidden cards included; `replay` plays it back, and
+//! `GameExport` carries just that much to check a game elsewhere.
+
+use serde::{Deserialize, Serialize};
+use zobbo_protocol::{FinishReason, RuleConfig};
+
+use crate::engine::{GameResult, GameState, RuleError};
+use crate::types::{Action, Event};
+
+/// One entry in a game's log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GameEvent {
+    /// The deal: `GameState::new` with this `seed`.
+    Dealt { players: usize, rules: RuleConfig, seed: u64 },
+    /// `seat` played `action`, which `GameState::apply` accepted with `events`.
+    Acted { seat: usize, action: Action, events: Vec<Event> },
+    /// The active seat ran out of time (`GameState::expire_turn`).
+    TurnExpired { seat: usize },
+    /// The game was ended from outside the rules (`GameState::forfeit`).
+    Forfeited { reason: FinishReason },
+}
+
+/// A `GameEvent` and when it happened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// Unix milliseconds.
+    #[serde(default)]
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub event: GameEvent,
+}
+
+/// Why a log doesn't play back.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayError {
+    #[error("the log doesn't start with the deal")]
+    NoDeal,
+    #[error("entry {index} deals a second game")]
+    Redealt { index: usize },
+    #[error("entry {index} was refused: {source}")]
+    Refused { index: usize, source: RuleError },
+    #[error("entry {index} played out differently than recorded")]
+    Diverged { index: usize },
+    #[error("entry {index} names a seat the game doesn't have")]
+    BadSeat { index: usize },
+    #[error("the game doesn't end with the recorded result")]
+    WrongResult,
+}
+
+/// Play `log` back from the deal, checking every action produces the events
+/// recorded with it. Returns the state after each entry, the deal included.
+pub fn replay(log: &[LogEntry]) -> Result<Vec<GameState>, ReplayError> {
+    let Some(GameEvent::Dealt { players, rules, seed }) = log.first().map(|e| &e.event) else {
+        return Err(ReplayError::NoDeal);
+    };
+    let mut game = GameState::new(*players, *rules, *seed);
+    let mut states = vec![game.clone()];
+    for (index, entry) in log.iter().enumerate().skip(1) {
+        match &entry.event {
+            GameEvent::Dealt { .. } => return Err(ReplayError::Redealt { index }),
+            GameEvent::Acted { seat, action, events } => {
+                check_seat(&game, *seat, index)?;
+                let replayed = game.apply(*seat, *action).map_err(|source| ReplayError::Refused { index, source })?;
+                if replayed != *events { return Err(ReplayError::Diverged { index }); }
+            }
+            GameEvent::TurnExpired { seat } => expire(&mut game, *seat, index)?,
+            GameEvent::Forfeited { reason } => forfeit(&mut game, *reason, index)?,
+        }
+        states.push(game.clone());
+    }
+    Ok(states)
+}
+
+fn expire(game: &mut GameState, seat: usize, index: usize) -> Result<(), ReplayError> {
+    check_seat(game, seat, index)?;
+    if game.active != seat { return Err(ReplayError::Diverged { index }); }
+    game.expire_turn();
+    Ok(())
+}
+
+fn forfeit(game: &mut GameState, reason: FinishReason, index: usize) -> Result<(), ReplayError> {
+    match reason {
+        FinishReason::Zobbo | FinishReason::AgreedDraw => {}
+        FinishReason::EmptyHand { seat }
+        | FinishReason::Forfeit { seat }
+        | FinishReason::Inactive { seat }
+        | FinishReason::OutOfTime { seat } => check_seat(game, seat, index)?,
+    }
+    game.forfeit(reason);
+    Ok(())
+}
+
+/// The engine indexes seats without checking them, so a log from elsewhere
+/// has every seat it names checked first.
+fn check_seat(game: &GameState, seat: usize, index: usize) -> Result<(), ReplayError> {
+    if seat >= game.seats.len() { return Err(ReplayError::BadSeat { index }); }
+    Ok(())
+}
+
+/// One move in a `GameExport`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Move {
+    /// `(seat, action)`.
+    Act(usize, Action),
+    /// The seat ran out of time.
+    Expire(usize),
+    Forfeit(FinishReason),
+}
+
+/// A finished game boiled down to what reproduces it: the deal, the moves
+/// and the result to check against. Entry timings and events are left out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameExport {
+    pub players: usize,
+    pub rules: RuleConfig,
+    pub seed: u64,
+    pub moves: Vec<Move>,
+    pub result: Option<GameResult>,
+}
+
+impl GameExport {
+    pub fn from_log(log: &[LogEntry], result: Option<GameResult>) -> Result<Self, ReplayError> {
+        let Some(GameEvent::Dealt { players, rules, seed }) = log.first().map(|e| &e.event) else {
+            return Err(ReplayError::NoDeal);
+        };
+        let moves = log
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(index, entry)| match &entry.event {
+                GameEvent::Dealt { .. } => Err(ReplayError::Redealt { index }),
+                GameEvent::Acted { seat, action, .. } => Ok(Move::Act(*seat, *action)),
+                GameEvent::TurnExpired { seat } => Ok(Move::Expire(*seat)),
+                GameEvent::Forfeited { reason } => Ok(Move::Forfeit(*reason)),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(GameExport { players: *players, rules: *rules, seed: *seed, moves, result })
+    }
+
+    /// Deal and play every move again, checking the game ends with `result`.
+    /// Errors count moves from 1, as log entries after the deal.
+    pub fn verify(&self) -> Result<GameState, ReplayError> {
+        let mut game = GameState::new(self.players, self.rules, self.seed);
+        for (index, m) in self.moves.iter().enumerate().map(|(i, m)| (i + 1, m)) {
+            match *m {
+                Move::Act(seat, action) => {
+                    check_seat(&game, seat, index)?;
+                    game.apply(seat, action).map_err(|source| ReplayError::Refused { index, source })?;
+                }
+                Move::Expire(seat) => expire(&mut game, seat, index)?,
+                Move::Forfeit(reason) => forfeit(&mut game, reason, index)?,
+            }
+        }
+        if game.result != self.result { return Err(ReplayError::WrongResult); }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::TurnStage;
+
+    const SEED: u64 = 2048;
+
+    /// A two-seat game played to the end: the first seat to play calls
+    /// Zobbo, then everyone draws and discards, skipping any power.
+    fn played() -> (Vec<LogEntry>, GameState) {
+        let (players, rules) = (2, RuleConfig::default());
+        let mut game = GameState::new(players, rules, SEED);
+        let mut log = vec![LogEntry { at_ms: 0, event: GameEvent::Dealt { players, rules, seed: SEED } }];
+        let mut act = |game: &mut GameState, action| {
+            let seat = game.active;
+            let events = game.apply(seat, action).unwrap();
+            log.push(LogEntry { at_ms: 0, event: GameEvent::Acted { seat, action, events } });
+        };
+        act(&mut game, Action::CallZobbo);
+        while !game.is_finished() {
+            act(&mut game, Action::DrawDeck);
+            act(&mut game, Action::DiscardDrawn);
+            if matches!(game.stage, TurnStage::Power(_)) {
+                act(&mut game, Action::SkipPower);
+            }
+        }
+        (log, game)
+    }
+
+    #[test]
+    fn replay_plays_the_log_back() {
+        let (log, game) = played();
+        let states = replay(&log).unwrap();
+        assert_eq!(states.len(), log.len());
+        assert_eq!(states.last().unwrap().result, game.result);
+    }
+
+    #[test]
+    fn replay_catches_a_log_that_diverges() {
+        let (mut log, _) = played();
+        let GameEvent::Acted { events, .. } = &mut log[2].event else { panic!("a move") };
+        events.clear();
+        assert_eq!(replay(&log).unwrap_err(), ReplayError::Diverged { index: 2 });
+        assert_eq!(replay(&log[1..]).unwrap_err(), ReplayError::NoDeal);
+    }
+
+    #[test]
+    fn export_round_trips_and_verifies() {
+        let (log, game) = played();
+        let export = GameExport::from_log(&log, game.result.clone()).unwrap();
+        assert_eq!(export.moves.len(), log.len() - 1);
+        let json = serde_json::to_string(&export).unwrap();
+        let back: GameExport = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, export);
+        assert_eq!(back.verify().unwrap().result, game.result);
+
+        let claimed = GameExport { result: None, ..export };
+        assert_eq!(claimed.verify().unwrap_err(), ReplayError::WrongResult);
+    }
+
+    #[test]
+    fn malformed_moves_are_refused() {
+        let (log, game) = played();
+        let export = GameExport::from_log(&log, game.result).unwrap();
+        let with = |m: Move| GameExport { moves: vec![m], ..export.clone() };
+        for m in [
+            Move::Act(9, Action::MatchTop { index: 0 }),
+            Move::Act(9, Action::MatchTopOpp { target: Some(0), opp_index: 0, give_index: 0 }),
+            Move::Act(2, Action::OfferDraw),
+            Move::Expire(9),
+            Move::Forfeit(FinishReason::Forfeit { seat: 9 }),
+            Move::Forfeit(FinishReason::EmptyHand { seat: 2 }),
+        ] {
+            assert_eq!(with(m).verify().unwrap_err(), ReplayError::BadSeat { index: 1 }, "{m:?}");
+        }
+        let first = GameState::new(export.players, export.rules, export.seed).active;
+        let out_of_turn = with(Move::Act(1 - first, Action::DrawDeck));
+        assert!(matches!(out_of_turn.verify(), Err(ReplayError::Refused { index: 1, .. })));
+    }
+}
@@ -0,0 +1,155 @@
+//! Core types: cards, actions, events.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use zobbo_protocol::ClientToServer;
+pub use zobbo_protocol::{CardPublic, DrawSource, Power, PowerTable, Rank, Suit};
+
+use crate::engine::GameResult;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Card {
+    pub rank: Rank,
+    pub suit: Suit,
+}
+
+/// Point value of a card: Joker −1, Black K 0, A 1, 2–10 face value, J 11, Q 12, Red K 13.
+pub fn rank_points(card: Card) -> i32 {
+    match card.rank {
+        Rank::Ace => 1,
+        Rank::Two => 2,
+        Rank::Three => 3,
+        Rank::Four => 4,
+        Rank::Five => 5,
+        Rank::Six => 6,
+        Rank::Seven => 7,
+        Rank::Eight => 8,
+        Rank::Nine => 9,
+        Rank::Ten => 10,
+        Rank::Jack => 11,
+        Rank::Queen => 12,
+        Rank::King if card.suit.is_red() => 13,
+        Rank::King => 0,
+        Rank::Joker => -1,
+    }
+}
+
+pub fn card_public(card: Card) -> CardPublic {
+    CardPublic { rank: card.rank, suit: card.suit, points: rank_points(card) }
+}
+
+/// A shuffled 52-card deck, plus a red and a black joker if `jokers`. The
+/// top of the deck is the end of the vec.
+pub fn build_deck(rng: &mut impl Rng, jokers: bool) -> Vec<Card> {
+    let mut deck: Vec<Card> = Suit::ALL
+        .iter()
+        .flat_map(|&suit| Rank::ALL.iter().map(move |&rank| Card { rank, suit }))
+        .collect();
+    if jokers {
+        deck.extend([Suit::Hearts, Suit::Spades].map(|suit| Card { rank: Rank::Joker, suit }));
+    }
+    deck.shuffle(rng);
+    deck
+}
+
+/// A player's move, as accepted by `GameState::apply`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Action {
+    DrawDeck,
+    DrawDiscard,
+    SwapWithHand { index: usize },
+    DiscardDrawn,
+    PeekOwn { index: usize },
+    /// `target: None` means the next seat.
+    PeekOpp { target: Option<usize>, index: usize },
+    SwapOwnWithDeck { index: usize },
+    BlindSwap { own_index: usize, target: Option<usize>, opp_index: usize },
+    SwapOppWithDeck { target: Option<usize>, opp_index: usize },
+    SkipPower,
+    MatchTop { index: usize },
+    MatchTopOpp { target: Option<usize>, opp_index: usize, give_index: usize },
+    CallZobbo,
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+}
+
+impl Action {
+    /// The game action a client message asks for; `None` for lobby, chat and
+    /// housekeeping messages.
+    pub fn from_client(msg: &ClientToServer) -> Option<Self> {
+        Some(match *msg {
+            ClientToServer::DrawDeck => Action::DrawDeck,
+            ClientToServer::DrawDiscard => Action::DrawDiscard,
+            ClientToServer::SwapWithHand { index } => Action::SwapWithHand { index },
+            ClientToServer::DiscardDrawn => Action::DiscardDrawn,
+            ClientToServer::PeekOwn { index } => Action::PeekOwn { index },
+            ClientToServer::PeekOpp { index, target } => Action::PeekOpp { target, index },
+            ClientToServer::SwapOwnWithDeck { index } => Action::SwapOwnWithDeck { index },
+            ClientToServer::BlindSwap { own_index, opp_index, target } => {
+                Action::BlindSwap { own_index, target, opp_index }
+            }
+            ClientToServer::SwapOppWithDeck { opp_index, target } => Action::SwapOppWithDeck { target, opp_index },
+            ClientToServer::SkipPower => Action::SkipPower,
+            ClientToServer::MatchTop { index } => Action::MatchTop { index },
+            ClientToServer::MatchTopOpp { opp_index, give_index, target } => {
+                Action::MatchTopOpp { target, opp_index, give_index }
+            }
+            ClientToServer::CallZobbo => Action::CallZobbo,
+            ClientToServer::OfferDraw => Action::OfferDraw,
+            ClientToServer::AcceptDraw => Action::AcceptDraw,
+            ClientToServer::DeclineDraw => Action::DeclineDraw,
+            ClientToServer::Hello { .. }
+            | ClientToServer::Ready
+            | ClientToServer::RequestPause
+            | ClientToServer::Resume
+            | ClientToServer::Resync
+            | ClientToServer::KickPlayer { .. }
+            | ClientToServer::UpdateSettings { .. }
+            | ClientToServer::SetHandicap { .. }
+            | ClientToServer::Chat { .. }
+            | ClientToServer::RequestState { .. }
+            | ClientToServer::Ping => return None,
+        })
+    }
+    /// The power this action uses, if it's one.
+    pub fn power(&self) -> Option<Power> {
+        match self {
+            Action::PeekOwn { .. } => Some(Power::PeekOwn),
+            Action::PeekOpp { .. } => Some(Power::PeekOpp),
+            Action::SwapOwnWithDeck { .. } => Some(Power::SwapOwnWithDeck),
+            Action::BlindSwap { .. } => Some(Power::BlindSwap),
+            Action::SwapOppWithDeck { .. } => Some(Power::SwapOppWithDeck),
+            _ => None,
+        }
+    }
+}
+
+/// Something that happened while applying an `Action`. Public state changes
+/// are visible on `GameState` itself; events carry what a host must route,
+/// in particular the private ones.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    /// Private to `seat`: the card it now holds.
+    Drawn { seat: usize, card: Card, from: DrawSource },
+    /// Private to `viewer`: `owner`'s slot `index` holds `card` as of `version`.
+    Peeked { viewer: usize, owner: usize, index: usize, card: Card, version: u32 },
+    /// `seat` discarded a power card and may now use `power`.
+    PowerOffered { seat: usize, power: Power },
+    MatchAttempted { seat: usize, index: usize, matched: bool },
+    /// `seat` tried to match `target`'s `opp_index`; on a match, its own
+    /// `give_index` now sits in that slot.
+    OppMatchAttempted { seat: usize, target: usize, opp_index: usize, give_index: usize, matched: bool },
+    ZobboCalled { seat: usize },
+    /// `seat` offered or accepted a draw that is still short of everyone.
+    DrawAgreed { seat: usize },
+    /// `seat` turned down or withdrew the draw offer, or let it lapse by
+    /// playing on.
+    DrawDeclined { seat: usize },
+    /// The reveal happened; the game is over.
+    Finished(GameResult),
+}
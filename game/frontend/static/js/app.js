@@ -1 +1,709 @@
-// Alpine/HTMX integration hooks will be added later.
+// Alpine component for the room page: owns the game WebSocket and renders
+// the lobby/table from server messages. Registered before Alpine starts.
+
+/** @typedef {import('./protocol').ServerToClient} ServerToClient */
+/** @typedef {import('./protocol').ClientToServer} ClientToServer */
+
+// Schema revision this file was written against; see `PROTOCOL_VERSION` in the protocol crate.
+const PROTOCOL_VERSION = 32;
+
+const SUITS = { clubs: '♣', diamonds: '♦', hearts: '♥', spades: '♠' };
+
+const POWER_HINTS = {
+  peek_own: 'pick one of your cards to look at',
+  peek_opp: "pick one of an opponent's cards to look at",
+  swap_own_with_deck: 'pick one of your cards to swap with the deck',
+  blind_swap: "pick one of your cards, then one of an opponent's",
+  swap_opp_with_deck: "pick one of an opponent's cards to swap with the deck",
+};
+
+// Human-readable `GameMode`.
+function describeMode(mode) {
+  if (mode.kind === 'zobbo_battle') return `Zobbo Battle, ${mode.rounds} rounds`;
+  if (mode.kind === 'best_of') return `Best of ${mode.rounds}`;
+  if (mode.kind === 'race_to') return `Race to ${mode.points} points`;
+  return 'Single game';
+}
+
+// Fold a `game_delta` into a copy of `game`: absent fields are unchanged,
+// `null` clears them.
+function applyDelta(game, delta) {
+  const next = { ...game, seats: game.seats.map((s) => ({ ...s, slots: [...s.slots] })) };
+  for (const key of Object.keys(delta)) {
+    if (key !== 'type' && key !== 'slots' && key !== 'skip_next') next[key] = delta[key];
+  }
+  for (const c of delta.slots || []) next.seats[c.seat].slots[c.index] = c.slot;
+  (delta.skip_next || []).forEach((skip, seat) => { next.seats[seat].skip_next = skip; });
+  return next;
+}
+
+// `GameUpdate::state_hash`: FNV-1a over the active seat, the pile counts,
+// the stage's name and every slot, the way the server packs them.
+function stateHash(game) {
+  let hash = 0x811c9dc5;
+  const feed = (byte) => { hash = Math.imul(hash ^ byte, 0x01000193) >>> 0; };
+  const u32 = (n) => { for (let i = 0; i < 4; i++) feed((n >>> (8 * i)) & 0xff); };
+  [game.active, game.deck_count, game.discard_count].forEach(u32);
+  for (const c of game.stage) feed(c.charCodeAt(0));
+  for (const seat of game.seats) {
+    for (const slot of seat.slots) {
+      feed(slot.filled ? 1 : 0);
+      u32(slot.version);
+    }
+  }
+  return hash;
+}
+
+// This browser's guest identity, `{ player_id, name, token }`, kept in localStorage.
+// Checked with the server on every call, so a token it no longer accepts is
+// swapped for a fresh identity; pass `name` to rename.
+async function playerIdentity(name) {
+  let held = null;
+  try { held = JSON.parse(localStorage.getItem('zobbo.identity')); } catch (_) { /* start over */ }
+  const post = (body) => fetch('/api/identity', {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(body),
+  });
+  let res = await post({ token: held ? held.token : null, name });
+  if (res.status === 401) res = await post({ name });
+  // A refused name comes back as `{ code, message }`.
+  if (res.status === 422) throw new Error((await res.json()).message);
+  if (!res.ok) throw new Error(await res.text());
+  const identity = await res.json();
+  localStorage.setItem('zobbo.identity', JSON.stringify(identity));
+  return identity;
+}
+
+document.addEventListener('alpine:init', () => {
+  Alpine.data('zobboRoom', () => ({
+    roomId: '',
+    token: '',
+    // Our guest identity token, if we could get one; names us to the other seats.
+    identity: null,
+    reconnect: null,
+    // Milliseconds to wait before the next reconnect, when the server said how long.
+    retryAfter: 0,
+    seat: null,
+    status: 'connecting…',
+    // Set when the server turns us away for good (outdated script, kicked, or the room closed).
+    outdated: false,
+    error: '',
+    players: [],
+    started: false,
+    // Lobby settings as the server last reported them; only the host may change them.
+    host: null,
+    mode: { kind: 'single' },
+    turnSecs: null,
+    bankSecs: null,
+    // `YYYY-MM-DD` of the daily challenge this room deals, if it's one.
+    daily: null,
+    ranked: false,
+    settingsForm: { rounds: 1, turn_secs: 0 },
+    // `seat` is '' for no handicap.
+    handicapForm: { seat: '', kind: 'extra_card' },
+    game: null,
+    // Highest GameUpdate.seq seen; a jump means we missed something.
+    lastSeq: 0,
+    held: null,
+    // `${owner}:${index}` -> { version, card }; only valid while the slot version matches.
+    peeks: {},
+    result: null,
+    // The current or last game's id, which names its log once it's over.
+    gameId: '',
+    // Once loaded from `/api/replay`: the finished game's frames and which one is shown.
+    replay: null,
+    // seat -> Unix seconds it forfeits at, for seats that dropped mid-game.
+    away: {},
+    // Announcement from the latest `zobbo_called`, until the next deal.
+    zobboBanner: '',
+    // Tutorial rooms: the latest `tutorial_hint`, until the next deal.
+    hint: null,
+    // ZobboBattle progress; `rounds` stays 1 for single games.
+    round: 1,
+    rounds: 1,
+    totals: null,
+    // Latest `scoreboard`: round wins so far, and how many take a best-of match.
+    wins: null,
+    winsNeeded: null,
+    // The room's house rules, as of the last lobby update or deal.
+    rules: { discard_draw: true, powers: true },
+    betweenRounds: false,
+    matchOver: null,
+    chat: [],
+    chatText: '',
+    matching: false,
+    picked: null,
+    // While matching: the opponent's `{ target, index }` to match, before picking a card to give them.
+    gift: null,
+    // Unix seconds, ticked so the turn timer re-renders.
+    now: Date.now() / 1000,
+    // When the last update's `time_bank_ms` was received, in Unix seconds.
+    bankAt: 0,
+
+    async init() {
+      this.roomId = this.$el.dataset.roomId;
+      this.token = this.$el.dataset.token;
+      this.identity = await playerIdentity().then((id) => id.token, () => null);
+      setInterval(() => { this.now = Date.now() / 1000; }, 500);
+      // A sleeping tab may have missed frames; ask the server if we're behind.
+      document.addEventListener('visibilitychange', () => {
+        if (!document.hidden) this.send({ type: 'request_state', last_seq: this.lastSeq });
+      });
+      this.connect();
+    },
+
+    // Swap our tokens for a one-time ticket, so they stay out of the socket's URL.
+    async wsTicket() {
+      const post = (seat) => fetch(`/api/rooms/${this.roomId}/ws-ticket`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ ...seat, identity: this.identity }),
+      });
+      // Reconnect tokens are single-use, so fall back to the join token if one fails.
+      let res = this.reconnect ? await post({ reconnect: this.reconnect }) : null;
+      this.reconnect = null;
+      if (!res || res.status === 401) res = await post({ token: this.token });
+      if (!res.ok) throw new Error(await res.text());
+      return (await res.json()).ticket;
+    },
+
+    async connect() {
+      let ticket;
+      try {
+        ticket = await this.wsTicket();
+      } catch (_) {
+        this.status = 'disconnected, retrying…';
+        setTimeout(() => this.connect(), this.retryAfter || 1500);
+        this.retryAfter = 0;
+        return;
+      }
+      const scheme = location.protocol === 'https:' ? 'wss' : 'ws';
+      const ws = new WebSocket(`${scheme}://${location.host}/ws?room_id=${this.roomId}&ticket=${ticket}`, ['zobbo.v1']);
+      ws.onopen = () => this.send({ type: 'hello', version: PROTOCOL_VERSION, capabilities: ['game_delta'] });
+      ws.onmessage = (e) => this.onMessage(JSON.parse(e.data));
+      ws.onclose = () => {
+        if (this.outdated) return;
+        if (!this.retryAfter) this.status = 'disconnected, retrying…';
+        setTimeout(() => this.connect(), this.retryAfter || 1500);
+        this.retryAfter = 0;
+      };
+      this.ws = ws;
+    },
+
+    /** @param {ClientToServer} msg */
+    send(msg) {
+      if (this.ws && this.ws.readyState === WebSocket.OPEN) this.ws.send(JSON.stringify(msg));
+    },
+
+    /** @param {ServerToClient} msg */
+    onMessage(msg) {
+      switch (msg.type) {
+        case 'welcome':
+          // History is replayed after every (re)connect.
+          this.chat = [];
+          this.seat = msg.seat;
+          this.reconnect = msg.reconnect_token;
+          this.status = 'connected';
+          sessionStorage.removeItem('zobbo-reloaded');
+          break;
+        case 'unsupported_version':
+          // This page's script is older than the server accepts: reload once for a fresh copy.
+          this.status = 'this page is out of date, please reload';
+          this.outdated = true;
+          if (!sessionStorage.getItem('zobbo-reloaded')) {
+            sessionStorage.setItem('zobbo-reloaded', '1');
+            location.reload();
+          }
+          break;
+        case 'reconnect_token':
+          this.reconnect = msg.token;
+          break;
+        case 'lobby_state':
+          this.players = msg.players;
+          for (const p of msg.players) if (p.connected) delete this.away[p.seat];
+          this.started = msg.started;
+          this.host = msg.host;
+          this.mode = msg.mode;
+          this.turnSecs = msg.turn_secs;
+          this.bankSecs = msg.time_bank_secs;
+          this.daily = msg.daily;
+          this.ranked = msg.ranked;
+          this.rules = msg.rules;
+          this.settingsForm = {
+            rounds: msg.mode.rounds || 1,
+            turn_secs: msg.turn_secs || 0,
+          };
+          this.handicapForm = msg.rules.handicap
+            ? { ...msg.rules.handicap }
+            : { seat: '', kind: this.handicapForm.kind };
+          break;
+        case 'kicked':
+          // Our token is dead now, so don't keep reconnecting with it.
+          this.status = 'the host removed you from this room';
+          this.outdated = true;
+          break;
+        case 'room_closed':
+          this.status = 'this room has been closed';
+          this.outdated = true;
+          break;
+        case 'server_shutdown':
+          // The game is kept, but reconnect tokens don't survive the restart.
+          this.status = 'server restarting, reconnecting shortly…';
+          this.reconnect = null;
+          this.retryAfter = msg.reconnect_after_secs * 1000;
+          break;
+        case 'game_start':
+          this.gameId = msg.game_id;
+          this.replay = null;
+          this.peeks = {};
+          this.held = null;
+          this.result = null;
+          this.zobboBanner = '';
+          this.hint = null;
+          this.round = msg.round;
+          this.rounds = msg.rounds;
+          this.rules = msg.rules;
+          this.betweenRounds = false;
+          if (msg.round === 1) {
+            this.totals = null;
+            this.wins = null;
+            this.matchOver = null;
+          }
+          break;
+        case 'game_update':
+          if (this.lastSeq && msg.seq > this.lastSeq + 1) {
+            this.send({ type: 'request_state', last_seq: this.lastSeq });
+          }
+          this.setGame(msg);
+          break;
+        case 'game_delta': {
+          // Deltas only apply on top of the update right before them.
+          if (!this.game || msg.seq !== this.lastSeq + 1) {
+            this.send({ type: 'request_state', last_seq: this.lastSeq });
+            break;
+          }
+          const next = applyDelta(this.game, msg);
+          // A board that hashes differently from the server's has drifted.
+          if (msg.checksum !== undefined && stateHash(next) !== next.checksum) {
+            this.send({ type: 'request_state', last_seq: this.lastSeq });
+            break;
+          }
+          this.setGame(next);
+          break;
+        }
+        case 'opponent_disconnected':
+          this.away[msg.seat] = msg.deadline;
+          break;
+        case 'zobbo_called':
+          this.zobboBanner = `${msg.caller === this.seat ? 'You' : this.who(msg.caller)} called Zobbo! `
+            + `${msg.turns_remaining} final turn(s), then the reveal.`;
+          break;
+        case 'drawn':
+          this.held = msg.card;
+          break;
+        case 'peek_result':
+          this.peeks[`${msg.owner}:${msg.index}`] = { version: msg.version, card: msg.card };
+          break;
+        case 'game_over':
+          this.result = msg;
+          break;
+        case 'round_result':
+          this.totals = msg.totals;
+          // Reaching a race's limit ends the match early.
+          this.betweenRounds = msg.round < msg.rounds && !msg.eliminated.length;
+          break;
+        case 'scoreboard':
+          this.totals = msg.totals;
+          this.wins = msg.wins;
+          this.winsNeeded = msg.wins_needed;
+          break;
+        case 'match_over':
+          this.totals = msg.totals;
+          this.matchOver = msg;
+          break;
+        case 'tutorial_hint':
+          this.hint = msg;
+          break;
+        case 'chat':
+          this.chat.push(msg);
+          if (this.chat.length > 50) this.chat.shift();
+          break;
+        case 'action_rejected':
+        case 'error':
+          this.error = msg.message;
+          setTimeout(() => { if (this.error === msg.message) this.error = ''; }, 4000);
+          break;
+      }
+    },
+
+    // Whether the tutorial's hint points at `kind` (and for a slot, `seat`'s slot `index`).
+    hinted(kind, seat, index) {
+      const h = this.hint && this.hint.highlight;
+      return !!h && h.kind === kind && (kind !== 'slot' || (h.seat === seat && h.index === index));
+    },
+
+    setGame(game) {
+      this.lastSeq = game.seq;
+      this.game = game;
+      this.bankAt = Date.now() / 1000;
+      // A pause keeps whatever the active player was in the middle of.
+      if (game.stage !== 'holding' && game.stage !== 'paused') this.held = null;
+      if (game.stage !== 'power' && game.stage !== 'paused') this.picked = null;
+    },
+
+    isHost() { return this.seat !== null && this.host === this.seat; },
+
+    modeText() {
+      const mode = this.daily ? `Daily challenge ${this.daily}` : describeMode(this.mode);
+      const timer = this.turnSecs ? `, ${this.turnSecs}s turns` : '';
+      const bank = this.bankSecs ? `, ${this.bankSecs / 60} min each` : '';
+      return `${this.ranked ? 'Ranked: ' : ''}${mode}${timer}${bank}${this.rules.powers ? '' : ', no powers'}`;
+    },
+
+    // Every seat's rating after a ranked game, e.g. "Ratings: you 1216 (+16), Bob 1184 (−16)".
+    ratingText() {
+      const change = (r) => `${r.seat === this.seat ? 'you' : this.who(r.seat)} ${r.rating} (${r.delta < 0 ? '−' : '+'}${Math.abs(r.delta)})`;
+      return `Ratings: ${this.result.ratings.map(change).join(', ')}`;
+    },
+
+    updateSettings() {
+      this.send({ type: 'update_settings', ...this.settingsForm });
+    },
+
+    setHandicap() {
+      const { seat, kind } = this.handicapForm;
+      this.send({ type: 'set_handicap', handicap: seat === '' ? null : { seat: Number(seat), kind } });
+    },
+
+    // e.g. "(extra card)" beside the handicapped seat in the lobby.
+    handicapText(seat) {
+      const handicap = this.rules.handicap;
+      if (handicap?.seat !== seat) return '';
+      return handicap.kind === 'extra_card' ? '(starts with an extra card)' : '(sees an extra card at the start)';
+    },
+
+    sendChat() {
+      const text = this.chatText.trim();
+      if (!text) return;
+      this.send({ type: 'chat', text });
+      this.chatText = '';
+    },
+
+    me() { return this.game.seats[this.seat]; },
+    // Other seats in the order they play after us.
+    opponents() {
+      const n = this.game.seats.length;
+      return Array.from({ length: n - 1 }, (_, k) => (this.seat + 1 + k) % n);
+    },
+
+    who(s) {
+      if (s === this.seat) return 'You';
+      const player = this.players.find((p) => p.seat === s);
+      if (player && player.name) return player.name;
+      return this.players.length > 2 ? `Player ${s + 1}` : 'Opponent';
+    },
+
+    roundText() {
+      const of = this.mode.kind === 'race_to' ? ` (race to ${this.mode.points})` : ` of ${this.rounds}`;
+      return `Round ${this.round}${of}` + (this.totals ? ` — totals: ${this.totalsText()}` : '');
+    },
+    totalsText() {
+      const name = (s) => (s === this.seat ? 'you' : this.who(s));
+      const totals = this.totals.map((t, s) => `${name(s)} ${t}`).join(', ');
+      if (!this.winsNeeded || !this.wins) return totals;
+      const wins = this.wins.map((w, s) => `${name(s)} ${w}`).join(', ');
+      return `wins: ${wins} (first to ${this.winsNeeded}); points: ${totals}`;
+    },
+    myTurn() { return this.game && this.game.active === this.seat; },
+    canDraw() { return this.myTurn() && this.game.stage === 'draw'; },
+
+    known(owner, index) {
+      const p = this.peeks[`${owner}:${index}`];
+      const slot = this.game && this.game.seats[owner].slots[index];
+      return p && slot && p.version === slot.version ? p.card : null;
+    },
+
+    awayText() {
+      return Object.entries(this.away)
+        .map(([s, at]) => `${this.who(Number(s))} disconnected; they forfeit in ${Math.max(0, Math.ceil(at - this.now))}s unless they return.`)
+        .join(' ');
+    },
+    secondsLeft() {
+      return Math.max(0, Math.ceil(this.game.turn_deadline_unix - this.now));
+    },
+    // Every seat's time bank as m:ss, counting down the active seat's between updates.
+    bankText() {
+      const running = this.game.stage !== 'paused' && this.game.stage !== 'finished';
+      return this.game.time_bank_ms.map((ms, s) => {
+        const spent = running && s === this.game.active ? this.now - this.bankAt : 0;
+        const secs = Math.max(0, Math.ceil(ms / 1000 - spent));
+        const clock = `${Math.floor(secs / 60)}:${String(secs % 60).padStart(2, '0')}`;
+        return `${s === this.seat ? 'You' : this.who(s)} ${clock}`;
+      }).join(' · ');
+    },
+
+    label(card) {
+      // Jokers carry a suit only to tell the red one from the black one.
+      return `${card.rank === 'Joker' ? '🃏' : card.rank}${SUITS[card.suit]}`;
+    },
+
+    async loadReplay() {
+      const res = await fetch(`/api/replay/${this.gameId}`);
+      if (!res.ok) {
+        this.error = 'That game can no longer be reviewed.';
+        return;
+      }
+      this.replay = { frames: (await res.json()).frames, index: 0 };
+    },
+    replayFrame() { return this.replay.frames[this.replay.index]; },
+    replayStep(by) {
+      this.replay.index = Math.min(Math.max(this.replay.index + by, 0), this.replay.frames.length - 1);
+    },
+    replayText() {
+      const { update, at_ms } = this.replayFrame();
+      const top = update.discard_top ? this.label(update.discard_top) : 'nothing';
+      const secs = Math.round(at_ms / 1000);
+      return `Move ${this.replay.index} of ${this.replay.frames.length - 1} (${secs}s in): `
+        + `${this.who(update.active)} to ${update.stage === 'finished' ? 'reveal' : update.stage}, ${top} on the discard`;
+    },
+
+    turnText() {
+      const g = this.game;
+      let text = this.myTurn() ? 'Your turn' : `${this.who(g.active)}'s turn`;
+      if (this.myTurn() && g.stage === 'holding') text += ': swap into your hand' + (g.held_from === 'deck' ? ' or discard' : '');
+      if (this.myTurn() && g.stage === 'power') text += `: ${POWER_HINTS[g.power]}`;
+      if (g.zobbo_remaining != null) text += ` (Zobbo! ${g.zobbo_remaining} turn(s) left)`;
+      if (g.stage === 'paused') return 'Paused — anyone can resume';
+      const pauses = g.pause_requests.filter((s) => s !== this.seat);
+      if (pauses.length) text += ` (${pauses.map((s) => this.who(s)).join(', ')} asked to pause)`;
+      const offers = g.draw_agreed.filter((s) => s !== this.seat);
+      if (offers.length) text += ` (${offers.map((s) => this.who(s)).join(', ')} would agree a draw)`;
+      if (this.matching) text = 'Matching: pick the card, yours or an opponent\'s, that matches the discard';
+      if (this.matching && this.gift) text = `Matching: pick one of your cards to give ${this.who(this.gift.target)}`;
+      return text;
+    },
+
+    clickOwn(index) {
+      if (this.matching) {
+        this.matching = false;
+        const gift = this.gift;
+        this.gift = null;
+        if (gift) return this.send({ type: 'match_top_opp', target: gift.target, opp_index: gift.index, give_index: index });
+        return this.send({ type: 'match_top', index });
+      }
+      if (!this.myTurn()) return;
+      const g = this.game;
+      if (g.stage === 'holding') return this.send({ type: 'swap_with_hand', index });
+      if (g.stage !== 'power') return;
+      if (g.power === 'peek_own') this.send({ type: 'peek_own', index });
+      if (g.power === 'swap_own_with_deck') this.send({ type: 'swap_own_with_deck', index });
+      if (g.power === 'blind_swap') this.picked = index;
+    },
+
+    clickOpp(target, index) {
+      if (this.matching) {
+        this.gift = { target, index };
+        return;
+      }
+      if (!this.myTurn() || this.game.stage !== 'power') return;
+      const power = this.game.power;
+      if (power === 'peek_opp') this.send({ type: 'peek_opp', index, target });
+      if (power === 'swap_opp_with_deck') this.send({ type: 'swap_opp_with_deck', opp_index: index, target });
+      if (power === 'blind_swap' && this.picked !== null) {
+        this.send({ type: 'blind_swap', own_index: this.picked, opp_index: index, target });
+      }
+    },
+  }));
+
+  // Lobby "Your name" card: the name other players see.
+  Alpine.data('playerName', () => ({
+    name: '',
+    saved: false,
+    error: '',
+
+    // Our `GET /api/player/{id}/stats`, once loaded.
+    stats: null,
+
+    async init() {
+      const identity = await playerIdentity().catch(() => null);
+      if (!identity) return;
+      this.name = identity.name;
+      const res = await fetch(`/api/player/${identity.player_id}/stats`);
+      if (res.ok) this.stats = await res.json();
+    },
+
+    statsText() {
+      const s = this.stats;
+      const pct = (rate) => `${Math.round(rate * 100)}%`;
+      let text = `${s.games} games, ${pct(s.win_rate)} won, ${s.average_score.toFixed(1)} points on average`;
+      if (s.zobbo_calls) text += `; Zobbo calls won ${pct(s.zobbo_success_rate)}`;
+      return text + '.';
+    },
+
+    async save() {
+      this.error = '';
+      try {
+        this.name = (await playerIdentity(this.name)).name;
+        this.saved = true;
+      } catch (e) {
+        this.error = e.message;
+      }
+    },
+  }));
+
+  // Lobby "Join by code" card.
+  Alpine.data('joinByCode', () => ({
+    code: '',
+    error: '',
+
+    async join() {
+      const res = await fetch('/api/join-by-code', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ code: this.code }),
+      });
+      if (res.ok) {
+        location.href = (await res.json()).url;
+        return;
+      }
+      this.error = res.status === 409 ? 'That room is full.' : 'No room has that code.';
+    },
+  }));
+
+  // Lobby "Open rooms" card: public rooms waiting for a second player.
+  Alpine.data('openRooms', () => ({
+    rooms: [],
+    error: '',
+
+    init() {
+      this.refresh();
+      setInterval(() => this.refresh(), 5000);
+    },
+
+    async refresh() {
+      const res = await fetch('/api/rooms?status=waiting');
+      if (res.ok) this.rooms = await res.json();
+    },
+
+    describe(r) {
+      const mode = describeMode(r.mode);
+      const timer = r.turn_secs ? `, ${r.turn_secs}s turns` : '';
+      const seats = r.capacity > 2 ? `, ${r.players}/${r.capacity} players` : '';
+      const age = r.age_secs < 60 ? 'just now' : `${Math.floor(r.age_secs / 60)} min ago`;
+      return `${mode}${timer}${seats} — opened ${age}`;
+    },
+
+    async claim(id) {
+      const res = await fetch(`/api/rooms/${id}/claim`, { method: 'POST' });
+      if (res.ok) {
+        location.href = (await res.json()).url;
+        return;
+      }
+      this.error = res.status === 409 ? 'Someone else joined that room first.' : 'That room is gone.';
+      this.refresh();
+    },
+  }));
+
+  // Lobby "Daily challenge" card: today's seed and best scores.
+  Alpine.data('dailyChallenge', () => ({
+    seedId: '',
+    leaderboard: [],
+
+    async init() {
+      const res = await fetch('/api/daily');
+      if (!res.ok) return;
+      const body = await res.json();
+      this.seedId = body.seed_id;
+      this.leaderboard = body.leaderboard;
+    },
+
+    describe(s) {
+      return `${s.name ? `${s.name}: ` : ''}${s.score} points${s.won ? ', won' : ''} (${s.players} players)`;
+    },
+  }));
+
+  // Lobby "Leaderboard" card: top ratings, or this week's best daily challenge scores.
+  Alpine.data('leaderboard', () => ({
+    period: 'alltime',
+    players: [],
+
+    init() {
+      this.refresh();
+    },
+
+    async refresh() {
+      const res = await fetch(`/api/leaderboard?period=${this.period}&limit=10`);
+      if (res.ok) this.players = (await res.json()).players;
+    },
+
+    describe(p) {
+      const games = p.games === 1 ? '1 game' : `${p.games} games`;
+      return this.period === 'weekly' ? `${p.name}: ${p.score} points (${games})` : `${p.name}: ${p.score} (${games})`;
+    },
+  }));
+
+  // Lobby "Create room" form: a rule preset stands in for the fields it covers,
+  // which are disabled so they aren't posted over it.
+  Alpine.data('createRoom', () => ({
+    presets: [],
+    rulePreset: '',
+
+    async init() {
+      const res = await fetch('/api/presets');
+      if (res.ok) this.presets = await res.json();
+    },
+  }));
+
+    // Lobby "Quick match" card: long-polls the server until paired, then opens the room.
+  Alpine.data('quickmatch', () => ({
+    searching: false,
+    error: '',
+    abort: null,
+
+    async start() {
+      this.searching = true;
+      this.error = '';
+      this.abort = new AbortController();
+      try {
+        while (this.searching) {
+          const res = await fetch('/api/quickmatch', { method: 'POST', signal: this.abort.signal });
+          if (!res.ok) throw new Error(`quickmatch failed (${res.status})`);
+          const body = await res.json();
+          if (body.status === 'matched') {
+            location.href = body.url;
+            return;
+          }
+        }
+      } catch (e) {
+        if (e.name !== 'AbortError') this.error = e.message;
+        this.searching = false;
+      }
+    },
+
+    cancel() {
+      this.searching = false;
+      if (this.abort) this.abort.abort();
+    },
+  }));
+
+  // Lobby "Practice" card: a new room with a bot already seated, or the
+  // tutorial's scripted one, then straight in.
+  Alpine.data('practice', () => ({
+    starting: false,
+    error: '',
+
+    async start(path = '/api/practice') {
+      this.starting = true;
+      this.error = '';
+      try {
+        const res = await fetch(path, { method: 'POST' });
+        if (!res.ok) throw new Error(`practice failed (${res.status})`);
+        location.href = (await res.json()).url;
+      } catch (e) {
+        this.error = e.message;
+        this.starting = false;
+      }
+    },
+  }));
+});
@@ -0,0 +1,195 @@
+// Generated by `cargo run --features ts --bin protocol-dts` in game/shared. Do not edit.
+
+export type Suit = "clubs" | "diamonds" | "hearts" | "spades";
+
+export type Rank = "A" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" | "10" | "J" | "Q" | "K" | "Joker";
+
+export type CardPublic = { rank: Rank, suit: Suit, points: number, };
+
+export type Power = "peek_own" | "peek_opp" | "swap_own_with_deck" | "blind_swap" | "swap_opp_with_deck";
+
+export type DrawSource = "deck" | "discard";
+
+export type Stage = "draw" | "holding" | "power" | "finished" | "paused";
+
+export type GameMode = { "kind": "single" } | { "kind": "zobbo_battle", rounds: number, } | { "kind": "best_of", rounds: number, } | { "kind": "race_to", points: number, };
+
+export type FinishReason = { "kind": "zobbo" } | { "kind": "empty_hand", seat: number, } | { "kind": "agreed_draw" } | { "kind": "forfeit", seat: number, } | { "kind": "inactive", seat: number, } | { "kind": "out_of_time", seat: number, };
+
+export type RuleConfig = { 
+/**
+ * Cards dealt to each seat.
+ */
+hand_size: number, 
+/**
+ * How many of their own cards each player is shown before the first
+ * turn, counted from the end of the hand.
+ */
+initial_peeks: number, 
+/**
+ * Rounds per match; more than one makes a `ZobboBattle`, or a `BestOf`
+ * with `best_of`.
+ */
+rounds: number, 
+/**
+ * Win a match on rounds won rather than on total points.
+ */
+best_of: boolean, 
+/**
+ * Play rounds until someone's total reaches this many points, instead
+ * of a fixed number of `rounds`.
+ */
+race_to?: number, 
+/**
+ * Whether a turn may start by taking the top of the discard pile.
+ */
+discard_draw: boolean, 
+/**
+ * Whether discarding a 5–K from the deck grants its power. Off makes a
+ * casual game where every discard just ends the turn.
+ */
+powers: boolean, 
+/**
+ * Deal a 54-card deck with two jokers.
+ */
+jokers: boolean, 
+/**
+ * Whether a wrong `MatchTop` also deals the player an unseen card from
+ * the deck, into their first empty slot or onto the end of their hand.
+ */
+match_penalty_draw: boolean, 
+/**
+ * Points added to the Zobbo caller's score when they don't have the
+ * strictly lowest hand at the reveal; 0 makes calling free.
+ */
+zobbo_penalty: number, 
+/**
+ * Which ranks grant which power, when `powers` is on.
+ */
+power_table: { [key in Rank]?: Power }, 
+/**
+ * One seat dealt an extra card or shown an extra one, set by the host.
+ */
+handicap?: Handicap, };
+
+export type SlotPublic = { filled: boolean, version: number, };
+
+export type SeatPublic = { seat: number, slots: Array<SlotPublic>, 
+/**
+ * Set after a failed match: this seat's next turn is skipped.
+ */
+skip_next: boolean, };
+
+export type GameUpdate = { 
+/**
+ * Per-room sequence number, bumped on every public state change. A gap
+ * means the client missed an update and should send `RequestState`.
+ */
+seq: number, active: number, stage: Stage, 
+/**
+ * Where the held card came from, while `stage` is `holding`.
+ */
+held_from?: DrawSource, 
+/**
+ * The power on offer, while `stage` is `power`.
+ */
+power?: Power, deck_count: number, discard_top?: CardPublic, discard_count: number, seats: Array<SeatPublic>, 
+/**
+ * Turns left before the reveal, once someone has called Zobbo.
+ */
+zobbo_remaining?: number, 
+/**
+ * Who called Zobbo, once someone has.
+ */
+zobbo_caller?: number, 
+/**
+ * Seats that have offered or accepted a draw; the game ends as a tie
+ * once every seat is here.
+ */
+draw_agreed: Array<number>, 
+/**
+ * Seats that have asked to pause; the game pauses once every seat has.
+ */
+pause_requests: Array<number>, 
+/**
+ * When the active player's turn times out (Unix seconds), if the room has a turn timer.
+ */
+turn_deadline_unix?: number, 
+/**
+ * How long past `turn_deadline_unix` the server waits before playing the
+ * turn out: the active player's measured round-trip time, capped, so a
+ * slow connection doesn't cost them part of every turn.
+ */
+turn_compensation_ms?: number, 
+/**
+ * Each seat's time bank left, in milliseconds, as of this update; empty
+ * if the room has no time bank. The active seat's is running unless
+ * the game is paused or finished.
+ */
+time_bank_ms: number[], 
+/**
+ * `state_hash` of this update, so a client keeping its own copy from
+ * deltas can tell it has drifted and send `RequestState`.
+ */
+checksum: number, };
+
+export type SlotChange = { seat: number, index: number, slot: SlotPublic, };
+
+export type GameDelta = { seq: number, active?: number, stage?: Stage, held_from?: DrawSource | null, power?: Power | null, deck_count?: number, discard_top?: CardPublic | null, discard_count?: number, slots?: Array<SlotChange>, 
+/**
+ * Every seat's `skip_next`, sent whole when any of them changed.
+ */
+skip_next?: Array<boolean>, zobbo_remaining?: number | null, zobbo_caller?: number | null, draw_agreed?: Array<number>, pause_requests?: Array<number>, turn_deadline_unix?: number | null, turn_compensation_ms?: number | null, time_bank_ms?: Array<number>, checksum?: number, };
+
+export type LobbyPlayer = { seat: number, connected: boolean, ready: boolean, 
+/**
+ * The guest identity last connected to the seat, if it presented one.
+ */
+player_id?: string, name?: string, };
+
+export type RatingChange = { seat: number, rating: number, 
+/**
+ * How far the game moved it.
+ */
+delta: number, };
+
+export type ClientToServer = { "type": "hello", version: number, capabilities: Array<string>, } | { "type": "ready" } | { "type": "draw_deck" } | { "type": "draw_discard" } | { "type": "swap_with_hand", index: number, } | { "type": "discard_drawn" } | { "type": "peek_own", index: number, } | { "type": "peek_opp", index: number, target?: number, } | { "type": "swap_own_with_deck", index: number, } | { "type": "blind_swap", own_index: number, opp_index: number, target?: number, } | { "type": "swap_opp_with_deck", opp_index: number, target?: number, } | { "type": "skip_power" } | { "type": "match_top", index: number, } | { "type": "match_top_opp", opp_index: number, give_index: number, target?: number, } | { "type": "call_zobbo" } | { "type": "offer_draw" } | { "type": "accept_draw" } | { "type": "decline_draw" } | { "type": "request_pause" } | { "type": "resume" } | { "type": "resync" } | { "type": "kick_player", seat: number, } | { "type": "update_settings", rounds: number, turn_secs: bigint, } | { "type": "set_handicap", handicap: Handicap | null, } | { "type": "chat", text: string, } | { "type": "request_state", last_seq: number, } | { "type": "ping" };
+
+export type RejectCode = "not_started" | "game_over" | "not_your_turn" | "wrong_stage" | "wrong_power" | "bad_index" | "empty_slot" | "empty_deck" | "empty_discard" | "zobbo_already_called" | "between_rounds" | "discard_draw_off" | "bad_target" | "no_draw_offer" | "paused" | "not_paused" | "not_host" | "in_progress" | "invalid_settings" | "chat_empty" | "chat_too_long" | "rate_limited" | "needs_identity" | "off_script";
+
+export type TutorialHighlight = { "kind": "deck" } | { "kind": "discard" } | { "kind": "slot", seat: number, index: number, } | { "kind": "zobbo" };
+
+export type ServerToClient = { "type": "welcome", room_id: string, seat: number, reconnect_token: string, reconnect_ttl_secs: number, protocol_version: number, } | { "type": "unsupported_version", client_version: number, min_version: number, server_version: number, } | { "type": "reconnect_token", token: string, ttl_secs: number, } | { "type": "lobby_state", players: Array<LobbyPlayer>, started: boolean, host?: number, mode: GameMode, turn_secs?: number, 
+/**
+ * Each player's time bank for a whole game, if the room has one.
+ */
+time_bank_secs?: number, 
+/**
+ * The daily challenge the room deals (`YYYY-MM-DD`), if it's a challenge room.
+ */
+daily?: string, 
+/**
+ * Games here move the players' ratings; everyone needs an identity to ready up.
+ */
+ranked: boolean, rules: RuleConfig, } | { "type": "game_start", game_id: string, seats: number, hand_size: number, first: number, round: number, rounds: number, rules: RuleConfig, } | { "type": "game_update" } & GameUpdate | { "type": "game_delta" } & GameDelta | { "type": "zobbo_called", caller: number, turns_remaining: number, } | { "type": "opponent_disconnected", seat: number, deadline: number, } | { "type": "drawn", card: CardPublic, from: DrawSource, } | { "type": "peek_result", owner: number, index: number, version: number, card: CardPublic, } | { "type": "game_over", 
+/**
+ * `None` on a tie for the lowest score.
+ */
+winner?: number, scores: Array<number>, hands: Array<Array<CardPublic | null>>, zobbo_caller?: number, 
+/**
+ * Points the caller was penalised for not having the lowest hand;
+ * already included in `scores`.
+ */
+zobbo_penalty?: number, reason: FinishReason, 
+/**
+ * Every seat's new rating, in ranked rooms; empty otherwise.
+ */
+ratings: Array<RatingChange>, } | { "type": "round_result", round: number, rounds: number, winner?: number, 
+/**
+ * What this round added to each seat's total.
+ */
+scores: Array<number>, totals: Array<number>, 
+/**
+ * `RaceTo` only: seats whose total has reached the limit, which ends the match.
+ */
+eliminated: Array<number>, } | { "type": "scoreboard", round: number, rounds: number, wins: Array<number>, totals: Array<number>, wins_needed?: number, } | { "type": "match_over", winner?: number, totals: Array<number>, } | { "type": "chat", from: number, name: string, text: string, at: number, } | { "type": "tutorial_hint", step: number, text: string, highlight?: TutorialHighlight, } | { "type": "action_rejected", action: string, code: RejectCode, message: string, } | { "type": "kicked" } | { "type": "room_closed" } | { "type": "server_shutdown", reconnect_after_secs: number, } | { "type": "error", message: string, } | { "type": "pong" };
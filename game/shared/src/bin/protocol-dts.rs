@@ -0,0 +1,35 @@
+//! Print TypeScript declarations for every wire type to stdout.
+//!
+//! `cargo run --features ts --bin protocol-dts > ../frontend/static/js/protocol.d.ts`
+
+use ts_rs::TS;
+use zobbo_protocol::*;
+
+fn main() {
+    println!("// Generated by `cargo run --features ts --bin protocol-dts` in game/shared. Do not edit.");
+    let decls = [
+        Suit::decl(),
+        Rank::decl(),
+        CardPublic::decl(),
+        Power::decl(),
+        DrawSource::decl(),
+        Stage::decl(),
+        GameMode::decl(),
+        FinishReason::decl(),
+        RuleConfig::decl(),
+        SlotPublic::decl(),
+        SeatPublic::decl(),
+        GameUpdate::decl(),
+        SlotChange::decl(),
+        GameDelta::decl(),
+        LobbyPlayer::decl(),
+        RatingChange::decl(),
+        ClientToServer::decl(),
+        RejectCode::decl(),
+        TutorialHighlight::decl(),
+        ServerToClient::decl(),
+    ];
+    for decl in decls {
+        println!("\nexport {decl}");
+    }
+}
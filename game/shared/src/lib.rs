@@ -0,0 +1,1215 @@
+//! Zobbo wire protocol: every message exchanged over the game WebSocket.
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Subprotocols this server speaks, in decreasing order of preference.
+///
+/// Negotiated through `Sec-WebSocket-Protocol` on upgrade. A breaking
+/// revision gets a new entry (e.g. `zobbo.v2`) so old and new clients can
+/// coexist while a frontend rollout is in flight.
+pub const SUBPROTOCOLS: &[&str] = &["zobbo.v1"];
+
+/// Revision of the message schema within the current subprotocol, sent in
+/// `Welcome`. Bumped for additive changes. Older clients, by their `Hello`,
+/// aren't sent messages newer than they are (see `ServerToClient::downgrade`);
+/// fields and enum values added since still reach them as they are.
+///
+/// - 1: the original messages.
+/// - 2: `Hello`, `GameDelta`.
+/// - 3: host role: `KickPlayer`, `UpdateSettings`, `Kicked`, and the
+///   host and settings in `LobbyState`.
+/// - 4: `RuleConfig` in `GameStart`.
+/// - 5: `RuleConfig::powers`, and the rules in `LobbyState`.
+/// - 6: `RuleConfig::power_table`.
+/// - 7: `Rank::Joker` and `RuleConfig::jokers`.
+/// - 8: rooms of up to four: `target` on the opponent powers.
+/// - 9: `GameMode::BestOf`, `RuleConfig::best_of` and `Scoreboard`.
+/// - 10: `GameMode::RaceTo`, `RuleConfig::race_to` and `RoundResult::eliminated`.
+/// - 11: `RuleConfig::zobbo_penalty` and `GameOver::zobbo_penalty`.
+/// - 12: `ZobboCalled`, and `zobbo_caller` in `GameUpdate` and `GameDelta`.
+/// - 13: `RuleConfig::match_penalty_draw`.
+/// - 14: `MatchTopOpp`.
+/// - 15: `GameOver::reason`.
+/// - 16: `OfferDraw`, `AcceptDraw` and `draw_agreed` in `GameUpdate` and `GameDelta`.
+/// - 17: `RequestPause`, `Resume`, `Stage::Paused` and `pause_requests` in
+///   `GameUpdate` and `GameDelta`.
+/// - 18: `OpponentDisconnected` and `FinishReason::Forfeit`.
+/// - 19: `FinishReason::Inactive`.
+/// - 20: time banks: `time_bank_ms` in `GameUpdate` and `GameDelta`,
+///   `LobbyState::time_bank_secs` and `FinishReason::OutOfTime`.
+/// - 21: `LobbyState::daily`.
+/// - 22: `GameStart::game_id`.
+/// - 23: `LobbyPlayer::player_id` and `LobbyPlayer::name`.
+/// - 24: ranked rooms: `LobbyState::ranked`, `GameOver::ratings` and
+///   `RejectCode::NeedsIdentity`.
+/// - 25: `RoomClosed`.
+/// - 26: `ServerShutdown`.
+/// - 27: `DeclineDraw`; a draw offer lapses once a seat that hasn't agreed
+///   makes another move.
+/// - 28: `checksum` in `GameUpdate` and `GameDelta`.
+/// - 29: optional fields that are unset are left out rather than sent as
+///   `null` (outside `GameDelta`, where `null` clears a field).
+/// - 30: `RuleConfig::handicap` and `SetHandicap`.
+/// - 31: `turn_compensation_ms` in `GameUpdate` and `GameDelta`.
+/// - 32: tutorial rooms: `TutorialHint` and `RejectCode::OffScript`.
+pub const PROTOCOL_VERSION: u32 = 32;
+
+/// Oldest `Hello::version` the server still serves.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Optional features a client can ask for in `Hello::capabilities`.
+pub mod capability {
+    /// Receive `GameDelta` instead of a full `GameUpdate` after each action.
+    pub const GAME_DELTA: &str = "game_delta";
+}
+
+/// Pick the subprotocol to answer with from a client's comma-separated offer.
+///
+/// Returns `None` when none of the offered names is one we support.
+pub fn select_subprotocol(offered: &str) -> Option<&'static str> {
+    SUBPROTOCOLS
+        .iter()
+        .copied()
+        .find(|ours| offered.split(',').any(|p| p.trim() == *ours))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+impl Suit {
+    pub const ALL: [Suit; 4] = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+
+    pub fn is_red(self) -> bool {
+        matches!(self, Suit::Diamonds | Suit::Hearts)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Rank {
+    #[cfg_attr(feature = "serde", serde(rename = "A"))]
+    Ace,
+    #[cfg_attr(feature = "serde", serde(rename = "2"))]
+    Two,
+    #[cfg_attr(feature = "serde", serde(rename = "3"))]
+    Three,
+    #[cfg_attr(feature = "serde", serde(rename = "4"))]
+    Four,
+    #[cfg_attr(feature = "serde", serde(rename = "5"))]
+    Five,
+    #[cfg_attr(feature = "serde", serde(rename = "6"))]
+    Six,
+    #[cfg_attr(feature = "serde", serde(rename = "7"))]
+    Seven,
+    #[cfg_attr(feature = "serde", serde(rename = "8"))]
+    Eight,
+    #[cfg_attr(feature = "serde", serde(rename = "9"))]
+    Nine,
+    #[cfg_attr(feature = "serde", serde(rename = "10"))]
+    Ten,
+    #[cfg_attr(feature = "serde", serde(rename = "J"))]
+    Jack,
+    #[cfg_attr(feature = "serde", serde(rename = "Q"))]
+    Queen,
+    #[cfg_attr(feature = "serde", serde(rename = "K"))]
+    King,
+    /// Only dealt with `RuleConfig::jokers`: one red (hearts) and one black
+    /// (spades), worth −1 each.
+    #[cfg_attr(feature = "serde", serde(rename = "Joker"))]
+    Joker,
+}
+
+impl Rank {
+    /// The thirteen ranks of a standard deck; `Joker` is left out.
+    pub const ALL: [Rank; 13] = [
+        Rank::Ace,
+        Rank::Two,
+        Rank::Three,
+        Rank::Four,
+        Rank::Five,
+        Rank::Six,
+        Rank::Seven,
+        Rank::Eight,
+        Rank::Nine,
+        Rank::Ten,
+        Rank::Jack,
+        Rank::Queen,
+        Rank::King,
+    ];
+}
+
+/// A face-up card as shown to a client, with its point value precomputed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CardPublic {
+    pub rank: Rank,
+    pub suit: Suit,
+    pub points: i32,
+}
+
+/// Card powers, usable only when the card was drawn from the deck and discarded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Power {
+    /// 5–8: look at one of your own cards.
+    PeekOwn,
+    /// 9–10: look at one of your opponent's cards.
+    PeekOpp,
+    /// J: swap one of your cards with the top of the deck.
+    SwapOwnWithDeck,
+    /// Q: swap one of your cards with one of your opponent's, unseen.
+    BlindSwap,
+    /// Red K: swap one of your opponent's cards with the top of the deck.
+    SwapOppWithDeck,
+}
+
+/// Which ranks grant which power when discarded from the deck; the default
+/// is the standard table above. A king only counts when red, since a black
+/// king is already worth nothing.
+///
+/// On the wire this is a map from rank to power, leaving out ranks without one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "BTreeMap<Rank, Power>", into = "BTreeMap<Rank, Power>"))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PowerTable([Option<Power>; 14]);
+
+impl Default for PowerTable {
+    fn default() -> Self {
+        let mut table = PowerTable::none();
+        for rank in [Rank::Five, Rank::Six, Rank::Seven, Rank::Eight] {
+            table.set(rank, Some(Power::PeekOwn));
+        }
+        table.set(Rank::Nine, Some(Power::PeekOpp));
+        table.set(Rank::Ten, Some(Power::PeekOpp));
+        table.set(Rank::Jack, Some(Power::SwapOwnWithDeck));
+        table.set(Rank::Queen, Some(Power::BlindSwap));
+        table.set(Rank::King, Some(Power::SwapOppWithDeck));
+        table
+    }
+}
+
+impl PowerTable {
+    /// A table where no card has a power.
+    pub fn none() -> Self {
+        PowerTable([None; 14])
+    }
+
+    pub fn get(&self, rank: Rank) -> Option<Power> {
+        self.0[rank as usize]
+    }
+
+    pub fn set(&mut self, rank: Rank, power: Option<Power>) {
+        self.0[rank as usize] = power;
+    }
+
+    /// The power a card of `rank` and `suit` grants, if any.
+    pub fn power(&self, rank: Rank, suit: Suit) -> Option<Power> {
+        if rank == Rank::King && !suit.is_red() { return None; }
+        self.get(rank)
+    }
+}
+
+impl From<BTreeMap<Rank, Power>> for PowerTable {
+    fn from(map: BTreeMap<Rank, Power>) -> Self {
+        let mut table = PowerTable::none();
+        for (rank, power) in map {
+            table.set(rank, Some(power));
+        }
+        table
+    }
+}
+
+impl From<PowerTable> for BTreeMap<Rank, Power> {
+    fn from(table: PowerTable) -> Self {
+        Rank::ALL.into_iter().chain([Rank::Joker]).filter_map(|rank| Some((rank, table.get(rank)?))).collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DrawSource {
+    Deck,
+    Discard,
+}
+
+/// Where the active player is within their turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Stage {
+    /// Waiting for the active player to draw (or call Zobbo).
+    Draw,
+    /// The active player holds a drawn card (see `GameUpdate::held_from`).
+    Holding,
+    /// The active player discarded a power card and may use `GameUpdate::power`.
+    Power,
+    /// Cards have been revealed; see `GameOver`.
+    Finished,
+    /// Everyone agreed to pause: no actions and no turn timer until someone resumes.
+    Paused,
+}
+
+impl Stage {
+    /// The name on the wire.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Stage::Draw => "draw",
+            Stage::Holding => "holding",
+            Stage::Power => "power",
+            Stage::Finished => "finished",
+            Stage::Paused => "paused",
+        }
+    }
+}
+
+/// Why a game ended, in `GameOver`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "snake_case"))]
+pub enum FinishReason {
+    /// Everyone played their last turn after a Zobbo call.
+    #[default]
+    Zobbo,
+    /// `seat` matched away its last card and wins outright.
+    EmptyHand { seat: usize },
+    /// Every seat agreed to call the game a tie.
+    AgreedDraw,
+    /// `seat` left mid-game and didn't come back in time.
+    Forfeit { seat: usize },
+    /// `seat` let too many turns in a row run out of time.
+    Inactive { seat: usize },
+    /// `seat` used up its time bank.
+    OutOfTime { seat: usize },
+}
+
+/// How a room plays, chosen at creation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "snake_case"))]
+pub enum GameMode {
+    /// One round; the lowest score wins.
+    #[default]
+    Single,
+    /// Several rounds with scores summed; the lowest total wins the match.
+    ZobboBattle { rounds: u8 },
+    /// Up to `rounds` rounds; the first to win a majority of them takes the
+    /// match. A tied round counts for nobody.
+    BestOf { rounds: u8 },
+    /// Rounds repeat until someone's total reaches `points`; that knocks them
+    /// out and ends the match, and the lowest total wins.
+    RaceTo { points: i32 },
+}
+
+impl GameMode {
+    /// Most rounds the match can last. A `RaceTo` has no fixed length, so
+    /// this is only a backstop for rounds that keep scoring nothing.
+    pub fn rounds(self) -> u8 {
+        match self {
+            GameMode::Single => 1,
+            GameMode::ZobboBattle { rounds } | GameMode::BestOf { rounds } => rounds,
+            GameMode::RaceTo { .. } => u8::MAX,
+        }
+    }
+
+    /// Round wins that take a `BestOf` match.
+    pub fn wins_needed(self) -> Option<u8> {
+        match self {
+            GameMode::BestOf { rounds } => Some(rounds / 2 + 1),
+            _ => None,
+        }
+    }
+}
+
+/// House rules a room deals its games with. Fields missing from a saved or
+/// sent config take the standard rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RuleConfig {
+    /// Cards dealt to each seat.
+    pub hand_size: usize,
+    /// How many of their own cards each player is shown before the first
+    /// turn, counted from the end of the hand.
+    pub initial_peeks: usize,
+    /// Rounds per match; more than one makes a `ZobboBattle`, or a `BestOf`
+    /// with `best_of`.
+    pub rounds: u8,
+    /// Win a match on rounds won rather than on total points.
+    pub best_of: bool,
+    /// Play rounds until someone's total reaches this many points, instead
+    /// of a fixed number of `rounds`.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub race_to: Option<i32>,
+    /// Whether a turn may start by taking the top of the discard pile.
+    pub discard_draw: bool,
+    /// Whether discarding a 5–K from the deck grants its power. Off makes a
+    /// casual game where every discard just ends the turn.
+    pub powers: bool,
+    /// Deal a 54-card deck with two jokers.
+    pub jokers: bool,
+    /// Whether a wrong `MatchTop` also deals the player an unseen card from
+    /// the deck, into their first empty slot or onto the end of their hand.
+    pub match_penalty_draw: bool,
+    /// Points added to the Zobbo caller's score when they don't have the
+    /// strictly lowest hand at the reveal; 0 makes calling free.
+    pub zobbo_penalty: i32,
+    /// Which ranks grant which power, when `powers` is on.
+    #[cfg_attr(feature = "ts", ts(as = "BTreeMap<Rank, Power>"))]
+    pub power_table: PowerTable,
+    /// One seat dealt an extra card or shown an extra one, set by the host.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub handicap: Option<Handicap>,
+}
+
+/// A handicap the host gives one seat, to even out a game between players
+/// of different strength.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Handicap {
+    pub seat: usize,
+    pub kind: HandicapKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum HandicapKind {
+    /// Dealt one card more than `hand_size`, seeing no more of them.
+    ExtraCard,
+    /// Shown one more of their own cards before the first turn.
+    ExtraPeek,
+}
+
+impl Default for RuleConfig {
+    fn default() -> Self {
+        RuleConfig {
+            hand_size: 6,
+            initial_peeks: 3,
+            rounds: 1,
+            best_of: false,
+            race_to: None,
+            discard_draw: true,
+            powers: true,
+            jokers: false,
+            match_penalty_draw: false,
+            zobbo_penalty: 10,
+            power_table: PowerTable::default(),
+            handicap: None,
+        }
+    }
+}
+
+impl RuleConfig {
+    /// Cards dealt to `seat`: `hand_size`, or one more under an extra-card handicap.
+    pub fn hand_size_of(&self, seat: usize) -> usize {
+        self.hand_size + usize::from(self.handicapped(seat, HandicapKind::ExtraCard))
+    }
+
+    /// Cards `seat` is shown before the first turn: `initial_peeks`, or one
+    /// more under an extra-peek handicap.
+    pub fn initial_peeks_of(&self, seat: usize) -> usize {
+        self.initial_peeks + usize::from(self.handicapped(seat, HandicapKind::ExtraPeek))
+    }
+
+    fn handicapped(&self, seat: usize, kind: HandicapKind) -> bool {
+        self.handicap == Some(Handicap { seat, kind })
+    }
+
+    pub fn mode(&self) -> GameMode {
+        if let Some(points) = self.race_to {
+            return GameMode::RaceTo { points };
+        }
+        match self.rounds {
+            0 | 1 => GameMode::Single,
+            rounds if self.best_of => GameMode::BestOf { rounds },
+            rounds => GameMode::ZobboBattle { rounds },
+        }
+    }
+}
+
+/// Public view of one hand slot. `version` bumps whenever the card in the
+/// slot changes, so clients can tell when a card they peeked is stale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SlotPublic {
+    pub filled: bool,
+    pub version: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SeatPublic {
+    pub seat: usize,
+    pub slots: Vec<SlotPublic>,
+    /// Set after a failed match: this seat's next turn is skipped.
+    pub skip_next: bool,
+}
+
+/// Authoritative public game state. Sent in full on (re)sync, at each deal
+/// and periodically; in between, seats get `GameDelta`s instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GameUpdate {
+    /// Per-room sequence number, bumped on every public state change. A gap
+    /// means the client missed an update and should send `RequestState`.
+    #[cfg_attr(feature = "ts", ts(type = "number"))]
+    pub seq: u64,
+    pub active: usize,
+    pub stage: Stage,
+    /// Where the held card came from, while `stage` is `holding`.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub held_from: Option<DrawSource>,
+    /// The power on offer, while `stage` is `power`.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub power: Option<Power>,
+    pub deck_count: usize,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub discard_top: Option<CardPublic>,
+    pub discard_count: usize,
+    pub seats: Vec<SeatPublic>,
+    /// Turns left before the reveal, once someone has called Zobbo.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub zobbo_remaining: Option<u8>,
+    /// Who called Zobbo, once someone has.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub zobbo_caller: Option<usize>,
+    /// Seats that have offered or accepted a draw; the game ends as a tie
+    /// once every seat is here.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub draw_agreed: Vec<usize>,
+    /// Seats that have asked to pause; the game pauses once every seat has.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pause_requests: Vec<usize>,
+    /// When the active player's turn times out (Unix seconds), if the room has a turn timer.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(as = "Option<f64>", optional))]
+    pub turn_deadline_unix: Option<u64>,
+    /// How long past `turn_deadline_unix` the server waits before playing the
+    /// turn out: the active player's measured round-trip time, capped, so a
+    /// slow connection doesn't cost them part of every turn.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub turn_compensation_ms: Option<u32>,
+    /// Each seat's time bank left, in milliseconds, as of this update; empty
+    /// if the room has no time bank. The active seat's is running unless
+    /// the game is paused or finished.
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "ts", ts(type = "number[]"))]
+    pub time_bank_ms: Vec<u64>,
+    /// `state_hash` of this update, so a client keeping its own copy from
+    /// deltas can tell it has drifted and send `RequestState`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub checksum: u32,
+}
+
+/// One hand slot that changed, within a `GameDelta`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SlotChange {
+    pub seat: usize,
+    pub index: usize,
+    pub slot: SlotPublic,
+}
+
+/// The fields of a `GameUpdate` that changed since the one numbered `seq - 1`.
+///
+/// Absent fields are unchanged. For the nullable ones, an explicit `null`
+/// means the value was cleared. Only applies on top of `seq - 1`; on a gap,
+/// send `RequestState`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GameDelta {
+    #[cfg_attr(feature = "ts", ts(type = "number"))]
+    pub seq: u64,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub active: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub stage: Option<Stage>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none", with = "nullable"))]
+    #[cfg_attr(feature = "ts", ts(as = "Option<DrawSource>", optional = nullable))]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<DrawSource>"))]
+    pub held_from: Option<Option<DrawSource>>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none", with = "nullable"))]
+    #[cfg_attr(feature = "ts", ts(as = "Option<Power>", optional = nullable))]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<Power>"))]
+    pub power: Option<Option<Power>>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub deck_count: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none", with = "nullable"))]
+    #[cfg_attr(feature = "ts", ts(as = "Option<CardPublic>", optional = nullable))]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<CardPublic>"))]
+    pub discard_top: Option<Option<CardPublic>>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub discard_count: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+    pub slots: Vec<SlotChange>,
+    /// Every seat's `skip_next`, sent whole when any of them changed.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub skip_next: Option<Vec<bool>>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none", with = "nullable"))]
+    #[cfg_attr(feature = "ts", ts(as = "Option<u8>", optional = nullable))]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<u8>"))]
+    pub zobbo_remaining: Option<Option<u8>>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none", with = "nullable"))]
+    #[cfg_attr(feature = "ts", ts(as = "Option<usize>", optional = nullable))]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<usize>"))]
+    pub zobbo_caller: Option<Option<usize>>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub draw_agreed: Option<Vec<usize>>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub pause_requests: Option<Vec<usize>>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none", with = "nullable"))]
+    #[cfg_attr(feature = "ts", ts(as = "Option<f64>", optional = nullable))]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<u64>"))]
+    pub turn_deadline_unix: Option<Option<u64>>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none", with = "nullable"))]
+    #[cfg_attr(feature = "ts", ts(as = "Option<u32>", optional = nullable))]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<u32>"))]
+    pub turn_compensation_ms: Option<Option<u32>>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(as = "Option<Vec<f64>>", optional))]
+    pub time_bank_ms: Option<Vec<u64>>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub checksum: Option<u32>,
+}
+
+/// Tells "absent" (`None`) apart from an explicit `null` (`Some(None)`).
+#[cfg(feature = "serde")]
+mod nullable {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer, T: Serialize>(value: &Option<Option<T>>, s: S) -> Result<S::Ok, S::Error> {
+        // Only reached for `Some`; `None` is skipped by the field attribute.
+        value.as_ref().and_then(Option::as_ref).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>>(d: D) -> Result<Option<Option<T>>, D::Error> {
+        Option::<T>::deserialize(d).map(Some)
+    }
+}
+
+impl GameUpdate {
+    /// What changed between `self` and `next`, or `None` when the table's
+    /// shape changed (seat or slot counts) and a full update is simpler.
+    pub fn diff(&self, next: &GameUpdate) -> Option<GameDelta> {
+        if self.seats.len() != next.seats.len()
+            || self.seats.iter().zip(&next.seats).any(|(a, b)| a.slots.len() != b.slots.len())
+        {
+            return None;
+        }
+        fn changed<T: PartialEq + Clone>(old: &T, new: &T) -> Option<T> {
+            (old != new).then(|| new.clone())
+        }
+        let slots = next
+            .seats
+            .iter()
+            .zip(&self.seats)
+            .flat_map(|(new, old)| {
+                new.slots
+                    .iter()
+                    .zip(&old.slots)
+                    .enumerate()
+                    .filter(|(_, (a, b))| a != b)
+                    .map(|(index, (slot, _))| SlotChange { seat: new.seat, index, slot: *slot })
+            })
+            .collect();
+        let skip_next = |u: &GameUpdate| u.seats.iter().map(|s| s.skip_next).collect::<Vec<_>>();
+        Some(GameDelta {
+            seq: next.seq,
+            active: changed(&self.active, &next.active),
+            stage: changed(&self.stage, &next.stage),
+            held_from: changed(&self.held_from, &next.held_from),
+            power: changed(&self.power, &next.power),
+            deck_count: changed(&self.deck_count, &next.deck_count),
+            discard_top: changed(&self.discard_top, &next.discard_top),
+            discard_count: changed(&self.discard_count, &next.discard_count),
+            slots,
+            skip_next: changed(&skip_next(self), &skip_next(next)),
+            zobbo_remaining: changed(&self.zobbo_remaining, &next.zobbo_remaining),
+            zobbo_caller: changed(&self.zobbo_caller, &next.zobbo_caller),
+            draw_agreed: changed(&self.draw_agreed, &next.draw_agreed),
+            pause_requests: changed(&self.pause_requests, &next.pause_requests),
+            turn_deadline_unix: changed(&self.turn_deadline_unix, &next.turn_deadline_unix),
+            turn_compensation_ms: changed(&self.turn_compensation_ms, &next.turn_compensation_ms),
+            time_bank_ms: changed(&self.time_bank_ms, &next.time_bank_ms),
+            checksum: changed(&self.checksum, &next.checksum),
+        })
+    }
+
+    /// Bring `self` up to `delta.seq`. Returns `false`, leaving `self`
+    /// untouched, unless `delta` follows directly on from `self` and the
+    /// result matches the server's `checksum`.
+    pub fn apply(&mut self, delta: &GameDelta) -> bool {
+        let in_range = delta
+            .slots
+            .iter()
+            .all(|c| self.seats.get(c.seat).is_some_and(|s| c.index < s.slots.len()));
+        if delta.seq != self.seq + 1 || !in_range {
+            return false;
+        }
+        let mut next = self.clone();
+        next.fold(delta);
+        // Servers from before checksums leave them out.
+        if delta.checksum.is_some() && next.checksum != next.state_hash() {
+            return false;
+        }
+        *self = next;
+        true
+    }
+
+    /// A cheap hash of the public state: who's active, the stage, the pile
+    /// counts and every slot. FNV-1a over, in order: `active`, `deck_count`
+    /// and `discard_count` as little-endian u32s, the stage's wire name, then
+    /// each slot's `filled` as a byte and `version` as a little-endian u32.
+    pub fn state_hash(&self) -> u32 {
+        let mut hash: u32 = 0x811c_9dc5;
+        let mut feed = |bytes: &[u8]| {
+            for b in bytes {
+                hash = (hash ^ u32::from(*b)).wrapping_mul(0x0100_0193);
+            }
+        };
+        for n in [self.active, self.deck_count, self.discard_count] {
+            feed(&(n as u32).to_le_bytes());
+        }
+        feed(self.stage.as_str().as_bytes());
+        for slot in self.seats.iter().flat_map(|s| &s.slots) {
+            feed(&[u8::from(slot.filled)]);
+            feed(&slot.version.to_le_bytes());
+        }
+        hash
+    }
+
+    /// `self` with `checksum` set to match its state, as the server sends it.
+    pub fn sealed(mut self) -> Self {
+        self.checksum = self.state_hash();
+        self
+    }
+
+    fn fold(&mut self, delta: &GameDelta) {
+        self.seq = delta.seq;
+        if let Some(v) = delta.active { self.active = v; }
+        if let Some(v) = delta.stage { self.stage = v; }
+        if let Some(v) = delta.held_from { self.held_from = v; }
+        if let Some(v) = delta.power { self.power = v; }
+        if let Some(v) = delta.deck_count { self.deck_count = v; }
+        if let Some(v) = delta.discard_top { self.discard_top = v; }
+        if let Some(v) = delta.discard_count { self.discard_count = v; }
+        for c in &delta.slots {
+            self.seats[c.seat].slots[c.index] = c.slot;
+        }
+        if let Some(skips) = &delta.skip_next {
+            for (seat, skip) in self.seats.iter_mut().zip(skips) {
+                seat.skip_next = *skip;
+            }
+        }
+        if let Some(v) = delta.zobbo_remaining { self.zobbo_remaining = v; }
+        if let Some(v) = delta.zobbo_caller { self.zobbo_caller = v; }
+        if let Some(v) = &delta.draw_agreed { self.draw_agreed.clone_from(v); }
+        if let Some(v) = &delta.pause_requests { self.pause_requests.clone_from(v); }
+        if let Some(v) = delta.turn_deadline_unix { self.turn_deadline_unix = v; }
+        if let Some(v) = delta.turn_compensation_ms { self.turn_compensation_ms = v; }
+        if let Some(v) = &delta.time_bank_ms { self.time_bank_ms.clone_from(v); }
+        if let Some(v) = delta.checksum { self.checksum = v; }
+    }
+}
+
+/// A seat's rating after a ranked game, in `GameOver`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RatingChange {
+    pub seat: usize,
+    pub rating: i32,
+    /// How far the game moved it.
+    pub delta: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LobbyPlayer {
+    pub seat: usize,
+    pub connected: bool,
+    pub ready: bool,
+    /// The guest identity last connected to the seat, if it presented one.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub player_id: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub name: Option<String>,
+}
+
+/// Messages sent from the client to the server, as JSON text frames.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum ClientToServer {
+    /// First message on a connection: the schema `version` the client was
+    /// built against and the optional `capability` names it understands.
+    /// Clients that never send it are treated as version 1 with none.
+    Hello {
+        version: u32,
+        #[cfg_attr(feature = "serde", serde(default))]
+        capabilities: Vec<String>,
+    },
+    /// Ready up in the lobby; the game starts once every seat is ready.
+    Ready,
+    DrawDeck,
+    DrawDiscard,
+    /// Put the held card into `index`, discarding the card that was there.
+    SwapWithHand { index: usize },
+    /// Discard the card drawn from the deck, triggering its power if it has one.
+    DiscardDrawn,
+    PeekOwn { index: usize },
+    /// The opponent powers act on `target`'s hand; without one, on the seat
+    /// that plays next, which is the only opponent in a two-player room.
+    PeekOpp {
+        index: usize,
+        #[cfg_attr(feature = "serde", serde(default))]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        target: Option<usize>,
+    },
+    SwapOwnWithDeck { index: usize },
+    BlindSwap {
+        own_index: usize,
+        opp_index: usize,
+        #[cfg_attr(feature = "serde", serde(default))]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        target: Option<usize>,
+    },
+    SwapOppWithDeck {
+        opp_index: usize,
+        #[cfg_attr(feature = "serde", serde(default))]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        target: Option<usize>,
+    },
+    /// Decline the power on offer and end the turn.
+    SkipPower,
+    /// Throw `index` onto the discard pile if it matches the top card's rank.
+    /// Allowed at any time; a wrong guess skips your next turn.
+    MatchTop { index: usize },
+    /// Throw `target`'s slot `opp_index` onto the discard pile if it matches
+    /// the top card's rank, and fill the gap with your own `give_index`.
+    /// Allowed at any time; a wrong guess is punished like a wrong `MatchTop`.
+    MatchTopOpp {
+        opp_index: usize,
+        give_index: usize,
+        #[cfg_attr(feature = "serde", serde(default))]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        target: Option<usize>,
+    },
+    /// Call Zobbo at the start of your turn, before drawing.
+    CallZobbo,
+    /// Propose ending the game as a tie; allowed at any time. The offer
+    /// lapses when a seat that hasn't agreed makes any other move.
+    OfferDraw,
+    /// Agree to a pending draw offer. The game ends once every seat has.
+    AcceptDraw,
+    /// Turn down a pending draw offer, or withdraw your own.
+    DeclineDraw,
+    /// Ask to pause the game; it pauses once every seat has asked.
+    RequestPause,
+    /// Resume a paused game; any seat may.
+    Resume,
+    /// Ask for the full current state again: lobby, game, every still-valid
+    /// peek and the held card. Also sent automatically on (re)connect.
+    Resync,
+    /// Host only, before a game or match starts: remove whoever holds `seat`.
+    /// Their token stops working; the seat is free for someone new.
+    KickPlayer { seat: usize },
+    /// Host only, before a game or match starts: change the room's rules.
+    /// Same ranges as when creating a room; unreadies everyone.
+    UpdateSettings { rounds: u8, turn_secs: u64 },
+    /// Host only, before a game or match starts: give one seat a handicap,
+    /// or clear it with `null`. Not in ranked or daily rooms; unreadies everyone.
+    SetHandicap { handicap: Option<Handicap> },
+    /// Say something to the room. Trimmed; limited in length and rate.
+    Chat { text: String },
+    /// The client saw a gap in `GameUpdate::seq` (e.g. after the tab slept).
+    /// Answered with the current `GameUpdate`, plus a full resync when the
+    /// client is actually behind.
+    RequestState {
+        #[cfg_attr(feature = "ts", ts(type = "number"))]
+        last_seq: u64,
+    },
+    Ping,
+}
+
+impl ClientToServer {
+    /// The message's `type` tag, e.g. `"draw_deck"`; echoed in `ActionRejected`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ClientToServer::Hello { .. } => "hello",
+            ClientToServer::Ready => "ready",
+            ClientToServer::DrawDeck => "draw_deck",
+            ClientToServer::DrawDiscard => "draw_discard",
+            ClientToServer::SwapWithHand { .. } => "swap_with_hand",
+            ClientToServer::DiscardDrawn => "discard_drawn",
+            ClientToServer::PeekOwn { .. } => "peek_own",
+            ClientToServer::PeekOpp { .. } => "peek_opp",
+            ClientToServer::SwapOwnWithDeck { .. } => "swap_own_with_deck",
+            ClientToServer::BlindSwap { .. } => "blind_swap",
+            ClientToServer::SwapOppWithDeck { .. } => "swap_opp_with_deck",
+            ClientToServer::SkipPower => "skip_power",
+            ClientToServer::MatchTop { .. } => "match_top",
+            ClientToServer::MatchTopOpp { .. } => "match_top_opp",
+            ClientToServer::CallZobbo => "call_zobbo",
+            ClientToServer::OfferDraw => "offer_draw",
+            ClientToServer::AcceptDraw => "accept_draw",
+            ClientToServer::DeclineDraw => "decline_draw",
+            ClientToServer::RequestPause => "request_pause",
+            ClientToServer::Resume => "resume",
+            ClientToServer::KickPlayer { .. } => "kick_player",
+            ClientToServer::UpdateSettings { .. } => "update_settings",
+            ClientToServer::SetHandicap { .. } => "set_handicap",
+            ClientToServer::Resync => "resync",
+            ClientToServer::Chat { .. } => "chat",
+            ClientToServer::RequestState { .. } => "request_state",
+            ClientToServer::Ping => "ping",
+        }
+    }
+}
+
+/// Why an action was refused; stable across releases so UIs can key on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RejectCode {
+    NotStarted,
+    GameOver,
+    NotYourTurn,
+    WrongStage,
+    WrongPower,
+    BadIndex,
+    EmptySlot,
+    EmptyDeck,
+    EmptyDiscard,
+    ZobboAlreadyCalled,
+    BetweenRounds,
+    DiscardDrawOff,
+    BadTarget,
+    NoDrawOffer,
+    Paused,
+    NotPaused,
+    NotHost,
+    InProgress,
+    InvalidSettings,
+    ChatEmpty,
+    ChatTooLong,
+    RateLimited,
+    NeedsIdentity,
+    OffScript,
+}
+
+/// The part of the table a `TutorialHint` points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "snake_case"))]
+pub enum TutorialHighlight {
+    Deck,
+    Discard,
+    Slot { seat: usize, index: usize },
+    Zobbo,
+}
+
+/// Messages sent from the server to a connected client, as JSON text frames.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum ServerToClient {
+    /// First frame on every connection.
+    ///
+    /// `reconnect_token` is only good for re-opening a dropped socket, swapped
+    /// for a ticket at `POST /api/rooms/{id}/ws-ticket`, and expires after
+    /// `reconnect_ttl_secs`.
+    /// `protocol_version` is the server's `PROTOCOL_VERSION`.
+    Welcome {
+        room_id: String,
+        seat: usize,
+        reconnect_token: String,
+        #[cfg_attr(feature = "ts", ts(type = "number"))]
+        reconnect_ttl_secs: u64,
+        #[cfg_attr(feature = "serde", serde(default = "legacy_version"))]
+        protocol_version: u32,
+    },
+    /// `client_version` (from `Hello`) is older than `min_version`; the socket
+    /// closes after this. A browser should reload to pick up a fresh frontend.
+    UnsupportedVersion { client_version: u32, min_version: u32, server_version: u32 },
+    /// A freshly rotated reconnect token; replaces the previous one.
+    ReconnectToken {
+        token: String,
+        #[cfg_attr(feature = "ts", ts(type = "number"))]
+        ttl_secs: u64,
+    },
+    /// `host` may kick and change settings: the first human seat that connected,
+    /// or once it leaves, the next one still connected.
+    /// `rules` are what the next game will be dealt with.
+    LobbyState {
+        players: Vec<LobbyPlayer>,
+        started: bool,
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        host: Option<usize>,
+        #[cfg_attr(feature = "serde", serde(default))]
+        mode: GameMode,
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        #[cfg_attr(feature = "ts", ts(as = "Option<f64>", optional))]
+        turn_secs: Option<u64>,
+        /// Each player's time bank for a whole game, if the room has one.
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        #[cfg_attr(feature = "ts", ts(as = "Option<f64>", optional))]
+        time_bank_secs: Option<u64>,
+        /// The daily challenge the room deals (`YYYY-MM-DD`), if it's a challenge room.
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        daily: Option<String>,
+        /// Games here move the players' ratings; everyone needs an identity to ready up.
+        #[cfg_attr(feature = "serde", serde(default))]
+        ranked: bool,
+        #[cfg_attr(feature = "serde", serde(default))]
+        rules: RuleConfig,
+    },
+    /// A fresh deal. `round` counts from 1; both are 1 outside `ZobboBattle`.
+    /// `hand_size` repeats `rules.hand_size` for clients older than version 4.
+    /// `game_id` names the game's log once it's over.
+    GameStart {
+        #[cfg_attr(feature = "serde", serde(default))]
+        game_id: String,
+        seats: usize,
+        hand_size: usize,
+        first: usize,
+        round: u8,
+        rounds: u8,
+        #[cfg_attr(feature = "serde", serde(default))]
+        rules: RuleConfig,
+    },
+    GameUpdate(GameUpdate),
+    /// Changes since the previous update; see `GameDelta`. A full
+    /// `GameUpdate` is still sent every few updates and at each deal.
+    GameDelta(GameDelta),
+    /// `caller` just called Zobbo: everyone gets `turns_remaining` more
+    /// turns, the caller's current one included, before the reveal.
+    ZobboCalled { caller: usize, turns_remaining: u8 },
+    /// `seat` dropped mid-game; unless it reconnects by `deadline` (Unix
+    /// seconds), it forfeits. `LobbyState` shows when it is back.
+    OpponentDisconnected {
+        seat: usize,
+        #[cfg_attr(feature = "ts", ts(type = "number"))]
+        deadline: u64,
+    },
+    /// Private to the drawing player: the card now held.
+    Drawn { card: CardPublic, from: DrawSource },
+    /// Private: the face of `owner`'s slot `index` as of slot `version`.
+    PeekResult { owner: usize, index: usize, version: u32, card: CardPublic },
+    GameOver {
+        /// `None` on a tie for the lowest score.
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        winner: Option<usize>,
+        scores: Vec<i32>,
+        hands: Vec<Vec<Option<CardPublic>>>,
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        zobbo_caller: Option<usize>,
+        /// Points the caller was penalised for not having the lowest hand;
+        /// already included in `scores`.
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        zobbo_penalty: Option<i32>,
+        #[cfg_attr(feature = "serde", serde(default))]
+        reason: FinishReason,
+        /// Every seat's new rating, in ranked rooms; empty otherwise.
+        #[cfg_attr(feature = "serde", serde(default))]
+        ratings: Vec<RatingChange>,
+    },
+    /// Matches only: follows each round's `GameOver` with running totals.
+    RoundResult {
+        round: u8,
+        rounds: u8,
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        winner: Option<usize>,
+        /// What this round added to each seat's total.
+        scores: Vec<i32>,
+        totals: Vec<i32>,
+        /// `RaceTo` only: seats whose total has reached the limit, which ends the match.
+        #[cfg_attr(feature = "serde", serde(default))]
+        eliminated: Vec<usize>,
+    },
+    /// Matches only: follows each `RoundResult` with the standings so far.
+    /// `wins_needed` is how many round wins take a `BestOf` match.
+    Scoreboard {
+        round: u8,
+        rounds: u8,
+        wins: Vec<u8>,
+        totals: Vec<i32>,
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        wins_needed: Option<u8>,
+    },
+    /// Matches only: sent after the last round, or once a `RaceTo` limit is
+    /// reached. The winner has the lowest total, or in a `BestOf` the most
+    /// round wins; `None` on a tie.
+    MatchOver {
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        winner: Option<usize>,
+        totals: Vec<i32>,
+    },
+    /// A chat line; also replayed from recent history on (re)connect.
+    /// `at` is Unix milliseconds.
+    Chat {
+        from: usize,
+        name: String,
+        text: String,
+        #[cfg_attr(feature = "ts", ts(type = "number"))]
+        at: u64,
+    },
+    /// Tutorial rooms only: what to do next. `step` counts from 0, and the
+    /// room refuses any move but the one `text` asks for with `OffScript`.
+    /// The last hint, sent once the game is over, asks for nothing.
+    TutorialHint {
+        step: u32,
+        text: String,
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        highlight: Option<TutorialHighlight>,
+    },
+    /// The sender's `action` (its `type` tag) was refused; nothing changed.
+    ActionRejected { action: String, code: RejectCode, message: String },
+    /// The host removed you from the room; the socket closes after this.
+    Kicked,
+    /// The server closed the room for good; the socket closes after this.
+    RoomClosed,
+    /// The server is restarting; the socket closes after this. The room and
+    /// its game are kept, so reconnect after about `reconnect_after_secs`.
+    ServerShutdown {
+        #[cfg_attr(feature = "ts", ts(type = "number"))]
+        reconnect_after_secs: u64,
+    },
+    /// A problem with the connection or a malformed message, rather than a game rule.
+    Error { message: String },
+    Pong,
+}
+
+/// Servers from before versioning didn't send `protocol_version`.
+#[cfg(feature = "serde")]
+fn legacy_version() -> u32 {
+    1
+}
+
+impl ServerToClient {
+    /// Fields of this message that carry secrets or hidden cards, which
+    /// anything mirroring traffic (e.g. dev tooling) should blank out.
+    pub fn secret_fields(&self) -> &'static [&'static str] {
+        match self {
+            ServerToClient::Welcome { .. } => &["reconnect_token"],
+            ServerToClient::ReconnectToken { .. } => &["token"],
+            ServerToClient::Drawn { .. } | ServerToClient::PeekResult { .. } => &["card"],
+            _ => &[],
+        }
+    }
+
+    /// The `PROTOCOL_VERSION` that introduced this message.
+    pub fn since(&self) -> u32 {
+        match self {
+            ServerToClient::UnsupportedVersion { .. } | ServerToClient::GameDelta(_) => 2,
+            ServerToClient::Kicked => 3,
+            ServerToClient::Scoreboard { .. } => 9,
+            ServerToClient::ZobboCalled { .. } => 12,
+            ServerToClient::OpponentDisconnected { .. } => 18,
+            ServerToClient::RoomClosed => 25,
+            ServerToClient::ServerShutdown { .. } => 26,
+            ServerToClient::TutorialHint { .. } => 32,
+            _ => 1,
+        }
+    }
+
+    /// This message for a client on `version`: as it is if the client knows
+    /// it, else an `Error` for one that closes the socket, and `None` for the
+    /// rest, whose news reaches older clients through `GameUpdate` and
+    /// `LobbyState` anyway.
+    pub fn downgrade(self, version: u32) -> Option<ServerToClient> {
+        if version >= self.since() {
+            return Some(self);
+        }
+        let message = match self {
+            ServerToClient::Kicked => "the host removed you from the room",
+            ServerToClient::RoomClosed => "the room was closed",
+            ServerToClient::ServerShutdown { .. } => "the server is restarting",
+            _ => return None,
+        };
+        Some(ServerToClient::Error { message: message.into() })
+    }
+}
@@ -0,0 +1,375 @@
+//! Golden files for the JSON of every message, both ways.
+//!
+//! A diff in `tests/snapshots/` means deployed frontends would see (or have
+//! to send) something different: bump `PROTOCOL_VERSION`, or undo the serde
+//! change. Review with `cargo insta review`, or rerun with `INSTA_UPDATE=always`.
+#![cfg(feature = "serde")]
+
+use zobbo_protocol::*;
+
+const QUEEN: CardPublic = CardPublic { rank: Rank::Queen, suit: Suit::Hearts, points: 10 };
+const TEN: CardPublic = CardPublic { rank: Rank::Ten, suit: Suit::Clubs, points: 10 };
+
+/// One of each `ClientToServer` variant, with every optional field set.
+fn client_samples() -> Vec<ClientToServer> {
+    vec![
+        ClientToServer::Hello { version: 27, capabilities: vec![capability::GAME_DELTA.into()] },
+        ClientToServer::Ready,
+        ClientToServer::DrawDeck,
+        ClientToServer::DrawDiscard,
+        ClientToServer::SwapWithHand { index: 2 },
+        ClientToServer::DiscardDrawn,
+        ClientToServer::PeekOwn { index: 0 },
+        ClientToServer::PeekOpp { index: 1, target: Some(2) },
+        ClientToServer::SwapOwnWithDeck { index: 3 },
+        ClientToServer::BlindSwap { own_index: 0, opp_index: 5, target: Some(1) },
+        ClientToServer::SwapOppWithDeck { opp_index: 4, target: Some(3) },
+        ClientToServer::SkipPower,
+        ClientToServer::MatchTop { index: 1 },
+        ClientToServer::MatchTopOpp { opp_index: 2, give_index: 0, target: Some(1) },
+        ClientToServer::CallZobbo,
+        ClientToServer::OfferDraw,
+        ClientToServer::AcceptDraw,
+        ClientToServer::DeclineDraw,
+        ClientToServer::RequestPause,
+        ClientToServer::Resume,
+        ClientToServer::Resync,
+        ClientToServer::KickPlayer { seat: 1 },
+        ClientToServer::UpdateSettings { rounds: 3, turn_secs: 30 },
+        ClientToServer::SetHandicap { handicap: Some(Handicap { seat: 1, kind: HandicapKind::ExtraCard }) },
+        ClientToServer::Chat { text: "gg".into() },
+        ClientToServer::RequestState { last_seq: 41 },
+        ClientToServer::Ping,
+    ]
+}
+
+/// Position of `msg`'s variant in `client_samples`. No wildcard arm, so a new
+/// variant won't compile until it's numbered here and given a sample.
+fn client_variant(msg: &ClientToServer) -> usize {
+    match msg {
+        ClientToServer::Hello { .. } => 0,
+        ClientToServer::Ready => 1,
+        ClientToServer::DrawDeck => 2,
+        ClientToServer::DrawDiscard => 3,
+        ClientToServer::SwapWithHand { .. } => 4,
+        ClientToServer::DiscardDrawn => 5,
+        ClientToServer::PeekOwn { .. } => 6,
+        ClientToServer::PeekOpp { .. } => 7,
+        ClientToServer::SwapOwnWithDeck { .. } => 8,
+        ClientToServer::BlindSwap { .. } => 9,
+        ClientToServer::SwapOppWithDeck { .. } => 10,
+        ClientToServer::SkipPower => 11,
+        ClientToServer::MatchTop { .. } => 12,
+        ClientToServer::MatchTopOpp { .. } => 13,
+        ClientToServer::CallZobbo => 14,
+        ClientToServer::OfferDraw => 15,
+        ClientToServer::AcceptDraw => 16,
+        ClientToServer::DeclineDraw => 17,
+        ClientToServer::RequestPause => 18,
+        ClientToServer::Resume => 19,
+        ClientToServer::Resync => 20,
+        ClientToServer::KickPlayer { .. } => 21,
+        ClientToServer::UpdateSettings { .. } => 22,
+        ClientToServer::SetHandicap { .. } => 23,
+        ClientToServer::Chat { .. } => 24,
+        ClientToServer::RequestState { .. } => 25,
+        ClientToServer::Ping => 26,
+    }
+}
+
+fn game_update() -> GameUpdate {
+    GameUpdate {
+        seq: 7,
+        active: 1,
+        stage: Stage::Holding,
+        held_from: Some(DrawSource::Deck),
+        power: None,
+        deck_count: 39,
+        discard_top: Some(TEN),
+        discard_count: 2,
+        seats: (0..2)
+            .map(|seat| SeatPublic {
+                seat,
+                slots: vec![SlotPublic { filled: true, version: 0 }, SlotPublic { filled: false, version: 3 }],
+                skip_next: seat == 0,
+            })
+            .collect(),
+        zobbo_remaining: Some(1),
+        zobbo_caller: Some(0),
+        draw_agreed: vec![1],
+        pause_requests: vec![0],
+        turn_deadline_unix: Some(1_790_000_000),
+        turn_compensation_ms: Some(180),
+        time_bank_ms: vec![60_000, 45_500],
+        checksum: 0,
+    }
+    .sealed()
+}
+
+/// One of each `ServerToClient` variant, with every optional field set.
+fn server_samples() -> Vec<ServerToClient> {
+    let rules = RuleConfig {
+        race_to: Some(100),
+        jokers: true,
+        handicap: Some(Handicap { seat: 0, kind: HandicapKind::ExtraPeek }),
+        ..RuleConfig::default()
+    };
+    vec![
+        ServerToClient::Welcome {
+            room_id: "r1".into(),
+            seat: 1,
+            reconnect_token: "reconnect".into(),
+            reconnect_ttl_secs: 600,
+            protocol_version: 27,
+        },
+        ServerToClient::UnsupportedVersion { client_version: 1, min_version: 2, server_version: 27 },
+        ServerToClient::ReconnectToken { token: "rotated".into(), ttl_secs: 600 },
+        ServerToClient::LobbyState {
+            players: vec![LobbyPlayer {
+                seat: 0,
+                connected: true,
+                ready: false,
+                player_id: Some("p1".into()),
+                name: Some("Ada".into()),
+            }],
+            started: false,
+            host: Some(0),
+            mode: rules.mode(),
+            turn_secs: Some(30),
+            time_bank_secs: Some(300),
+            daily: Some("2026-10-16".into()),
+            ranked: true,
+            rules,
+        },
+        ServerToClient::GameStart {
+            game_id: "g1".into(),
+            seats: 2,
+            hand_size: 6,
+            first: 0,
+            round: 2,
+            rounds: 3,
+            rules: RuleConfig { rounds: 3, best_of: true, ..RuleConfig::default() },
+        },
+        ServerToClient::GameUpdate(game_update()),
+        ServerToClient::GameDelta(GameDelta {
+            seq: 8,
+            active: Some(0),
+            stage: Some(Stage::Power),
+            held_from: Some(None),
+            power: Some(Some(Power::BlindSwap)),
+            deck_count: Some(38),
+            discard_top: Some(Some(QUEEN)),
+            discard_count: Some(3),
+            slots: vec![SlotChange { seat: 1, index: 1, slot: SlotPublic { filled: true, version: 4 } }],
+            skip_next: Some(vec![false, false]),
+            zobbo_remaining: Some(None),
+            zobbo_caller: Some(None),
+            draw_agreed: Some(vec![]),
+            pause_requests: Some(vec![]),
+            turn_deadline_unix: Some(None),
+            turn_compensation_ms: Some(None),
+            time_bank_ms: Some(vec![58_000, 45_500]),
+            checksum: Some(0x2f0c_91a4),
+        }),
+        ServerToClient::ZobboCalled { caller: 1, turns_remaining: 2 },
+        ServerToClient::OpponentDisconnected { seat: 1, deadline: 1_790_000_060 },
+        ServerToClient::Drawn { card: QUEEN, from: DrawSource::Discard },
+        ServerToClient::PeekResult { owner: 1, index: 2, version: 3, card: TEN },
+        ServerToClient::GameOver {
+            winner: Some(0),
+            scores: vec![4, 21],
+            hands: vec![vec![Some(TEN), None], vec![Some(QUEEN)]],
+            zobbo_caller: Some(1),
+            zobbo_penalty: Some(10),
+            reason: FinishReason::Zobbo,
+            ratings: vec![RatingChange { seat: 0, rating: 1216, delta: 16 }],
+        },
+        ServerToClient::RoundResult { round: 2, rounds: 3, winner: None, scores: vec![5, 5], totals: vec![12, 30], eliminated: vec![1] },
+        ServerToClient::Scoreboard { round: 2, rounds: 3, wins: vec![1, 1], totals: vec![12, 30], wins_needed: Some(2) },
+        ServerToClient::MatchOver { winner: Some(0), totals: vec![12, 30] },
+        ServerToClient::Chat { from: 1, name: "Ada".into(), text: "gg".into(), at: 1_790_000_000_123 },
+        ServerToClient::TutorialHint {
+            step: 1,
+            text: "Swap it for your 10.".into(),
+            highlight: Some(TutorialHighlight::Slot { seat: 0, index: 3 }),
+        },
+        ServerToClient::ActionRejected {
+            action: "draw_deck".into(),
+            code: RejectCode::NotYourTurn,
+            message: "not your turn".into(),
+        },
+        ServerToClient::Kicked,
+        ServerToClient::RoomClosed,
+        ServerToClient::ServerShutdown { reconnect_after_secs: 5 },
+        ServerToClient::Error { message: "malformed message".into() },
+        ServerToClient::Pong,
+    ]
+}
+
+/// Position of `msg`'s variant in `server_samples`; see `client_variant`.
+fn server_variant(msg: &ServerToClient) -> usize {
+    match msg {
+        ServerToClient::Welcome { .. } => 0,
+        ServerToClient::UnsupportedVersion { .. } => 1,
+        ServerToClient::ReconnectToken { .. } => 2,
+        ServerToClient::LobbyState { .. } => 3,
+        ServerToClient::GameStart { .. } => 4,
+        ServerToClient::GameUpdate(_) => 5,
+        ServerToClient::GameDelta(_) => 6,
+        ServerToClient::ZobboCalled { .. } => 7,
+        ServerToClient::OpponentDisconnected { .. } => 8,
+        ServerToClient::Drawn { .. } => 9,
+        ServerToClient::PeekResult { .. } => 10,
+        ServerToClient::GameOver { .. } => 11,
+        ServerToClient::RoundResult { .. } => 12,
+        ServerToClient::Scoreboard { .. } => 13,
+        ServerToClient::MatchOver { .. } => 14,
+        ServerToClient::Chat { .. } => 15,
+        ServerToClient::TutorialHint { .. } => 16,
+        ServerToClient::ActionRejected { .. } => 17,
+        ServerToClient::Kicked => 18,
+        ServerToClient::RoomClosed => 19,
+        ServerToClient::ServerShutdown { .. } => 20,
+        ServerToClient::Error { .. } => 21,
+        ServerToClient::Pong => 22,
+    }
+}
+
+#[test]
+fn every_variant_has_a_sample() {
+    let client: Vec<usize> = client_samples().iter().map(client_variant).collect();
+    assert_eq!(client, (0..client.len()).collect::<Vec<_>>());
+    assert_eq!(client.len(), client_variant(&ClientToServer::Ping) + 1);
+    let server: Vec<usize> = server_samples().iter().map(server_variant).collect();
+    assert_eq!(server, (0..server.len()).collect::<Vec<_>>());
+    assert_eq!(server.len(), server_variant(&ServerToClient::Pong) + 1);
+}
+
+#[test]
+fn client_messages() {
+    insta::assert_json_snapshot!(client_samples());
+}
+
+#[test]
+fn server_messages() {
+    insta::assert_json_snapshot!(server_samples());
+}
+
+#[test]
+fn client_messages_round_trip() {
+    for msg in client_samples() {
+        let json = serde_json::to_string(&msg).unwrap();
+        assert_eq!(serde_json::from_str::<ClientToServer>(&json).unwrap(), msg, "{json}");
+    }
+}
+
+#[test]
+fn server_messages_round_trip() {
+    for msg in server_samples() {
+        let json = serde_json::to_string(&msg).unwrap();
+        assert_eq!(serde_json::from_str::<ServerToClient>(&json).unwrap(), msg, "{json}");
+    }
+}
+
+#[test]
+fn client_messages_accept_omitted_optionals() {
+    let cases = [
+        (r#"{"type":"hello","version":3}"#, ClientToServer::Hello { version: 3, capabilities: vec![] }),
+        (r#"{"type":"peek_opp","index":1}"#, ClientToServer::PeekOpp { index: 1, target: None }),
+        (
+            r#"{"type":"blind_swap","own_index":0,"opp_index":5}"#,
+            ClientToServer::BlindSwap { own_index: 0, opp_index: 5, target: None },
+        ),
+        (r#"{"type":"swap_opp_with_deck","opp_index":4}"#, ClientToServer::SwapOppWithDeck { opp_index: 4, target: None }),
+        (
+            r#"{"type":"match_top_opp","opp_index":2,"give_index":0}"#,
+            ClientToServer::MatchTopOpp { opp_index: 2, give_index: 0, target: None },
+        ),
+    ];
+    for (json, msg) in cases {
+        assert_eq!(serde_json::from_str::<ClientToServer>(json).unwrap(), msg, "{json}");
+    }
+}
+
+#[test]
+fn welcome_from_an_unversioned_server() {
+    let json = r#"{"type":"welcome","room_id":"r1","seat":0,"reconnect_token":"t","reconnect_ttl_secs":600}"#;
+    let ServerToClient::Welcome { protocol_version, .. } = serde_json::from_str(json).unwrap() else { panic!("not a welcome") };
+    assert_eq!(protocol_version, 1);
+}
+
+#[test]
+fn empty_delta_is_just_seq() {
+    let delta = ServerToClient::GameDelta(GameDelta { seq: 9, ..GameDelta::default() });
+    assert_eq!(serde_json::to_string(&delta).unwrap(), r#"{"type":"game_delta","seq":9}"#);
+}
+
+#[test]
+fn older_clients_are_spared_newer_messages() {
+    for msg in server_samples() {
+        let since = msg.since();
+        assert_eq!(msg.clone().downgrade(since), Some(msg.clone()));
+        assert_eq!(msg.clone().downgrade(PROTOCOL_VERSION), Some(msg.clone()));
+        if since == 1 { continue; }
+        match msg.clone().downgrade(since - 1) {
+            Some(ServerToClient::Error { .. }) => {
+                assert!(matches!(msg, ServerToClient::Kicked | ServerToClient::RoomClosed | ServerToClient::ServerShutdown { .. }));
+            }
+            None => {}
+            Some(other) => panic!("{msg:?} reached a client older than it as {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn deltas_are_checked_against_the_checksum() {
+    let old = game_update();
+    let mut next = old.clone();
+    next.seq += 1;
+    next.deck_count -= 1;
+    next.seats[1].slots[1] = SlotPublic { filled: true, version: 4 };
+    let next = next.sealed();
+    assert_ne!(old.checksum, next.checksum);
+    let delta = old.diff(&next).unwrap();
+    let mut copy = old.clone();
+    assert!(copy.apply(&delta));
+    assert_eq!(copy, next);
+
+    // A copy that drifted without a gap in `seq` is caught, and left alone.
+    let mut drifted = old.clone();
+    drifted.seats[0].slots[0].version = 9;
+    let before = drifted.clone();
+    assert!(!drifted.apply(&delta));
+    assert_eq!(drifted, before);
+    // Deltas from servers without checksums aren't checked.
+    assert!(drifted.apply(&GameDelta { checksum: None, ..delta }));
+}
+
+#[test]
+fn unset_optionals_are_left_out() {
+    let update = GameUpdate {
+        held_from: None,
+        power: None,
+        discard_top: None,
+        zobbo_remaining: None,
+        zobbo_caller: None,
+        turn_deadline_unix: None,
+        turn_compensation_ms: None,
+        ..game_update()
+    };
+    let over = ServerToClient::GameOver {
+        winner: None,
+        scores: vec![5, 5],
+        hands: vec![vec![None], vec![Some(TEN)]],
+        zobbo_caller: None,
+        zobbo_penalty: None,
+        reason: FinishReason::AgreedDraw,
+        ratings: vec![],
+    };
+    for msg in [ServerToClient::GameUpdate(update), over] {
+        let json = serde_json::to_string(&msg).unwrap();
+        // A hidden card in `hands` stays `null`: it holds the slot's place.
+        assert!(!json.replace(r#""hands":[[null]"#, "").contains("null"), "{json}");
+        assert_eq!(serde_json::from_str::<ServerToClient>(&json).unwrap(), msg);
+    }
+}